@@ -24,6 +24,50 @@ pub struct Initialize<'info> {
     pub token_program: AccountInfo<'info>,
 }
 
+/// Accounts for an [crate::initialize_v2] instruction.
+#[derive(Accounts, Clone)]
+pub struct InitializeV2<'info> {
+    /// The payer of the new accounts and provider of the initial liquidity.
+    #[account(signer)]
+    pub payer: AccountInfo<'info>,
+    /// The swap, derived from the mints.
+    pub swap: AccountInfo<'info>,
+    /// The authority of the swap.
+    pub swap_authority: AccountInfo<'info>,
+    /// The admin of the swap.
+    pub admin: AccountInfo<'info>,
+    /// The A token of the swap. The reserve is derived from the swap.
+    pub token_a: InitToken<'info>,
+    /// The B token of the swap. The reserve is derived from the swap.
+    pub token_b: InitToken<'info>,
+    /// The pool mint of the swap, derived from the swap.
+    pub pool_mint: AccountInfo<'info>,
+    /// The payer's A token account providing the initial liquidity.
+    pub source_a: AccountInfo<'info>,
+    /// The payer's B token account providing the initial liquidity.
+    pub source_b: AccountInfo<'info>,
+    /// The new output account for LP tokens.
+    #[account(signer)]
+    pub output_lp: AccountInfo<'info>,
+    /// The spl_token program.
+    pub token_program: AccountInfo<'info>,
+    /// The system program.
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::create_pool] instruction.
+#[derive(Accounts, Clone)]
+pub struct CreatePool<'info> {
+    /// The pool factory.
+    pub factory: AccountInfo<'info>,
+    /// The pool template of the amp tier.
+    pub template: AccountInfo<'info>,
+    /// The registry entry of the pool, derived from the mints and amp tier.
+    pub registry_entry: AccountInfo<'info>,
+    /// The accounts of the new pool. The admin is the admin of the factory.
+    pub pool: InitializeV2<'info>,
+}
+
 /// Accounts for a [crate::deposit] instruction.
 #[derive(Accounts, Clone)]
 pub struct Deposit<'info> {
@@ -50,6 +94,24 @@ pub struct Swap<'info> {
     pub output: SwapOutput<'info>,
 }
 
+/// Accounts for a [crate::swap_with_referral] instruction.
+#[derive(Accounts, Clone)]
+pub struct SwapWithReferral<'info> {
+    /// Accounts of the swap.
+    pub swap: Swap<'info>,
+    /// The token account of the referrer receiving the referral fee, in the output token.
+    pub referrer: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::swap_with_fee_tier] instruction.
+#[derive(Accounts, Clone)]
+pub struct SwapWithFeeTier<'info> {
+    /// Accounts of the swap.
+    pub swap: Swap<'info>,
+    /// The fee tier of the user authority.
+    pub fee_tier: AccountInfo<'info>,
+}
+
 /// Accounts for a [crate::withdraw_one] instruction.
 #[derive(Accounts, Clone)]
 pub struct WithdrawOne<'info> {
@@ -90,6 +152,90 @@ pub struct Withdraw<'info> {
     pub output_b: SwapOutput<'info>,
 }
 
+/// Accounts for a [crate::flash_loan] instruction.
+///
+/// Accounts forwarded to the receiver program are passed as remaining accounts.
+#[derive(Accounts, Clone)]
+pub struct FlashLoan<'info> {
+    /// The spl_token program.
+    pub token_program: AccountInfo<'info>,
+    /// The authority of the swap.
+    pub swap_authority: AccountInfo<'info>,
+    /// The swap.
+    pub swap: AccountInfo<'info>,
+    /// Accounts for the borrowed tokens.
+    pub output: SwapOutput<'info>,
+    /// The program receiving the flash loan.
+    pub receiver_program: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::quote_swap] instruction.
+#[derive(Accounts, Clone)]
+pub struct QuoteSwap<'info> {
+    /// The swap.
+    pub swap: AccountInfo<'info>,
+    /// The reserves of the input token.
+    pub input_reserve: AccountInfo<'info>,
+    /// The reserves of the output token.
+    pub output_reserve: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::quote_withdraw_one] instruction.
+#[derive(Accounts, Clone)]
+pub struct QuoteWithdrawOne<'info> {
+    /// The swap.
+    pub swap: AccountInfo<'info>,
+    /// The reserves of the token to withdraw.
+    pub base_reserve: AccountInfo<'info>,
+    /// The reserves of the other token.
+    pub quote_reserve: AccountInfo<'info>,
+    /// The pool mint of the swap.
+    pub pool_mint: AccountInfo<'info>,
+}
+
+/// Accounts for the [crate::quote_deposit] and [crate::get_virtual_price] instructions.
+#[derive(Accounts, Clone)]
+pub struct QuotePool<'info> {
+    /// The swap.
+    pub swap: AccountInfo<'info>,
+    /// The reserves of the A token.
+    pub reserve_a: AccountInfo<'info>,
+    /// The reserves of the B token.
+    pub reserve_b: AccountInfo<'info>,
+    /// The pool mint of the swap.
+    pub pool_mint: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::claim_admin_fees] instruction.
+#[derive(Accounts, Clone)]
+pub struct ClaimAdminFees<'info> {
+    /// The spl_token program.
+    pub token_program: AccountInfo<'info>,
+    /// The authority of the swap.
+    pub swap_authority: AccountInfo<'info>,
+    /// The swap.
+    pub swap: AccountInfo<'info>,
+    /// The token account for the pool's reserves of token A.
+    pub reserve_a: AccountInfo<'info>,
+    /// The token account for the pool's reserves of token B.
+    pub reserve_b: AccountInfo<'info>,
+    /// The token account for the fees associated with token A.
+    pub fees_a: AccountInfo<'info>,
+    /// The token account for the fees associated with token B.
+    pub fees_b: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::claim_admin_fees_as_lp] instruction.
+#[derive(Accounts, Clone)]
+pub struct ClaimAdminFeesAsLp<'info> {
+    /// Accounts shared with [crate::claim_admin_fees].
+    pub claim: ClaimAdminFees<'info>,
+    /// The pool mint of the swap.
+    pub pool_mint: AccountInfo<'info>,
+    /// The pool token account receiving the admin fees.
+    pub lp_fees: AccountInfo<'info>,
+}
+
 /// Accounts for a [crate::set_fee_account] instruction.
 #[derive(Accounts, Clone)]
 pub struct SetFeeAccount<'info> {
@@ -99,7 +245,20 @@ pub struct SetFeeAccount<'info> {
     pub fee_account: AccountInfo<'info>,
 }
 
-/// Accounts for a [crate::apply_new_admin] instruction.
+/// Accounts for a [crate::set_admin_fee_mode] instruction.
+#[derive(Accounts, Clone)]
+pub struct SetAdminFeeMode<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The token account for the pool's reserves of token A.
+    pub token_a: AccountInfo<'info>,
+    /// The token account for the pool's reserves of token B.
+    pub token_b: AccountInfo<'info>,
+    /// The pool mint of the swap.
+    pub pool_mint: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::commit_new_admin] instruction.
 #[derive(Accounts, Clone)]
 pub struct CommitNewAdmin<'info> {
     /// The context of the admin user.
@@ -108,6 +267,122 @@ pub struct CommitNewAdmin<'info> {
     pub new_admin: AccountInfo<'info>,
 }
 
+/// Accounts for a [crate::apply_new_admin] instruction.
+#[derive(Accounts, Clone)]
+pub struct ApplyNewAdmin<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The account of the new admin, which must sign.
+    pub new_admin: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::set_guardian] instruction.
+#[derive(Accounts, Clone)]
+pub struct SetGuardian<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The account of the new guardian.
+    pub guardian: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::grant_role] instruction.
+#[derive(Accounts, Clone)]
+pub struct GrantRole<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The account to grant the role to.
+    pub grantee: AccountInfo<'info>,
+}
+
+/// Accounts for instructions on the admin action queue of a swap.
+#[derive(Accounts, Clone)]
+pub struct AdminQueueContext<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The admin action queue of the swap.
+    pub admin_queue: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::migrate_swap_info] instruction.
+#[derive(Accounts, Clone)]
+pub struct MigrateSwapInfo<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The payer of the additional rent.
+    pub payer: AccountInfo<'info>,
+    /// The system program.
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::set_fee_tier] instruction.
+#[derive(Accounts, Clone)]
+pub struct SetFeeTier<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The payer of new fee tiers.
+    pub payer: AccountInfo<'info>,
+    /// The fee tier of the user authority.
+    pub fee_tier: AccountInfo<'info>,
+    /// The user authority receiving the discount.
+    pub user_authority: AccountInfo<'info>,
+    /// The system program.
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::remove_fee_tier] instruction.
+#[derive(Accounts, Clone)]
+pub struct RemoveFeeTier<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The fee tier to close.
+    pub fee_tier: AccountInfo<'info>,
+    /// The account receiving the rent of the fee tier.
+    pub rent_destination: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::set_gate] instruction.
+#[derive(Accounts, Clone)]
+pub struct SetGate<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The gate of the allowlist, or the default pubkey to remove the gate.
+    pub gate: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::set_price_oracle] instruction.
+#[derive(Accounts, Clone)]
+pub struct SetPriceOracle<'info> {
+    /// The context of the admin user.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The price feed, or the default pubkey to remove the price oracle.
+    pub feed: AccountInfo<'info>,
+    /// The mint of token A.
+    pub token_a_mint: AccountInfo<'info>,
+    /// The mint of token B.
+    pub token_b_mint: AccountInfo<'info>,
+}
+
+/// Accounts for a [crate::close_pool] instruction.
+#[derive(Accounts, Clone)]
+pub struct ClosePool<'info> {
+    /// The context of the admin user. The admin receives the returned rent.
+    pub admin_ctx: AdminUserContext<'info>,
+    /// The authority of the swap.
+    pub swap_authority: AccountInfo<'info>,
+    /// The pool mint of the swap.
+    pub pool_mint: AccountInfo<'info>,
+    /// The token account for the pool's reserves of token A.
+    pub reserve_a: AccountInfo<'info>,
+    /// The token account for the pool's reserves of token B.
+    pub reserve_b: AccountInfo<'info>,
+    /// The token account for the fees associated with token A.
+    pub fees_a: AccountInfo<'info>,
+    /// The token account for the fees associated with token B.
+    pub fees_b: AccountInfo<'info>,
+    /// The spl_token program.
+    pub token_program: AccountInfo<'info>,
+}
+
 // --------------------------------
 // Various accounts
 // --------------------------------
@@ -2,6 +2,10 @@
 
 use crate::*;
 use anchor_lang::{prelude::*, solana_program};
+use stable_swap_client::results::{
+    DepositResult, FlashLoanResult, InstructionResult, SwapResult, WithdrawOneResult,
+    WithdrawResult,
+};
 
 /// Creates and invokes a [stable_swap_client::instruction::initialize] instruction.
 ///
@@ -58,8 +62,104 @@ pub fn initialize<'a, 'b, 'c, 'info>(
     Ok(())
 }
 
+/// Creates and invokes a [stable_swap_client::instruction::initialize_v2] instruction.
+///
+/// # Arguments
+///
+/// See [stable_swap_client::instruction::InitializeV2Data].
+///
+/// * `amp_factor` - Amplification factor.
+/// * `fees` - Initial fees.
+/// * `token_a_amount` - Initial liquidity of token A.
+/// * `token_b_amount` - Initial liquidity of token B.
+pub fn initialize_v2<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, InitializeV2<'info>>,
+    amp_factor: u64,
+    fees: stable_swap_client::fees::Fees,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::initialize_v2(
+        ctx.accounts.token_program.key,
+        ctx.accounts.payer.key,
+        ctx.accounts.admin.key,
+        ctx.accounts.token_a.fees.key,
+        ctx.accounts.token_b.fees.key,
+        ctx.accounts.token_a.mint.key,
+        ctx.accounts.token_b.mint.key,
+        ctx.accounts.source_a.key,
+        ctx.accounts.source_b.key,
+        ctx.accounts.output_lp.key,
+        amp_factor,
+        fees,
+        token_a_amount,
+        token_b_amount,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.payer,
+            ctx.accounts.swap,
+            ctx.accounts.swap_authority,
+            ctx.accounts.admin,
+            ctx.accounts.token_a.fees,
+            ctx.accounts.token_b.fees,
+            ctx.accounts.token_a.mint,
+            ctx.accounts.token_a.reserve,
+            ctx.accounts.token_b.mint,
+            ctx.accounts.token_b.reserve,
+            ctx.accounts.pool_mint,
+            ctx.accounts.source_a,
+            ctx.accounts.source_b,
+            ctx.accounts.output_lp,
+            ctx.accounts.token_program,
+            ctx.accounts.system_program,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::create_pool] instruction.
+///
+/// # Arguments
+///
+/// See [stable_swap_client::instruction::CreatePoolData].
+///
+/// * `amp_tier` - The amp tier of the pool.
+/// * `token_a_amount` - Initial liquidity of token A.
+/// * `token_b_amount` - Initial liquidity of token B.
+pub fn create_pool<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, CreatePool<'info>>,
+    amp_tier: u8,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    let ix = stable_swap_client::instruction::create_pool(
+        pool.token_program.key,
+        pool.payer.key,
+        pool.admin.key,
+        pool.token_a.fees.key,
+        pool.token_b.fees.key,
+        pool.token_a.mint.key,
+        pool.token_b.mint.key,
+        pool.source_a.key,
+        pool.source_b.key,
+        pool.output_lp.key,
+        amp_tier,
+        token_a_amount,
+        token_b_amount,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
 /// Creates and invokes a [stable_swap_client::instruction::deposit] instruction.
 ///
+/// Returns the [DepositResult] of the instruction.
+///
 /// # Arguments
 ///
 /// See [stable_swap_client::instruction::DepositData].
@@ -72,7 +172,7 @@ pub fn deposit<'a, 'b, 'c, 'info>(
     token_a_amount: u64,
     token_b_amount: u64,
     min_mint_amount: u64,
-) -> Result<()> {
+) -> Result<DepositResult> {
     let ix = stable_swap_client::instruction::deposit(
         // token program ID is verified by the stable swap program
         ctx.accounts.user.token_program.key,
@@ -107,11 +207,13 @@ pub fn deposit<'a, 'b, 'c, 'info>(
         ],
         ctx.signer_seeds,
     )?;
-    Ok(())
+    Ok(DepositResult::from_return_data()?)
 }
 
 /// Creates and invokes a [stable_swap_client::instruction::swap] instruction.
 ///
+/// Returns the [SwapResult] of the instruction.
+///
 /// # Arguments
 ///
 /// See [stable_swap_client::instruction::SwapData].
@@ -122,7 +224,7 @@ pub fn swap<'a, 'b, 'c, 'info>(
     ctx: CpiContext<'a, 'b, 'c, 'info, Swap<'info>>,
     amount_in: u64,
     minimum_amount_out: u64,
-) -> Result<()> {
+) -> Result<SwapResult> {
     let ix = stable_swap_client::instruction::swap(
         ctx.accounts.user.token_program.key,
         ctx.accounts.user.swap.key,
@@ -153,11 +255,114 @@ pub fn swap<'a, 'b, 'c, 'info>(
         ],
         ctx.signer_seeds,
     )?;
-    Ok(())
+    Ok(SwapResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::swap_with_referral] instruction.
+///
+/// Returns the [SwapResult] of the instruction.
+///
+/// # Arguments
+///
+/// See [stable_swap_client::instruction::SwapData].
+///
+/// * `amount_in` - Amount of [`Swap::input`] tokens to swap.
+/// * `minimum_amount_out` - Minimum amount of [`Swap::output`] tokens to receive.
+pub fn swap_with_referral<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SwapWithReferral<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<SwapResult> {
+    let ix = stable_swap_client::instruction::swap_with_referral(
+        ctx.accounts.swap.user.token_program.key,
+        ctx.accounts.swap.user.swap.key,
+        ctx.accounts.swap.user.swap_authority.key,
+        ctx.accounts.swap.user.user_authority.key,
+        ctx.accounts.swap.input.user.key,
+        ctx.accounts.swap.input.reserve.key,
+        ctx.accounts.swap.output.user_token.reserve.key,
+        ctx.accounts.swap.output.user_token.user.key,
+        ctx.accounts.swap.output.fees.key,
+        Some(ctx.accounts.referrer.key),
+        amount_in,
+        minimum_amount_out,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.swap.user.token_program,
+            ctx.accounts.swap.user.swap,
+            ctx.accounts.swap.user.swap_authority,
+            ctx.accounts.swap.user.user_authority,
+            // swap
+            ctx.accounts.swap.input.user,
+            ctx.accounts.swap.input.reserve,
+            ctx.accounts.swap.output.user_token.reserve,
+            ctx.accounts.swap.output.user_token.user,
+            ctx.accounts.swap.output.fees,
+            ctx.accounts.referrer,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(SwapResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::swap] instruction
+/// with the fee tier of the user authority.
+///
+/// Returns the [SwapResult] of the instruction.
+///
+/// # Arguments
+///
+/// See [stable_swap_client::instruction::SwapData].
+///
+/// * `amount_in` - Amount of [`Swap::input`] tokens to swap.
+/// * `minimum_amount_out` - Minimum amount of [`Swap::output`] tokens to receive.
+pub fn swap_with_fee_tier<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SwapWithFeeTier<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<SwapResult> {
+    let ix = stable_swap_client::instruction::swap(
+        ctx.accounts.swap.user.token_program.key,
+        ctx.accounts.swap.user.swap.key,
+        ctx.accounts.swap.user.swap_authority.key,
+        ctx.accounts.swap.user.user_authority.key,
+        ctx.accounts.swap.input.user.key,
+        ctx.accounts.swap.input.reserve.key,
+        ctx.accounts.swap.output.user_token.reserve.key,
+        ctx.accounts.swap.output.user_token.user.key,
+        ctx.accounts.swap.output.fees.key,
+        amount_in,
+        minimum_amount_out,
+    )?;
+    let ix = stable_swap_client::instruction::with_fee_tier(ix, ctx.accounts.fee_tier.key);
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.swap.user.token_program,
+            ctx.accounts.swap.user.swap,
+            ctx.accounts.swap.user.swap_authority,
+            ctx.accounts.swap.user.user_authority,
+            // swap
+            ctx.accounts.swap.input.user,
+            ctx.accounts.swap.input.reserve,
+            ctx.accounts.swap.output.user_token.reserve,
+            ctx.accounts.swap.output.user_token.user,
+            ctx.accounts.swap.output.fees,
+            ctx.accounts.fee_tier,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(SwapResult::from_return_data()?)
 }
 
 /// Creates and invokes a [stable_swap_client::instruction::withdraw_one] instruction.
 ///
+/// Returns the [WithdrawOneResult] of the instruction.
+///
 /// # Arguments
 ///
 /// See [stable_swap_client::instruction::WithdrawOneData].
@@ -168,7 +373,7 @@ pub fn withdraw_one<'a, 'b, 'c, 'info>(
     ctx: CpiContext<'a, 'b, 'c, 'info, WithdrawOne<'info>>,
     pool_token_amount: u64,
     minimum_token_amount: u64,
-) -> Result<()> {
+) -> Result<WithdrawOneResult> {
     let ix = stable_swap_client::instruction::withdraw_one(
         ctx.accounts.user.token_program.key,
         ctx.accounts.user.swap.key,
@@ -201,11 +406,13 @@ pub fn withdraw_one<'a, 'b, 'c, 'info>(
         ],
         ctx.signer_seeds,
     )?;
-    Ok(())
+    Ok(WithdrawOneResult::from_return_data()?)
 }
 
 /// Creates and invokes a [stable_swap_client::instruction::withdraw] instruction.
 ///
+/// Returns the [WithdrawResult] of the instruction.
+///
 /// # Arguments
 ///
 /// See [stable_swap_client::instruction::WithdrawData].
@@ -218,7 +425,7 @@ pub fn withdraw<'a, 'b, 'c, 'info>(
     pool_token_amount: u64,
     minimum_token_a_amount: u64,
     minimum_token_b_amount: u64,
-) -> Result<()> {
+) -> Result<WithdrawResult> {
     let ix = stable_swap_client::instruction::withdraw(
         // token program ID is verified by the stable swap program
         ctx.accounts.user.token_program.key,
@@ -239,6 +446,202 @@ pub fn withdraw<'a, 'b, 'c, 'info>(
         minimum_token_b_amount,
     )?;
     solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(WithdrawResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::flash_loan] instruction.
+///
+/// Returns the [FlashLoanResult] of the instruction.
+///
+/// # Arguments
+///
+/// See [stable_swap_client::instruction::FlashLoanData].
+///
+/// * `amount` - Amount of reserve tokens to borrow.
+pub fn flash_loan<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, FlashLoan<'info>>,
+    amount: u64,
+) -> Result<FlashLoanResult> {
+    let receiver_accounts: Vec<AccountMeta> = ctx
+        .remaining_accounts
+        .iter()
+        .map(|info| AccountMeta {
+            pubkey: *info.key,
+            is_signer: info.is_signer,
+            is_writable: info.is_writable,
+        })
+        .collect();
+    let ix = stable_swap_client::instruction::flash_loan(
+        ctx.accounts.token_program.key,
+        ctx.accounts.swap.key,
+        ctx.accounts.swap_authority.key,
+        ctx.accounts.output.user_token.reserve.key,
+        ctx.accounts.output.user_token.user.key,
+        ctx.accounts.output.fees.key,
+        ctx.accounts.receiver_program.key,
+        &receiver_accounts,
+        amount,
+    )?;
+    let mut account_infos = vec![
+        ctx.program,
+        ctx.accounts.token_program,
+        ctx.accounts.swap,
+        ctx.accounts.swap_authority,
+        ctx.accounts.output.user_token.reserve,
+        ctx.accounts.output.user_token.user,
+        ctx.accounts.output.fees,
+        ctx.accounts.receiver_program,
+    ];
+    account_infos.extend(ctx.remaining_accounts);
+    solana_program::program::invoke_signed(&ix, &account_infos, ctx.signer_seeds)?;
+    Ok(FlashLoanResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::quote_swap] instruction.
+///
+/// Returns the [SwapResult] that a [crate::swap] of `amount_in` would return.
+pub fn quote_swap<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, QuoteSwap<'info>>,
+    amount_in: u64,
+) -> Result<SwapResult> {
+    let ix = stable_swap_client::instruction::quote_swap(
+        ctx.accounts.swap.key,
+        ctx.accounts.input_reserve.key,
+        ctx.accounts.output_reserve.key,
+        amount_in,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.swap,
+            ctx.accounts.input_reserve,
+            ctx.accounts.output_reserve,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(SwapResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::quote_deposit] instruction.
+///
+/// Returns the [DepositResult] that a [crate::deposit] of the amounts would return.
+pub fn quote_deposit<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, QuotePool<'info>>,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<DepositResult> {
+    let ix = stable_swap_client::instruction::quote_deposit(
+        ctx.accounts.swap.key,
+        ctx.accounts.reserve_a.key,
+        ctx.accounts.reserve_b.key,
+        ctx.accounts.pool_mint.key,
+        token_a_amount,
+        token_b_amount,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.swap,
+            ctx.accounts.reserve_a,
+            ctx.accounts.reserve_b,
+            ctx.accounts.pool_mint,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(DepositResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::quote_withdraw_one] instruction.
+///
+/// Returns the [WithdrawOneResult] that a [crate::withdraw_one] of
+/// `pool_token_amount` would return.
+pub fn quote_withdraw_one<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, QuoteWithdrawOne<'info>>,
+    pool_token_amount: u64,
+) -> Result<WithdrawOneResult> {
+    let ix = stable_swap_client::instruction::quote_withdraw_one(
+        ctx.accounts.swap.key,
+        ctx.accounts.base_reserve.key,
+        ctx.accounts.quote_reserve.key,
+        ctx.accounts.pool_mint.key,
+        pool_token_amount,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.swap,
+            ctx.accounts.base_reserve,
+            ctx.accounts.quote_reserve,
+            ctx.accounts.pool_mint,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(WithdrawOneResult::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::get_virtual_price] instruction.
+///
+/// Returns the current [stable_swap_client::events::PoolState] of the pool.
+pub fn get_virtual_price<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, QuotePool<'info>>,
+) -> Result<stable_swap_client::events::PoolState> {
+    let ix = stable_swap_client::instruction::get_virtual_price(
+        ctx.accounts.swap.key,
+        ctx.accounts.reserve_a.key,
+        ctx.accounts.reserve_b.key,
+        ctx.accounts.pool_mint.key,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.program,
+            ctx.accounts.swap,
+            ctx.accounts.reserve_a,
+            ctx.accounts.reserve_b,
+            ctx.accounts.pool_mint,
+        ],
+        ctx.signer_seeds,
+    )?;
+    Ok(stable_swap_client::events::PoolState::from_return_data()?)
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::claim_admin_fees] instruction.
+pub fn claim_admin_fees<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, ClaimAdminFees<'info>>,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::claim_admin_fees(
+        ctx.accounts.token_program.key,
+        ctx.accounts.swap.key,
+        ctx.accounts.swap_authority.key,
+        ctx.accounts.reserve_a.key,
+        ctx.accounts.reserve_b.key,
+        ctx.accounts.fees_a.key,
+        ctx.accounts.fees_b.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::claim_admin_fees_as_lp] instruction.
+pub fn claim_admin_fees_as_lp<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, ClaimAdminFeesAsLp<'info>>,
+) -> Result<()> {
+    let claim = &ctx.accounts.claim;
+    let ix = stable_swap_client::instruction::claim_admin_fees_as_lp(
+        claim.token_program.key,
+        claim.swap.key,
+        claim.swap_authority.key,
+        claim.reserve_a.key,
+        claim.reserve_b.key,
+        claim.fees_a.key,
+        claim.fees_b.key,
+        ctx.accounts.pool_mint.key,
+        ctx.accounts.lp_fees.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
     Ok(())
 }
 
@@ -298,11 +701,178 @@ pub fn unpause<'a, 'b, 'c, 'info>(
 
 /// Creates and invokes a [stable_swap_client::instruction::apply_new_admin] instruction.
 pub fn apply_new_admin<'a, 'b, 'c, 'info>(
-    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+    ctx: CpiContext<'a, 'b, 'c, 'info, ApplyNewAdmin<'info>>,
 ) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
     let ix = stable_swap_client::instruction::apply_new_admin(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.new_admin.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::accept_admin] instruction.
+///
+/// The admin of the context must be the new admin.
+pub fn accept_admin<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::accept_admin(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::cancel_admin_transfer] instruction.
+pub fn cancel_admin_transfer<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::cancel_admin_transfer(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::renounce_admin] instruction.
+pub fn renounce_admin<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::renounce_admin(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::migrate_swap_info] instruction.
+pub fn migrate_swap_info<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, MigrateSwapInfo<'info>>,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::migrate_swap_info(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.payer.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_fee_tier] instruction.
+///
+/// # Arguments
+///
+/// * `fee_multiplier_bps` - Share of the trade fee paid by the user, in basis points.
+pub fn set_fee_tier<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetFeeTier<'info>>,
+    fee_multiplier_bps: u16,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::set_fee_tier(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.payer.key,
+        ctx.accounts.user_authority.key,
+        fee_multiplier_bps,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::remove_fee_tier] instruction.
+///
+/// # Arguments
+///
+/// * `user_authority` - The user authority of the fee tier.
+pub fn remove_fee_tier<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, RemoveFeeTier<'info>>,
+    user_authority: Pubkey,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::remove_fee_tier(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        &user_authority,
+        ctx.accounts.rent_destination.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_gate] instruction.
+pub fn set_gate<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetGate<'info>>,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::set_gate(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.gate.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_circuit_breaker] instruction.
+///
+/// # Arguments
+///
+/// * `max_price_deviation_bps` - Maximum deviation of the price within a window, in basis points.
+/// * `window_slots` - Length of a window, in slots.
+pub fn set_circuit_breaker<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+    max_price_deviation_bps: u16,
+    window_slots: u64,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::set_circuit_breaker(
         ctx.accounts.swap.key,
         ctx.accounts.admin.key,
+        max_price_deviation_bps,
+        window_slots,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::reset_circuit_breaker] instruction.
+pub fn reset_circuit_breaker<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::reset_circuit_breaker(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_price_oracle] instruction.
+///
+/// # Arguments
+///
+/// * `max_deviation_bps` - Maximum deviation of the price from the feed, in basis points.
+/// * `max_staleness_slots` - Maximum age of the price of the feed, in slots.
+pub fn set_price_oracle<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetPriceOracle<'info>>,
+    max_deviation_bps: u16,
+    max_staleness_slots: u64,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::set_price_oracle(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.feed.key,
+        ctx.accounts.token_a_mint.key,
+        ctx.accounts.token_b_mint.key,
+        max_deviation_bps,
+        max_staleness_slots,
     )?;
     solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
     Ok(())
@@ -356,3 +926,238 @@ pub fn set_new_fees<'a, 'b, 'c, 'info>(
     solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
     Ok(())
 }
+
+/// Creates and invokes a [stable_swap_client::instruction::set_extra_fees] instruction.
+///
+/// # Arguments
+///
+/// * `extra_fees` - new [`stable_swap_client::fees::ExtraFees`].
+pub fn set_extra_fees<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+    extra_fees: stable_swap_client::fees::ExtraFees,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::set_extra_fees(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+        extra_fees,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_admin_fee_mode] instruction.
+///
+/// # Arguments
+///
+/// * `mode` - new [`stable_swap_client::state::AdminFeeMode`].
+pub fn set_admin_fee_mode<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetAdminFeeMode<'info>>,
+    mode: stable_swap_client::state::AdminFeeMode,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::set_admin_fee_mode(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.token_a.key,
+        ctx.accounts.token_b.key,
+        ctx.accounts.pool_mint.key,
+        mode,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::close_pool] instruction.
+pub fn close_pool<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, ClosePool<'info>>,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::close_pool(
+        ctx.accounts.token_program.key,
+        ctx.accounts.admin_ctx.swap.key,
+        ctx.accounts.admin_ctx.admin.key,
+        ctx.accounts.swap_authority.key,
+        ctx.accounts.pool_mint.key,
+        ctx.accounts.reserve_a.key,
+        ctx.accounts.reserve_b.key,
+        ctx.accounts.fees_a.key,
+        ctx.accounts.fees_b.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_pause_flags] instruction.
+///
+/// # Arguments
+///
+/// * `flags` - new [`stable_swap_client::state::PauseFlags`].
+pub fn set_pause_flags<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+    flags: stable_swap_client::state::PauseFlags,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::set_pause_flags(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+        flags,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_guardian] instruction.
+pub fn set_guardian<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetGuardian<'info>>,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::set_guardian(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.guardian.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::grant_role] instruction.
+///
+/// # Arguments
+///
+/// * `role` - [`stable_swap_client::state::AdminRole`] to grant to the grantee.
+pub fn grant_role<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, GrantRole<'info>>,
+    role: stable_swap_client::state::AdminRole,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::grant_role(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        role,
+        ctx.accounts.grantee.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::revoke_role] instruction.
+///
+/// # Arguments
+///
+/// * `role` - [`stable_swap_client::state::AdminRole`] to revoke.
+pub fn revoke_role<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+    role: stable_swap_client::state::AdminRole,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::revoke_role(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+        role,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::set_timelock_delay] instruction.
+///
+/// # Arguments
+///
+/// * `delay` - Delay in seconds before queued admin actions can be executed.
+pub fn set_timelock_delay<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminUserContext<'info>>,
+    delay: i64,
+) -> Result<()> {
+    let ix = stable_swap_client::instruction::set_timelock_delay(
+        ctx.accounts.swap.key,
+        ctx.accounts.admin.key,
+        delay,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::initialize_admin_queue] instruction.
+pub fn initialize_admin_queue<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminQueueContext<'info>>,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::initialize_admin_queue(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.admin_queue.key,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::queue_admin_action] instruction.
+/// The accounts of the queued instruction are taken from the remaining accounts.
+///
+/// # Arguments
+///
+/// * `action` - Admin instruction to queue.
+pub fn queue_admin_action<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminQueueContext<'info>>,
+    action: &stable_swap_client::instruction::AdminInstruction,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let action_accounts: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|a| *a.key).collect();
+    let ix = stable_swap_client::instruction::queue_admin_action(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.admin_queue.key,
+        action,
+        &action_accounts,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::cancel_admin_action] instruction.
+///
+/// # Arguments
+///
+/// * `index` - Index of the action in the admin queue.
+pub fn cancel_admin_action<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminQueueContext<'info>>,
+    index: u8,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let ix = stable_swap_client::instruction::cancel_admin_action(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.admin_queue.key,
+        index,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
+
+/// Creates and invokes a [stable_swap_client::instruction::execute_admin_action] instruction.
+/// The accounts of the queued instruction are taken from the remaining accounts.
+///
+/// # Arguments
+///
+/// * `index` - Index of the action in the admin queue.
+pub fn execute_admin_action<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, AdminQueueContext<'info>>,
+    index: u8,
+) -> Result<()> {
+    let admin_ctx = &ctx.accounts.admin_ctx;
+    let action_accounts: Vec<AccountMeta> = ctx
+        .remaining_accounts
+        .iter()
+        .map(|a| AccountMeta {
+            pubkey: *a.key,
+            is_signer: a.is_signer,
+            is_writable: a.is_writable,
+        })
+        .collect();
+    let ix = stable_swap_client::instruction::execute_admin_action(
+        admin_ctx.swap.key,
+        admin_ctx.admin.key,
+        ctx.accounts.admin_queue.key,
+        index,
+        &action_accounts,
+    )?;
+    solana_program::program::invoke_signed(&ix, &ctx.to_account_infos(), ctx.signer_seeds)?;
+    Ok(())
+}
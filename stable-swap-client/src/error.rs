@@ -98,6 +98,85 @@ pub enum SwapError {
     /// Token mint decimals must be the same.
     #[error("Token mints must have same decimals")]
     MismatchedDecimals,
+    /// The flash loan receiver did not return the borrowed amount plus fee.
+    #[error("Flash loan was not repaid")]
+    FlashLoanNotRepaid,
+    /// The swap has no flash loan fee, which disables flash loans.
+    #[error("Flash loans are disabled")]
+    FlashLoansDisabled,
+    /// The swap uses the legacy layout, which cannot store the change until it
+    /// is migrated with [crate::instruction::AdminInstruction::MigrateSwapInfo].
+    #[error("Swap uses the legacy layout")]
+    LegacySwapLayout,
+    /// The token mint has an extension that the swap does not support.
+    #[error("Token mint has an unsupported extension")]
+    UnsupportedMint,
+    /// The admin instruction must be queued while the timelock is enabled.
+    #[error("Admin instruction must be queued")]
+    TimelockRequired,
+    /// The queued admin action cannot be executed yet.
+    #[error("Admin action is not ready")]
+    AdminActionNotReady,
+    /// The queued admin action was not executed within the grace period.
+    #[error("Admin action has expired")]
+    AdminActionExpired,
+    /// The admin action queue has no free slots.
+    #[error("Admin action queue is full")]
+    AdminQueueFull,
+    /// A fee denominator is zero.
+    #[error("Fee denominator is zero")]
+    ZeroFeeDenominator,
+    /// The trade fee exceeds the maximum trade fee.
+    #[error("Trade fee is too high")]
+    TradeFeeTooHigh,
+    /// The withdraw fee exceeds the maximum withdraw fee.
+    #[error("Withdraw fee is too high")]
+    WithdrawFeeTooHigh,
+    /// The flash loan fee exceeds the maximum flash loan fee.
+    #[error("Flash loan fee is too high")]
+    FlashLoanFeeTooHigh,
+    /// An admin fee takes more than 100% of the fee it applies to.
+    #[error("Admin fee is too high")]
+    AdminFeeTooHigh,
+    /// The admin of the swap was renounced.
+    #[error("Swap admin has been renounced")]
+    AdminRenounced,
+    /// The mints of a pool are not sorted.
+    #[error("Token mints are not sorted")]
+    UnsortedMints,
+    /// The pool template is not enabled.
+    #[error("Pool template is not enabled")]
+    TemplateDisabled,
+    /// The invoked instruction did not set return data.
+    #[error("Return data is missing")]
+    MissingReturnData,
+    /// The admin and referral fees together take more than 100% of the trade fee.
+    #[error("Referral fee is too high")]
+    ReferralFeeTooHigh,
+    /// The fee tier does not apply to the swap or to the user.
+    #[error("Fee tier does not apply to the swap or user")]
+    InvalidFeeTier,
+    /// The user is not on the allowlist of the gate of the swap.
+    #[error("User is not on the allowlist of the swap")]
+    NotAllowlisted,
+    /// The operation moves the price beyond the band of the circuit breaker.
+    #[error("Price deviation exceeds the circuit breaker band")]
+    PriceDeviationExceeded,
+    /// The oracle price feed is missing or is not a price account.
+    #[error("Oracle price feed is invalid")]
+    InvalidOracle,
+    /// The oracle price is too old or not trading.
+    #[error("Oracle price is stale")]
+    StaleOracle,
+    /// The swap moves the price too far from the oracle price.
+    #[error("Price deviation from the oracle exceeds the limit")]
+    OracleDeviationExceeded,
+    /// Pool token admin fees must be claimed before leaving the LpMint mode.
+    #[error("Pool token admin fees are unclaimed")]
+    UnclaimedAdminFees,
+    /// The reserves hold more than the accrued admin fees.
+    #[error("Pool reserves are not empty")]
+    NonEmptyReserves,
 }
 
 impl From<SwapError> for ProgramError {
@@ -173,6 +252,38 @@ impl PrintProgramError for SwapError {
             SwapError::NoActiveTransfer => msg!("Error: No active admin transfer in progress"),
             SwapError::AdminDeadlineExceeded => msg!("Error: Admin transfer deadline exceeded"),
             SwapError::MismatchedDecimals => msg!("Error: Token mints must have same decimals"),
+            SwapError::FlashLoanNotRepaid => msg!("Error: Flash loan was not repaid"),
+            SwapError::FlashLoansDisabled => msg!("Error: Flash loans are disabled"),
+            SwapError::LegacySwapLayout => msg!("Error: Swap uses the legacy layout"),
+            SwapError::UnsupportedMint => msg!("Error: Token mint has an unsupported extension"),
+            SwapError::TimelockRequired => msg!("Error: Admin instruction must be queued"),
+            SwapError::AdminActionNotReady => msg!("Error: Admin action is not ready"),
+            SwapError::AdminActionExpired => msg!("Error: Admin action has expired"),
+            SwapError::AdminQueueFull => msg!("Error: Admin action queue is full"),
+            SwapError::ZeroFeeDenominator => msg!("Error: Fee denominator is zero"),
+            SwapError::TradeFeeTooHigh => msg!("Error: Trade fee is too high"),
+            SwapError::WithdrawFeeTooHigh => msg!("Error: Withdraw fee is too high"),
+            SwapError::FlashLoanFeeTooHigh => msg!("Error: Flash loan fee is too high"),
+            SwapError::AdminFeeTooHigh => msg!("Error: Admin fee is too high"),
+            SwapError::AdminRenounced => msg!("Error: Swap admin has been renounced"),
+            SwapError::UnsortedMints => msg!("Error: Token mints are not sorted"),
+            SwapError::TemplateDisabled => msg!("Error: Pool template is not enabled"),
+            SwapError::MissingReturnData => msg!("Error: Return data is missing"),
+            SwapError::ReferralFeeTooHigh => msg!("Error: Referral fee is too high"),
+            SwapError::InvalidFeeTier => {
+                msg!("Error: Fee tier does not apply to the swap or user")
+            }
+            SwapError::NotAllowlisted => msg!("Error: User is not on the allowlist of the swap"),
+            SwapError::PriceDeviationExceeded => {
+                msg!("Error: Price deviation exceeds the circuit breaker band")
+            }
+            SwapError::InvalidOracle => msg!("Error: Oracle price feed is invalid"),
+            SwapError::StaleOracle => msg!("Error: Oracle price is stale"),
+            SwapError::OracleDeviationExceeded => {
+                msg!("Error: Price deviation from the oracle exceeds the limit")
+            }
+            SwapError::UnclaimedAdminFees => msg!("Error: Pool token admin fees are unclaimed"),
+            SwapError::NonEmptyReserves => msg!("Error: Pool reserves are not empty"),
         }
     }
 }
@@ -0,0 +1,277 @@
+//! Events emitted by the program with `sol_log_data`.
+//!
+//! Every event is logged as a single Borsh-encoded [Event], which shows up in
+//! the transaction logs as a `Program data: <base64>` line.
+
+use crate::instruction::AdminInstruction;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+/// Number of decimals of [PoolState::virtual_price].
+pub const VIRTUAL_PRICE_DECIMALS: u8 = 12;
+
+/// Reserves of the pool after an event, excluding unclaimed admin fees.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Reserves {
+    /// Amount of token A in the pool
+    pub token_a: u64,
+    /// Amount of token B in the pool
+    pub token_b: u64,
+}
+
+impl From<(u64, u64)> for Reserves {
+    fn from((token_a, token_b): (u64, u64)) -> Self {
+        Self { token_a, token_b }
+    }
+}
+
+/// State of the pool after an event that changes the pool token supply.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolState {
+    /// Reserves of the pool
+    pub reserves: Reserves,
+    /// Supply of the pool token
+    pub pool_token_supply: u64,
+    /// Value of one pool token in underlying tokens, with
+    /// [VIRTUAL_PRICE_DECIMALS] decimals. Zero if the pool is empty.
+    pub virtual_price: u64,
+}
+
+/// A swap of one token of the pool for the other.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapEvent {
+    /// The StableSwap account
+    pub swap: Pubkey,
+    /// The user authority that signed the swap
+    pub user: Pubkey,
+    /// True if token A was swapped for token B
+    pub a_to_b: bool,
+    /// Amount of the source token transferred from the user
+    pub amount_in: u64,
+    /// Amount of the destination token received by the user, net of any
+    /// transfer fee
+    pub amount_out: u64,
+    /// Trade fee, in the destination token
+    pub fee: u64,
+    /// Part of the trade fee taken by the admin
+    pub admin_fee: u64,
+    /// Part of the trade fee paid to the referrer
+    pub referral_fee: u64,
+    /// Reserves of the pool after the swap
+    pub reserves: Reserves,
+    /// Virtual price of the pool after the swap, as in
+    /// [PoolState::virtual_price]. Only known if the pool token mint is passed
+    /// to the swap, see [crate::instruction::with_pool_mint].
+    pub virtual_price: Option<u64>,
+}
+
+/// A deposit of both tokens of the pool.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepositEvent {
+    /// The StableSwap account
+    pub swap: Pubkey,
+    /// The user authority that signed the deposit
+    pub user: Pubkey,
+    /// Amount of token A transferred from the user
+    pub token_a_amount: u64,
+    /// Amount of token B transferred from the user
+    pub token_b_amount: u64,
+    /// Amount of pool tokens minted to the user
+    pub pool_token_amount: u64,
+    /// State of the pool after the deposit
+    pub pool: PoolState,
+}
+
+/// A withdrawal of both tokens of the pool.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithdrawEvent {
+    /// The StableSwap account
+    pub swap: Pubkey,
+    /// The user authority that signed the withdrawal
+    pub user: Pubkey,
+    /// Amount of token A transferred to the user
+    pub token_a_amount: u64,
+    /// Amount of token B transferred to the user
+    pub token_b_amount: u64,
+    /// Withdraw fee in token A
+    pub token_a_fee: u64,
+    /// Withdraw fee in token B
+    pub token_b_fee: u64,
+    /// Amount of pool tokens burned
+    pub pool_token_amount: u64,
+    /// State of the pool after the withdrawal
+    pub pool: PoolState,
+}
+
+/// A withdrawal of one token of the pool.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithdrawOneEvent {
+    /// The StableSwap account
+    pub swap: Pubkey,
+    /// The user authority that signed the withdrawal
+    pub user: Pubkey,
+    /// Index of the withdrawn token. Token A = 0, Token B = 1.
+    pub token_index: u8,
+    /// Amount of the token received by the user, net of any transfer fee
+    pub token_amount: u64,
+    /// Trade fee of the imbalanced withdrawal
+    pub fee: u64,
+    /// Amount of pool tokens burned
+    pub pool_token_amount: u64,
+    /// State of the pool after the withdrawal
+    pub pool: PoolState,
+}
+
+/// An admin instruction that changed the swap.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdminEvent {
+    /// The StableSwap account
+    pub swap: Pubkey,
+    /// The key that signed the instruction
+    pub signer: Pubkey,
+    /// The packed [AdminInstruction]
+    pub instruction: Vec<u8>,
+}
+
+impl AdminEvent {
+    /// Unpacks the admin instruction of the event.
+    pub fn admin_instruction(&self) -> Result<AdminInstruction, ProgramError> {
+        AdminInstruction::unpack(&self.instruction)?
+            .ok_or_else(|| crate::error::SwapError::InvalidInstruction.into())
+    }
+}
+
+/// An event emitted by the program.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A [SwapEvent]
+    Swap(SwapEvent),
+    /// A [DepositEvent]
+    Deposit(DepositEvent),
+    /// A [WithdrawEvent]
+    Withdraw(WithdrawEvent),
+    /// A [WithdrawOneEvent]
+    WithdrawOne(WithdrawOneEvent),
+    /// An [AdminEvent]
+    Admin(AdminEvent),
+}
+
+impl Event {
+    /// Encodes the event as the data logged by the program.
+    pub fn encode(&self) -> Result<Vec<u8>, ProgramError> {
+        self.try_to_vec()
+            .map_err(|err| ProgramError::BorshIoError(err.to_string()))
+    }
+
+    /// Decodes an event from the data logged by the program.
+    pub fn decode(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::try_from_slice(data).map_err(|err| ProgramError::BorshIoError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::fees::Fees;
+
+    #[test]
+    fn test_event_encoding() {
+        let pool = PoolState {
+            reserves: Reserves {
+                token_a: 1_000,
+                token_b: 2_000,
+            },
+            pool_token_supply: 3_000,
+            virtual_price: 1_000_000_000_000,
+        };
+        let events = vec![
+            Event::Swap(SwapEvent {
+                swap: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                a_to_b: true,
+                amount_in: 10,
+                amount_out: 9,
+                fee: 1,
+                admin_fee: 0,
+                referral_fee: 0,
+                reserves: pool.reserves,
+                virtual_price: Some(pool.virtual_price),
+            }),
+            Event::Deposit(DepositEvent {
+                swap: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                token_a_amount: 1,
+                token_b_amount: 2,
+                pool_token_amount: 3,
+                pool,
+            }),
+            Event::Withdraw(WithdrawEvent {
+                swap: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                token_a_amount: 1,
+                token_b_amount: 2,
+                token_a_fee: 3,
+                token_b_fee: 4,
+                pool_token_amount: 5,
+                pool,
+            }),
+            Event::WithdrawOne(WithdrawOneEvent {
+                swap: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                token_index: 1,
+                token_amount: 1,
+                fee: 2,
+                pool_token_amount: 3,
+                pool,
+            }),
+        ];
+        for event in events {
+            let data = event.encode().unwrap();
+            assert_eq!(Event::decode(&data).unwrap(), event);
+        }
+
+        // the variant is the first byte, followed by the fields in order
+        let swap = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let data = Event::Swap(SwapEvent {
+            swap,
+            user,
+            a_to_b: false,
+            amount_in: 10,
+            amount_out: 9,
+            fee: 1,
+            admin_fee: 0,
+            referral_fee: 0,
+            reserves: pool.reserves,
+            virtual_price: Some(pool.virtual_price),
+        })
+        .encode()
+        .unwrap();
+        let mut expect = vec![0_u8];
+        expect.extend_from_slice(swap.as_ref());
+        expect.extend_from_slice(user.as_ref());
+        expect.push(0);
+        for amount in [10_u64, 9, 1, 0, 0, 1_000, 2_000] {
+            expect.extend_from_slice(&amount.to_le_bytes());
+        }
+        expect.push(1);
+        expect.extend_from_slice(&pool.virtual_price.to_le_bytes());
+        assert_eq!(data, expect);
+
+        let instruction = AdminInstruction::SetNewFees(Fees::default());
+        let event = Event::Admin(AdminEvent {
+            swap: Pubkey::new_unique(),
+            signer: Pubkey::new_unique(),
+            instruction: instruction.pack(),
+        });
+        let decoded = Event::decode(&event.encode().unwrap()).unwrap();
+        assert_eq!(decoded, event);
+        match decoded {
+            Event::Admin(admin) => assert_eq!(admin.admin_instruction().unwrap(), instruction),
+            _ => panic!("expected admin event"),
+        }
+
+        assert!(Event::decode(&[5]).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 //! Program fees
 
+use crate::error::SwapError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
     program_error::ProgramError,
@@ -29,6 +30,100 @@ pub struct Fees {
     pub withdraw_fee_denominator: u64,
 }
 
+/// Maximum trade fee, in basis points.
+pub const MAX_TRADE_FEE_BPS: u64 = 1_000; // 10%
+/// Maximum withdraw fee, in basis points.
+pub const MAX_WITHDRAW_FEE_BPS: u64 = 1_000; // 10%
+/// Maximum flash loan fee, in basis points.
+pub const MAX_FLASH_LOAN_FEE_BPS: u64 = 1_000; // 10%
+/// Denominator of the basis point fee maximums.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Returns true if `numerator / denominator` is at most `max_numerator / max_denominator`.
+fn fee_at_most(numerator: u64, denominator: u64, max_numerator: u64, max_denominator: u64) -> bool {
+    u128::from(numerator).saturating_mul(u128::from(max_denominator))
+        <= u128::from(max_numerator).saturating_mul(u128::from(denominator))
+}
+
+/// Returns the greatest common divisor of `a` and `b`.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while let Some(remainder) = a.checked_rem(b) {
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+impl Fees {
+    /// Checks that the fees can be applied by the program.
+    ///
+    /// Every denominator must be non-zero, the trade and withdraw fees must not
+    /// exceed their maximums, and the admin fees may take at most 100% of the fee
+    /// they apply to.
+    pub fn validate(&self) -> Result<(), SwapError> {
+        if self.admin_trade_fee_denominator == 0
+            || self.admin_withdraw_fee_denominator == 0
+            || self.trade_fee_denominator == 0
+            || self.withdraw_fee_denominator == 0
+        {
+            return Err(SwapError::ZeroFeeDenominator);
+        }
+        if !fee_at_most(
+            self.trade_fee_numerator,
+            self.trade_fee_denominator,
+            MAX_TRADE_FEE_BPS,
+            BPS_DENOMINATOR,
+        ) {
+            return Err(SwapError::TradeFeeTooHigh);
+        }
+        if !fee_at_most(
+            self.withdraw_fee_numerator,
+            self.withdraw_fee_denominator,
+            MAX_WITHDRAW_FEE_BPS,
+            BPS_DENOMINATOR,
+        ) {
+            return Err(SwapError::WithdrawFeeTooHigh);
+        }
+        if self.admin_trade_fee_numerator > self.admin_trade_fee_denominator
+            || self.admin_withdraw_fee_numerator > self.admin_withdraw_fee_denominator
+        {
+            return Err(SwapError::AdminFeeTooHigh);
+        }
+        Ok(())
+    }
+
+    /// Returns the fees with the trade fee scaled by `fee_multiplier_bps` basis
+    /// points, the fees paid by traders of a [crate::state::FeeTier].
+    ///
+    /// The multiplier is applied exactly to both terms of the trade fee where the
+    /// scaled denominator fits, and to the numerator alone otherwise, rounded up
+    /// so that the discount never exceeds the multiplier.
+    pub fn with_trade_fee_multiplier(&self, fee_multiplier_bps: u16) -> Option<Fees> {
+        let divisor = gcd(u64::from(fee_multiplier_bps), BPS_DENOMINATOR);
+        let multiplier = u64::from(fee_multiplier_bps).checked_div(divisor)?;
+        let denominator = BPS_DENOMINATOR.checked_div(divisor)?;
+        let (trade_fee_numerator, trade_fee_denominator) =
+            match self.trade_fee_denominator.checked_mul(denominator) {
+                Some(trade_fee_denominator) => (
+                    self.trade_fee_numerator.checked_mul(multiplier)?,
+                    trade_fee_denominator,
+                ),
+                None => {
+                    let scaled = u128::from(self.trade_fee_numerator)
+                        .checked_mul(multiplier.into())?
+                        .checked_add(u128::from(denominator).checked_sub(1)?)?
+                        .checked_div(denominator.into())?;
+                    (u64::try_from(scaled).ok()?, self.trade_fee_denominator)
+                }
+            };
+        Some(Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            ..*self
+        })
+    }
+}
+
 impl Sealed for Fees {}
 impl Pack for Fees {
     const LEN: usize = 64;
@@ -80,6 +175,98 @@ impl Pack for Fees {
     }
 }
 
+/// Fees added after the layout of [Fees], which swaps store in the versioned
+/// layout of [crate::state::SwapInfo].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct ExtraFees {
+    /// Flash loan fee numerator
+    pub flash_loan_fee_numerator: u64,
+    /// Flash loan fee denominator. Zero if flash loans are disabled.
+    pub flash_loan_fee_denominator: u64,
+    /// Referral fee numerator, the share of the trade fee paid to the referrer of a swap
+    pub referral_fee_numerator: u64,
+    /// Referral fee denominator. Zero if no referral fee is charged.
+    pub referral_fee_denominator: u64,
+}
+
+impl ExtraFees {
+    /// Returns true if flash loans can be taken from the swap.
+    pub fn is_flash_loan_enabled(&self) -> bool {
+        self.flash_loan_fee_denominator != 0
+    }
+
+    /// Checks that the fees can be applied by the program along with `fees`.
+    ///
+    /// A flash loan fee of 0/0 disables flash loans and a referral fee of 0/0
+    /// charges no referral fee; other fees must have a non-zero denominator. The
+    /// flash loan fee must not exceed its maximum, and the admin trade fee and
+    /// the referral fee together may take at most 100% of the trade fee.
+    pub fn validate(&self, fees: &Fees) -> Result<(), SwapError> {
+        if (self.flash_loan_fee_denominator == 0 && self.flash_loan_fee_numerator != 0)
+            || (self.referral_fee_denominator == 0 && self.referral_fee_numerator != 0)
+        {
+            return Err(SwapError::ZeroFeeDenominator);
+        }
+        if !fee_at_most(
+            self.flash_loan_fee_numerator,
+            self.flash_loan_fee_denominator,
+            MAX_FLASH_LOAN_FEE_BPS,
+            BPS_DENOMINATOR,
+        ) {
+            return Err(SwapError::FlashLoanFeeTooHigh);
+        }
+        let referral_and_admin_share = u128::from(self.referral_fee_numerator)
+            .saturating_mul(u128::from(fees.admin_trade_fee_denominator))
+            .saturating_add(
+                u128::from(fees.admin_trade_fee_numerator)
+                    .saturating_mul(u128::from(self.referral_fee_denominator)),
+            );
+        if referral_and_admin_share
+            > u128::from(fees.admin_trade_fee_denominator)
+                .saturating_mul(u128::from(self.referral_fee_denominator))
+        {
+            return Err(SwapError::ReferralFeeTooHigh);
+        }
+        Ok(())
+    }
+}
+
+impl Sealed for ExtraFees {}
+impl Pack for ExtraFees {
+    const LEN: usize = 32;
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 32];
+        let (
+            flash_loan_fee_numerator,
+            flash_loan_fee_denominator,
+            referral_fee_numerator,
+            referral_fee_denominator,
+        ) = array_refs![input, 8, 8, 8, 8];
+        Ok(Self {
+            flash_loan_fee_numerator: u64::from_le_bytes(*flash_loan_fee_numerator),
+            flash_loan_fee_denominator: u64::from_le_bytes(*flash_loan_fee_denominator),
+            referral_fee_numerator: u64::from_le_bytes(*referral_fee_numerator),
+            referral_fee_denominator: u64::from_le_bytes(*referral_fee_denominator),
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 32];
+        let (
+            flash_loan_fee_numerator,
+            flash_loan_fee_denominator,
+            referral_fee_numerator,
+            referral_fee_denominator,
+        ) = mut_array_refs![output, 8, 8, 8, 8];
+        *flash_loan_fee_numerator = self.flash_loan_fee_numerator.to_le_bytes();
+        *flash_loan_fee_denominator = self.flash_loan_fee_denominator.to_le_bytes();
+        *referral_fee_numerator = self.referral_fee_numerator.to_le_bytes();
+        *referral_fee_denominator = self.referral_fee_denominator.to_le_bytes();
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -123,4 +310,197 @@ mod tests {
         let unpacked = Fees::unpack_from_slice(&packed).unwrap();
         assert_eq!(fees, unpacked);
     }
+
+    #[test]
+    fn pack_extra_fees() {
+        let flash_loan_fee_numerator = 1;
+        let flash_loan_fee_denominator = 2;
+        let referral_fee_numerator = 3;
+        let referral_fee_denominator = 4;
+        let extra_fees = ExtraFees {
+            flash_loan_fee_numerator,
+            flash_loan_fee_denominator,
+            referral_fee_numerator,
+            referral_fee_denominator,
+        };
+
+        let mut packed = vec![];
+        packed.extend_from_slice(&flash_loan_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&flash_loan_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&referral_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&referral_fee_denominator.to_le_bytes());
+        assert_eq!(ExtraFees::unpack_from_slice(&packed).unwrap(), extra_fees);
+        let mut repacked = [0u8; ExtraFees::LEN];
+        extra_fees.pack_into_slice(&mut repacked);
+        assert_eq!(repacked[..], packed[..]);
+    }
+
+    #[test]
+    fn validate_fees() {
+        let fees = Fees {
+            admin_trade_fee_numerator: 1,
+            admin_trade_fee_denominator: 2,
+            admin_withdraw_fee_numerator: 1,
+            admin_withdraw_fee_denominator: 1,
+            trade_fee_numerator: 4,
+            trade_fee_denominator: 10_000,
+            withdraw_fee_numerator: 0,
+            withdraw_fee_denominator: 10_000,
+        };
+        assert_eq!(fees.validate(), Ok(()));
+
+        let zero_denominator = Fees {
+            withdraw_fee_denominator: 0,
+            ..fees
+        };
+        assert_eq!(
+            zero_denominator.validate(),
+            Err(SwapError::ZeroFeeDenominator)
+        );
+
+        let high_trade_fee = Fees {
+            trade_fee_numerator: MAX_TRADE_FEE_BPS + 1,
+            ..fees
+        };
+        assert_eq!(high_trade_fee.validate(), Err(SwapError::TradeFeeTooHigh));
+        let max_trade_fee = Fees {
+            trade_fee_numerator: MAX_TRADE_FEE_BPS,
+            ..fees
+        };
+        assert_eq!(max_trade_fee.validate(), Ok(()));
+
+        let high_withdraw_fee = Fees {
+            withdraw_fee_numerator: 1,
+            withdraw_fee_denominator: 2,
+            ..fees
+        };
+        assert_eq!(
+            high_withdraw_fee.validate(),
+            Err(SwapError::WithdrawFeeTooHigh)
+        );
+
+        let high_admin_fee = Fees {
+            admin_trade_fee_numerator: 3,
+            admin_trade_fee_denominator: 2,
+            ..fees
+        };
+        assert_eq!(high_admin_fee.validate(), Err(SwapError::AdminFeeTooHigh));
+    }
+
+    #[test]
+    fn validate_extra_fees() {
+        // half of the trade fee to the admin
+        let fees = Fees {
+            admin_trade_fee_numerator: 1,
+            admin_trade_fee_denominator: 2,
+            trade_fee_numerator: 4,
+            trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let extra_fees = ExtraFees {
+            flash_loan_fee_numerator: 1,
+            flash_loan_fee_denominator: 10,
+            referral_fee_numerator: 1,
+            referral_fee_denominator: 10,
+        };
+        assert_eq!(extra_fees.validate(&fees), Ok(()));
+        // flash loans disabled and no referral fee
+        assert_eq!(ExtraFees::default().validate(&fees), Ok(()));
+
+        let zero_flash_loan_denominator = ExtraFees {
+            flash_loan_fee_denominator: 0,
+            ..extra_fees
+        };
+        assert_eq!(
+            zero_flash_loan_denominator.validate(&fees),
+            Err(SwapError::ZeroFeeDenominator)
+        );
+        let zero_referral_denominator = ExtraFees {
+            referral_fee_denominator: 0,
+            ..extra_fees
+        };
+        assert_eq!(
+            zero_referral_denominator.validate(&fees),
+            Err(SwapError::ZeroFeeDenominator)
+        );
+
+        let high_flash_loan_fee = ExtraFees {
+            flash_loan_fee_numerator: u64::MAX,
+            flash_loan_fee_denominator: u64::MAX - 1,
+            ..extra_fees
+        };
+        assert_eq!(
+            high_flash_loan_fee.validate(&fees),
+            Err(SwapError::FlashLoanFeeTooHigh)
+        );
+
+        // half of the trade fee to the admin and half to the referrer
+        let max_referral_fee = ExtraFees {
+            referral_fee_numerator: 1,
+            referral_fee_denominator: 2,
+            ..extra_fees
+        };
+        assert_eq!(max_referral_fee.validate(&fees), Ok(()));
+        let high_referral_fee = ExtraFees {
+            referral_fee_numerator: 6,
+            referral_fee_denominator: 10,
+            ..extra_fees
+        };
+        assert_eq!(
+            high_referral_fee.validate(&fees),
+            Err(SwapError::ReferralFeeTooHigh)
+        );
+    }
+
+    #[test]
+    fn trade_fee_multiplier() {
+        let fees = Fees {
+            trade_fee_numerator: 4,
+            trade_fee_denominator: 10_000,
+            withdraw_fee_numerator: 1,
+            withdraw_fee_denominator: 100,
+            ..Fees::default()
+        };
+        // a quarter of the trade fee
+        let discounted = fees.with_trade_fee_multiplier(2_500).unwrap();
+        assert_eq!(discounted.trade_fee_numerator, 4);
+        assert_eq!(discounted.trade_fee_denominator, 40_000);
+        assert_eq!(discounted.withdraw_fee_numerator, 1);
+        assert_eq!(discounted.withdraw_fee_denominator, 100);
+        assert_eq!(
+            fees.with_trade_fee_multiplier(0)
+                .unwrap()
+                .trade_fee_numerator,
+            0
+        );
+
+        // the fraction of the multiplier is reduced before scaling the fee
+        let discounted = fees.with_trade_fee_multiplier(5_000).unwrap();
+        assert_eq!(discounted.trade_fee_numerator, 4);
+        assert_eq!(discounted.trade_fee_denominator, 20_000);
+
+        // the numerator alone is scaled if the denominator cannot be, rounding up
+        let large_denominator = Fees {
+            trade_fee_numerator: 3,
+            trade_fee_denominator: u64::MAX,
+            ..fees
+        };
+        let discounted = large_denominator.with_trade_fee_multiplier(2_500).unwrap();
+        assert_eq!(discounted.trade_fee_numerator, 1);
+        assert_eq!(discounted.trade_fee_denominator, u64::MAX);
+        let large_numerator = Fees {
+            trade_fee_numerator: u64::MAX / 10,
+            ..large_denominator
+        };
+        let discounted = large_numerator.with_trade_fee_multiplier(9_999).unwrap();
+        // 1_844_674_407_370_955_161 * 9_999 / 10_000 = 1_844_489_939_930_218_065.98
+        assert_eq!(discounted.trade_fee_numerator, 1_844_489_939_930_218_066);
+        assert_eq!(
+            large_numerator
+                .with_trade_fee_multiplier(10_000)
+                .unwrap()
+                .trade_fee_numerator,
+            u64::MAX / 10
+        );
+    }
 }
@@ -3,12 +3,19 @@
 #![allow(clippy::too_many_arguments)]
 
 use crate::error::SwapError;
-use crate::fees::Fees;
+use crate::fees::{ExtraFees, Fees};
+use crate::pda::{
+    find_allowlist_entry_address, find_factory_address, find_fee_tier_address, find_gate_address,
+    find_registry_address, find_template_address, PoolAddresses,
+};
+use crate::state::{AdminFeeMode, AdminRole, PauseFlags};
 use solana_program::{
+    bpf_loader_upgradeable,
     instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
+    system_program,
 };
 use std::mem::size_of;
 
@@ -25,6 +32,50 @@ pub struct InitializeData {
     pub fees: Fees,
 }
 
+/// InitializeV2 instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct InitializeV2Data {
+    /// Amplification coefficient (A)
+    pub amp_factor: u64,
+    /// Fees
+    pub fees: Fees,
+    /// Amount of token A to transfer into the reserves
+    pub token_a_amount: u64,
+    /// Amount of token B to transfer into the reserves
+    pub token_b_amount: u64,
+}
+
+/// QuoteSwap instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct QuoteSwapData {
+    /// SOURCE amount to quote the swap of
+    pub amount_in: u64,
+}
+
+/// QuoteDeposit instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct QuoteDepositData {
+    /// Amount of token A to quote the deposit of
+    pub token_a_amount: u64,
+    /// Amount of token B to quote the deposit of
+    pub token_b_amount: u64,
+}
+
+/// QuoteWithdrawOne instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct QuoteWithdrawOneData {
+    /// Amount of pool tokens to quote the withdrawal of
+    pub pool_token_amount: u64,
+}
+
 /// Swap instruction data
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -75,6 +126,53 @@ pub struct WithdrawOneData {
     pub minimum_token_amount: u64,
 }
 
+/// FlashLoan instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct FlashLoanData {
+    /// Amount of reserve tokens to lend to the receiver
+    pub amount: u64,
+}
+
+/// Data passed to the flash loan receiver program.
+///
+/// The receiver must transfer `amount + fee` back into the reserve
+/// before returning.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReceiveFlashLoanData {
+    /// Amount of tokens lent
+    pub amount: u64,
+    /// Fee owed on top of the lent amount
+    pub fee: u64,
+}
+
+impl ReceiveFlashLoanData {
+    /// Instruction tag sent to the flash loan receiver program.
+    pub const TAG: u8 = 0;
+
+    /// Unpacks a byte buffer into a [ReceiveFlashLoanData].
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input.split_first().ok_or(SwapError::InvalidInstruction)?;
+        if tag != Self::TAG {
+            return Err(SwapError::InvalidInstruction.into());
+        }
+        let (amount, rest) = unpack_u64(rest)?;
+        let (fee, _rest) = unpack_u64(rest)?;
+        Ok(Self { amount, fee })
+    }
+
+    /// Packs a [ReceiveFlashLoanData] into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(17);
+        buf.push(Self::TAG);
+        buf.extend_from_slice(&self.amount.to_le_bytes());
+        buf.extend_from_slice(&self.fee.to_le_bytes());
+        buf
+    }
+}
+
 /// RampA instruction data
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -86,6 +184,76 @@ pub struct RampAData {
     pub stop_ramp_ts: i64,
 }
 
+/// SetCircuitBreaker instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct CircuitBreakerData {
+    /// Maximum deviation of the price within a window, in basis points. Zero
+    /// disables the circuit breaker.
+    pub max_price_deviation_bps: u16,
+    /// Length of a window, in slots
+    pub window_slots: u64,
+}
+
+/// SetPriceOracle instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct PriceOracleData {
+    /// Maximum deviation of the price after a swap from the oracle price, in
+    /// basis points
+    pub max_deviation_bps: u16,
+    /// Maximum age of the oracle price, in slots
+    pub max_staleness_slots: u64,
+}
+
+/// Maximum length of a packed [AdminInstruction] that can be queued.
+pub const MAX_ADMIN_ACTION_LEN: usize = 1 + Fees::LEN;
+
+/// A packed [AdminInstruction] queued in the admin action timelock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdminActionData {
+    len: u8,
+    data: [u8; MAX_ADMIN_ACTION_LEN],
+}
+
+impl AdminActionData {
+    /// Packs an admin instruction to be queued.
+    pub fn new(action: &AdminInstruction) -> Result<Self, ProgramError> {
+        if !action.can_be_queued() {
+            return Err(SwapError::InvalidInstruction.into());
+        }
+        Self::from_bytes(&action.pack())
+    }
+
+    /// Wraps a packed admin instruction.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() > MAX_ADMIN_ACTION_LEN {
+            return Err(SwapError::InvalidInstruction.into());
+        }
+        let mut data = [0u8; MAX_ADMIN_ACTION_LEN];
+        data[..bytes.len()].copy_from_slice(bytes);
+        Ok(Self {
+            len: bytes.len() as u8,
+            data,
+        })
+    }
+
+    /// The packed admin instruction.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..usize::from(self.len)]
+    }
+
+    /// Unpacks the queued admin instruction.
+    pub fn unpack(&self) -> Result<AdminInstruction, ProgramError> {
+        let action = AdminInstruction::unpack(self.as_bytes())?
+            .filter(AdminInstruction::can_be_queued)
+            .ok_or(SwapError::InvalidInstruction)?;
+        Ok(action)
+    }
+}
+
 /// Admin only instructions.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -102,13 +270,14 @@ pub enum AdminInstruction {
     /// 1. `[signer]` Admin account
     StopRampA,
 
-    /// Pauses swap, deposit, and withdraw_one.
+    /// Pauses swap, deposit, withdraw_one, and flash loans. Other paused
+    /// operations stay paused. May also be signed by the guardian.
     ///
     /// 0. `[writable]` StableSwap
-    /// 1. `[signer]` Admin account
+    /// 1. `[signer]` Admin or guardian account
     Pause,
 
-    /// Unpauses the swap.
+    /// Unpauses all operations of the swap.
     ///
     /// 0. `[writable]` StableSwap
     /// 1. `[signer]` Admin account
@@ -118,16 +287,21 @@ pub enum AdminInstruction {
     ///
     /// 0. `[writable]` StableSwap
     /// 1. `[signer]` Admin account
-    /// 2. `[]` Token account to receive fees. Must have mint of Token A or Token B.
+    /// 2. `[]` Token account to receive fees. Must have mint of Token A, Token B, or the pool token.
     SetFeeAccount,
 
-    /// Finalizes the admin transfer. This is run after CommitNewAdmin.
+    /// Finalizes the admin transfer, signed by both the admin and the new admin.
+    /// This is run after CommitNewAdmin.
+    ///
+    /// Deprecated: kept for existing scripts, which must now also have the new
+    /// admin sign. Use [AdminInstruction::AcceptAdmin] instead.
     ///
     /// 0. `[writable]` StableSwap
     /// 1. `[signer]` Admin account
+    /// 2. `[signer]` New admin account
     ApplyNewAdmin,
 
-    /// Commits a new admin. The admin must accept ownership within 3 days.
+    /// Commits a new admin. The new admin must accept ownership within 3 days.
     ///
     /// 0. `[writable]` StableSwap
     /// 1. `[signer]` Admin account
@@ -139,6 +313,207 @@ pub enum AdminInstruction {
     /// 0. `[writable]` StableSwap
     /// 1. `[signer]` Admin account
     SetNewFees(Fees),
+
+    /// Updates the flash loan and referral fees of the swap.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    SetExtraFees(ExtraFees),
+
+    /// Sets how admin fees are collected. Switching to [AdminFeeMode::LpMint]
+    /// records the invariant that the growth of the pool is measured from.
+    /// Switching from it fails until the pool token admin fees for that
+    /// growth are claimed with [SwapInstruction::ClaimAdminFees].
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[]` token_a Base Account.
+    /// 3. `[]` token_b Base Account.
+    /// 4. `[]` Pool MINT account.
+    SetAdminFeeMode(AdminFeeMode),
+
+    /// Closes a pool with no outstanding pool tokens and no reserves apart
+    /// from accrued admin fees. The accrued admin fees are sent to the admin
+    /// fee accounts, then the reserve token accounts and the swap account are
+    /// closed and their rent is returned to the admin. Transfer fees withheld in the reserves of Token-2022
+    /// pools are first harvested to the mints, which are passed writable.
+    ///
+    /// The pool mint stays open with no supply. Its mint authority remains
+    /// the swap authority, which nothing can sign for once the swap is closed.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer, writable]` Admin account
+    /// 2. `[]` $authority
+    /// 3. `[]` Pool token mint
+    /// 4. `[writable]` token_a Base Account
+    /// 5. `[writable]` token_b Base Account
+    /// 6. `[writable]` admin fee Account for token_a
+    /// 7. `[writable]` admin fee Account for token_b
+    /// 8. `[]` Token program id
+    ClosePool,
+
+    /// Sets the operations of the swap that are paused, replacing the current flags.
+    /// May also be signed by the guardian if no operation is unpaused.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin or guardian account
+    SetPauseFlags(PauseFlags),
+
+    /// Sets the guardian, which may pause the swap. The default pubkey removes the guardian.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[]` New guardian account
+    SetGuardian,
+
+    /// Grants a delegated role to an account, replacing its current holder.
+    /// The admin role cannot be granted.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[]` Account to grant the role to
+    GrantRole(AdminRole),
+
+    /// Revokes a delegated role from its holder.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    RevokeRole(AdminRole),
+
+    /// Sets the delay of the admin action timelock. While the delay is non-zero,
+    /// instructions that change parameters of the swap must be queued with
+    /// [AdminInstruction::QueueAdminAction]. A non-zero delay requires an
+    /// initialized admin queue.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    SetTimelockDelay(i64),
+
+    /// Initializes the admin action queue of the swap.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[writable]` Uninitialized admin queue account owned by the program
+    InitializeAdminQueue,
+
+    /// Queues an admin instruction to be executed after the timelock delay.
+    /// Must be signed by a role allowed to run the queued instruction.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin or role account
+    /// 2. `[writable]` Admin queue account
+    /// 3. ..3+N. `[]` Accounts of the queued instruction after the signer
+    QueueAdminAction(AdminActionData),
+
+    /// Cancels a queued admin action.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[writable]` Admin queue account
+    CancelAdminAction(u8),
+
+    /// Executes a queued admin action once its execution time has passed and
+    /// before it expires. Must be signed by a role allowed to run the queued
+    /// instruction.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[writable, signer]` Admin or role account, receives the rent of a queued ClosePool.
+    /// 2. `[writable]` Admin queue account
+    /// 3. ..3+N. `[]` Accounts of the queued instruction after the signer, as
+    ///    queued, with the signer and writable flags of the queued instruction
+    ExecuteAdminAction(u8),
+
+    /// Finalizes the admin transfer. Must be signed by the new admin before the
+    /// deadline set by CommitNewAdmin.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` New admin account
+    AcceptAdmin,
+
+    /// Cancels the admin transfer started by CommitNewAdmin.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    CancelAdminTransfer,
+
+    /// Permanently renounces the admin of the swap. Every admin instruction
+    /// fails afterwards, including those of the guardian and delegated roles.
+    /// Fails if any operation is paused. The circuit breaker keeps its band and
+    /// window, and resets at the start of every window.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    RenounceAdmin,
+
+    /// Reallocates a swap in the legacy layout of [crate::state::SwapInfo::LEGACY_LEN]
+    /// bytes to the versioned layout. The payer funds the additional rent.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[writable, signer]` Payer account
+    /// 3. `[]` System program
+    MigrateSwapInfo,
+
+    /// Creates or updates the fee tier of a user for the swap, which scales the
+    /// trade fee of the swaps of the user by a multiplier in basis points. When
+    /// executed from the admin queue, the payer must sign the transaction.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[writable, signer]` Payer account, pays for new fee tiers.
+    /// 3. `[writable]` Fee tier, derived from the swap and the user authority.
+    /// 4. `[]` User authority
+    /// 5. `[]` System program
+    SetFeeTier(u16),
+
+    /// Closes a fee tier of the swap and returns its rent.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[writable]` Fee tier
+    /// 3. `[writable]` Account to return the rent to
+    RemoveFeeTier,
+
+    /// Sets the gate of the swap. Only the users on the allowlist of the gate
+    /// may swap, deposit and withdraw afterwards. The default pubkey makes the
+    /// swap permissionless.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[]` Gate account, or the default pubkey
+    SetGate,
+
+    /// Configures the circuit breaker of the swap, which rejects swaps,
+    /// deposits and single sided withdrawals that move the marginal price
+    /// further than the band from the price at the start of the current
+    /// window of slots.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    SetCircuitBreaker(CircuitBreakerData),
+
+    /// Resets the reference price of the circuit breaker, so that the next
+    /// swap starts a new window at the current price. May also be signed by
+    /// the guardian.
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin or guardian account
+    ResetCircuitBreaker,
+
+    /// Sets the price feed of the swap, see [crate::oracle::PriceFeed]. Swaps
+    /// must then pass the feed and are rejected if the price of the feed is
+    /// stale or if the marginal price after the swap deviates too far from it,
+    /// unless the swap moves the price closer to it. The band is widened by
+    /// the confidence interval of the feed. The default pubkey removes the
+    /// price feed. The feed must be owned by
+    /// the [crate::oracle::pyth_program].
+    ///
+    /// 0. `[writable]` StableSwap
+    /// 1. `[signer]` Admin account
+    /// 2. `[]` Price feed of token A in token B, or the default pubkey
+    /// 3. `[]` token_a Mint. Only read if the price feed is set.
+    /// 4. `[]` token_b Mint. Only read if the price feed is set.
+    SetPriceOracle(PriceOracleData),
 }
 
 impl AdminInstruction {
@@ -164,10 +539,190 @@ impl AdminInstruction {
                 let fees = Fees::unpack_unchecked(rest)?;
                 Some(Self::SetNewFees(fees))
             }
+            108 => {
+                let extra_fees = ExtraFees::unpack_unchecked(rest)?;
+                Some(Self::SetExtraFees(extra_fees))
+            }
+            109 => {
+                let (&mode, _rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                let mode = AdminFeeMode::from_u8(mode).ok_or(SwapError::InvalidInstruction)?;
+                Some(Self::SetAdminFeeMode(mode))
+            }
+            110 => Some(Self::ClosePool),
+            111 => {
+                let (&flags, _rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                let flags = PauseFlags::from_bits(flags).ok_or(SwapError::InvalidInstruction)?;
+                Some(Self::SetPauseFlags(flags))
+            }
+            112 => Some(Self::SetGuardian),
+            113 | 114 => {
+                let (&role, _rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                let role = AdminRole::from_u8(role).ok_or(SwapError::InvalidInstruction)?;
+                Some(if tag == 113 {
+                    Self::GrantRole(role)
+                } else {
+                    Self::RevokeRole(role)
+                })
+            }
+            115 => {
+                let (delay, _rest) = unpack_i64(rest)?;
+                Some(Self::SetTimelockDelay(delay))
+            }
+            116 => Some(Self::InitializeAdminQueue),
+            117 => {
+                let action = AdminActionData::from_bytes(rest)?;
+                action.unpack()?;
+                Some(Self::QueueAdminAction(action))
+            }
+            118 | 119 => {
+                let (&index, _rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                Some(if tag == 118 {
+                    Self::CancelAdminAction(index)
+                } else {
+                    Self::ExecuteAdminAction(index)
+                })
+            }
+            120 => Some(Self::AcceptAdmin),
+            121 => Some(Self::CancelAdminTransfer),
+            122 => Some(Self::RenounceAdmin),
+            123 => Some(Self::MigrateSwapInfo),
+            124 => {
+                let (fee_multiplier_bps, _rest) = unpack_u16(rest)?;
+                Some(Self::SetFeeTier(fee_multiplier_bps))
+            }
+            125 => Some(Self::RemoveFeeTier),
+            126 => Some(Self::SetGate),
+            127 => {
+                let (max_price_deviation_bps, rest) = unpack_u16(rest)?;
+                let (window_slots, _rest) = unpack_u64(rest)?;
+                Some(Self::SetCircuitBreaker(CircuitBreakerData {
+                    max_price_deviation_bps,
+                    window_slots,
+                }))
+            }
+            128 => Some(Self::ResetCircuitBreaker),
+            129 => {
+                let (max_deviation_bps, rest) = unpack_u16(rest)?;
+                let (max_staleness_slots, _rest) = unpack_u64(rest)?;
+                Some(Self::SetPriceOracle(PriceOracleData {
+                    max_deviation_bps,
+                    max_staleness_slots,
+                }))
+            }
             _ => None,
         })
     }
 
+    /// Roles that may sign the instruction. The admin may sign every instruction.
+    pub fn allowed_roles(&self) -> &'static [AdminRole] {
+        match self {
+            Self::RampA(_) | Self::StopRampA => &[AdminRole::Admin, AdminRole::AmpManager],
+            Self::Pause | Self::SetPauseFlags(_) => &[
+                AdminRole::Admin,
+                AdminRole::PauseManager,
+                AdminRole::Guardian,
+            ],
+            Self::Unpause | Self::SetCircuitBreaker(_) => {
+                &[AdminRole::Admin, AdminRole::PauseManager]
+            }
+            Self::ResetCircuitBreaker => &[
+                AdminRole::Admin,
+                AdminRole::PauseManager,
+                AdminRole::Guardian,
+            ],
+            Self::SetFeeAccount => &[AdminRole::Admin, AdminRole::FeeAccountManager],
+            Self::SetNewFees(_)
+            | Self::SetExtraFees(_)
+            | Self::SetAdminFeeMode(_)
+            | Self::SetFeeTier(_)
+            | Self::RemoveFeeTier => &[AdminRole::Admin, AdminRole::FeeManager],
+            Self::QueueAdminAction(action) => action
+                .unpack()
+                .map(|action| action.allowed_roles())
+                .unwrap_or(&[AdminRole::Admin]),
+            // the signer is checked against the roles of the queued instruction
+            Self::ExecuteAdminAction(_) => &[
+                AdminRole::Admin,
+                AdminRole::Guardian,
+                AdminRole::FeeManager,
+                AdminRole::AmpManager,
+                AdminRole::FeeAccountManager,
+                AdminRole::PauseManager,
+            ],
+            // the signer is checked against the future admin
+            Self::AcceptAdmin => &[],
+            Self::ApplyNewAdmin
+            | Self::CommitNewAdmin
+            | Self::CancelAdminTransfer
+            | Self::RenounceAdmin
+            | Self::ClosePool
+            | Self::SetGuardian
+            | Self::GrantRole(_)
+            | Self::RevokeRole(_)
+            | Self::SetTimelockDelay(_)
+            | Self::InitializeAdminQueue
+            | Self::CancelAdminAction(_)
+            | Self::MigrateSwapInfo
+            | Self::SetGate
+            | Self::SetPriceOracle(_) => &[AdminRole::Admin],
+        }
+    }
+
+    /// Returns true if the instruction must be queued while the timelock is
+    /// enabled. Every instruction that changes the swap or who administers it
+    /// is timelocked, except:
+    ///
+    /// - StopRampA, which only freezes A at its current value.
+    /// - Pause, Unpause, SetPauseFlags and ResetCircuitBreaker, which respond
+    ///   to incidents and must take effect at once.
+    /// - ApplyNewAdmin, AcceptAdmin and CancelAdminTransfer, which complete or
+    ///   cancel a transfer started by the timelocked CommitNewAdmin.
+    /// - InitializeAdminQueue, QueueAdminAction, CancelAdminAction and
+    ///   ExecuteAdminAction, which operate the timelock itself.
+    /// - MigrateSwapInfo, which only changes the layout of the swap account.
+    pub fn requires_timelock(&self) -> bool {
+        match self {
+            Self::RampA(_)
+            | Self::SetFeeAccount
+            | Self::CommitNewAdmin
+            | Self::SetNewFees(_)
+            | Self::SetExtraFees(_)
+            | Self::SetAdminFeeMode(_)
+            | Self::ClosePool
+            | Self::SetGuardian
+            | Self::GrantRole(_)
+            | Self::RevokeRole(_)
+            | Self::SetTimelockDelay(_)
+            | Self::RenounceAdmin
+            | Self::SetFeeTier(_)
+            | Self::RemoveFeeTier
+            | Self::SetGate
+            | Self::SetCircuitBreaker(_)
+            | Self::SetPriceOracle(_) => true,
+            Self::StopRampA
+            | Self::Pause
+            | Self::Unpause
+            | Self::SetPauseFlags(_)
+            | Self::ResetCircuitBreaker
+            | Self::ApplyNewAdmin
+            | Self::AcceptAdmin
+            | Self::CancelAdminTransfer
+            | Self::InitializeAdminQueue
+            | Self::QueueAdminAction(_)
+            | Self::CancelAdminAction(_)
+            | Self::ExecuteAdminAction(_)
+            | Self::MigrateSwapInfo => false,
+        }
+    }
+
+    /// Returns true if the instruction can be queued in the admin action
+    /// timelock. Only timelocked instructions can be queued, so that roles
+    /// which may only run instructions taking effect at once, like the
+    /// guardian, cannot fill the queue.
+    pub fn can_be_queued(&self) -> bool {
+        self.requires_timelock()
+    }
+
     /// Packs a [AdminInstruction](enum.AdminInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
@@ -192,6 +747,74 @@ impl AdminInstruction {
                 Pack::pack_into_slice(&fees, &mut fees_slice[..]);
                 buf.extend_from_slice(&fees_slice);
             }
+            Self::SetExtraFees(extra_fees) => {
+                buf.push(108);
+                let mut extra_fees_slice = [0u8; ExtraFees::LEN];
+                Pack::pack_into_slice(&extra_fees, &mut extra_fees_slice[..]);
+                buf.extend_from_slice(&extra_fees_slice);
+            }
+            Self::SetAdminFeeMode(mode) => {
+                buf.push(109);
+                buf.push(mode as u8);
+            }
+            Self::ClosePool => buf.push(110),
+            Self::SetPauseFlags(flags) => {
+                buf.push(111);
+                buf.push(flags.bits());
+            }
+            Self::SetGuardian => buf.push(112),
+            Self::GrantRole(role) => {
+                buf.push(113);
+                buf.push(role as u8);
+            }
+            Self::RevokeRole(role) => {
+                buf.push(114);
+                buf.push(role as u8);
+            }
+            Self::SetTimelockDelay(delay) => {
+                buf.push(115);
+                buf.extend_from_slice(&delay.to_le_bytes());
+            }
+            Self::InitializeAdminQueue => buf.push(116),
+            Self::QueueAdminAction(action) => {
+                buf.push(117);
+                buf.extend_from_slice(action.as_bytes());
+            }
+            Self::CancelAdminAction(index) => {
+                buf.push(118);
+                buf.push(index);
+            }
+            Self::ExecuteAdminAction(index) => {
+                buf.push(119);
+                buf.push(index);
+            }
+            Self::AcceptAdmin => buf.push(120),
+            Self::CancelAdminTransfer => buf.push(121),
+            Self::RenounceAdmin => buf.push(122),
+            Self::MigrateSwapInfo => buf.push(123),
+            Self::SetFeeTier(fee_multiplier_bps) => {
+                buf.push(124);
+                buf.extend_from_slice(&fee_multiplier_bps.to_le_bytes());
+            }
+            Self::RemoveFeeTier => buf.push(125),
+            Self::SetGate => buf.push(126),
+            Self::SetCircuitBreaker(CircuitBreakerData {
+                max_price_deviation_bps,
+                window_slots,
+            }) => {
+                buf.push(127);
+                buf.extend_from_slice(&max_price_deviation_bps.to_le_bytes());
+                buf.extend_from_slice(&window_slots.to_le_bytes());
+            }
+            Self::ResetCircuitBreaker => buf.push(128),
+            Self::SetPriceOracle(PriceOracleData {
+                max_deviation_bps,
+                max_staleness_slots,
+            }) => {
+                buf.push(129);
+                buf.extend_from_slice(&max_deviation_bps.to_le_bytes());
+                buf.extend_from_slice(&max_staleness_slots.to_le_bytes());
+            }
         }
         buf
     }
@@ -241,7 +864,7 @@ pub fn stop_ramp_a(
     })
 }
 
-/// Creates a 'pause' instruction
+/// Creates a 'pause' instruction, signed by the admin or the guardian
 pub fn pause(swap_pubkey: &Pubkey, admin_pubkey: &Pubkey) -> Result<Instruction, ProgramError> {
     let data = AdminInstruction::Pause.pack();
 
@@ -274,15 +897,19 @@ pub fn unpause(swap_pubkey: &Pubkey, admin_pubkey: &Pubkey) -> Result<Instructio
 }
 
 /// Creates a 'apply_new_admin' instruction
+///
+/// Deprecated: use [accept_admin], which is signed by the new admin.
 pub fn apply_new_admin(
     swap_pubkey: &Pubkey,
     admin_pubkey: &Pubkey,
+    new_admin_pubkey: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
     let data = AdminInstruction::ApplyNewAdmin.pack();
 
     let accounts = vec![
         AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*new_admin_pubkey, true),
     ];
 
     Ok(Instruction {
@@ -313,18 +940,35 @@ pub fn commit_new_admin(
     })
 }
 
-/// Creates a 'set_fee_account' instruction
-pub fn set_fee_account(
+/// Creates an 'accept_admin' instruction
+pub fn accept_admin(
+    swap_pubkey: &Pubkey,
+    new_admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::AcceptAdmin.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*new_admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'cancel_admin_transfer' instruction
+pub fn cancel_admin_transfer(
     swap_pubkey: &Pubkey,
     admin_pubkey: &Pubkey,
-    new_fee_account_pubkey: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
-    let data = AdminInstruction::SetFeeAccount.pack();
+    let data = AdminInstruction::CancelAdminTransfer.pack();
 
     let accounts = vec![
         AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*admin_pubkey, true),
-        AccountMeta::new_readonly(*new_fee_account_pubkey, false),
     ];
 
     Ok(Instruction {
@@ -334,13 +978,12 @@ pub fn set_fee_account(
     })
 }
 
-/// Creates a 'set_new_fees' instruction
-pub fn set_new_fees(
+/// Creates a 'renounce_admin' instruction
+pub fn renounce_admin(
     swap_pubkey: &Pubkey,
     admin_pubkey: &Pubkey,
-    new_fees: Fees,
 ) -> Result<Instruction, ProgramError> {
-    let data = AdminInstruction::SetNewFees(new_fees).pack();
+    let data = AdminInstruction::RenounceAdmin.pack();
 
     let accounts = vec![
         AccountMeta::new(*swap_pubkey, false),
@@ -354,40 +997,660 @@ pub fn set_new_fees(
     })
 }
 
-/// Instructions supported by the SwapInfo program.
-#[repr(C)]
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
-pub enum SwapInstruction {
-    /// Initializes a new SwapInfo.
-    ///
-    /// 0. `[writable, signer]` New StableSwap to create.
-    /// 1. `[]` $authority derived from `create_program_address(&[StableSwap account])`
-    /// 2. `[]` admin Account.
-    /// 3. `[]` admin_fee_a admin fee Account for token_a.
-    /// 4. `[]` admin_fee_b admin fee Account for token_b.
-    /// 5. `[]` token_a Account. Must be non zero, owned by $authority.
-    /// 6. `[]` token_b Account. Must be non zero, owned by $authority.
-    /// 7. `[writable]` Pool Token Mint. Must be empty, owned by $authority.
-    Initialize(InitializeData),
-
-    /// Swap the tokens in the pool.
-    ///
-    /// 0. `[]`StableSwap
-    /// 1. `[]` $authority
-    /// 2. `[signer]` User authority.
-    /// 3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by $authority,
-    /// 4. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token.
-    /// 5. `[writable]` token_(A|B) Base Account to swap FROM.  Must be the DESTINATION token.
-    /// 6. `[writable]` token_(A|B) DESTINATION Account assigned to USER as the owner.
-    /// 7. `[writable]` token_(A|B) admin fee Account. Must have same mint as DESTINATION token.
-    /// 8. `[]` Token program id
-    Swap(SwapData),
+/// Creates a 'migrate_swap_info' instruction
+pub fn migrate_swap_info(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    payer_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::MigrateSwapInfo.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_fee_tier' instruction
+pub fn set_fee_tier(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    payer_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    fee_multiplier_bps: u16,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetFeeTier(fee_multiplier_bps).pack();
+
+    let (fee_tier, _) = find_fee_tier_address(&crate::ID, Some(swap_pubkey), user_authority_pubkey);
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new(fee_tier, false),
+        AccountMeta::new_readonly(*user_authority_pubkey, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'remove_fee_tier' instruction
+pub fn remove_fee_tier(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    rent_destination_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::RemoveFeeTier.pack();
+
+    let (fee_tier, _) = find_fee_tier_address(&crate::ID, Some(swap_pubkey), user_authority_pubkey);
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new(fee_tier, false),
+        AccountMeta::new(*rent_destination_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_gate' instruction
+pub fn set_gate(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    gate_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetGate.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*gate_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_circuit_breaker' instruction
+pub fn set_circuit_breaker(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    max_price_deviation_bps: u16,
+    window_slots: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetCircuitBreaker(CircuitBreakerData {
+        max_price_deviation_bps,
+        window_slots,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_price_oracle' instruction
+pub fn set_price_oracle(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    feed_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    max_deviation_bps: u16,
+    max_staleness_slots: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetPriceOracle(PriceOracleData {
+        max_deviation_bps,
+        max_staleness_slots,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*feed_pubkey, false),
+        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'reset_circuit_breaker' instruction
+pub fn reset_circuit_breaker(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::ResetCircuitBreaker.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_fee_account' instruction
+pub fn set_fee_account(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    new_fee_account_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetFeeAccount.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*new_fee_account_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_new_fees' instruction
+pub fn set_new_fees(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    new_fees: Fees,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetNewFees(new_fees).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_extra_fees' instruction
+pub fn set_extra_fees(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    extra_fees: ExtraFees,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetExtraFees(extra_fees).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_admin_fee_mode' instruction
+pub fn set_admin_fee_mode(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    mode: AdminFeeMode,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetAdminFeeMode(mode).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*swap_token_a_pubkey, false),
+        AccountMeta::new_readonly(*swap_token_b_pubkey, false),
+        AccountMeta::new_readonly(*pool_mint_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_pause_flags' instruction, signed by the admin or the guardian
+pub fn set_pause_flags(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    flags: PauseFlags,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetPauseFlags(flags).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_guardian' instruction
+pub fn set_guardian(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    guardian_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetGuardian.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*guardian_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'grant_role' instruction
+pub fn grant_role(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    role: AdminRole,
+    grantee_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::GrantRole(role).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*grantee_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'revoke_role' instruction
+pub fn revoke_role(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    role: AdminRole,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::RevokeRole(role).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_timelock_delay' instruction
+pub fn set_timelock_delay(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    delay: i64,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::SetTimelockDelay(delay).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'initialize_admin_queue' instruction
+pub fn initialize_admin_queue(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    admin_queue_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::InitializeAdminQueue.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new(*admin_queue_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'queue_admin_action' instruction. `action_accounts` are the accounts
+/// of the queued instruction after its signer.
+pub fn queue_admin_action(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    admin_queue_pubkey: &Pubkey,
+    action: &AdminInstruction,
+    action_accounts: &[Pubkey],
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::QueueAdminAction(AdminActionData::new(action)?).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new(*admin_queue_pubkey, false),
+    ];
+    accounts.extend(
+        action_accounts
+            .iter()
+            .map(|account| AccountMeta::new_readonly(*account, false)),
+    );
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'cancel_admin_action' instruction
+pub fn cancel_admin_action(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    admin_queue_pubkey: &Pubkey,
+    index: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::CancelAdminAction(index).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new(*admin_queue_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'execute_admin_action' instruction. `action_accounts` must be the
+/// accounts the action was queued with.
+pub fn execute_admin_action(
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    admin_queue_pubkey: &Pubkey,
+    index: u8,
+    action_accounts: &[AccountMeta],
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::ExecuteAdminAction(index).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new(*admin_pubkey, true),
+        AccountMeta::new(*admin_queue_pubkey, false),
+    ];
+    accounts.extend_from_slice(action_accounts);
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Appends the token A and token B mints that instructions of Token-2022 pools
+/// take as their last two accounts. [AdminInstruction::ClosePool] takes them
+/// writable to harvest the withheld transfer fees of the reserves.
+pub fn with_token_2022_mints(
+    mut instruction: Instruction,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+) -> Instruction {
+    let is_writable = matches!(
+        AdminInstruction::unpack(&instruction.data),
+        Ok(Some(AdminInstruction::ClosePool))
+    );
+    for mint_pubkey in [token_a_mint_pubkey, token_b_mint_pubkey] {
+        instruction.accounts.push(AccountMeta {
+            pubkey: *mint_pubkey,
+            is_signer: false,
+            is_writable,
+        });
+    }
+    instruction
+}
+
+/// Position of the fee tier among the optional accounts of swaps.
+const FEE_TIER_SLOT: usize = 0;
+/// Position of the allowlist entry among the optional accounts of swaps.
+const SWAP_ALLOWLIST_ENTRY_SLOT: usize = 1;
+/// Position of the price feed among the optional accounts of swaps.
+const PRICE_FEED_SLOT: usize = 2;
+/// Position of the pool token mint among the optional accounts of swaps.
+const POOL_MINT_SLOT: usize = 3;
+
+/// Returns the index of the first optional account of a user instruction,
+/// which follows its listed accounts.
+fn optional_accounts_index(instruction: &Instruction) -> usize {
+    match SwapInstruction::unpack(&instruction.data) {
+        Ok(SwapInstruction::QuoteSwap(_)) => 3,
+        Ok(SwapInstruction::Swap(_)) => 9,
+        Ok(SwapInstruction::SwapWithReferral(_) | SwapInstruction::Deposit(_)) => 10,
+        Ok(SwapInstruction::WithdrawOne(_)) => 10,
+        Ok(SwapInstruction::Withdraw(_)) => 12,
+        _ => instruction.accounts.len(),
+    }
+}
+
+/// Sets the optional account at `slot` of the optional accounts of a user
+/// instruction. The positions of the optional accounts before it are filled
+/// with the program id, which passes no account.
+fn with_optional_account(
+    mut instruction: Instruction,
+    slot: usize,
+    account: AccountMeta,
+) -> Instruction {
+    let index = optional_accounts_index(&instruction) + slot;
+    if instruction.accounts.len() <= index {
+        instruction
+            .accounts
+            .resize(index + 1, AccountMeta::new_readonly(crate::ID, false));
+    }
+    instruction.accounts[index] = account;
+    instruction
+}
+
+/// Adds the fee tier of the user authority to a 'swap', 'swap_with_referral'
+/// or 'quote_swap' instruction. Must be added before [with_token_2022_mints].
+pub fn with_fee_tier(instruction: Instruction, fee_tier_pubkey: &Pubkey) -> Instruction {
+    with_optional_account(
+        instruction,
+        FEE_TIER_SLOT,
+        AccountMeta::new_readonly(*fee_tier_pubkey, false),
+    )
+}
+
+/// Adds the price feed of the swap to a 'swap', 'swap_with_referral' or
+/// 'quote_swap' instruction of a swap with a price oracle. Must be added before
+/// [with_token_2022_mints].
+pub fn with_price_feed(instruction: Instruction, feed_pubkey: &Pubkey) -> Instruction {
+    with_optional_account(
+        instruction,
+        PRICE_FEED_SLOT,
+        AccountMeta::new_readonly(*feed_pubkey, false),
+    )
+}
+
+/// Adds the pool token mint to a 'swap' or 'swap_with_referral' instruction,
+/// so that its [crate::events::SwapEvent] includes the virtual price of the
+/// pool. Must be added before [with_token_2022_mints].
+pub fn with_pool_mint(instruction: Instruction, pool_mint_pubkey: &Pubkey) -> Instruction {
+    with_optional_account(
+        instruction,
+        POOL_MINT_SLOT,
+        AccountMeta::new_readonly(*pool_mint_pubkey, false),
+    )
+}
+
+/// Adds the allowlist entry of the user authority to a 'swap',
+/// 'swap_with_referral', 'quote_swap', 'deposit', 'withdraw' or
+/// 'withdraw_one' instruction of a gated pool. Must be added before
+/// [with_token_2022_mints].
+pub fn with_allowlist_entry(instruction: Instruction, entry_pubkey: &Pubkey) -> Instruction {
+    let slot = match SwapInstruction::unpack(&instruction.data) {
+        Ok(
+            SwapInstruction::Swap(_)
+            | SwapInstruction::SwapWithReferral(_)
+            | SwapInstruction::QuoteSwap(_),
+        ) => SWAP_ALLOWLIST_ENTRY_SLOT,
+        _ => 0,
+    };
+    with_optional_account(
+        instruction,
+        slot,
+        AccountMeta::new_readonly(*entry_pubkey, false),
+    )
+}
+
+/// Creates a 'close_pool' instruction
+pub fn close_pool(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    token_a_pubkey: &Pubkey,
+    token_b_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AdminInstruction::ClosePool.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new(*admin_pubkey, true),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*pool_mint_pubkey, false),
+        AccountMeta::new(*token_a_pubkey, false),
+        AccountMeta::new(*token_b_pubkey, false),
+        AccountMeta::new(*admin_fee_a_pubkey, false),
+        AccountMeta::new(*admin_fee_b_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Instructions supported by the SwapInfo program.
+///
+/// Instructions of pools on the Token-2022 program that move token A or token B
+/// additionally take the token A and token B mints as their last two accounts.
+/// See [with_token_2022_mints].
+///
+/// Optional accounts follow the listed accounts of an instruction at fixed
+/// positions, before the mints of Token-2022 pools. The program id passes no
+/// account in a position, and positions after the last passed optional
+/// account may be left out.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub enum SwapInstruction {
+    /// Initializes a new SwapInfo.
+    ///
+    /// 0. `[writable, signer]` New StableSwap to create.
+    /// 1. `[]` $authority derived from `create_program_address(&[StableSwap account])`
+    /// 2. `[]` admin Account.
+    /// 3. `[]` admin_fee_a admin fee Account for token_a.
+    /// 4. `[]` admin_fee_b admin fee Account for token_b.
+    /// 5. `[]` token_a Account. Must be non zero, owned by $authority.
+    /// 6. `[]` token_b Account. Must be non zero, owned by $authority.
+    /// 7. `[writable]` Pool Token Mint. Must be empty, owned by $authority.
+    ///
+    /// The token program passed to [initialize] is used for all tokens of the pool
+    /// and may be either spl_token or Token-2022.
+    Initialize(InitializeData),
+
+    /// Swap the tokens in the pool.
+    ///
+    /// 0. `[writable]`StableSwap
+    /// 1. `[]` $authority
+    /// 2. `[signer]` User authority.
+    /// 3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by $authority,
+    /// 4. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token.
+    /// 5. `[writable]` token_(A|B) Base Account to swap FROM.  Must be the DESTINATION token.
+    /// 6. `[writable]` token_(A|B) DESTINATION Account assigned to USER as the owner.
+    /// 7. `[writable]` token_(A|B) admin fee Account. Must have same mint as DESTINATION token.
+    ///    The program id may be passed unless the admin fee mode is [AdminFeeMode::Transfer].
+    /// 8. `[]` Token program id
+    /// 9. `[]` Optional fee tier of the user authority. See [with_fee_tier].
+    /// 10. `[]` Optional allowlist entry of the user authority. See [with_allowlist_entry].
+    /// 11. `[]` Optional price feed of the swap. See [with_price_feed].
+    /// 12. `[]` Optional pool token mint. See [with_pool_mint].
+    Swap(SwapData),
 
     /// Deposit some tokens into the pool.  The output is a "pool" token representing ownership
     /// into the pool. Inputs are converted to the current ratio.
     ///
-    /// 0. `[]`StableSwap
+    /// 0. `[writable]`StableSwap
     /// 1. `[]` $authority
     /// 2. `[signer]` User authority.
     /// 3. `[writable]` token_a $authority can transfer amount,
@@ -397,189 +1660,1185 @@ pub enum SwapInstruction {
     /// 7. `[writable]` Pool MINT account, $authority is the owner.
     /// 8. `[writable]` Pool Account to deposit the generated tokens, user is the owner.
     /// 9. `[]` Token program id
+    /// 10. `[]` Optional allowlist entry of the user authority. See [with_allowlist_entry].
     Deposit(DepositData),
 
-    /// Withdraw tokens from the pool at the current ratio.
+    /// Withdraw tokens from the pool at the current ratio. Withdrawing the
+    /// whole pool token supply pays no withdraw fee.
+    ///
+    /// 0. `[writable]`StableSwap
+    /// 1. `[]` $authority
+    /// 2. `[signer]` User authority.
+    /// 3. `[writable]` Pool mint account, $authority is the owner
+    /// 4. `[writable]` SOURCE Pool account, amount is transferable by $authority.
+    /// 5. `[writable]` token_a Swap Account to withdraw FROM.
+    /// 6. `[writable]` token_b Swap Account to withdraw FROM.
+    /// 7. `[writable]` token_a user Account to credit.
+    /// 8. `[writable]` token_b user Account to credit.
+    /// 9. `[writable]` admin_fee_a admin fee Account for token_a.
+    /// 10. `[writable]` admin_fee_b admin fee Account for token_b.
+    /// 11. `[]` Token program id
+    /// 12. `[]` Optional allowlist entry of the user authority. See [with_allowlist_entry].
+    Withdraw(WithdrawData),
+
+    /// Withdraw one token from the pool at the current ratio.
+    ///
+    /// 0. `[writable]`StableSwap
+    /// 1. `[]` $authority
+    /// 2. `[signer]` User authority.
+    /// 3. `[writable]` Pool mint account, $authority is the owner
+    /// 4. `[writable]` SOURCE Pool account, amount is transferable by $authority.
+    /// 5. `[writable]` token_(A|B) BASE token Swap Account to withdraw FROM.
+    /// 6. `[writable]` token_(A|B) QUOTE token Swap Account to exchange to base token.
+    /// 7. `[writable]` token_(A|B) BASE token user Account to credit.
+    /// 8. `[writable]` token_(A|B) admin fee Account. Must have same mint as BASE token.
+    /// 9. `[]` Token program id
+    /// 10. `[]` Optional allowlist entry of the user authority. See [with_allowlist_entry].
+    WithdrawOne(WithdrawOneData),
+
+    /// Lends tokens from a reserve for the duration of the instruction.
+    /// The receiver program is invoked with [ReceiveFlashLoanData] and must
+    /// return the amount plus the flash loan fee to the reserve.
+    ///
+    /// 0. `[writable]`StableSwap. Only written to if admin fees accrue.
+    /// 1. `[]` $authority
+    /// 2. `[writable]` token_(A|B) Base Account to borrow FROM.
+    /// 3. `[writable]` token_(A|B) DESTINATION Account to receive the loan.
+    /// 4. `[writable]` token_(A|B) admin fee Account. Must have same mint as the borrowed token.
+    /// 5. `[]` Token program id
+    /// 6. `[]` Flash loan receiver program id
+    /// 7. ..7+N `[]` Accounts forwarded to the receiver program
+    FlashLoan(FlashLoanData),
+
+    /// Transfers admin fees accrued in the reserves to the admin fee accounts.
+    /// If admin fees are minted as pool tokens, also mints the admin share of
+    /// the invariant growth since the last claim.
+    /// Anyone may call this instruction.
+    ///
+    /// 0. `[writable]`StableSwap
+    /// 1. `[]` $authority
+    /// 2. `[writable]` token_a Swap Account holding the accrued fees.
+    /// 3. `[writable]` token_b Swap Account holding the accrued fees.
+    /// 4. `[writable]` admin_fee_a admin fee Account for token_a.
+    /// 5. `[writable]` admin_fee_b admin fee Account for token_b.
+    /// 6. `[]` Token program id
+    /// 7. `[writable]` Pool MINT account. Only if admin fees are minted as pool tokens.
+    /// 8. `[writable]` Pool Account to receive admin fees. Only if admin fees are minted as pool tokens.
+    ClaimAdminFees,
+
+    /// Creates and initializes a new SwapInfo whose accounts are program derived
+    /// addresses of the sorted mint pair, the admin and the amplification
+    /// coefficient. See [crate::pda].
+    ///
+    /// The swap account, the reserves and the pool token mint are created by the
+    /// program and funded by the payer, who also provides the initial liquidity.
+    /// The token program may be either spl_token or Token-2022, whose reserves
+    /// are created with room for the extensions required by their mints.
+    ///
+    /// 0. `[writable, signer]` Payer, owner of the SOURCE accounts.
+    /// 1. `[writable]` StableSwap to create, derived from the mints, admin and amp.
+    /// 2. `[]` $authority derived from `create_program_address(&[StableSwap account])`
+    /// 3. `[]` admin Account.
+    /// 4. `[]` admin_fee_a admin fee Account for token_a.
+    /// 5. `[]` admin_fee_b admin fee Account for token_b.
+    /// 6. `[]` token_a Mint.
+    /// 7. `[writable]` token_a Base Account to create, derived from the swap and mint.
+    /// 8. `[]` token_b Mint.
+    /// 9. `[writable]` token_b Base Account to create, derived from the swap and mint.
+    /// 10. `[writable]` Pool Token Mint to create, derived from the swap.
+    /// 11. `[writable]` token_a SOURCE Account of the initial liquidity.
+    /// 12. `[writable]` token_b SOURCE Account of the initial liquidity.
+    /// 13. `[writable, signer]` Pool Account to create for the initial LP tokens, owned by the payer.
+    /// 14. `[]` Token program id
+    /// 15. `[]` System program id
+    InitializeV2(InitializeV2Data),
+
+    /// Quotes a swap without executing it. Returns the
+    /// [crate::results::SwapResult] that the swap would return, or fails like
+    /// the swap would on the gate, the circuit breaker and the price oracle.
+    ///
+    /// 0. `[]`StableSwap
+    /// 1. `[]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token.
+    /// 2. `[]` token_(A|B) Base Account to swap FROM.  Must be the DESTINATION token.
+    /// 3. `[]` Optional fee tier to quote the swap with. See [with_fee_tier].
+    /// 4. `[]` Optional allowlist entry of the user authority to quote the swap
+    ///    for. See [with_allowlist_entry].
+    /// 5. `[]` Optional price feed of the swap. See [with_price_feed].
+    QuoteSwap(QuoteSwapData),
+
+    /// Quotes a deposit without executing it. Returns the
+    /// [crate::results::DepositResult] that the deposit would return.
+    ///
+    /// 0. `[]`StableSwap
+    /// 1. `[]` token_a Base Account.
+    /// 2. `[]` token_b Base Account.
+    /// 3. `[]` Pool MINT account.
+    QuoteDeposit(QuoteDepositData),
+
+    /// Quotes a withdrawal of one token without executing it. Returns the
+    /// [crate::results::WithdrawOneResult] that the withdrawal would return.
+    ///
+    /// 0. `[]`StableSwap
+    /// 1. `[]` token_(A|B) BASE token Swap Account to withdraw FROM.
+    /// 2. `[]` token_(A|B) QUOTE token Swap Account to exchange to base token.
+    /// 3. `[]` Pool MINT account.
+    QuoteWithdrawOne(QuoteWithdrawOneData),
+
+    /// Returns the current [crate::events::PoolState] of the pool, including
+    /// the virtual price of the pool token.
+    ///
+    /// 0. `[]`StableSwap
+    /// 1. `[]` token_a Base Account.
+    /// 2. `[]` token_b Base Account.
+    /// 3. `[]` Pool MINT account.
+    GetVirtualPrice,
+
+    /// Swap the tokens in the pool, paying the referral share of the trade
+    /// fee to the referrer. Takes the accounts of [SwapInstruction::Swap],
+    /// followed by the referrer account and the optional accounts of swaps.
+    ///
+    /// 0. `[writable]`StableSwap
+    /// 1. `[]` $authority
+    /// 2. `[signer]` User authority.
+    /// 3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by $authority,
+    /// 4. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token.
+    /// 5. `[writable]` token_(A|B) Base Account to swap FROM.  Must be the DESTINATION token.
+    /// 6. `[writable]` token_(A|B) DESTINATION Account assigned to USER as the owner.
+    /// 7. `[writable]` token_(A|B) admin fee Account. Must have same mint as DESTINATION token.
+    /// 8. `[]` Token program id
+    /// 9. `[writable]` Referrer Account. Must have same mint as DESTINATION token.
+    ///    The program id in its place charges no referral fee.
+    /// 10. `[]` Optional fee tier of the user authority. See [with_fee_tier].
+    /// 11. `[]` Optional allowlist entry of the user authority. See [with_allowlist_entry].
+    /// 12. `[]` Optional price feed of the swap. See [with_price_feed].
+    /// 13. `[]` Optional pool token mint. See [with_pool_mint].
+    SwapWithReferral(SwapData),
+}
+
+impl SwapInstruction {
+    /// Unpacks a byte buffer into a [SwapInstruction](enum.SwapInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input.split_first().ok_or(SwapError::InvalidInstruction)?;
+        Ok(match tag {
+            0 => {
+                let (&nonce, rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                let (amp_factor, rest) = unpack_u64(rest)?;
+                let fees = Fees::unpack_unchecked(rest)?;
+                Self::Initialize(InitializeData {
+                    nonce,
+                    amp_factor,
+                    fees,
+                })
+            }
+            1 => {
+                let (amount_in, rest) = unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = unpack_u64(rest)?;
+                Self::Swap(SwapData {
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            2 => {
+                let (token_a_amount, rest) = unpack_u64(rest)?;
+                let (token_b_amount, rest) = unpack_u64(rest)?;
+                let (min_mint_amount, _rest) = unpack_u64(rest)?;
+                Self::Deposit(DepositData {
+                    token_a_amount,
+                    token_b_amount,
+                    min_mint_amount,
+                })
+            }
+            3 => {
+                let (pool_token_amount, rest) = unpack_u64(rest)?;
+                let (minimum_token_a_amount, rest) = unpack_u64(rest)?;
+                let (minimum_token_b_amount, _rest) = unpack_u64(rest)?;
+                Self::Withdraw(WithdrawData {
+                    pool_token_amount,
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                })
+            }
+            4 => {
+                let (pool_token_amount, rest) = unpack_u64(rest)?;
+                let (minimum_token_amount, _rest) = unpack_u64(rest)?;
+                Self::WithdrawOne(WithdrawOneData {
+                    pool_token_amount,
+                    minimum_token_amount,
+                })
+            }
+            5 => {
+                let (amount, _rest) = unpack_u64(rest)?;
+                Self::FlashLoan(FlashLoanData { amount })
+            }
+            6 => Self::ClaimAdminFees,
+            7 => {
+                let (amp_factor, rest) = unpack_u64(rest)?;
+                let fees = Fees::unpack_unchecked(
+                    rest.get(..Fees::LEN).ok_or(SwapError::InvalidInstruction)?,
+                )?;
+                let rest = rest.get(Fees::LEN..).ok_or(SwapError::InvalidInstruction)?;
+                let (token_a_amount, rest) = unpack_u64(rest)?;
+                let (token_b_amount, _rest) = unpack_u64(rest)?;
+                Self::InitializeV2(InitializeV2Data {
+                    amp_factor,
+                    fees,
+                    token_a_amount,
+                    token_b_amount,
+                })
+            }
+            8 => {
+                let (amount_in, _rest) = unpack_u64(rest)?;
+                Self::QuoteSwap(QuoteSwapData { amount_in })
+            }
+            9 => {
+                let (token_a_amount, rest) = unpack_u64(rest)?;
+                let (token_b_amount, _rest) = unpack_u64(rest)?;
+                Self::QuoteDeposit(QuoteDepositData {
+                    token_a_amount,
+                    token_b_amount,
+                })
+            }
+            10 => {
+                let (pool_token_amount, _rest) = unpack_u64(rest)?;
+                Self::QuoteWithdrawOne(QuoteWithdrawOneData { pool_token_amount })
+            }
+            11 => Self::GetVirtualPrice,
+            12 => {
+                let (amount_in, rest) = unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = unpack_u64(rest)?;
+                Self::SwapWithReferral(SwapData {
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            _ => return Err(SwapError::InvalidInstruction.into()),
+        })
+    }
+
+    /// Packs a [SwapInstruction](enum.SwapInstruction.html) into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match *self {
+            Self::Initialize(InitializeData {
+                nonce,
+                amp_factor,
+                fees,
+            }) => {
+                buf.push(0);
+                buf.push(nonce);
+                buf.extend_from_slice(&amp_factor.to_le_bytes());
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(&fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+            }
+            Self::Swap(SwapData {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(1);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::Deposit(DepositData {
+                token_a_amount,
+                token_b_amount,
+                min_mint_amount,
+            }) => {
+                buf.push(2);
+                buf.extend_from_slice(&token_a_amount.to_le_bytes());
+                buf.extend_from_slice(&token_b_amount.to_le_bytes());
+                buf.extend_from_slice(&min_mint_amount.to_le_bytes());
+            }
+            Self::Withdraw(WithdrawData {
+                pool_token_amount,
+                minimum_token_a_amount,
+                minimum_token_b_amount,
+            }) => {
+                buf.push(3);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_token_a_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_token_b_amount.to_le_bytes());
+            }
+            Self::WithdrawOne(WithdrawOneData {
+                pool_token_amount,
+                minimum_token_amount,
+            }) => {
+                buf.push(4);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_token_amount.to_le_bytes());
+            }
+            Self::FlashLoan(FlashLoanData { amount }) => {
+                buf.push(5);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::ClaimAdminFees => buf.push(6),
+            Self::InitializeV2(InitializeV2Data {
+                amp_factor,
+                fees,
+                token_a_amount,
+                token_b_amount,
+            }) => {
+                buf.push(7);
+                buf.extend_from_slice(&amp_factor.to_le_bytes());
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(&fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+                buf.extend_from_slice(&token_a_amount.to_le_bytes());
+                buf.extend_from_slice(&token_b_amount.to_le_bytes());
+            }
+            Self::QuoteSwap(QuoteSwapData { amount_in }) => {
+                buf.push(8);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+            }
+            Self::QuoteDeposit(QuoteDepositData {
+                token_a_amount,
+                token_b_amount,
+            }) => {
+                buf.push(9);
+                buf.extend_from_slice(&token_a_amount.to_le_bytes());
+                buf.extend_from_slice(&token_b_amount.to_le_bytes());
+            }
+            Self::QuoteWithdrawOne(QuoteWithdrawOneData { pool_token_amount }) => {
+                buf.push(10);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+            }
+            Self::GetVirtualPrice => buf.push(11),
+            Self::SwapWithReferral(SwapData {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(12);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+        }
+        buf
+    }
+}
+
+/// Creates an 'initialize' instruction.
+pub fn initialize(
+    pool_token_program_id: &Pubkey, // Token program used for the pool token
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    admin_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_a_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    token_b_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey, // Destination to mint pool tokens for bootstrapper
+    nonce: u8,
+    amp_factor: u64,
+    fees: Fees,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Initialize(InitializeData {
+        nonce,
+        amp_factor,
+        fees,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, true),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*admin_pubkey, false),
+        AccountMeta::new_readonly(*admin_fee_a_pubkey, false),
+        AccountMeta::new_readonly(*admin_fee_b_pubkey, false),
+        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_a_pubkey, false),
+        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_b_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*pool_token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'initialize_v2' instruction. The addresses of the pool are
+/// derived from the mints, the admin and the amplification coefficient, see
+/// [crate::pda::PoolAddresses]. The mints must be sorted, see
+/// [crate::pda::sort_mints].
+pub fn initialize_v2(
+    token_program_id: &Pubkey,
+    payer_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    source_a_pubkey: &Pubkey,
+    source_b_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey, // New account to mint pool tokens to
+    amp_factor: u64,
+    fees: Fees,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::InitializeV2(InitializeV2Data {
+        amp_factor,
+        fees,
+        token_a_amount,
+        token_b_amount,
+    })
+    .pack();
+
+    let pool = PoolAddresses::find(
+        &crate::ID,
+        token_a_mint_pubkey,
+        token_b_mint_pubkey,
+        admin_pubkey,
+        amp_factor,
+    );
+    let accounts = vec![
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new(pool.swap, false),
+        AccountMeta::new_readonly(pool.authority, false),
+        AccountMeta::new_readonly(*admin_pubkey, false),
+        AccountMeta::new_readonly(*admin_fee_a_pubkey, false),
+        AccountMeta::new_readonly(*admin_fee_b_pubkey, false),
+        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+        AccountMeta::new(pool.token_a_reserves, false),
+        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+        AccountMeta::new(pool.token_b_reserves, false),
+        AccountMeta::new(pool.pool_mint, false),
+        AccountMeta::new(*source_a_pubkey, false),
+        AccountMeta::new(*source_b_pubkey, false),
+        AccountMeta::new(*destination_pubkey, true),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'deposit' instruction.
+#[inline(always)]
+pub fn deposit(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    user_authority_key: &Pubkey,
+    deposit_token_a_pubkey: &Pubkey,
+    deposit_token_b_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    min_mint_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Deposit(DepositData {
+        token_a_amount,
+        token_b_amount,
+        min_mint_amount,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*user_authority_key, true),
+        AccountMeta::new(*deposit_token_a_pubkey, false),
+        AccountMeta::new(*deposit_token_b_pubkey, false),
+        AccountMeta::new(*swap_token_a_pubkey, false),
+        AccountMeta::new(*swap_token_b_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'withdraw' instruction.
+#[inline(always)]
+pub fn withdraw(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    user_authority_key: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    destination_token_a_pubkey: &Pubkey,
+    destination_token_b_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+    pool_token_amount: u64,
+    minimum_token_a_amount: u64,
+    minimum_token_b_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Withdraw(WithdrawData {
+        pool_token_amount,
+        minimum_token_a_amount,
+        minimum_token_b_amount,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*user_authority_key, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*swap_token_a_pubkey, false),
+        AccountMeta::new(*swap_token_b_pubkey, false),
+        AccountMeta::new(*destination_token_a_pubkey, false),
+        AccountMeta::new(*destination_token_b_pubkey, false),
+        AccountMeta::new(*admin_fee_a_pubkey, false),
+        AccountMeta::new(*admin_fee_b_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'swap' instruction.
+#[inline(always)]
+pub fn swap(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    user_authority_key: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    admin_fee_destination_pubkey: &Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Swap(SwapData {
+        amount_in,
+        minimum_amount_out,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*user_authority_key, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*swap_source_pubkey, false),
+        AccountMeta::new(*swap_destination_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*admin_fee_destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'swap_with_referral' instruction. Without a referrer, no
+/// referral fee is charged.
+#[inline(always)]
+pub fn swap_with_referral(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    user_authority_key: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    admin_fee_destination_pubkey: &Pubkey,
+    referrer_pubkey: Option<&Pubkey>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SwapWithReferral(SwapData {
+        amount_in,
+        minimum_amount_out,
+    })
+    .pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*user_authority_key, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*swap_source_pubkey, false),
+        AccountMeta::new(*swap_destination_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*admin_fee_destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.push(match referrer_pubkey {
+        Some(referrer_pubkey) => AccountMeta::new(*referrer_pubkey, false),
+        None => AccountMeta::new_readonly(crate::ID, false),
+    });
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'withdraw_one' instruction.
+#[inline(always)]
+pub fn withdraw_one(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    user_authority_key: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_base_token_pubkey: &Pubkey,
+    swap_quote_token_pubkey: &Pubkey,
+    base_destination_pubkey: &Pubkey,
+    admin_fee_destination_pubkey: &Pubkey,
+    pool_token_amount: u64,
+    minimum_token_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::WithdrawOne(WithdrawOneData {
+        pool_token_amount,
+        minimum_token_amount,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new_readonly(*user_authority_key, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*swap_base_token_pubkey, false),
+        AccountMeta::new(*swap_quote_token_pubkey, false),
+        AccountMeta::new(*base_destination_pubkey, false),
+        AccountMeta::new(*admin_fee_destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'flash_loan' instruction.
+///
+/// `receiver_accounts` are appended after the receiver program and
+/// forwarded to it as-is.
+pub fn flash_loan(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    swap_reserve_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    admin_fee_destination_pubkey: &Pubkey,
+    receiver_program_id: &Pubkey,
+    receiver_accounts: &[AccountMeta],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::FlashLoan(FlashLoanData { amount }).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new(*swap_reserve_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*admin_fee_destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(*receiver_program_id, false),
+    ];
+    accounts.extend_from_slice(receiver_accounts);
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'quote_swap' instruction.
+pub fn quote_swap(
+    swap_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    amount_in: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::QuoteSwap(QuoteSwapData { amount_in }).pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_source_pubkey, false),
+        AccountMeta::new_readonly(*swap_destination_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'quote_deposit' instruction.
+pub fn quote_deposit(
+    swap_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::QuoteDeposit(QuoteDepositData {
+        token_a_amount,
+        token_b_amount,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_token_a_pubkey, false),
+        AccountMeta::new_readonly(*swap_token_b_pubkey, false),
+        AccountMeta::new_readonly(*pool_mint_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'quote_withdraw_one' instruction.
+pub fn quote_withdraw_one(
+    swap_pubkey: &Pubkey,
+    swap_base_token_pubkey: &Pubkey,
+    swap_quote_token_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    pool_token_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::QuoteWithdrawOne(QuoteWithdrawOneData { pool_token_amount }).pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_base_token_pubkey, false),
+        AccountMeta::new_readonly(*swap_quote_token_pubkey, false),
+        AccountMeta::new_readonly(*pool_mint_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'get_virtual_price' instruction.
+pub fn get_virtual_price(
+    swap_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::GetVirtualPrice.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_token_a_pubkey, false),
+        AccountMeta::new_readonly(*swap_token_b_pubkey, false),
+        AccountMeta::new_readonly(*pool_mint_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'claim_admin_fees' instruction.
+pub fn claim_admin_fees(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::ClaimAdminFees.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*swap_authority_key, false),
+        AccountMeta::new(*swap_token_a_pubkey, false),
+        AccountMeta::new(*swap_token_b_pubkey, false),
+        AccountMeta::new(*admin_fee_a_pubkey, false),
+        AccountMeta::new(*admin_fee_b_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'claim_admin_fees' instruction for a swap that mints admin fees as pool tokens.
+pub fn claim_admin_fees_as_lp(
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    swap_authority_key: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    lp_admin_fee_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let mut ix = claim_admin_fees(
+        token_program_id,
+        swap_pubkey,
+        swap_authority_key,
+        swap_token_a_pubkey,
+        swap_token_b_pubkey,
+        admin_fee_a_pubkey,
+        admin_fee_b_pubkey,
+    )?;
+    ix.accounts.push(AccountMeta::new(*pool_mint_pubkey, false));
+    ix.accounts
+        .push(AccountMeta::new(*lp_admin_fee_pubkey, false));
+    Ok(ix)
+}
+
+/// SetPoolTemplate instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct PoolTemplateData {
+    /// The amp tier of the template
+    pub amp_tier: u8,
+    /// Amplification coefficient (A) of new pools
+    pub amp_factor: u64,
+    /// Fees of new pools
+    pub fees: Fees,
+    /// True if pools can be created with the template
+    pub is_enabled: bool,
+}
+
+/// CreatePool instruction data
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct CreatePoolData {
+    /// The amp tier of the pool
+    pub amp_tier: u8,
+    /// Amount of token A to transfer into the reserves
+    pub token_a_amount: u64,
+    /// Amount of token B to transfer into the reserves
+    pub token_b_amount: u64,
+}
+
+/// Instructions of the pool factory.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub enum FactoryInstruction {
+    /// Creates the pool factory. Must be signed by the upgrade authority of
+    /// the program.
     ///
-    /// 0. `[]`StableSwap
-    /// 1. `[]` $authority
-    /// 2. `[signer]` User authority.
-    /// 3. `[writable]` Pool mint account, $authority is the owner
-    /// 4. `[writable]` SOURCE Pool account, amount is transferable by $authority.
-    /// 5. `[writable]` token_a Swap Account to withdraw FROM.
-    /// 6. `[writable]` token_b Swap Account to withdraw FROM.
-    /// 7. `[writable]` token_a user Account to credit.
-    /// 8. `[writable]` token_b user Account to credit.
-    /// 9. `[writable]` admin_fee_a admin fee Account for token_a.
-    /// 10. `[writable]` admin_fee_b admin fee Account for token_b.
-    /// 11. `[]` Token program id
-    Withdraw(WithdrawData),
+    /// 0. `[writable, signer]` Payer.
+    /// 1. `[writable]` Factory to create, derived from [crate::pda::FACTORY_SEED].
+    /// 2. `[signer]` Upgrade authority of the program.
+    /// 3. `[]` Program data account of the program.
+    /// 4. `[]` Admin of the factory.
+    /// 5. `[]` System program id
+    InitializeFactory,
+
+    /// Sets the admin of the factory. Pools that were already created keep
+    /// their admin.
+    ///
+    /// 0. `[writable]` Factory
+    /// 1. `[signer]` Admin of the factory.
+    /// 2. `[]` New admin of the factory.
+    SetFactoryAdmin,
 
-    /// Withdraw one token from the pool at the current ratio.
+    /// Creates or updates the pool template of an amp tier.
     ///
-    /// 0. `[]`StableSwap
-    /// 1. `[]` $authority
-    /// 2. `[signer]` User authority.
-    /// 3. `[writable]` Pool mint account, $authority is the owner
-    /// 4. `[writable]` SOURCE Pool account, amount is transferable by $authority.
-    /// 5. `[writable]` token_(A|B) BASE token Swap Account to withdraw FROM.
-    /// 6. `[writable]` token_(A|B) QUOTE token Swap Account to exchange to base token.
-    /// 7. `[writable]` token_(A|B) BASE token user Account to credit.
-    /// 8. `[writable]` token_(A|B) admin fee Account. Must have same mint as BASE token.
-    /// 9. `[]` Token program id
-    WithdrawOne(WithdrawOneData),
+    /// 0. `[writable, signer]` Admin of the factory, pays for new templates.
+    /// 1. `[]` Factory
+    /// 2. `[writable]` Pool template, derived from the amp tier.
+    /// 3. `[]` System program id
+    SetPoolTemplate(PoolTemplateData),
+
+    /// Creates the canonical pool of a mint pair in an amp tier with the pool
+    /// template of the tier and records it in the registry. Anyone may call
+    /// this instruction. The mints must be sorted, see [crate::pda::sort_mints].
+    ///
+    /// 0. `[]` Factory
+    /// 1. `[]` Pool template of the amp tier.
+    /// 2. `[writable]` Registry entry to create, derived from the mints and amp tier.
+    /// 3. ..18. The accounts of [SwapInstruction::InitializeV2]. The admin must be
+    ///    the admin of the factory, who must own the admin fee accounts.
+    CreatePool(CreatePoolData),
+
+    /// Creates or updates the global fee tier of a user, which may be used in
+    /// place of a fee tier of a pool in the swaps of the user in the pools
+    /// created by the factory. The tier applies
+    /// [crate::state::GLOBAL_FEE_TIER_DELAY] after it is set.
+    ///
+    /// 0. `[writable, signer]` Admin of the factory, pays for new fee tiers.
+    /// 1. `[]` Factory
+    /// 2. `[writable]` Fee tier, derived from the user authority.
+    /// 3. `[]` User authority
+    /// 4. `[]` System program id
+    SetGlobalFeeTier(u16),
+
+    /// Closes a global fee tier and returns its rent.
+    ///
+    /// 0. `[signer]` Admin of the factory.
+    /// 1. `[]` Factory
+    /// 2. `[writable]` Fee tier
+    /// 3. `[writable]` Account to return the rent to
+    RemoveGlobalFeeTier,
 }
 
-impl SwapInstruction {
-    /// Unpacks a byte buffer into a [SwapInstruction](enum.SwapInstruction.html).
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+impl FactoryInstruction {
+    /// Unpacks a byte buffer into a [FactoryInstruction](enum.FactoryInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Option<Self>, ProgramError> {
         let (&tag, rest) = input.split_first().ok_or(SwapError::InvalidInstruction)?;
         Ok(match tag {
-            0 => {
-                let (&nonce, rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+            200 => Some(Self::InitializeFactory),
+            201 => Some(Self::SetFactoryAdmin),
+            202 => {
+                let (&amp_tier, rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
                 let (amp_factor, rest) = unpack_u64(rest)?;
-                let fees = Fees::unpack_unchecked(rest)?;
-                Self::Initialize(InitializeData {
-                    nonce,
+                let fees = Fees::unpack_unchecked(
+                    rest.get(..Fees::LEN).ok_or(SwapError::InvalidInstruction)?,
+                )?;
+                let is_enabled = match rest.get(Fees::LEN) {
+                    Some(0) => false,
+                    Some(1) => true,
+                    _ => return Err(SwapError::InvalidInstruction.into()),
+                };
+                Some(Self::SetPoolTemplate(PoolTemplateData {
+                    amp_tier,
                     amp_factor,
                     fees,
-                })
-            }
-            1 => {
-                let (amount_in, rest) = unpack_u64(rest)?;
-                let (minimum_amount_out, _rest) = unpack_u64(rest)?;
-                Self::Swap(SwapData {
-                    amount_in,
-                    minimum_amount_out,
-                })
+                    is_enabled,
+                }))
             }
-            2 => {
+            203 => {
+                let (&amp_tier, rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
                 let (token_a_amount, rest) = unpack_u64(rest)?;
-                let (token_b_amount, rest) = unpack_u64(rest)?;
-                let (min_mint_amount, _rest) = unpack_u64(rest)?;
-                Self::Deposit(DepositData {
+                let (token_b_amount, _rest) = unpack_u64(rest)?;
+                Some(Self::CreatePool(CreatePoolData {
+                    amp_tier,
                     token_a_amount,
-                    token_b_amount,
-                    min_mint_amount,
-                })
-            }
-            3 => {
-                let (pool_token_amount, rest) = unpack_u64(rest)?;
-                let (minimum_token_a_amount, rest) = unpack_u64(rest)?;
-                let (minimum_token_b_amount, _rest) = unpack_u64(rest)?;
-                Self::Withdraw(WithdrawData {
-                    pool_token_amount,
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                })
-            }
-            4 => {
-                let (pool_token_amount, rest) = unpack_u64(rest)?;
-                let (minimum_token_amount, _rest) = unpack_u64(rest)?;
-                Self::WithdrawOne(WithdrawOneData {
-                    pool_token_amount,
-                    minimum_token_amount,
-                })
+                    token_b_amount,
+                }))
             }
-            _ => return Err(SwapError::InvalidInstruction.into()),
+            204 => {
+                let (fee_multiplier_bps, _rest) = unpack_u16(rest)?;
+                Some(Self::SetGlobalFeeTier(fee_multiplier_bps))
+            }
+            205 => Some(Self::RemoveGlobalFeeTier),
+            _ => None,
         })
     }
 
-    /// Packs a [SwapInstruction](enum.SwapInstruction.html) into a byte buffer.
+    /// Packs a [FactoryInstruction](enum.FactoryInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
         match *self {
-            Self::Initialize(InitializeData {
-                nonce,
+            Self::InitializeFactory => buf.push(200),
+            Self::SetFactoryAdmin => buf.push(201),
+            Self::SetPoolTemplate(PoolTemplateData {
+                amp_tier,
                 amp_factor,
                 fees,
+                is_enabled,
             }) => {
-                buf.push(0);
-                buf.push(nonce);
+                buf.push(202);
+                buf.push(amp_tier);
                 buf.extend_from_slice(&amp_factor.to_le_bytes());
                 let mut fees_slice = [0u8; Fees::LEN];
                 Pack::pack_into_slice(&fees, &mut fees_slice[..]);
                 buf.extend_from_slice(&fees_slice);
+                buf.push(is_enabled as u8);
             }
-            Self::Swap(SwapData {
-                amount_in,
-                minimum_amount_out,
-            }) => {
-                buf.push(1);
-                buf.extend_from_slice(&amount_in.to_le_bytes());
-                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
-            }
-            Self::Deposit(DepositData {
+            Self::CreatePool(CreatePoolData {
+                amp_tier,
                 token_a_amount,
                 token_b_amount,
-                min_mint_amount,
             }) => {
-                buf.push(2);
+                buf.push(203);
+                buf.push(amp_tier);
                 buf.extend_from_slice(&token_a_amount.to_le_bytes());
                 buf.extend_from_slice(&token_b_amount.to_le_bytes());
-                buf.extend_from_slice(&min_mint_amount.to_le_bytes());
-            }
-            Self::Withdraw(WithdrawData {
-                pool_token_amount,
-                minimum_token_a_amount,
-                minimum_token_b_amount,
-            }) => {
-                buf.push(3);
-                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
-                buf.extend_from_slice(&minimum_token_a_amount.to_le_bytes());
-                buf.extend_from_slice(&minimum_token_b_amount.to_le_bytes());
             }
-            Self::WithdrawOne(WithdrawOneData {
-                pool_token_amount,
-                minimum_token_amount,
-            }) => {
-                buf.push(4);
-                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
-                buf.extend_from_slice(&minimum_token_amount.to_le_bytes());
+            Self::SetGlobalFeeTier(fee_multiplier_bps) => {
+                buf.push(204);
+                buf.extend_from_slice(&fee_multiplier_bps.to_le_bytes());
             }
+            Self::RemoveGlobalFeeTier => buf.push(205),
         }
         buf
     }
 }
 
-/// Creates an 'initialize' instruction.
-pub fn initialize(
-    pool_token_program_id: &Pubkey, // Token program used for the pool token
-    swap_pubkey: &Pubkey,
-    swap_authority_key: &Pubkey,
+/// Instructions managing the gates of permissioned pools and their allowlists.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub enum GateInstruction {
+    /// Creates a gate managed by the gatekeeper.
+    ///
+    /// 0. `[writable, signer]` Payer.
+    /// 1. `[writable]` Gate to create, derived from the gatekeeper.
+    /// 2. `[signer]` Gatekeeper.
+    /// 3. `[]` System program id
+    InitializeGate,
+
+    /// Sets the gatekeeper of a gate.
+    ///
+    /// 0. `[writable]` Gate
+    /// 1. `[signer]` Gatekeeper.
+    /// 2. `[]` New gatekeeper.
+    SetGatekeeper,
+
+    /// Adds a user to the allowlist of a gate.
+    ///
+    /// 0. `[]` Gate
+    /// 1. `[signer]` Gatekeeper.
+    /// 2. `[writable, signer]` Payer.
+    /// 3. `[writable]` Allowlist entry to create, derived from the gate and the user authority.
+    /// 4. `[]` User authority.
+    /// 5. `[]` System program id
+    AddToAllowlist,
+
+    /// Removes a user from the allowlist of a gate and returns the rent of its entry.
+    ///
+    /// 0. `[]` Gate
+    /// 1. `[signer]` Gatekeeper.
+    /// 2. `[writable]` Allowlist entry
+    /// 3. `[writable]` Account to return the rent to
+    RemoveFromAllowlist,
+}
+
+impl GateInstruction {
+    /// Unpacks a byte buffer into a [GateInstruction](enum.GateInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Option<Self>, ProgramError> {
+        let (&tag, _rest) = input.split_first().ok_or(SwapError::InvalidInstruction)?;
+        Ok(match tag {
+            210 => Some(Self::InitializeGate),
+            211 => Some(Self::SetGatekeeper),
+            212 => Some(Self::AddToAllowlist),
+            213 => Some(Self::RemoveFromAllowlist),
+            _ => None,
+        })
+    }
+
+    /// Packs a [GateInstruction](enum.GateInstruction.html) into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        vec![match self {
+            Self::InitializeGate => 210,
+            Self::SetGatekeeper => 211,
+            Self::AddToAllowlist => 212,
+            Self::RemoveFromAllowlist => 213,
+        }]
+    }
+}
+
+/// Creates an 'initialize_factory' instruction.
+pub fn initialize_factory(
+    payer_pubkey: &Pubkey,
+    upgrade_authority_pubkey: &Pubkey,
     admin_pubkey: &Pubkey,
-    admin_fee_a_pubkey: &Pubkey,
-    admin_fee_b_pubkey: &Pubkey,
-    token_a_mint_pubkey: &Pubkey,
-    token_a_pubkey: &Pubkey,
-    token_b_mint_pubkey: &Pubkey,
-    token_b_pubkey: &Pubkey,
-    pool_mint_pubkey: &Pubkey,
-    destination_pubkey: &Pubkey, // Destination to mint pool tokens for bootstrapper
-    nonce: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = FactoryInstruction::InitializeFactory.pack();
+
+    let (program_data, _) =
+        Pubkey::find_program_address(&[crate::ID.as_ref()], &bpf_loader_upgradeable::id());
+    let accounts = vec![
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new(find_factory_address(&crate::ID).0, false),
+        AccountMeta::new_readonly(*upgrade_authority_pubkey, true),
+        AccountMeta::new_readonly(program_data, false),
+        AccountMeta::new_readonly(*admin_pubkey, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_factory_admin' instruction.
+pub fn set_factory_admin(
+    admin_pubkey: &Pubkey,
+    new_admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = FactoryInstruction::SetFactoryAdmin.pack();
+
+    let accounts = vec![
+        AccountMeta::new(find_factory_address(&crate::ID).0, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*new_admin_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_pool_template' instruction.
+pub fn set_pool_template(
+    admin_pubkey: &Pubkey,
+    amp_tier: u8,
     amp_factor: u64,
     fees: Fees,
+    is_enabled: bool,
 ) -> Result<Instruction, ProgramError> {
-    let data = SwapInstruction::Initialize(InitializeData {
-        nonce,
+    let data = FactoryInstruction::SetPoolTemplate(PoolTemplateData {
+        amp_tier,
         amp_factor,
         fees,
+        is_enabled,
     })
     .pack();
 
     let accounts = vec![
-        AccountMeta::new(*swap_pubkey, true),
-        AccountMeta::new_readonly(*swap_authority_key, false),
-        AccountMeta::new_readonly(*admin_pubkey, false),
-        AccountMeta::new_readonly(*admin_fee_a_pubkey, false),
-        AccountMeta::new_readonly(*admin_fee_b_pubkey, false),
-        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
-        AccountMeta::new_readonly(*token_a_pubkey, false),
-        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
-        AccountMeta::new_readonly(*token_b_pubkey, false),
-        AccountMeta::new(*pool_mint_pubkey, false),
-        AccountMeta::new(*destination_pubkey, false),
-        AccountMeta::new_readonly(*pool_token_program_id, false),
+        AccountMeta::new(*admin_pubkey, true),
+        AccountMeta::new_readonly(find_factory_address(&crate::ID).0, false),
+        AccountMeta::new(find_template_address(&crate::ID, amp_tier).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
     Ok(Instruction {
@@ -589,41 +2848,62 @@ pub fn initialize(
     })
 }
 
-/// Creates a 'deposit' instruction.
-#[inline(always)]
-pub fn deposit(
+/// Creates a 'create_pool' instruction. The mints must be sorted and the admin
+/// fee accounts must be owned by the admin of the factory.
+pub fn create_pool(
     token_program_id: &Pubkey,
-    swap_pubkey: &Pubkey,
-    swap_authority_key: &Pubkey,
-    user_authority_key: &Pubkey,
-    deposit_token_a_pubkey: &Pubkey,
-    deposit_token_b_pubkey: &Pubkey,
-    swap_token_a_pubkey: &Pubkey,
-    swap_token_b_pubkey: &Pubkey,
-    pool_mint_pubkey: &Pubkey,
-    destination_pubkey: &Pubkey,
+    payer_pubkey: &Pubkey,
+    factory_admin_pubkey: &Pubkey,
+    admin_fee_a_pubkey: &Pubkey,
+    admin_fee_b_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    source_a_pubkey: &Pubkey,
+    source_b_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey, // New account to mint pool tokens to
+    amp_tier: u8,
     token_a_amount: u64,
     token_b_amount: u64,
-    min_mint_amount: u64,
 ) -> Result<Instruction, ProgramError> {
-    let data = SwapInstruction::Deposit(DepositData {
+    let data = FactoryInstruction::CreatePool(CreatePoolData {
+        amp_tier,
         token_a_amount,
         token_b_amount,
-        min_mint_amount,
     })
     .pack();
 
+    let pool = PoolAddresses::find_for_factory(
+        &crate::ID,
+        token_a_mint_pubkey,
+        token_b_mint_pubkey,
+        amp_tier,
+    );
+    let (registry_entry, _) = find_registry_address(
+        &crate::ID,
+        token_a_mint_pubkey,
+        token_b_mint_pubkey,
+        amp_tier,
+    );
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
-        AccountMeta::new_readonly(*swap_authority_key, false),
-        AccountMeta::new_readonly(*user_authority_key, true),
-        AccountMeta::new(*deposit_token_a_pubkey, false),
-        AccountMeta::new(*deposit_token_b_pubkey, false),
-        AccountMeta::new(*swap_token_a_pubkey, false),
-        AccountMeta::new(*swap_token_b_pubkey, false),
-        AccountMeta::new(*pool_mint_pubkey, false),
-        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(find_factory_address(&crate::ID).0, false),
+        AccountMeta::new_readonly(find_template_address(&crate::ID, amp_tier).0, false),
+        AccountMeta::new(registry_entry, false),
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new(pool.swap, false),
+        AccountMeta::new_readonly(pool.authority, false),
+        AccountMeta::new_readonly(*factory_admin_pubkey, false),
+        AccountMeta::new_readonly(*admin_fee_a_pubkey, false),
+        AccountMeta::new_readonly(*admin_fee_b_pubkey, false),
+        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+        AccountMeta::new(pool.token_a_reserves, false),
+        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+        AccountMeta::new(pool.token_b_reserves, false),
+        AccountMeta::new(pool.pool_mint, false),
+        AccountMeta::new(*source_a_pubkey, false),
+        AccountMeta::new(*source_b_pubkey, false),
+        AccountMeta::new(*destination_pubkey, true),
         AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
     Ok(Instruction {
@@ -633,45 +2913,23 @@ pub fn deposit(
     })
 }
 
-/// Creates a 'withdraw' instruction.
-#[inline(always)]
-pub fn withdraw(
-    token_program_id: &Pubkey,
-    swap_pubkey: &Pubkey,
-    swap_authority_key: &Pubkey,
-    user_authority_key: &Pubkey,
-    pool_mint_pubkey: &Pubkey,
-    source_pubkey: &Pubkey,
-    swap_token_a_pubkey: &Pubkey,
-    swap_token_b_pubkey: &Pubkey,
-    destination_token_a_pubkey: &Pubkey,
-    destination_token_b_pubkey: &Pubkey,
-    admin_fee_a_pubkey: &Pubkey,
-    admin_fee_b_pubkey: &Pubkey,
-    pool_token_amount: u64,
-    minimum_token_a_amount: u64,
-    minimum_token_b_amount: u64,
+/// Creates a 'set_global_fee_tier' instruction.
+pub fn set_global_fee_tier(
+    admin_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    fee_multiplier_bps: u16,
 ) -> Result<Instruction, ProgramError> {
-    let data = SwapInstruction::Withdraw(WithdrawData {
-        pool_token_amount,
-        minimum_token_a_amount,
-        minimum_token_b_amount,
-    })
-    .pack();
+    let data = FactoryInstruction::SetGlobalFeeTier(fee_multiplier_bps).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
-        AccountMeta::new_readonly(*swap_authority_key, false),
-        AccountMeta::new_readonly(*user_authority_key, true),
-        AccountMeta::new(*pool_mint_pubkey, false),
-        AccountMeta::new(*source_pubkey, false),
-        AccountMeta::new(*swap_token_a_pubkey, false),
-        AccountMeta::new(*swap_token_b_pubkey, false),
-        AccountMeta::new(*destination_token_a_pubkey, false),
-        AccountMeta::new(*destination_token_b_pubkey, false),
-        AccountMeta::new(*admin_fee_a_pubkey, false),
-        AccountMeta::new(*admin_fee_b_pubkey, false),
-        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new(*admin_pubkey, true),
+        AccountMeta::new_readonly(find_factory_address(&crate::ID).0, false),
+        AccountMeta::new(
+            find_fee_tier_address(&crate::ID, None, user_authority_pubkey).0,
+            false,
+        ),
+        AccountMeta::new_readonly(*user_authority_pubkey, false),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
     Ok(Instruction {
@@ -681,37 +2939,43 @@ pub fn withdraw(
     })
 }
 
-/// Creates a 'swap' instruction.
-#[inline(always)]
-pub fn swap(
-    token_program_id: &Pubkey,
-    swap_pubkey: &Pubkey,
-    swap_authority_key: &Pubkey,
-    user_authority_key: &Pubkey,
-    source_pubkey: &Pubkey,
-    swap_source_pubkey: &Pubkey,
-    swap_destination_pubkey: &Pubkey,
-    destination_pubkey: &Pubkey,
-    admin_fee_destination_pubkey: &Pubkey,
-    amount_in: u64,
-    minimum_amount_out: u64,
+/// Creates a 'remove_global_fee_tier' instruction.
+pub fn remove_global_fee_tier(
+    admin_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    rent_destination_pubkey: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
-    let data = SwapInstruction::Swap(SwapData {
-        amount_in,
-        minimum_amount_out,
+    let data = FactoryInstruction::RemoveGlobalFeeTier.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(find_factory_address(&crate::ID).0, false),
+        AccountMeta::new(
+            find_fee_tier_address(&crate::ID, None, user_authority_pubkey).0,
+            false,
+        ),
+        AccountMeta::new(*rent_destination_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
     })
-    .pack();
+}
+
+/// Creates an 'initialize_gate' instruction.
+pub fn initialize_gate(
+    payer_pubkey: &Pubkey,
+    gatekeeper_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = GateInstruction::InitializeGate.pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
-        AccountMeta::new_readonly(*swap_authority_key, false),
-        AccountMeta::new_readonly(*user_authority_key, true),
-        AccountMeta::new(*source_pubkey, false),
-        AccountMeta::new(*swap_source_pubkey, false),
-        AccountMeta::new(*swap_destination_pubkey, false),
-        AccountMeta::new(*destination_pubkey, false),
-        AccountMeta::new(*admin_fee_destination_pubkey, false),
-        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new(find_gate_address(&crate::ID, gatekeeper_pubkey).0, false),
+        AccountMeta::new_readonly(*gatekeeper_pubkey, true),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
     Ok(Instruction {
@@ -721,39 +2985,68 @@ pub fn swap(
     })
 }
 
-/// Creates a 'withdraw_one' instruction.
-#[inline(always)]
-pub fn withdraw_one(
-    token_program_id: &Pubkey,
-    swap_pubkey: &Pubkey,
-    swap_authority_key: &Pubkey,
-    user_authority_key: &Pubkey,
-    pool_mint_pubkey: &Pubkey,
-    source_pubkey: &Pubkey,
-    swap_base_token_pubkey: &Pubkey,
-    swap_quote_token_pubkey: &Pubkey,
-    base_destination_pubkey: &Pubkey,
-    admin_fee_destination_pubkey: &Pubkey,
-    pool_token_amount: u64,
-    minimum_token_amount: u64,
+/// Creates a 'set_gatekeeper' instruction.
+pub fn set_gatekeeper(
+    gate_pubkey: &Pubkey,
+    gatekeeper_pubkey: &Pubkey,
+    new_gatekeeper_pubkey: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
-    let data = SwapInstruction::WithdrawOne(WithdrawOneData {
-        pool_token_amount,
-        minimum_token_amount,
+    let data = GateInstruction::SetGatekeeper.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*gate_pubkey, false),
+        AccountMeta::new_readonly(*gatekeeper_pubkey, true),
+        AccountMeta::new_readonly(*new_gatekeeper_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'add_to_allowlist' instruction.
+pub fn add_to_allowlist(
+    gate_pubkey: &Pubkey,
+    gatekeeper_pubkey: &Pubkey,
+    payer_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = GateInstruction::AddToAllowlist.pack();
+
+    let (entry, _) = find_allowlist_entry_address(&crate::ID, gate_pubkey, user_authority_pubkey);
+    let accounts = vec![
+        AccountMeta::new_readonly(*gate_pubkey, false),
+        AccountMeta::new_readonly(*gatekeeper_pubkey, true),
+        AccountMeta::new(*payer_pubkey, true),
+        AccountMeta::new(entry, false),
+        AccountMeta::new_readonly(*user_authority_pubkey, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
     })
-    .pack();
+}
+
+/// Creates a 'remove_from_allowlist' instruction.
+pub fn remove_from_allowlist(
+    gate_pubkey: &Pubkey,
+    gatekeeper_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    rent_destination_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = GateInstruction::RemoveFromAllowlist.pack();
 
+    let (entry, _) = find_allowlist_entry_address(&crate::ID, gate_pubkey, user_authority_pubkey);
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
-        AccountMeta::new_readonly(*swap_authority_key, false),
-        AccountMeta::new_readonly(*user_authority_key, true),
-        AccountMeta::new(*pool_mint_pubkey, false),
-        AccountMeta::new(*source_pubkey, false),
-        AccountMeta::new(*swap_base_token_pubkey, false),
-        AccountMeta::new(*swap_quote_token_pubkey, false),
-        AccountMeta::new(*base_destination_pubkey, false),
-        AccountMeta::new(*admin_fee_destination_pubkey, false),
-        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(*gate_pubkey, false),
+        AccountMeta::new_readonly(*gatekeeper_pubkey, true),
+        AccountMeta::new(entry, false),
+        AccountMeta::new(*rent_destination_pubkey, false),
     ];
 
     Ok(Instruction {
@@ -777,6 +3070,20 @@ fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
     }
 }
 
+fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+    if input.len() >= 2 {
+        let (amount, rest) = input.split_at(2);
+        let amount = amount
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(SwapError::InvalidInstruction)?;
+        Ok((amount, rest))
+    } else {
+        Err(SwapError::InvalidInstruction.into())
+    }
+}
+
 fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
     if input.len() >= 8 {
         let (amount, rest) = input.split_at(8);
@@ -811,6 +3118,7 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = AdminInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
 
         let check = AdminInstruction::StopRampA;
         let packed = check.pack();
@@ -818,6 +3126,8 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = AdminInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, Some(check));
+        assert!(!check.requires_timelock());
+        assert!(!check.can_be_queued());
 
         let check = AdminInstruction::Pause;
         let packed = check.pack();
@@ -825,6 +3135,8 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = AdminInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, Some(check));
+        assert!(!check.requires_timelock());
+        assert!(!check.can_be_queued());
 
         let check = AdminInstruction::Unpause;
         let packed = check.pack();
@@ -846,6 +3158,7 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = AdminInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, Some(check));
+        assert!(!check.requires_timelock());
 
         let check = AdminInstruction::CommitNewAdmin;
         let packed = check.pack();
@@ -853,6 +3166,205 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = AdminInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+
+        let check = AdminInstruction::SetAdminFeeMode(AdminFeeMode::Accrue);
+        let packed = check.pack();
+        let expect = vec![109_u8, 1];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(AdminInstruction::unpack(&[109_u8, 3]).is_err());
+
+        let check = AdminInstruction::ClosePool;
+        let packed = check.pack();
+        let expect = vec![110_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+
+        let check = AdminInstruction::SetPauseFlags(PauseFlags::SWAP.union(PauseFlags::DEPOSIT));
+        let packed = check.pack();
+        let expect = vec![111_u8, 3];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(AdminInstruction::unpack(&[111_u8, 1 << 5]).is_err());
+
+        let check = AdminInstruction::SetGuardian;
+        let packed = check.pack();
+        let expect = vec![112_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+
+        let check = AdminInstruction::GrantRole(AdminRole::FeeManager);
+        let packed = check.pack();
+        let expect = vec![113_u8, 2];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+
+        let check = AdminInstruction::RevokeRole(AdminRole::PauseManager);
+        let packed = check.pack();
+        let expect = vec![114_u8, 5];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+        assert!(AdminInstruction::unpack(&[114_u8, 6]).is_err());
+
+        let check = AdminInstruction::SetTimelockDelay(86400);
+        let packed = check.pack();
+        let mut expect = vec![115_u8];
+        expect.extend_from_slice(&86400_i64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = AdminInstruction::InitializeAdminQueue;
+        let packed = check.pack();
+        let expect = vec![116_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let action = AdminInstruction::SetAdminFeeMode(AdminFeeMode::Accrue);
+        let check = AdminInstruction::QueueAdminAction(AdminActionData::new(&action).unwrap());
+        let packed = check.pack();
+        let expect = vec![117_u8, 109, 1];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert_eq!(
+            AdminActionData::new(&AdminInstruction::InitializeAdminQueue),
+            Err(SwapError::InvalidInstruction.into())
+        );
+        assert!(AdminInstruction::unpack(&[117_u8, 116]).is_err());
+        // instructions taking effect at once cannot be queued
+        assert!(AdminInstruction::unpack(&[117_u8, 102]).is_err());
+        assert!(AdminInstruction::unpack(&[117_u8, 117, 109, 1]).is_err());
+
+        let check = AdminInstruction::CancelAdminAction(3);
+        let packed = check.pack();
+        let expect = vec![118_u8, 3];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = AdminInstruction::ExecuteAdminAction(4);
+        let packed = check.pack();
+        let expect = vec![119_u8, 4];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = AdminInstruction::AcceptAdmin;
+        let packed = check.pack();
+        let expect = vec![120_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(!check.requires_timelock());
+        assert!(!check.can_be_queued());
+
+        let check = AdminInstruction::CancelAdminTransfer;
+        let packed = check.pack();
+        let expect = vec![121_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = AdminInstruction::RenounceAdmin;
+        let packed = check.pack();
+        let expect = vec![122_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = AdminInstruction::MigrateSwapInfo;
+        let packed = check.pack();
+        let expect = vec![123_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert_eq!(
+            AdminActionData::new(&check),
+            Err(SwapError::InvalidInstruction.into())
+        );
+
+        let fee_multiplier_bps: u16 = 2_500;
+        let check = AdminInstruction::SetFeeTier(fee_multiplier_bps);
+        let packed = check.pack();
+        let mut expect = vec![124_u8];
+        expect.extend_from_slice(&fee_multiplier_bps.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+        assert!(check.can_be_queued());
+        assert!(AdminInstruction::unpack(&expect[..2]).is_err());
+
+        let check = AdminInstruction::RemoveFeeTier;
+        let packed = check.pack();
+        let expect = vec![125_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+        assert!(check.can_be_queued());
+
+        let check = AdminInstruction::SetGate;
+        let packed = check.pack();
+        let expect = vec![126_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+
+        let max_price_deviation_bps: u16 = 300;
+        let window_slots: u64 = 150;
+        let check = AdminInstruction::SetCircuitBreaker(CircuitBreakerData {
+            max_price_deviation_bps,
+            window_slots,
+        });
+        let packed = check.pack();
+        let mut expect = vec![127_u8];
+        expect.extend_from_slice(&max_price_deviation_bps.to_le_bytes());
+        expect.extend_from_slice(&window_slots.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
+        assert!(AdminInstruction::unpack(&expect[..3]).is_err());
+
+        let check = AdminInstruction::ResetCircuitBreaker;
+        let packed = check.pack();
+        let expect = vec![128_u8];
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(!check.requires_timelock());
+        assert!(!check.can_be_queued());
+        assert!(check.allowed_roles().contains(&AdminRole::Guardian));
+
+        let max_deviation_bps: u16 = 100;
+        let max_staleness_slots: u64 = 25;
+        let check = AdminInstruction::SetPriceOracle(PriceOracleData {
+            max_deviation_bps,
+            max_staleness_slots,
+        });
+        let packed = check.pack();
+        let mut expect = vec![129_u8];
+        expect.extend_from_slice(&max_deviation_bps.to_le_bytes());
+        expect.extend_from_slice(&max_staleness_slots.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(check.requires_timelock());
 
         let new_fees = Fees {
             admin_trade_fee_numerator: 1,
@@ -873,6 +3385,23 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = AdminInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, Some(check));
+
+        let extra_fees = ExtraFees {
+            flash_loan_fee_numerator: 9,
+            flash_loan_fee_denominator: 10,
+            referral_fee_numerator: 11,
+            referral_fee_denominator: 12,
+        };
+        let check = AdminInstruction::SetExtraFees(extra_fees);
+        let packed = check.pack();
+        let mut expect = vec![108_u8];
+        let mut extra_fees_slice = [0u8; ExtraFees::LEN];
+        extra_fees.pack_into_slice(&mut extra_fees_slice[..]);
+        expect.extend_from_slice(&extra_fees_slice);
+        assert_eq!(packed, expect);
+        let unpacked = AdminInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        assert!(AdminInstruction::unpack(&expect[..ExtraFees::LEN]).is_err());
     }
 
     #[test]
@@ -965,5 +3494,238 @@ mod tests {
         assert_eq!(packed, expect);
         let unpacked = SwapInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
+
+        let amount: u64 = 1_000;
+        let check = SwapInstruction::FlashLoan(FlashLoanData { amount });
+        let packed = check.pack();
+        let mut expect = vec![5];
+        expect.extend_from_slice(&amount.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::ClaimAdminFees;
+        let packed = check.pack();
+        let expect = vec![6];
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let token_a_amount: u64 = 100;
+        let token_b_amount: u64 = 200;
+        let check = SwapInstruction::InitializeV2(InitializeV2Data {
+            amp_factor,
+            fees,
+            token_a_amount,
+            token_b_amount,
+        });
+        let packed = check.pack();
+        let mut expect = vec![7];
+        expect.extend_from_slice(&amp_factor.to_le_bytes());
+        expect.extend_from_slice(&fees_slice);
+        expect.extend_from_slice(&token_a_amount.to_le_bytes());
+        expect.extend_from_slice(&token_b_amount.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::QuoteSwap(QuoteSwapData { amount_in: 12 });
+        let packed = check.pack();
+        let mut expect = vec![8];
+        expect.extend_from_slice(&12_u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::QuoteDeposit(QuoteDepositData {
+            token_a_amount: 13,
+            token_b_amount: 14,
+        });
+        let packed = check.pack();
+        let mut expect = vec![9];
+        expect.extend_from_slice(&13_u64.to_le_bytes());
+        expect.extend_from_slice(&14_u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::QuoteWithdrawOne(QuoteWithdrawOneData {
+            pool_token_amount: 15,
+        });
+        let packed = check.pack();
+        let mut expect = vec![10];
+        expect.extend_from_slice(&15_u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::GetVirtualPrice;
+        let packed = check.pack();
+        let expect = vec![11];
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::SwapWithReferral(SwapData {
+            amount_in: 16,
+            minimum_amount_out: 17,
+        });
+        let packed = check.pack();
+        let mut expect = vec![12];
+        expect.extend_from_slice(&16_u64.to_le_bytes());
+        expect.extend_from_slice(&17_u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_receive_flash_loan_packing() {
+        let check = ReceiveFlashLoanData { amount: 50, fee: 3 };
+        let packed = check.pack();
+        let mut expect = vec![0_u8];
+        expect.extend_from_slice(&50_u64.to_le_bytes());
+        expect.extend_from_slice(&3_u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = ReceiveFlashLoanData::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        expect[0] = 1;
+        assert!(ReceiveFlashLoanData::unpack(&expect).is_err());
+    }
+
+    #[test]
+    fn test_factory_instruction_packing() {
+        let check = FactoryInstruction::InitializeFactory;
+        let packed = check.pack();
+        let expect = vec![200];
+        assert_eq!(packed, expect);
+        let unpacked = FactoryInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = FactoryInstruction::SetFactoryAdmin;
+        let packed = check.pack();
+        let expect = vec![201];
+        assert_eq!(packed, expect);
+        let unpacked = FactoryInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let amp_tier: u8 = 3;
+        let amp_factor: u64 = 100;
+        let fees = Fees {
+            trade_fee_numerator: 4,
+            trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let check = FactoryInstruction::SetPoolTemplate(PoolTemplateData {
+            amp_tier,
+            amp_factor,
+            fees,
+            is_enabled: true,
+        });
+        let packed = check.pack();
+        let mut expect = vec![202, amp_tier];
+        expect.extend_from_slice(&amp_factor.to_le_bytes());
+        let mut fees_slice = [0u8; Fees::LEN];
+        fees.pack_into_slice(&mut fees_slice[..]);
+        expect.extend_from_slice(&fees_slice);
+        expect.push(1);
+        assert_eq!(packed, expect);
+        let unpacked = FactoryInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+        // invalid enabled flag
+        *expect.last_mut().unwrap() = 2;
+        assert!(FactoryInstruction::unpack(&expect).is_err());
+
+        let token_a_amount: u64 = 10;
+        let token_b_amount: u64 = 20;
+        let check = FactoryInstruction::CreatePool(CreatePoolData {
+            amp_tier,
+            token_a_amount,
+            token_b_amount,
+        });
+        let packed = check.pack();
+        let mut expect = vec![203, amp_tier];
+        expect.extend_from_slice(&token_a_amount.to_le_bytes());
+        expect.extend_from_slice(&token_b_amount.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = FactoryInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let fee_multiplier_bps: u16 = 5_000;
+        let check = FactoryInstruction::SetGlobalFeeTier(fee_multiplier_bps);
+        let packed = check.pack();
+        let mut expect = vec![204];
+        expect.extend_from_slice(&fee_multiplier_bps.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = FactoryInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        let check = FactoryInstruction::RemoveGlobalFeeTier;
+        let packed = check.pack();
+        let expect = vec![205];
+        assert_eq!(packed, expect);
+        let unpacked = FactoryInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, Some(check));
+
+        // swap instructions are not factory instructions
+        assert_eq!(FactoryInstruction::unpack(&[1]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_swap_with_referral_accounts() {
+        let keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+        let ix = swap_with_referral(
+            &keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5], &keys[6], &keys[7],
+            &keys[8], None, 10, 1,
+        )
+        .unwrap();
+        assert_eq!(ix.accounts.len(), 10);
+        assert_eq!(ix.accounts[9].pubkey, crate::ID);
+
+        let ix = with_fee_tier(ix, &keys[9]);
+        assert_eq!(ix.accounts[9].pubkey, crate::ID);
+        assert_eq!(ix.accounts[10].pubkey, keys[9]);
+
+        let ix = with_pool_mint(ix, &keys[8]);
+        assert_eq!(ix.accounts.len(), 14);
+        assert_eq!(ix.accounts[12].pubkey, crate::ID);
+        assert_eq!(ix.accounts[13].pubkey, keys[8]);
+    }
+
+    #[test]
+    fn test_gate_instruction_packing() {
+        for (check, tag) in [
+            (GateInstruction::InitializeGate, 210),
+            (GateInstruction::SetGatekeeper, 211),
+            (GateInstruction::AddToAllowlist, 212),
+            (GateInstruction::RemoveFromAllowlist, 213),
+        ] {
+            let packed = check.pack();
+            assert_eq!(packed, vec![tag]);
+            let unpacked = GateInstruction::unpack(&packed).unwrap();
+            assert_eq!(unpacked, Some(check));
+        }
+
+        // factory instructions are not gate instructions
+        assert_eq!(GateInstruction::unpack(&[200]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_with_allowlist_entry() {
+        let entry = Pubkey::new_unique();
+        let mints = (Pubkey::new_unique(), Pubkey::new_unique());
+        let keys: Vec<Pubkey> = (0..9).map(|_| Pubkey::new_unique()).collect();
+        let ix = swap(
+            &keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5], &keys[6], &keys[7],
+            &keys[8], 10, 1,
+        )
+        .unwrap();
+        let ix = with_allowlist_entry(ix, &entry);
+        let ix = with_token_2022_mints(ix, &mints.0, &mints.1);
+        assert_eq!(ix.accounts.len(), 13);
+        assert_eq!(ix.accounts[9].pubkey, crate::ID);
+        assert_eq!(ix.accounts[10].pubkey, entry);
+        assert_eq!(ix.accounts[12].pubkey, mints.1);
     }
 }
@@ -5,9 +5,15 @@
 #![deny(missing_docs)]
 
 pub mod error;
+pub mod events;
 pub mod fees;
 pub mod instruction;
+pub mod logs;
+pub mod oracle;
+pub mod pda;
+pub mod results;
 pub mod state;
+pub mod token_2022;
 
 // Export current solana-program types for downstream users who may also be
 // building with a different solana-program version
@@ -0,0 +1,764 @@
+//! Parser for the log messages of transactions that invoke the program.
+//!
+//! The program logs events as an `Event: <name>` line followed by the
+//! `sol_log_64` values of the event, logs slippage errors with `sol_log_64`,
+//! logs admin changes with `Admin: ...` lines and emits Borsh-encoded
+//! [Event]s with `sol_log_data`. [parse_logs] turns the log messages of a
+//! transaction into typed values, attributing every line to the invocation of
+//! the program that logged it so that CPIs and instructions on multiple pools
+//! can be told apart.
+
+use crate::{
+    events::Event,
+    fees::{ExtraFees, Fees},
+    state::{AdminFeeMode, AdminRole, PauseFlags},
+};
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// An event logged by the program as an `Event: <name>` line followed by its
+/// `sol_log_64` values, or a slippage error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StableSwapEvent {
+    /// Pool tokens were minted for a deposit.
+    Deposit {
+        /// Amount of token A deposited
+        token_a_amount: u64,
+        /// Amount of token B deposited
+        token_b_amount: u64,
+        /// Amount of pool tokens minted
+        pool_token_amount: u64,
+    },
+    /// Pool tokens were burned by a withdrawal.
+    Burn {
+        /// Amount of pool tokens burned
+        pool_token_amount: u64,
+    },
+    /// Token A was swapped for token B.
+    SwapAToB {
+        /// Amount of token A transferred from the user
+        amount_in: u64,
+        /// Amount of token B received by the user, net of any transfer fee
+        amount_out: u64,
+        /// Trade fee, in token B
+        fee: u64,
+    },
+    /// Token B was swapped for token A.
+    SwapBToA {
+        /// Amount of token B transferred from the user
+        amount_in: u64,
+        /// Amount of token A received by the user, net of any transfer fee
+        amount_out: u64,
+        /// Trade fee, in token A
+        fee: u64,
+    },
+    /// Token A was withdrawn.
+    WithdrawA {
+        /// Amount of token A received by the user, net of any transfer fee
+        token_a_amount: u64,
+        /// Fee, in token A
+        fee: u64,
+    },
+    /// Token B was withdrawn.
+    WithdrawB {
+        /// Amount of token B received by the user, net of any transfer fee
+        token_b_amount: u64,
+        /// Fee, in token B
+        fee: u64,
+    },
+    /// A flash loan was repaid.
+    FlashLoan {
+        /// Amount of token A borrowed
+        token_a_amount: u64,
+        /// Amount of token B borrowed
+        token_b_amount: u64,
+        /// Flash loan fee
+        fee: u64,
+    },
+    /// Accrued admin fees were claimed.
+    ClaimAdminFees {
+        /// Amount of token A claimed
+        token_a_amount: u64,
+        /// Amount of token B claimed
+        token_b_amount: u64,
+        /// Amount of pool tokens minted as admin fees
+        pool_token_amount: u64,
+    },
+    /// The instruction failed because it exceeded the slippage limit.
+    ExceededSlippage {
+        /// Minimum amount requested by the user
+        minimum_amount: u64,
+        /// Amount computed by the program
+        computed_amount: u64,
+    },
+}
+
+/// An admin fee account of the swap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeAccountKind {
+    /// Admin fee account of token A
+    TokenA,
+    /// Admin fee account of token B
+    TokenB,
+    /// Admin fee account of the pool token
+    PoolToken,
+}
+
+/// A change made by an admin instruction, parsed from its `Admin: ...` line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdminChange {
+    /// A started ramping.
+    RampA {
+        /// Target amplification coefficient
+        target_amp: u64,
+        /// Timestamp at which the ramp ends
+        stop_ramp_ts: i64,
+    },
+    /// A stopped ramping.
+    StopRampA {
+        /// Amplification coefficient the ramp stopped at
+        amp: u64,
+    },
+    /// The swap was paused.
+    Paused,
+    /// The swap was unpaused.
+    Unpaused,
+    /// The pause flags were set.
+    PauseFlags(PauseFlags),
+    /// The guardian was set.
+    GuardianSet(Pubkey),
+    /// A role was granted.
+    RoleGranted {
+        /// The role
+        role: AdminRole,
+        /// The key holding the role
+        key: Pubkey,
+    },
+    /// A role was revoked.
+    RoleRevoked(AdminRole),
+    /// The timelock delay was set.
+    TimelockDelaySet(i64),
+    /// The admin queue was initialized.
+    AdminQueueInitialized(Pubkey),
+    /// An admin action was queued.
+    AdminActionQueued {
+        /// Index of the action in the admin queue
+        index: u8,
+        /// Time after which the action can be executed
+        eta: i64,
+    },
+    /// A queued admin action was cancelled.
+    AdminActionCancelled(u8),
+    /// A queued admin action was executed.
+    AdminActionExecuted(u8),
+    /// An admin fee account was set.
+    FeeAccountSet {
+        /// The admin fee account that was set
+        kind: FeeAccountKind,
+        /// The new admin fee account
+        account: Pubkey,
+    },
+    /// An admin transfer was started.
+    AdminTransferStarted {
+        /// The future admin
+        new_admin: Pubkey,
+        /// Deadline for the transfer to be finalized
+        deadline: i64,
+    },
+    /// An admin transfer was finalized.
+    AdminTransferFinalized(Pubkey),
+    /// An admin transfer was cancelled.
+    AdminTransferCancelled(Pubkey),
+    /// The admin was renounced.
+    AdminRenounced(Pubkey),
+    /// The swap was migrated to a version of the layout.
+    SwapInfoMigrated(u8),
+    /// The fees were updated.
+    NewFees(Fees),
+    /// The flash loan and referral fees were updated.
+    NewExtraFees(ExtraFees),
+    /// The admin fee mode was set.
+    AdminFeeMode(AdminFeeMode),
+    /// The pool was closed.
+    PoolClosed {
+        /// Lamports returned from the swap account
+        lamports: u64,
+    },
+    /// The gate was set. The default key removes the gate.
+    GateSet(Pubkey),
+    /// The circuit breaker was set.
+    CircuitBreakerSet {
+        /// Maximum deviation of the marginal price within a window, in bps
+        max_price_deviation_bps: u16,
+        /// Length of the window, in slots
+        window_slots: u64,
+    },
+    /// The reference price of the circuit breaker was reset.
+    CircuitBreakerReset,
+    /// The price oracle was set.
+    PriceOracleSet {
+        /// The Pyth price feed
+        feed: Pubkey,
+        /// Maximum deviation of the pool price from the feed, in bps
+        max_deviation_bps: u16,
+    },
+    /// The price oracle was removed.
+    PriceOracleRemoved,
+}
+
+/// A value parsed from the logs of the program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogEntry {
+    /// An event logged with text and `sol_log_64`
+    Event(StableSwapEvent),
+    /// An admin change
+    Admin(AdminChange),
+    /// A Borsh-encoded event logged with `sol_log_data`
+    Data(Event),
+}
+
+/// A value parsed from the logs, with the invocation of the program that
+/// logged it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedLog {
+    /// Index of the top-level instruction of the transaction
+    pub instruction_index: usize,
+    /// Invocation depth of the program, 1 if it was invoked by the transaction
+    pub depth: usize,
+    /// Index of the invocation among all invocations of the program in the
+    /// transaction, in execution order. Matches the order of the program's
+    /// instructions in the transaction and its inner instructions.
+    pub invocation: usize,
+    /// The parsed value
+    pub entry: LogEntry,
+}
+
+/// An invocation of a program that is being executed.
+struct Frame {
+    /// True if the invoked program is the StableSwap program
+    is_swap: bool,
+    invocation: usize,
+    /// Name of an `Event: <name>` line waiting for its values
+    pending_event: Option<String>,
+}
+
+/// Parses the log messages of a transaction into the values logged by the
+/// StableSwap program `program_id`. Lines logged by other programs are
+/// ignored, and unknown lines of the program are skipped.
+pub fn parse_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<ParsedLog> {
+    let invoke_prefix = format!("Program {} invoke [", program_id);
+    let mut parsed = vec![];
+    let mut stack: Vec<Frame> = vec![];
+    let mut instruction_count = 0_usize;
+    let mut invocation_count = 0_usize;
+
+    for line in logs.iter().map(AsRef::as_ref) {
+        if line.starts_with("Program ") && line.contains(" invoke [") {
+            let is_swap = line.starts_with(&invoke_prefix);
+            if stack.is_empty() {
+                instruction_count = instruction_count.saturating_add(1);
+            }
+            stack.push(Frame {
+                is_swap,
+                invocation: invocation_count,
+                pending_event: None,
+            });
+            if is_swap {
+                invocation_count = invocation_count.saturating_add(1);
+            }
+            continue;
+        }
+        if line.starts_with("Program ")
+            && (line.ends_with(" success") || line.contains(" failed: "))
+        {
+            stack.pop();
+            continue;
+        }
+
+        let depth = stack.len();
+        let frame = match stack.last_mut() {
+            Some(frame) if frame.is_swap => frame,
+            _ => continue,
+        };
+        let entry = if let Some(message) = line.strip_prefix("Program log: ") {
+            parse_message(frame, message)
+        } else if let Some(data) = line.strip_prefix("Program data: ") {
+            parse_data(data).map(LogEntry::Data)
+        } else {
+            None
+        };
+        if let Some(entry) = entry {
+            parsed.push(ParsedLog {
+                instruction_index: instruction_count.saturating_sub(1),
+                depth,
+                invocation: frame.invocation,
+                entry,
+            });
+        }
+    }
+    parsed
+}
+
+fn parse_message(frame: &mut Frame, message: &str) -> Option<LogEntry> {
+    if let Some(name) = message.strip_prefix("Event: ") {
+        frame.pending_event = Some(name.to_string());
+        return None;
+    }
+    if let Some(change) = message.strip_prefix("Admin: ") {
+        return parse_admin_change(change).map(LogEntry::Admin);
+    }
+    let values = parse_log_64(message)?;
+    let event = match frame.pending_event.take() {
+        Some(name) => parse_event(&name, values)?,
+        None => match values {
+            [0, 0, 0, minimum_amount, computed_amount] => StableSwapEvent::ExceededSlippage {
+                minimum_amount,
+                computed_amount,
+            },
+            _ => return None,
+        },
+    };
+    Some(LogEntry::Event(event))
+}
+
+/// Parses the `sol_log_64` format, five comma-separated hex numbers.
+fn parse_log_64(message: &str) -> Option<[u64; 5]> {
+    let mut values = [0_u64; 5];
+    let mut parts = message.split(", ");
+    for value in values.iter_mut() {
+        let part = parts.next()?.strip_prefix("0x")?;
+        *value = u64::from_str_radix(part, 16).ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(values)
+}
+
+fn parse_event(name: &str, values: [u64; 5]) -> Option<StableSwapEvent> {
+    let [kind, token_a_amount, token_b_amount, pool_token_amount, fee] = values;
+    Some(match (name, kind) {
+        ("Burn", 0) => StableSwapEvent::Burn { pool_token_amount },
+        ("Deposit", 1) => StableSwapEvent::Deposit {
+            token_a_amount,
+            token_b_amount,
+            pool_token_amount,
+        },
+        ("SwapAToB", 2) => StableSwapEvent::SwapAToB {
+            amount_in: token_a_amount,
+            amount_out: token_b_amount,
+            fee,
+        },
+        ("SwapBToA", 3) => StableSwapEvent::SwapBToA {
+            amount_in: token_b_amount,
+            amount_out: token_a_amount,
+            fee,
+        },
+        ("WithdrawA", 4) => StableSwapEvent::WithdrawA {
+            token_a_amount,
+            fee,
+        },
+        ("WithdrawB", 5) => StableSwapEvent::WithdrawB {
+            token_b_amount,
+            fee,
+        },
+        ("FlashLoan", 6) => StableSwapEvent::FlashLoan {
+            token_a_amount,
+            token_b_amount,
+            fee,
+        },
+        ("ClaimAdminFees", 7) => StableSwapEvent::ClaimAdminFees {
+            token_a_amount,
+            token_b_amount,
+            pool_token_amount,
+        },
+        _ => return None,
+    })
+}
+
+fn parse_data(data: &str) -> Option<Event> {
+    let mut bytes = vec![];
+    for field in data.split(' ') {
+        bytes.extend(base64::decode(field).ok()?);
+    }
+    Event::decode(&bytes).ok()
+}
+
+fn parse_admin_change(change: &str) -> Option<AdminChange> {
+    if let Some(rest) = change.strip_prefix("Ramping A to ") {
+        let (target_amp, stop_ramp_ts) = rest.split_once(", ending at ")?;
+        return Some(AdminChange::RampA {
+            target_amp: target_amp.parse().ok()?,
+            stop_ramp_ts: stop_ramp_ts.parse().ok()?,
+        });
+    }
+    if let Some(amp) = change.strip_prefix("Current A set to ") {
+        return Some(AdminChange::StopRampA {
+            amp: amp.parse().ok()?,
+        });
+    }
+    if let Some(rest) = change.strip_prefix("Starting admin transfer to ") {
+        let (new_admin, deadline) = rest.split_once(", deadline at ")?;
+        return Some(AdminChange::AdminTransferStarted {
+            new_admin: Pubkey::from_str(new_admin).ok()?,
+            deadline: deadline.parse().ok()?,
+        });
+    }
+    if let Some(rest) = change.strip_prefix("Granted ") {
+        let (role, key) = rest.split_once(" to ")?;
+        return Some(AdminChange::RoleGranted {
+            role: parse_role(role)?,
+            key: Pubkey::from_str(key).ok()?,
+        });
+    }
+    if let Some(rest) = change.strip_prefix("Queued admin action ") {
+        let (index, eta) = rest.split_once(" executable at ")?;
+        return Some(AdminChange::AdminActionQueued {
+            index: index.parse().ok()?,
+            eta: eta.parse().ok()?,
+        });
+    }
+    if let Some(rest) = change.strip_prefix("Fee account ") {
+        let (kind, account) = rest.split_once(" set to ")?;
+        let kind = match kind {
+            "A" => FeeAccountKind::TokenA,
+            "B" => FeeAccountKind::TokenB,
+            "LP" => FeeAccountKind::PoolToken,
+            _ => return None,
+        };
+        return Some(AdminChange::FeeAccountSet {
+            kind,
+            account: Pubkey::from_str(account).ok()?,
+        });
+    }
+    if let Some(lamports) = change
+        .strip_prefix("Closed pool, returned ")
+        .and_then(|rest| rest.strip_suffix(" lamports"))
+    {
+        return Some(AdminChange::PoolClosed {
+            lamports: lamports.parse().ok()?,
+        });
+    }
+    if let Some(rest) = change
+        .strip_prefix("Circuit breaker set to ")
+        .and_then(|rest| rest.strip_suffix(" slots"))
+    {
+        let (max_price_deviation_bps, window_slots) = rest.split_once(" bps per ")?;
+        return Some(AdminChange::CircuitBreakerSet {
+            max_price_deviation_bps: max_price_deviation_bps.parse().ok()?,
+            window_slots: window_slots.parse().ok()?,
+        });
+    }
+    if let Some(rest) = change
+        .strip_prefix("Price oracle set to ")
+        .and_then(|rest| rest.strip_suffix(" bps"))
+    {
+        let (feed, max_deviation_bps) = rest.split_once(" within ")?;
+        return Some(AdminChange::PriceOracleSet {
+            feed: Pubkey::from_str(feed).ok()?,
+            max_deviation_bps: max_deviation_bps.parse().ok()?,
+        });
+    }
+    if let Some(fees) = change.strip_prefix("New fees ") {
+        return parse_fees(fees).map(AdminChange::NewFees);
+    }
+    if let Some(extra_fees) = change.strip_prefix("New extra fees ") {
+        return parse_extra_fees(extra_fees).map(AdminChange::NewExtraFees);
+    }
+    if let Some(mode) = change.strip_prefix("New admin fee mode ") {
+        let mode = match mode {
+            "Transfer" => AdminFeeMode::Transfer,
+            "Accrue" => AdminFeeMode::Accrue,
+            "LpMint" => AdminFeeMode::LpMint,
+            _ => return None,
+        };
+        return Some(AdminChange::AdminFeeMode(mode));
+    }
+    Some(match change {
+        "Program paused" => AdminChange::Paused,
+        "Program unpaused" => AdminChange::Unpaused,
+        "Circuit breaker reset" => AdminChange::CircuitBreakerReset,
+        "Price oracle removed" => AdminChange::PriceOracleRemoved,
+        _ => {
+            if let Some(flags) = change.strip_prefix("Pause flags set to ") {
+                AdminChange::PauseFlags(PauseFlags::from_bits(flags.parse().ok()?)?)
+            } else if let Some(key) = change.strip_prefix("Gate set to ") {
+                AdminChange::GateSet(Pubkey::from_str(key).ok()?)
+            } else if let Some(key) = change.strip_prefix("Guardian set to ") {
+                AdminChange::GuardianSet(Pubkey::from_str(key).ok()?)
+            } else if let Some(role) = change.strip_prefix("Revoked ") {
+                AdminChange::RoleRevoked(parse_role(role)?)
+            } else if let Some(delay) = change.strip_prefix("Timelock delay set to ") {
+                AdminChange::TimelockDelaySet(delay.parse().ok()?)
+            } else if let Some(key) = change.strip_prefix("Initialized admin queue ") {
+                AdminChange::AdminQueueInitialized(Pubkey::from_str(key).ok()?)
+            } else if let Some(index) = change.strip_prefix("Cancelled admin action ") {
+                AdminChange::AdminActionCancelled(index.parse().ok()?)
+            } else if let Some(index) = change.strip_prefix("Executing admin action ") {
+                AdminChange::AdminActionExecuted(index.parse().ok()?)
+            } else if let Some(key) = change.strip_prefix("Finalized new admin ") {
+                AdminChange::AdminTransferFinalized(Pubkey::from_str(key).ok()?)
+            } else if let Some(key) = change.strip_prefix("Cancelled admin transfer to ") {
+                AdminChange::AdminTransferCancelled(Pubkey::from_str(key).ok()?)
+            } else if let Some(key) = change.strip_prefix("Renouncing admin ") {
+                AdminChange::AdminRenounced(Pubkey::from_str(key).ok()?)
+            } else if let Some(version) = change.strip_prefix("Migrated swap info to version ") {
+                AdminChange::SwapInfoMigrated(version.parse().ok()?)
+            } else {
+                return None;
+            }
+        }
+    })
+}
+
+fn parse_role(role: &str) -> Option<AdminRole> {
+    Some(match role {
+        "Admin" => AdminRole::Admin,
+        "Guardian" => AdminRole::Guardian,
+        "FeeManager" => AdminRole::FeeManager,
+        "AmpManager" => AdminRole::AmpManager,
+        "FeeAccountManager" => AdminRole::FeeAccountManager,
+        "PauseManager" => AdminRole::PauseManager,
+        _ => return None,
+    })
+}
+
+/// Parses the `Debug` output of [Fees].
+fn parse_fees(fees: &str) -> Option<Fees> {
+    let fields = fees.strip_prefix("Fees { ")?.strip_suffix(" }")?;
+    let mut parsed = Fees::default();
+    for field in fields.split(", ") {
+        let (name, value) = field.split_once(": ")?;
+        let value = value.parse().ok()?;
+        match name {
+            "admin_trade_fee_numerator" => parsed.admin_trade_fee_numerator = value,
+            "admin_trade_fee_denominator" => parsed.admin_trade_fee_denominator = value,
+            "admin_withdraw_fee_numerator" => parsed.admin_withdraw_fee_numerator = value,
+            "admin_withdraw_fee_denominator" => parsed.admin_withdraw_fee_denominator = value,
+            "trade_fee_numerator" => parsed.trade_fee_numerator = value,
+            "trade_fee_denominator" => parsed.trade_fee_denominator = value,
+            "withdraw_fee_numerator" => parsed.withdraw_fee_numerator = value,
+            "withdraw_fee_denominator" => parsed.withdraw_fee_denominator = value,
+            _ => return None,
+        }
+    }
+    Some(parsed)
+}
+
+/// Parses the `Debug` output of [ExtraFees].
+fn parse_extra_fees(extra_fees: &str) -> Option<ExtraFees> {
+    let fields = extra_fees
+        .strip_prefix("ExtraFees { ")?
+        .strip_suffix(" }")?;
+    let mut parsed = ExtraFees::default();
+    for field in fields.split(", ") {
+        let (name, value) = field.split_once(": ")?;
+        let value = value.parse().ok()?;
+        match name {
+            "flash_loan_fee_numerator" => parsed.flash_loan_fee_numerator = value,
+            "flash_loan_fee_denominator" => parsed.flash_loan_fee_denominator = value,
+            "referral_fee_numerator" => parsed.referral_fee_numerator = value,
+            "referral_fee_denominator" => parsed.referral_fee_denominator = value,
+            _ => return None,
+        }
+    }
+    Some(parsed)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::events::{Reserves, SwapEvent};
+
+    #[test]
+    fn test_parse_logs() {
+        let program_id = crate::ID;
+        let aggregator_id = Pubkey::new_unique();
+        let token_program_id = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let new_admin = Pubkey::new_unique();
+        let gate = Pubkey::new_unique();
+        let price_feed = Pubkey::new_unique();
+        let fees = Fees {
+            admin_trade_fee_numerator: 1,
+            admin_trade_fee_denominator: 2,
+            trade_fee_numerator: 4,
+            trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let extra_fees = ExtraFees {
+            flash_loan_fee_numerator: 9,
+            flash_loan_fee_denominator: 10_000,
+            referral_fee_numerator: 1,
+            referral_fee_denominator: 4,
+        };
+        let swap_event = Event::Swap(SwapEvent {
+            swap: pool,
+            user: Pubkey::new_unique(),
+            a_to_b: false,
+            amount_in: 100,
+            amount_out: 99,
+            fee: 1,
+            admin_fee: 0,
+            referral_fee: 0,
+            reserves: Reserves {
+                token_a: 1_000,
+                token_b: 2_000,
+            },
+            virtual_price: None,
+        });
+        let logs = vec![
+            // a deposit on the first pool
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: Deposit".to_string(),
+            format!("Program {} invoke [2]", token_program_id),
+            "Program log: Instruction: Transfer".to_string(),
+            // values logged by another program are ignored
+            "Program log: 0x0, 0x0, 0x0, 0x1, 0x2".to_string(),
+            format!("Program {} success", token_program_id),
+            "Program log: Event: Deposit".to_string(),
+            "Program log: 0x1, 0x64, 0xc8, 0x12c, 0x0".to_string(),
+            format!(
+                "Program {} consumed 20000 of 200000 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+            // a swap on a second pool through an aggregator
+            format!("Program {} invoke [1]", aggregator_id),
+            "Program log: Event: SwapAToB".to_string(),
+            format!("Program {} invoke [2]", program_id),
+            "Program log: Instruction: Swap".to_string(),
+            "Program log: Event: SwapBToA".to_string(),
+            "Program log: 0x3, 0x63, 0x64, 0x0, 0x1".to_string(),
+            format!(
+                "Program data: {}",
+                base64::encode(swap_event.encode().unwrap())
+            ),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [2]", program_id),
+            "Program log: Instruction: Swap".to_string(),
+            "Program log: 0x0, 0x0, 0x0, 0x64, 0x63".to_string(),
+            format!("Program {} failed: custom program error: 0x10", program_id),
+            format!("Program {} success", aggregator_id),
+            // admin changes
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: SetNewFees".to_string(),
+            format!("Program log: Admin: Old fees {:?}", Fees::default()),
+            format!("Program log: Admin: New fees {:?}", fees),
+            "Program log: Instruction: SetExtraFees".to_string(),
+            format!("Program log: Admin: New extra fees {:?}", extra_fees),
+            format!(
+                "Program log: Admin: Starting admin transfer to {}, deadline at 259200",
+                new_admin
+            ),
+            "Program log: Admin: Ramping A to 100, ending at 86400".to_string(),
+            format!(
+                "Program log: Admin: Granted {:?} to {}",
+                AdminRole::FeeManager,
+                new_admin
+            ),
+            format!(
+                "Program log: Admin: New admin fee mode {:?}",
+                AdminFeeMode::LpMint
+            ),
+            "Program log: Admin: Pause flags set to 31".to_string(),
+            "Program log: Admin: Migrated swap info to version 1".to_string(),
+            format!("Program log: Admin: Gate set to {}", gate),
+            "Program log: Admin: Circuit breaker set to 500 bps per 150 slots".to_string(),
+            "Program log: Admin: Circuit breaker reset".to_string(),
+            format!(
+                "Program log: Admin: Price oracle set to {} within 100 bps",
+                price_feed
+            ),
+            "Program log: Admin: Price oracle removed".to_string(),
+            "Program log: Admin: Unknown change".to_string(),
+            format!("Program {} success", program_id),
+        ];
+
+        let parsed = parse_logs(&program_id, &logs);
+        let expected = [
+            ParsedLog {
+                instruction_index: 0,
+                depth: 1,
+                invocation: 0,
+                entry: LogEntry::Event(StableSwapEvent::Deposit {
+                    token_a_amount: 100,
+                    token_b_amount: 200,
+                    pool_token_amount: 300,
+                }),
+            },
+            ParsedLog {
+                instruction_index: 1,
+                depth: 2,
+                invocation: 1,
+                entry: LogEntry::Event(StableSwapEvent::SwapBToA {
+                    amount_in: 100,
+                    amount_out: 99,
+                    fee: 1,
+                }),
+            },
+            ParsedLog {
+                instruction_index: 1,
+                depth: 2,
+                invocation: 1,
+                entry: LogEntry::Data(swap_event),
+            },
+            ParsedLog {
+                instruction_index: 1,
+                depth: 2,
+                invocation: 2,
+                entry: LogEntry::Event(StableSwapEvent::ExceededSlippage {
+                    minimum_amount: 100,
+                    computed_amount: 99,
+                }),
+            },
+        ];
+        assert_eq!(parsed[..4], expected[..]);
+
+        let changes = parsed[4..]
+            .iter()
+            .map(|log| {
+                assert_eq!(log.instruction_index, 2);
+                assert_eq!(log.invocation, 3);
+                match log.entry {
+                    LogEntry::Admin(change) => change,
+                    _ => panic!("expected admin change"),
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                AdminChange::NewFees(fees),
+                AdminChange::NewExtraFees(extra_fees),
+                AdminChange::AdminTransferStarted {
+                    new_admin,
+                    deadline: 259200,
+                },
+                AdminChange::RampA {
+                    target_amp: 100,
+                    stop_ramp_ts: 86400,
+                },
+                AdminChange::RoleGranted {
+                    role: AdminRole::FeeManager,
+                    key: new_admin,
+                },
+                AdminChange::AdminFeeMode(AdminFeeMode::LpMint),
+                AdminChange::PauseFlags(PauseFlags::ALL),
+                AdminChange::SwapInfoMigrated(1),
+                AdminChange::GateSet(gate),
+                AdminChange::CircuitBreakerSet {
+                    max_price_deviation_bps: 500,
+                    window_slots: 150,
+                },
+                AdminChange::CircuitBreakerReset,
+                AdminChange::PriceOracleSet {
+                    feed: price_feed,
+                    max_deviation_bps: 100,
+                },
+                AdminChange::PriceOracleRemoved,
+            ]
+        );
+    }
+}
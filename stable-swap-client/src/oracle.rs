@@ -0,0 +1,175 @@
+//! Price feeds of external oracles.
+//!
+//! Feeds are read in the layout of Pyth price accounts (version 2). Only the
+//! header and the aggregate price of the account are decoded here. Feeds must
+//! be owned by the [pyth_program].
+
+use crate::error::SwapError;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+/// The Pyth oracle program, which owns its price accounts.
+pub mod pyth_program {
+    solana_program::declare_id!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+}
+
+/// Magic number of Pyth accounts.
+pub const MAGIC: u32 = 0xa1b2_c3d4;
+/// Version of the supported layout.
+pub const VERSION: u32 = 2;
+/// Length of the decoded prefix of a price account.
+pub const PRICE_FEED_LEN: usize = 240;
+
+/// Account type of price accounts.
+const ACCOUNT_TYPE_PRICE: u32 = 3;
+/// Status of an aggregate price that is trading.
+const STATUS_TRADING: u32 = 1;
+/// Offset of the exponent of the prices.
+const EXPO_OFFSET: usize = 20;
+/// Offset of the aggregate price.
+const AGGREGATE_OFFSET: usize = 208;
+
+/// Aggregate price of a price feed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceFeed {
+    /// Price, in units of 10^expo
+    pub price: i64,
+    /// Confidence interval of the price, in units of 10^expo
+    pub conf: u64,
+    /// Exponent of the price
+    pub expo: i32,
+    /// True if the price is trading. Halted or unknown prices must not be used.
+    pub is_trading: bool,
+    /// Slot at which the price was published
+    pub pub_slot: u64,
+}
+
+impl PriceFeed {
+    /// Decodes the price feed of a price account.
+    pub fn unpack(input: &[u8]) -> Result<Self, SwapError> {
+        let input = input
+            .get(..PRICE_FEED_LEN)
+            .ok_or(SwapError::InvalidOracle)?;
+        let input = array_ref![input, 0, PRICE_FEED_LEN];
+        let (magic, version, account_type) = array_refs![array_ref![input, 0, 12], 4, 4, 4];
+        if u32::from_le_bytes(*magic) != MAGIC
+            || u32::from_le_bytes(*version) != VERSION
+            || u32::from_le_bytes(*account_type) != ACCOUNT_TYPE_PRICE
+        {
+            return Err(SwapError::InvalidOracle);
+        }
+        let expo = array_ref![input, EXPO_OFFSET, 4];
+        let (price, conf, status, _corp_act, pub_slot) =
+            array_refs![array_ref![input, AGGREGATE_OFFSET, 32], 8, 8, 4, 4, 8];
+        Ok(Self {
+            price: i64::from_le_bytes(*price),
+            conf: u64::from_le_bytes(*conf),
+            expo: i32::from_le_bytes(*expo),
+            is_trading: u32::from_le_bytes(*status) == STATUS_TRADING,
+            pub_slot: u64::from_le_bytes(*pub_slot),
+        })
+    }
+
+    /// Encodes the price feed into the first [PRICE_FEED_LEN] bytes of a price
+    /// account, e.g. to mock a feed.
+    pub fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, PRICE_FEED_LEN];
+        let (magic, version, account_type) =
+            mut_array_refs![array_mut_ref![output, 0, 12], 4, 4, 4];
+        *magic = MAGIC.to_le_bytes();
+        *version = VERSION.to_le_bytes();
+        *account_type = ACCOUNT_TYPE_PRICE.to_le_bytes();
+        *array_mut_ref![output, EXPO_OFFSET, 4] = self.expo.to_le_bytes();
+        let (price, conf, status, _corp_act, pub_slot) =
+            mut_array_refs![array_mut_ref![output, AGGREGATE_OFFSET, 32], 8, 8, 4, 4, 8];
+        *price = self.price.to_le_bytes();
+        *conf = self.conf.to_le_bytes();
+        *status = u32::from(self.is_trading).to_le_bytes();
+        *pub_slot = self.pub_slot.to_le_bytes();
+    }
+
+    /// Returns the price of an atom of the base token in atoms of the quote
+    /// token, scaled by `precision`. The price of the feed is quoted in whole
+    /// tokens, so it is converted using the decimals of both tokens. Returns
+    /// `None` if the price is not positive or does not fit.
+    pub fn scaled_price(
+        &self,
+        precision: u64,
+        base_decimals: u8,
+        quote_decimals: u8,
+    ) -> Option<u64> {
+        let price = u128::try_from(self.price).ok().filter(|price| *price > 0)?;
+        let expo = self
+            .expo
+            .checked_add(quote_decimals.into())?
+            .checked_sub(base_decimals.into())?;
+        let scale = 10u128.checked_pow(expo.unsigned_abs())?;
+        let scaled = price.checked_mul(precision.into())?;
+        let scaled = if expo >= 0 {
+            scaled.checked_mul(scale)?
+        } else {
+            scaled.checked_div(scale)?
+        };
+        u64::try_from(scaled).ok().filter(|price| *price > 0)
+    }
+
+    /// Returns the confidence interval of the price in basis points of the
+    /// price, rounded down. Returns `None` if the price is not positive.
+    pub fn confidence_bps(&self) -> Option<u64> {
+        let price = u128::try_from(self.price).ok().filter(|price| *price > 0)?;
+        u128::from(self.conf)
+            .checked_mul(10_000)?
+            .checked_div(price)?
+            .try_into()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_feed() {
+        let feed = PriceFeed {
+            price: 99_500_000,
+            conf: 10_000,
+            expo: -8,
+            is_trading: true,
+            pub_slot: 1_234,
+        };
+        let mut data = vec![0u8; 3312];
+        feed.pack_into_slice(&mut data);
+        assert_eq!(data[..4], MAGIC.to_le_bytes());
+        assert_eq!(data[208..216], 99_500_000i64.to_le_bytes());
+        assert_eq!(PriceFeed::unpack(&data), Ok(feed));
+        assert_eq!(feed.scaled_price(1_000_000, 6, 6), Some(995_000));
+        // one atom of a token with 9 decimals in atoms of a token with 6 decimals
+        assert_eq!(feed.scaled_price(1_000_000, 9, 6), Some(995));
+        assert_eq!(feed.scaled_price(1_000_000, 6, 9), Some(995_000_000));
+        assert_eq!(feed.confidence_bps(), Some(1));
+
+        let halted = PriceFeed {
+            is_trading: false,
+            ..feed
+        };
+        halted.pack_into_slice(&mut data);
+        assert!(!PriceFeed::unpack(&data).unwrap().is_trading);
+
+        // prices with positive exponents
+        let feed = PriceFeed { expo: 2, ..feed };
+        assert_eq!(feed.scaled_price(1, 0, 0), Some(9_950_000_000));
+        // prices must be positive
+        let feed = PriceFeed { price: 0, ..feed };
+        assert_eq!(feed.scaled_price(1, 0, 0), None);
+        assert_eq!(feed.confidence_bps(), None);
+
+        // other accounts are rejected
+        assert_eq!(
+            PriceFeed::unpack(&data[..PRICE_FEED_LEN - 1]),
+            Err(SwapError::InvalidOracle)
+        );
+        data[8] = 2; // product account
+        assert_eq!(PriceFeed::unpack(&data), Err(SwapError::InvalidOracle));
+    }
+}
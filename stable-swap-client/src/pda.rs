@@ -0,0 +1,282 @@
+//! Program derived addresses of pools created by [crate::instruction::initialize_v2]
+//! and of the pool factory.
+
+use solana_program::pubkey::Pubkey;
+
+/// Seed prefix of the swap account.
+pub const SWAP_SEED: &[u8] = b"swap";
+/// Seed prefix of the reserve token accounts.
+pub const RESERVES_SEED: &[u8] = b"reserves";
+/// Seed prefix of the pool token mint.
+pub const POOL_MINT_SEED: &[u8] = b"pool_mint";
+/// Seed of the pool factory.
+pub const FACTORY_SEED: &[u8] = b"factory";
+/// Seed prefix of the pool templates of the factory.
+pub const TEMPLATE_SEED: &[u8] = b"template";
+/// Seed prefix of the registry entries of the factory.
+pub const REGISTRY_SEED: &[u8] = b"registry";
+/// Seed prefix of the fee tiers of users.
+pub const FEE_TIER_SEED: &[u8] = b"fee_tier";
+/// Seed prefix of the gates of permissioned pools.
+pub const GATE_SEED: &[u8] = b"gate";
+/// Seed prefix of the allowlist entries of gates.
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+/// Finds the swap account of the pool of `token_a_mint` and `token_b_mint`
+/// created with `admin` and `amp_factor`. The mints must be sorted.
+///
+/// The admin and the initial amplification coefficient are part of the seeds
+/// so that a creator cannot claim the address of the pool of a mint pair with
+/// parameters of their choosing.
+pub fn find_swap_address(
+    program_id: &Pubkey,
+    token_a_mint: &Pubkey,
+    token_b_mint: &Pubkey,
+    admin: &Pubkey,
+    amp_factor: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            SWAP_SEED,
+            token_a_mint.as_ref(),
+            token_b_mint.as_ref(),
+            admin.as_ref(),
+            &amp_factor.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Finds the swap account of the pool of `token_a_mint` and `token_b_mint`
+/// created by the factory in `amp_tier`. The mints must be sorted.
+pub fn find_factory_swap_address(
+    program_id: &Pubkey,
+    token_a_mint: &Pubkey,
+    token_b_mint: &Pubkey,
+    amp_tier: u8,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            SWAP_SEED,
+            token_a_mint.as_ref(),
+            token_b_mint.as_ref(),
+            &[amp_tier],
+        ],
+        program_id,
+    )
+}
+
+/// Finds the authority of a swap. The bump seed is the nonce of the swap.
+pub fn find_swap_authority(program_id: &Pubkey, swap: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[swap.as_ref()], program_id)
+}
+
+/// Finds the reserve token account of `mint` of a swap.
+pub fn find_reserves_address(program_id: &Pubkey, swap: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RESERVES_SEED, swap.as_ref(), mint.as_ref()], program_id)
+}
+
+/// Finds the pool token mint of a swap.
+pub fn find_pool_mint_address(program_id: &Pubkey, swap: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_MINT_SEED, swap.as_ref()], program_id)
+}
+
+/// Sorts a mint pair into the order of the mints of derived pools.
+pub fn sort_mints(mint_x: Pubkey, mint_y: Pubkey) -> (Pubkey, Pubkey) {
+    if mint_x <= mint_y {
+        (mint_x, mint_y)
+    } else {
+        (mint_y, mint_x)
+    }
+}
+
+/// Finds the pool factory.
+pub fn find_factory_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FACTORY_SEED], program_id)
+}
+
+/// Finds the pool template of `amp_tier`.
+pub fn find_template_address(program_id: &Pubkey, amp_tier: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TEMPLATE_SEED, &[amp_tier]], program_id)
+}
+
+/// Finds the registry entry of the canonical pool of a mint pair in
+/// `amp_tier`. The mints may be given in any order.
+pub fn find_registry_address(
+    program_id: &Pubkey,
+    mint_x: &Pubkey,
+    mint_y: &Pubkey,
+    amp_tier: u8,
+) -> (Pubkey, u8) {
+    let (token_a_mint, token_b_mint) = sort_mints(*mint_x, *mint_y);
+    Pubkey::find_program_address(
+        &[
+            REGISTRY_SEED,
+            token_a_mint.as_ref(),
+            token_b_mint.as_ref(),
+            &[amp_tier],
+        ],
+        program_id,
+    )
+}
+
+/// Finds the fee tier of `authority` for `swap`, or the global fee tier of
+/// `authority` if `swap` is `None`.
+pub fn find_fee_tier_address(
+    program_id: &Pubkey,
+    swap: Option<&Pubkey>,
+    authority: &Pubkey,
+) -> (Pubkey, u8) {
+    let scope = swap.copied().unwrap_or_default();
+    Pubkey::find_program_address(
+        &[FEE_TIER_SEED, scope.as_ref(), authority.as_ref()],
+        program_id,
+    )
+}
+
+/// Finds the gate created by `base`.
+pub fn find_gate_address(program_id: &Pubkey, base: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GATE_SEED, base.as_ref()], program_id)
+}
+
+/// Finds the allowlist entry of `authority` in `gate`.
+pub fn find_allowlist_entry_address(
+    program_id: &Pubkey,
+    gate: &Pubkey,
+    authority: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ALLOWLIST_SEED, gate.as_ref(), authority.as_ref()],
+        program_id,
+    )
+}
+
+/// Addresses of a pool created by [crate::instruction::initialize_v2] or by
+/// the factory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAddresses {
+    /// The swap account
+    pub swap: Pubkey,
+    /// The swap authority
+    pub authority: Pubkey,
+    /// Nonce of the swap authority
+    pub nonce: u8,
+    /// Reserve token account of token A
+    pub token_a_reserves: Pubkey,
+    /// Reserve token account of token B
+    pub token_b_reserves: Pubkey,
+    /// The pool token mint
+    pub pool_mint: Pubkey,
+}
+
+impl PoolAddresses {
+    /// Derives the addresses of the pool of `token_a_mint` and `token_b_mint`
+    /// created with `admin` and `amp_factor`. The mints must be sorted.
+    pub fn find(
+        program_id: &Pubkey,
+        token_a_mint: &Pubkey,
+        token_b_mint: &Pubkey,
+        admin: &Pubkey,
+        amp_factor: u64,
+    ) -> Self {
+        let (swap, _) =
+            find_swap_address(program_id, token_a_mint, token_b_mint, admin, amp_factor);
+        Self::for_swap(program_id, swap, token_a_mint, token_b_mint)
+    }
+
+    /// Derives the addresses of the pool of `token_a_mint` and `token_b_mint`
+    /// created by the factory in `amp_tier`. The mints must be sorted.
+    pub fn find_for_factory(
+        program_id: &Pubkey,
+        token_a_mint: &Pubkey,
+        token_b_mint: &Pubkey,
+        amp_tier: u8,
+    ) -> Self {
+        let (swap, _) = find_factory_swap_address(program_id, token_a_mint, token_b_mint, amp_tier);
+        Self::for_swap(program_id, swap, token_a_mint, token_b_mint)
+    }
+
+    fn for_swap(
+        program_id: &Pubkey,
+        swap: Pubkey,
+        token_a_mint: &Pubkey,
+        token_b_mint: &Pubkey,
+    ) -> Self {
+        let (authority, nonce) = find_swap_authority(program_id, &swap);
+        Self {
+            swap,
+            authority,
+            nonce,
+            token_a_reserves: find_reserves_address(program_id, &swap, token_a_mint).0,
+            token_b_reserves: find_reserves_address(program_id, &swap, token_b_mint).0,
+            pool_mint: find_pool_mint_address(program_id, &swap).0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_swap_address() {
+        let program_id = crate::ID;
+        let (token_a_mint, token_b_mint) = sort_mints(Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(token_a_mint < token_b_mint);
+        assert_eq!(
+            sort_mints(token_b_mint, token_a_mint),
+            (token_a_mint, token_b_mint)
+        );
+        let admin = Pubkey::new_unique();
+        let swap = find_swap_address(&program_id, &token_a_mint, &token_b_mint, &admin, 100);
+        assert_ne!(
+            swap,
+            find_swap_address(
+                &program_id,
+                &token_a_mint,
+                &token_b_mint,
+                &Pubkey::new_unique(),
+                100
+            )
+        );
+        assert_ne!(
+            swap,
+            find_swap_address(&program_id, &token_a_mint, &token_b_mint, &admin, 101)
+        );
+    }
+
+    #[test]
+    fn test_find_registry_address() {
+        let program_id = crate::ID;
+        let mint_x = Pubkey::new_unique();
+        let mint_y = Pubkey::new_unique();
+        assert_eq!(
+            find_registry_address(&program_id, &mint_x, &mint_y, 1),
+            find_registry_address(&program_id, &mint_y, &mint_x, 1)
+        );
+        assert_ne!(
+            find_registry_address(&program_id, &mint_x, &mint_y, 1),
+            find_registry_address(&program_id, &mint_x, &mint_y, 2)
+        );
+        let admin = Pubkey::new_unique();
+        assert_ne!(
+            PoolAddresses::find_for_factory(&program_id, &mint_x, &mint_y, 1).swap,
+            PoolAddresses::find(&program_id, &mint_x, &mint_y, &admin, 1).swap
+        );
+    }
+
+    #[test]
+    fn test_find_fee_tier_address() {
+        let program_id = crate::ID;
+        let swap = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        assert_eq!(
+            find_fee_tier_address(&program_id, None, &authority),
+            find_fee_tier_address(&program_id, Some(&Pubkey::default()), &authority)
+        );
+        assert_ne!(
+            find_fee_tier_address(&program_id, None, &authority),
+            find_fee_tier_address(&program_id, Some(&swap), &authority)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Results of user instructions.
+//!
+//! Swap, deposit, withdraw, flash loan and quote instructions set a Borsh-encoded
+//! result as their return data with `set_return_data`, so that programs
+//! invoking them can read what they received with
+//! [InstructionResult::from_return_data] instead of re-reading token accounts.
+
+use crate::error::SwapError;
+use crate::events::{PoolState, Reserves};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    program::{get_return_data, MAX_RETURN_DATA},
+    program_error::ProgramError,
+};
+
+/// A result set as the return data of an instruction.
+pub trait InstructionResult: BorshSerialize + BorshDeserialize {
+    /// Encodes the result as the return data of the instruction.
+    fn encode(&self) -> Result<Vec<u8>, ProgramError> {
+        self.try_to_vec()
+            .map_err(|err| ProgramError::BorshIoError(err.to_string()))
+    }
+
+    /// Decodes a result from the return data of the instruction. RPC nodes
+    /// trim trailing zeros from return data, so missing bytes are read as zeros.
+    fn decode(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut padded = data.to_vec();
+        padded.resize(MAX_RETURN_DATA.max(data.len()), 0);
+        Self::deserialize(&mut padded.as_slice())
+            .map_err(|err| ProgramError::BorshIoError(err.to_string()))
+    }
+
+    /// Reads the result of the last instruction of the swap program invoked
+    /// by the current program.
+    fn from_return_data() -> Result<Self, ProgramError> {
+        match get_return_data() {
+            Some((program_id, data)) if program_id == crate::ID => Self::decode(&data),
+            _ => Err(SwapError::MissingReturnData.into()),
+        }
+    }
+}
+
+/// Result of a [crate::instruction::SwapInstruction::Swap].
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapResult {
+    /// Amount of the source token transferred from the user
+    pub amount_in: u64,
+    /// Amount of the destination token received by the user, net of any
+    /// transfer fee
+    pub amount_out: u64,
+    /// Trade fee, in the destination token
+    pub fee: u64,
+    /// Part of the trade fee taken by the admin
+    pub admin_fee: u64,
+    /// Part of the trade fee paid to the referrer of the swap
+    pub referral_fee: u64,
+    /// Reserves of the pool after the swap
+    pub reserves: Reserves,
+}
+
+/// Result of a [crate::instruction::SwapInstruction::Deposit].
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepositResult {
+    /// Amount of token A transferred from the user
+    pub token_a_amount: u64,
+    /// Amount of token B transferred from the user
+    pub token_b_amount: u64,
+    /// Amount of pool tokens minted to the user
+    pub pool_token_amount: u64,
+    /// State of the pool after the deposit
+    pub pool: PoolState,
+}
+
+/// Result of a [crate::instruction::SwapInstruction::Withdraw].
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithdrawResult {
+    /// Amount of token A transferred to the user
+    pub token_a_amount: u64,
+    /// Amount of token B transferred to the user
+    pub token_b_amount: u64,
+    /// Withdraw fee in token A
+    pub token_a_fee: u64,
+    /// Withdraw fee in token B
+    pub token_b_fee: u64,
+    /// Amount of pool tokens burned
+    pub pool_token_amount: u64,
+    /// State of the pool after the withdrawal
+    pub pool: PoolState,
+}
+
+/// Result of a [crate::instruction::SwapInstruction::WithdrawOne].
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithdrawOneResult {
+    /// Amount of the token received by the user, net of any transfer fee
+    pub token_amount: u64,
+    /// Trade fee of the imbalanced withdrawal
+    pub fee: u64,
+    /// Amount of pool tokens burned
+    pub pool_token_amount: u64,
+    /// State of the pool after the withdrawal
+    pub pool: PoolState,
+}
+
+/// Result of a [crate::instruction::SwapInstruction::FlashLoan].
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlashLoanResult {
+    /// Amount of the reserve token lent
+    pub amount: u64,
+    /// Fee paid on top of the amount
+    pub fee: u64,
+}
+
+impl InstructionResult for SwapResult {}
+impl InstructionResult for DepositResult {}
+impl InstructionResult for WithdrawResult {}
+impl InstructionResult for WithdrawOneResult {}
+impl InstructionResult for FlashLoanResult {}
+impl InstructionResult for PoolState {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_encoding() {
+        let result = WithdrawResult {
+            token_a_amount: 1,
+            token_b_amount: 2,
+            token_a_fee: 3,
+            token_b_fee: 4,
+            pool_token_amount: 5,
+            pool: PoolState {
+                reserves: Reserves {
+                    token_a: 1_000,
+                    token_b: 2_000,
+                },
+                pool_token_supply: 3_000,
+                virtual_price: 0,
+            },
+        };
+        let data = result.encode().unwrap();
+        assert_eq!(data.len(), 8 * 9);
+        assert_eq!(WithdrawResult::decode(&data).unwrap(), result);
+
+        // trailing zeros trimmed by RPC nodes
+        let trimmed = &data[..data.len() - 8];
+        assert_eq!(WithdrawResult::decode(trimmed).unwrap(), result);
+    }
+}
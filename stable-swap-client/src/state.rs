@@ -1,13 +1,20 @@
 //! State transition types
 
-use crate::fees::Fees;
+use crate::error::SwapError;
+use crate::fees::{ExtraFees, Fees};
+use crate::instruction::{AdminActionData, MAX_ADMIN_ACTION_LEN};
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
+    hash::Hash,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
 
+/// Token program of the swaps in the legacy layout.
+const SPL_TOKEN_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
 /// Program states.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -15,8 +22,8 @@ pub struct SwapInfo {
     /// Initialized state
     pub is_initialized: bool,
 
-    /// Paused state
-    pub is_paused: bool,
+    /// Operations that are paused
+    pub pause_flags: PauseFlags,
 
     /// Nonce used in program address
     /// The program address is created deterministically with the nonce,
@@ -51,6 +58,305 @@ pub struct SwapInfo {
     pub pool_mint: Pubkey,
     /// Fees
     pub fees: Fees,
+    /// Flash loan and referral fees
+    pub extra_fees: ExtraFees,
+    /// How admin fees are collected
+    pub admin_fee_mode: AdminFeeMode,
+    /// Public key of the pool token account to receive admin fees minted as pool tokens
+    pub lp_admin_fees: Pubkey,
+    /// Invariant (D) at the last pool token admin fee checkpoint
+    pub lp_admin_fee_last_d: u64,
+    /// Pool token supply at the last pool token admin fee checkpoint
+    pub lp_admin_fee_last_supply: u64,
+    /// Amplification coefficient (A) at the last pool token admin fee
+    /// checkpoint, which fee growth is measured at
+    pub lp_admin_fee_last_amp_factor: u64,
+    /// Token program of the pool's mints and token accounts: spl_token or Token-2022
+    pub token_program_id: Pubkey,
+    /// Public key of the guardian account, which may pause the swap but not
+    /// unpause it. The default pubkey if the swap has no guardian.
+    pub guardian_key: Pubkey,
+    /// Keys that admin operations are delegated to
+    pub roles: RoleKeys,
+    /// Delay in seconds before queued admin actions can be executed. Zero if
+    /// the timelock is disabled.
+    pub timelock_delay: i64,
+    /// Public key of the [AdminQueue] account of the swap
+    pub admin_queue: Pubkey,
+    /// True if the admin was renounced. Admin instructions can no longer be run
+    /// on the swap.
+    pub is_admin_renounced: bool,
+    /// True if the global fee tiers of the factory discount swaps of the pool.
+    /// Only set for pools created by the factory.
+    pub accepts_global_fee_tiers: bool,
+    /// Public key of the [Gate] whose allowlisted users may swap, deposit and
+    /// withdraw. The default pubkey if the swap is permissionless.
+    pub gate: Pubkey,
+    /// Circuit breaker rejecting swaps that move the price too far
+    pub circuit_breaker: CircuitBreaker,
+    /// Oracle rejecting swaps that move the price too far from an external price
+    pub price_oracle: PriceOracle,
+    /// Invariant (D) last computed by a swap, deposit or withdrawal, used as
+    /// the initial guess of the invariant by the next swap. Flash loans only
+    /// grow a reserve by their fee and leave it as is. Zero if unknown.
+    pub last_d: u64,
+    /// Amplification coefficient (A) that [SwapInfo::last_d] was computed at
+    pub last_amp_factor: u64,
+    /// Sum of the reserves that [SwapInfo::last_d] was computed for
+    pub last_sum_x: u64,
+}
+
+/// Roles of the keys of a swap that may sign admin instructions.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminRole {
+    /// The admin, which may run every admin instruction.
+    Admin = 0,
+    /// The guardian, which may only pause operations of the swap.
+    Guardian = 1,
+    /// May update the fees and how admin fees are collected.
+    FeeManager = 2,
+    /// May ramp A.
+    AmpManager = 3,
+    /// May set the admin fee accounts.
+    FeeAccountManager = 4,
+    /// May pause and unpause operations of the swap.
+    PauseManager = 5,
+}
+
+impl AdminRole {
+    /// Converts a packed byte into an [AdminRole].
+    pub fn from_u8(role: u8) -> Option<Self> {
+        match role {
+            0 => Some(Self::Admin),
+            1 => Some(Self::Guardian),
+            2 => Some(Self::FeeManager),
+            3 => Some(Self::AmpManager),
+            4 => Some(Self::FeeAccountManager),
+            5 => Some(Self::PauseManager),
+            _ => None,
+        }
+    }
+}
+
+/// Keys holding the delegated admin roles of a swap. Unassigned roles hold the
+/// default pubkey.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RoleKeys {
+    /// Key holding [AdminRole::FeeManager]
+    pub fee_manager: Pubkey,
+    /// Key holding [AdminRole::AmpManager]
+    pub amp_manager: Pubkey,
+    /// Key holding [AdminRole::FeeAccountManager]
+    pub fee_account_manager: Pubkey,
+    /// Key holding [AdminRole::PauseManager]
+    pub pause_manager: Pubkey,
+}
+
+impl RoleKeys {
+    const LEN: usize = 128;
+
+    fn unpack(input: &[u8; Self::LEN]) -> Self {
+        let (fee_manager, amp_manager, fee_account_manager, pause_manager) =
+            array_refs![input, 32, 32, 32, 32];
+        Self {
+            fee_manager: Pubkey::new_from_array(*fee_manager),
+            amp_manager: Pubkey::new_from_array(*amp_manager),
+            fee_account_manager: Pubkey::new_from_array(*fee_account_manager),
+            pause_manager: Pubkey::new_from_array(*pause_manager),
+        }
+    }
+
+    fn pack(&self, output: &mut [u8; Self::LEN]) {
+        let (fee_manager, amp_manager, fee_account_manager, pause_manager) =
+            mut_array_refs![output, 32, 32, 32, 32];
+        fee_manager.copy_from_slice(self.fee_manager.as_ref());
+        amp_manager.copy_from_slice(self.amp_manager.as_ref());
+        fee_account_manager.copy_from_slice(self.fee_account_manager.as_ref());
+        pause_manager.copy_from_slice(self.pause_manager.as_ref());
+    }
+}
+
+/// Circuit breaker of a swap. Swaps, deposits and single sided withdrawals
+/// are rejected if they move the marginal price of the swap further than the
+/// band from the price at the start of the current window of slots.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CircuitBreaker {
+    /// Maximum deviation of the marginal price from the reference price, in
+    /// basis points. Zero if the circuit breaker is disabled.
+    pub max_price_deviation_bps: u16,
+    /// Length of a window, in slots
+    pub window_slots: u64,
+    /// Slot at which the current window started
+    pub reference_slot: u64,
+    /// Marginal price of token A in token B at the start of the current
+    /// window, scaled by 10^12. Zero if no window was started since the last
+    /// reset.
+    pub reference_price: u64,
+}
+
+impl CircuitBreaker {
+    const LEN: usize = 26;
+
+    /// Returns true if swaps are checked against the band.
+    pub fn is_enabled(&self) -> bool {
+        self.max_price_deviation_bps != 0
+    }
+
+    /// Returns true if a new window starts at `slot`.
+    pub fn is_window_expired(&self, slot: u64) -> bool {
+        self.reference_price == 0 || slot >= self.reference_slot.saturating_add(self.window_slots)
+    }
+
+    fn unpack(input: &[u8; Self::LEN]) -> Self {
+        let (max_price_deviation_bps, window_slots, reference_slot, reference_price) =
+            array_refs![input, 2, 8, 8, 8];
+        Self {
+            max_price_deviation_bps: u16::from_le_bytes(*max_price_deviation_bps),
+            window_slots: u64::from_le_bytes(*window_slots),
+            reference_slot: u64::from_le_bytes(*reference_slot),
+            reference_price: u64::from_le_bytes(*reference_price),
+        }
+    }
+
+    fn pack(&self, output: &mut [u8; Self::LEN]) {
+        let (max_price_deviation_bps, window_slots, reference_slot, reference_price) =
+            mut_array_refs![output, 2, 8, 8, 8];
+        *max_price_deviation_bps = self.max_price_deviation_bps.to_le_bytes();
+        *window_slots = self.window_slots.to_le_bytes();
+        *reference_slot = self.reference_slot.to_le_bytes();
+        *reference_price = self.reference_price.to_le_bytes();
+    }
+}
+
+/// External price feed of a swap. Swaps are rejected if the marginal price
+/// after the swap deviates too far from the price of the feed, or if the price
+/// of the feed is stale. A rejected swap fails its transaction, which rolls
+/// back all of its writes, so the feed cannot pause the swap: pausing is left
+/// to the admin and the guardian.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PriceOracle {
+    /// Price feed of token A in token B, see [crate::oracle::PriceFeed]. The
+    /// default pubkey if the swap has no oracle.
+    pub feed: Pubkey,
+    /// Maximum deviation of the marginal price from the price of the feed, in
+    /// basis points, before widening by the confidence interval of the feed
+    pub max_deviation_bps: u16,
+    /// Maximum age of the price of the feed, in slots
+    pub max_staleness_slots: u64,
+    /// Decimals of token A, which converts the price of the feed to atoms
+    pub decimals_a: u8,
+    /// Decimals of token B, which converts the price of the feed to atoms
+    pub decimals_b: u8,
+}
+
+impl PriceOracle {
+    const LEN: usize = 44;
+
+    /// Returns true if swaps are checked against the feed.
+    pub fn is_enabled(&self) -> bool {
+        self.feed != Pubkey::default()
+    }
+
+    fn unpack(input: &[u8; Self::LEN]) -> Self {
+        let (feed, max_deviation_bps, max_staleness_slots, decimals_a, decimals_b) =
+            array_refs![input, 32, 2, 8, 1, 1];
+        Self {
+            feed: Pubkey::new_from_array(*feed),
+            max_deviation_bps: u16::from_le_bytes(*max_deviation_bps),
+            max_staleness_slots: u64::from_le_bytes(*max_staleness_slots),
+            decimals_a: decimals_a[0],
+            decimals_b: decimals_b[0],
+        }
+    }
+
+    fn pack(&self, output: &mut [u8; Self::LEN]) {
+        let (feed, max_deviation_bps, max_staleness_slots, decimals_a, decimals_b) =
+            mut_array_refs![output, 32, 2, 8, 1, 1];
+        feed.copy_from_slice(self.feed.as_ref());
+        *max_deviation_bps = self.max_deviation_bps.to_le_bytes();
+        *max_staleness_slots = self.max_staleness_slots.to_le_bytes();
+        decimals_a[0] = self.decimals_a;
+        decimals_b[0] = self.decimals_b;
+    }
+}
+
+/// How admin fees are collected by the swap.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdminFeeMode {
+    /// Admin fees are transferred to the admin fee accounts by every instruction that charges them.
+    Transfer = 0,
+    /// Admin fees are left in the reserves and tracked in [SwapTokenInfo::accrued_admin_fees]
+    /// until they are claimed.
+    Accrue = 1,
+    /// Admin fees are left in the pool and the admin share of the growth of the invariant
+    /// is minted as pool tokens to [SwapInfo::lp_admin_fees] when claimed.
+    LpMint = 2,
+}
+
+impl AdminFeeMode {
+    /// Converts a packed byte into an [AdminFeeMode].
+    pub fn from_u8(mode: u8) -> Option<Self> {
+        match mode {
+            0 => Some(Self::Transfer),
+            1 => Some(Self::Accrue),
+            2 => Some(Self::LpMint),
+            _ => None,
+        }
+    }
+}
+
+/// Bitmask of the operations of the swap that are paused.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PauseFlags(u8);
+
+impl PauseFlags {
+    /// No operations are paused.
+    pub const NONE: Self = Self(0);
+    /// Swaps are paused.
+    pub const SWAP: Self = Self(1 << 0);
+    /// Deposits are paused.
+    pub const DEPOSIT: Self = Self(1 << 1);
+    /// Single token withdrawals are paused.
+    pub const WITHDRAW_ONE: Self = Self(1 << 2);
+    /// Flash loans are paused.
+    pub const FLASH_LOAN: Self = Self(1 << 3);
+    /// Proportional withdrawals are paused.
+    pub const WITHDRAW: Self = Self(1 << 4);
+    /// Operations paused by `Pause`. Proportional withdrawals stay open so that
+    /// LPs can always exit.
+    pub const PAUSE: Self =
+        Self(Self::SWAP.0 | Self::DEPOSIT.0 | Self::WITHDRAW_ONE.0 | Self::FLASH_LOAN.0);
+    /// All operations.
+    pub const ALL: Self = Self(Self::PAUSE.0 | Self::WITHDRAW.0);
+
+    /// Converts a packed byte into [PauseFlags], rejecting unknown bits.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !Self::ALL.0 != 0 {
+            return None;
+        }
+        Some(Self(bits))
+    }
+
+    /// The packed byte of the flags.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if all of the operations in `other` are paused.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the union of both sets of flags.
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
 }
 
 /// Information about one of the tokens.
@@ -65,6 +371,46 @@ pub struct SwapTokenInfo {
     pub admin_fees: Pubkey,
     /// The index of the token. Token A = 0, Token B = 1.
     pub index: u8,
+    /// Admin fees held in the reserves that have not been claimed yet.
+    pub accrued_admin_fees: u64,
+}
+
+impl SwapTokenInfo {
+    /// Amount of the reserves that belongs to the pool, excluding unclaimed admin fees.
+    pub fn pool_reserves(&self, reserves_amount: u64) -> Option<u64> {
+        reserves_amount.checked_sub(self.accrued_admin_fees)
+    }
+}
+
+impl SwapInfo {
+    /// Public key holding the role, or the default pubkey if the role is not assigned.
+    pub fn role_key(&self, role: AdminRole) -> &Pubkey {
+        match role {
+            AdminRole::Admin => &self.admin_key,
+            AdminRole::Guardian => &self.guardian_key,
+            AdminRole::FeeManager => &self.roles.fee_manager,
+            AdminRole::AmpManager => &self.roles.amp_manager,
+            AdminRole::FeeAccountManager => &self.roles.fee_account_manager,
+            AdminRole::PauseManager => &self.roles.pause_manager,
+        }
+    }
+
+    /// Mutable reference to the public key holding the role.
+    pub fn role_key_mut(&mut self, role: AdminRole) -> &mut Pubkey {
+        match role {
+            AdminRole::Admin => &mut self.admin_key,
+            AdminRole::Guardian => &mut self.guardian_key,
+            AdminRole::FeeManager => &mut self.roles.fee_manager,
+            AdminRole::AmpManager => &mut self.roles.amp_manager,
+            AdminRole::FeeAccountManager => &mut self.roles.fee_account_manager,
+            AdminRole::PauseManager => &mut self.roles.pause_manager,
+        }
+    }
+
+    /// Returns true if any operation of the swap is paused.
+    pub fn is_paused(&self) -> bool {
+        self.pause_flags != PauseFlags::NONE
+    }
 }
 
 impl Sealed for SwapInfo {}
@@ -74,12 +420,69 @@ impl IsInitialized for SwapInfo {
     }
 }
 
+impl SwapInfo {
+    /// Length, in bytes, of the unversioned layout of swaps created before the
+    /// layout was versioned. Such swaps can be migrated with
+    /// [crate::instruction::AdminInstruction::MigrateSwapInfo].
+    pub const LEGACY_LEN: usize = 395;
+    /// Current version of the layout, stored after the fields of the legacy layout.
+    pub const VERSION: u8 = 1;
+
+    /// Returns true if `input` is a swap in the legacy layout.
+    pub fn is_legacy_layout(input: &[u8]) -> bool {
+        input.len() == Self::LEGACY_LEN
+    }
+
+    /// Returns true if the swap can be packed into the legacy layout without
+    /// losing any field other than the last invariant.
+    fn fits_legacy_layout(&self) -> bool {
+        let mut legacy = [0u8; Self::LEGACY_LEN];
+        self.pack_into_slice(&mut legacy);
+        Self::unpack_from_slice(&legacy)
+            == Ok(Self {
+                last_d: 0,
+                last_amp_factor: 0,
+                last_sum_x: 0,
+                ..*self
+            })
+    }
+}
+
 impl Pack for SwapInfo {
-    const LEN: usize = 395;
+    /// The legacy layout followed by the version, the fields added since the
+    /// legacy layout and 162 reserved bytes for future fields.
+    const LEN: usize = 1024;
+
+    /// Unpacks a byte buffer in either layout.
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != Self::LEN && !Self::is_legacy_layout(input) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::unpack_from_slice(input)
+    }
+
+    /// Packs into a byte buffer in either layout. Swaps in the legacy layout
+    /// keep it until they are migrated, and cannot use the fields added since
+    /// the legacy layout. They do not keep the last invariant either.
+    fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != Self::LEN && !Self::is_legacy_layout(dst) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if Self::is_legacy_layout(dst) && !src.fits_legacy_layout() {
+            return Err(SwapError::LegacySwapLayout.into());
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
 
     /// Unpacks a byte buffer into a [SwapInfo](struct.SwapInfo.html).
     fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![input, 0, 395];
+        let (legacy, versioned) = if Self::is_legacy_layout(input) {
+            (array_ref![input, 0, 395], None)
+        } else {
+            let (legacy, versioned) = array_refs![array_ref![input, 0, 1024], 395, 629];
+            (legacy, Some(versioned))
+        };
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             is_initialized,
@@ -100,17 +503,15 @@ impl Pack for SwapInfo {
             admin_fee_key_a,
             admin_fee_key_b,
             fees,
-        ) = array_refs![input, 1, 1, 1, 8, 8, 8, 8, 8, 32, 32, 32, 32, 32, 32, 32, 32, 32, 64];
-        Ok(Self {
-            is_initialized: match is_initialized {
-                [0] => false,
-                [1] => true,
-                _ => return Err(ProgramError::InvalidAccountData),
-            },
-            is_paused: match is_paused {
-                [0] => false,
-                [1] => true,
-                _ => return Err(ProgramError::InvalidAccountData),
+        ) = array_refs![legacy, 1, 1, 1, 8, 8, 8, 8, 8, 32, 32, 32, 32, 32, 32, 32, 32, 32, 64];
+        let mut swap_info = Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            // the legacy pause blocked everything `Pause` blocks, and the
+            // extended layout overrides it with the stored flags
+            pause_flags: if unpack_bool(is_paused)? {
+                PauseFlags::PAUSE
+            } else {
+                PauseFlags::NONE
             },
             nonce: nonce[0],
             initial_amp_factor: u64::from_le_bytes(*initial_amp_factor),
@@ -125,20 +526,123 @@ impl Pack for SwapInfo {
                 mint: Pubkey::new_from_array(*token_a_mint),
                 admin_fees: Pubkey::new_from_array(*admin_fee_key_a),
                 index: 0,
+                accrued_admin_fees: 0,
             },
             token_b: SwapTokenInfo {
                 reserves: Pubkey::new_from_array(*token_b),
                 mint: Pubkey::new_from_array(*token_b_mint),
                 admin_fees: Pubkey::new_from_array(*admin_fee_key_b),
                 index: 1,
+                accrued_admin_fees: 0,
             },
             pool_mint: Pubkey::new_from_array(*pool_mint),
             fees: Fees::unpack_from_slice(fees)?,
-        })
+            extra_fees: ExtraFees::default(),
+            admin_fee_mode: AdminFeeMode::Transfer,
+            lp_admin_fees: Pubkey::default(),
+            lp_admin_fee_last_d: 0,
+            lp_admin_fee_last_supply: 0,
+            lp_admin_fee_last_amp_factor: 0,
+            // swaps in the legacy layout predate Token-2022 support
+            token_program_id: SPL_TOKEN_PROGRAM_ID,
+            guardian_key: Pubkey::default(),
+            roles: RoleKeys::default(),
+            timelock_delay: 0,
+            admin_queue: Pubkey::default(),
+            is_admin_renounced: false,
+            accepts_global_fee_tiers: false,
+            gate: Pubkey::default(),
+            circuit_breaker: CircuitBreaker::default(),
+            price_oracle: PriceOracle::default(),
+            last_d: 0,
+            last_amp_factor: 0,
+            last_sum_x: 0,
+        };
+        let versioned = match versioned {
+            Some(versioned) => versioned,
+            None => return Ok(swap_info),
+        };
+
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            flash_loan_fee_numerator,
+            flash_loan_fee_denominator,
+            admin_fee_mode,
+            accrued_admin_fees_a,
+            accrued_admin_fees_b,
+            lp_admin_fees,
+            lp_admin_fee_last_d,
+            lp_admin_fee_last_supply,
+            lp_admin_fee_last_amp_factor,
+            token_program_id,
+            pause_flags,
+            guardian_key,
+            roles,
+            timelock_delay,
+            admin_queue,
+            is_admin_renounced,
+            referral_fee_numerator,
+            referral_fee_denominator,
+            accepts_global_fee_tiers,
+            gate,
+            circuit_breaker,
+            price_oracle,
+            last_d,
+            last_amp_factor,
+            last_sum_x,
+            _reserved,
+        ) = array_refs![
+            versioned, 1, 8, 8, 1, 8, 8, 32, 8, 8, 8, 32, 1, 32, 128, 8, 32, 1, 8, 8, 1, 32, 26,
+            44, 8, 8, 8, 162
+        ];
+        match version[0] {
+            Self::VERSION => {}
+            // the version of a new account is only set when it is initialized
+            0 if !swap_info.is_initialized => {}
+            _ => return Err(ProgramError::InvalidAccountData),
+        }
+        swap_info.extra_fees.flash_loan_fee_numerator =
+            u64::from_le_bytes(*flash_loan_fee_numerator);
+        swap_info.extra_fees.flash_loan_fee_denominator =
+            u64::from_le_bytes(*flash_loan_fee_denominator);
+        swap_info.admin_fee_mode =
+            AdminFeeMode::from_u8(admin_fee_mode[0]).ok_or(ProgramError::InvalidAccountData)?;
+        swap_info.token_a.accrued_admin_fees = u64::from_le_bytes(*accrued_admin_fees_a);
+        swap_info.token_b.accrued_admin_fees = u64::from_le_bytes(*accrued_admin_fees_b);
+        swap_info.lp_admin_fees = Pubkey::new_from_array(*lp_admin_fees);
+        swap_info.lp_admin_fee_last_d = u64::from_le_bytes(*lp_admin_fee_last_d);
+        swap_info.lp_admin_fee_last_supply = u64::from_le_bytes(*lp_admin_fee_last_supply);
+        swap_info.lp_admin_fee_last_amp_factor = u64::from_le_bytes(*lp_admin_fee_last_amp_factor);
+        swap_info.token_program_id = Pubkey::new_from_array(*token_program_id);
+        swap_info.pause_flags =
+            PauseFlags::from_bits(pause_flags[0]).ok_or(ProgramError::InvalidAccountData)?;
+        swap_info.guardian_key = Pubkey::new_from_array(*guardian_key);
+        swap_info.roles = RoleKeys::unpack(roles);
+        swap_info.timelock_delay = i64::from_le_bytes(*timelock_delay);
+        swap_info.admin_queue = Pubkey::new_from_array(*admin_queue);
+        swap_info.is_admin_renounced = unpack_bool(is_admin_renounced)?;
+        swap_info.extra_fees.referral_fee_numerator = u64::from_le_bytes(*referral_fee_numerator);
+        swap_info.extra_fees.referral_fee_denominator =
+            u64::from_le_bytes(*referral_fee_denominator);
+        swap_info.accepts_global_fee_tiers = unpack_bool(accepts_global_fee_tiers)?;
+        swap_info.gate = Pubkey::new_from_array(*gate);
+        swap_info.circuit_breaker = CircuitBreaker::unpack(circuit_breaker);
+        swap_info.price_oracle = PriceOracle::unpack(price_oracle);
+        swap_info.last_d = u64::from_le_bytes(*last_d);
+        swap_info.last_amp_factor = u64::from_le_bytes(*last_amp_factor);
+        swap_info.last_sum_x = u64::from_le_bytes(*last_sum_x);
+        Ok(swap_info)
     }
 
     fn pack_into_slice(&self, output: &mut [u8]) {
-        let output = array_mut_ref![output, 0, 395];
+        let (legacy, versioned) = if Self::is_legacy_layout(output) {
+            (array_mut_ref![output, 0, 395], None)
+        } else {
+            let (legacy, versioned) = mut_array_refs![array_mut_ref![output, 0, 1024], 395, 629];
+            (legacy, Some(versioned))
+        };
+
         let (
             is_initialized,
             is_paused,
@@ -158,9 +662,9 @@ impl Pack for SwapInfo {
             admin_fee_key_a,
             admin_fee_key_b,
             fees,
-        ) = mut_array_refs![output, 1, 1, 1, 8, 8, 8, 8, 8, 32, 32, 32, 32, 32, 32, 32, 32, 32, 64];
+        ) = mut_array_refs![legacy, 1, 1, 1, 8, 8, 8, 8, 8, 32, 32, 32, 32, 32, 32, 32, 32, 32, 64];
         is_initialized[0] = self.is_initialized as u8;
-        is_paused[0] = self.is_paused as u8;
+        is_paused[0] = self.is_paused() as u8;
         nonce[0] = self.nonce;
         *initial_amp_factor = self.initial_amp_factor.to_le_bytes();
         *target_amp_factor = self.target_amp_factor.to_le_bytes();
@@ -176,10 +680,498 @@ impl Pack for SwapInfo {
         token_b_mint.copy_from_slice(self.token_b.mint.as_ref());
         admin_fee_key_a.copy_from_slice(self.token_a.admin_fees.as_ref());
         admin_fee_key_b.copy_from_slice(self.token_b.admin_fees.as_ref());
+        self.fees.pack_into_slice(fees);
+        let versioned = match versioned {
+            Some(versioned) => versioned,
+            None => return,
+        };
+
+        let (
+            version,
+            flash_loan_fee_numerator,
+            flash_loan_fee_denominator,
+            admin_fee_mode,
+            accrued_admin_fees_a,
+            accrued_admin_fees_b,
+            lp_admin_fees,
+            lp_admin_fee_last_d,
+            lp_admin_fee_last_supply,
+            lp_admin_fee_last_amp_factor,
+            token_program_id,
+            pause_flags,
+            guardian_key,
+            roles,
+            timelock_delay,
+            admin_queue,
+            is_admin_renounced,
+            referral_fee_numerator,
+            referral_fee_denominator,
+            accepts_global_fee_tiers,
+            gate,
+            circuit_breaker,
+            price_oracle,
+            last_d,
+            last_amp_factor,
+            last_sum_x,
+            reserved,
+        ) = mut_array_refs![
+            versioned, 1, 8, 8, 1, 8, 8, 32, 8, 8, 8, 32, 1, 32, 128, 8, 32, 1, 8, 8, 1, 32, 26,
+            44, 8, 8, 8, 162
+        ];
+        version[0] = Self::VERSION;
+        *flash_loan_fee_numerator = self.extra_fees.flash_loan_fee_numerator.to_le_bytes();
+        *flash_loan_fee_denominator = self.extra_fees.flash_loan_fee_denominator.to_le_bytes();
+        admin_fee_mode[0] = self.admin_fee_mode as u8;
+        *accrued_admin_fees_a = self.token_a.accrued_admin_fees.to_le_bytes();
+        *accrued_admin_fees_b = self.token_b.accrued_admin_fees.to_le_bytes();
+        lp_admin_fees.copy_from_slice(self.lp_admin_fees.as_ref());
+        *lp_admin_fee_last_d = self.lp_admin_fee_last_d.to_le_bytes();
+        *lp_admin_fee_last_supply = self.lp_admin_fee_last_supply.to_le_bytes();
+        *lp_admin_fee_last_amp_factor = self.lp_admin_fee_last_amp_factor.to_le_bytes();
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        pause_flags[0] = self.pause_flags.bits();
+        guardian_key.copy_from_slice(self.guardian_key.as_ref());
+        self.roles.pack(roles);
+        *timelock_delay = self.timelock_delay.to_le_bytes();
+        admin_queue.copy_from_slice(self.admin_queue.as_ref());
+        is_admin_renounced[0] = self.is_admin_renounced as u8;
+        *referral_fee_numerator = self.extra_fees.referral_fee_numerator.to_le_bytes();
+        *referral_fee_denominator = self.extra_fees.referral_fee_denominator.to_le_bytes();
+        accepts_global_fee_tiers[0] = self.accepts_global_fee_tiers as u8;
+        gate.copy_from_slice(self.gate.as_ref());
+        self.circuit_breaker.pack(circuit_breaker);
+        self.price_oracle.pack(price_oracle);
+        *last_d = self.last_d.to_le_bytes();
+        *last_amp_factor = self.last_amp_factor.to_le_bytes();
+        *last_sum_x = self.last_sum_x.to_le_bytes();
+        *reserved = [0u8; 162];
+    }
+}
+
+/// Seconds after its execution time during which a queued admin action can be executed.
+pub const ADMIN_ACTION_GRACE_PERIOD: i64 = 14 * 86400; // 14 days
+/// Maximum delay of the admin action timelock.
+pub const MAX_TIMELOCK_DELAY: i64 = 30 * 86400; // 30 days
+/// Seconds after which a global fee tier set by the factory admin applies.
+pub const GLOBAL_FEE_TIER_DELAY: i64 = 3 * 86400; // 3 days
+/// Number of admin actions that can be queued at once.
+pub const ADMIN_QUEUE_CAPACITY: usize = 8;
+
+/// An admin instruction waiting in the [AdminQueue].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueuedAdminAction {
+    /// Unix timestamp after which the action can be executed
+    pub eta: i64,
+    /// The queued admin instruction
+    pub action: AdminActionData,
+    /// Hash of the keys of the accounts the action was queued with
+    pub accounts_hash: Hash,
+}
+
+impl QueuedAdminAction {
+    const LEN: usize = 1 + 8 + 1 + MAX_ADMIN_ACTION_LEN + 32;
+
+    /// Returns true if the grace period of the action has passed.
+    pub fn is_expired(&self, current_ts: i64) -> bool {
+        current_ts > self.eta.saturating_add(ADMIN_ACTION_GRACE_PERIOD)
+    }
+
+    fn unpack(input: &[u8; Self::LEN]) -> Result<Option<Self>, ProgramError> {
+        let (is_queued, eta, len, data, accounts_hash) =
+            array_refs![input, 1, 8, 1, MAX_ADMIN_ACTION_LEN, 32];
+        match is_queued {
+            [0] => Ok(None),
+            [1] => Ok(Some(Self {
+                eta: i64::from_le_bytes(*eta),
+                action: AdminActionData::from_bytes(
+                    data.get(..usize::from(len[0]))
+                        .ok_or(ProgramError::InvalidAccountData)?,
+                )?,
+                accounts_hash: Hash::new_from_array(*accounts_hash),
+            })),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    fn pack(queued: &Option<Self>, output: &mut [u8; Self::LEN]) {
+        let (is_queued, eta, len, data, accounts_hash) =
+            mut_array_refs![output, 1, 8, 1, MAX_ADMIN_ACTION_LEN, 32];
+        *data = [0u8; MAX_ADMIN_ACTION_LEN];
+        match queued {
+            None => {
+                is_queued[0] = 0;
+                *eta = [0u8; 8];
+                len[0] = 0;
+                *accounts_hash = [0u8; 32];
+            }
+            Some(queued) => {
+                let bytes = queued.action.as_bytes();
+                is_queued[0] = 1;
+                *eta = queued.eta.to_le_bytes();
+                len[0] = bytes.len() as u8;
+                data[..bytes.len()].copy_from_slice(bytes);
+                *accounts_hash = queued.accounts_hash.to_bytes();
+            }
+        }
+    }
+}
+
+/// Admin actions of a swap waiting for the timelock delay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdminQueue {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// The swap the queue belongs to
+    pub swap: Pubkey,
+    /// Queued actions, addressed by their index
+    pub actions: [Option<QueuedAdminAction>; ADMIN_QUEUE_CAPACITY],
+}
+
+impl Sealed for AdminQueue {}
+impl IsInitialized for AdminQueue {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for AdminQueue {
+    const LEN: usize = 1 + 32 + ADMIN_QUEUE_CAPACITY * QueuedAdminAction::LEN;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, AdminQueue::LEN];
+        let (is_initialized, swap, actions) =
+            array_refs![input, 1, 32, ADMIN_QUEUE_CAPACITY * QueuedAdminAction::LEN];
+        let mut queue = Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            swap: Pubkey::new_from_array(*swap),
+            actions: [None; ADMIN_QUEUE_CAPACITY],
+        };
+        for (action, packed) in queue
+            .actions
+            .iter_mut()
+            .zip(actions.chunks_exact(QueuedAdminAction::LEN))
+        {
+            *action = QueuedAdminAction::unpack(array_ref![packed, 0, QueuedAdminAction::LEN])?;
+        }
+        Ok(queue)
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, AdminQueue::LEN];
+        let (is_initialized, swap, actions) =
+            mut_array_refs![output, 1, 32, ADMIN_QUEUE_CAPACITY * QueuedAdminAction::LEN];
+        is_initialized[0] = self.is_initialized as u8;
+        swap.copy_from_slice(self.swap.as_ref());
+        for (action, packed) in self
+            .actions
+            .iter()
+            .zip(actions.chunks_exact_mut(QueuedAdminAction::LEN))
+        {
+            QueuedAdminAction::pack(action, array_mut_ref![packed, 0, QueuedAdminAction::LEN]);
+        }
+    }
+}
+
+fn unpack_bool(input: &[u8; 1]) -> Result<bool, ProgramError> {
+    match input {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// The pool factory. Its admin approves the [PoolTemplate]s pools are created
+/// with and becomes the admin of every pool the factory creates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Factory {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// Admin of the factory and of its pools
+    pub admin: Pubkey,
+}
+
+impl Sealed for Factory {}
+impl IsInitialized for Factory {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Factory {
+    const LEN: usize = 1 + 32;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, Factory::LEN];
+        let (is_initialized, admin) = array_refs![input, 1, 32];
+        Ok(Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            admin: Pubkey::new_from_array(*admin),
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, Factory::LEN];
+        let (is_initialized, admin) = mut_array_refs![output, 1, 32];
+        is_initialized[0] = self.is_initialized as u8;
+        admin.copy_from_slice(self.admin.as_ref());
+    }
+}
+
+/// Amplification coefficient and fees of the pools of an amp tier created by
+/// the factory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolTemplate {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// True if pools can be created with the template
+    pub is_enabled: bool,
+    /// The amp tier of the template
+    pub amp_tier: u8,
+    /// Amplification coefficient (A) of new pools
+    pub amp_factor: u64,
+    /// Fees of new pools
+    pub fees: Fees,
+}
+
+impl Sealed for PoolTemplate {}
+impl IsInitialized for PoolTemplate {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for PoolTemplate {
+    const LEN: usize = 1 + 1 + 1 + 8 + Fees::LEN;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, PoolTemplate::LEN];
+        let (is_initialized, is_enabled, amp_tier, amp_factor, fees) =
+            array_refs![input, 1, 1, 1, 8, Fees::LEN];
+        Ok(Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            is_enabled: unpack_bool(is_enabled)?,
+            amp_tier: amp_tier[0],
+            amp_factor: u64::from_le_bytes(*amp_factor),
+            fees: Fees::unpack_from_slice(fees)?,
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, PoolTemplate::LEN];
+        let (is_initialized, is_enabled, amp_tier, amp_factor, fees) =
+            mut_array_refs![output, 1, 1, 1, 8, Fees::LEN];
+        is_initialized[0] = self.is_initialized as u8;
+        is_enabled[0] = self.is_enabled as u8;
+        amp_tier[0] = self.amp_tier;
+        *amp_factor = self.amp_factor.to_le_bytes();
         self.fees.pack_into_slice(&mut fees[..]);
     }
 }
 
+/// Registry entry of the canonical pool of a mint pair and amp tier, created
+/// with the pool by the factory. The mints are sorted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegistryEntry {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// Mint of token A, the lower of the two mints
+    pub token_a_mint: Pubkey,
+    /// Mint of token B, the higher of the two mints
+    pub token_b_mint: Pubkey,
+    /// The amp tier of the pool
+    pub amp_tier: u8,
+    /// The canonical pool
+    pub swap: Pubkey,
+}
+
+impl Sealed for RegistryEntry {}
+impl IsInitialized for RegistryEntry {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for RegistryEntry {
+    const LEN: usize = 1 + 32 + 32 + 1 + 32;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, RegistryEntry::LEN];
+        let (is_initialized, token_a_mint, token_b_mint, amp_tier, swap) =
+            array_refs![input, 1, 32, 32, 1, 32];
+        Ok(Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            token_a_mint: Pubkey::new_from_array(*token_a_mint),
+            token_b_mint: Pubkey::new_from_array(*token_b_mint),
+            amp_tier: amp_tier[0],
+            swap: Pubkey::new_from_array(*swap),
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, RegistryEntry::LEN];
+        let (is_initialized, token_a_mint, token_b_mint, amp_tier, swap) =
+            mut_array_refs![output, 1, 32, 32, 1, 32];
+        is_initialized[0] = self.is_initialized as u8;
+        token_a_mint.copy_from_slice(self.token_a_mint.as_ref());
+        token_b_mint.copy_from_slice(self.token_b_mint.as_ref());
+        amp_tier[0] = self.amp_tier;
+        swap.copy_from_slice(self.swap.as_ref());
+    }
+}
+
+/// Trade fee discount of a user, set by the admin of a swap for the swap or by
+/// the admin of the factory for every swap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeeTier {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// The swap of the tier, or the default pubkey for a global tier
+    pub swap: Pubkey,
+    /// The user authority that receives the discount
+    pub authority: Pubkey,
+    /// Share of the trade fee paid by the user, in basis points
+    pub fee_multiplier_bps: u16,
+    /// Time from which the tier applies. Global tiers apply
+    /// [GLOBAL_FEE_TIER_DELAY] after they are set, tiers of a swap at once.
+    pub activation_ts: i64,
+}
+
+impl FeeTier {
+    /// Returns true if the tier is a global tier of the factory.
+    pub fn is_global(&self) -> bool {
+        self.swap == Pubkey::default()
+    }
+
+    /// Returns true if the tier applies at `now`.
+    pub fn is_active(&self, now: i64) -> bool {
+        now >= self.activation_ts
+    }
+}
+
+impl Sealed for FeeTier {}
+impl IsInitialized for FeeTier {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for FeeTier {
+    const LEN: usize = 1 + 32 + 32 + 2 + 8;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, FeeTier::LEN];
+        let (is_initialized, swap, authority, fee_multiplier_bps, activation_ts) =
+            array_refs![input, 1, 32, 32, 2, 8];
+        Ok(Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            swap: Pubkey::new_from_array(*swap),
+            authority: Pubkey::new_from_array(*authority),
+            fee_multiplier_bps: u16::from_le_bytes(*fee_multiplier_bps),
+            activation_ts: i64::from_le_bytes(*activation_ts),
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, FeeTier::LEN];
+        let (is_initialized, swap, authority, fee_multiplier_bps, activation_ts) =
+            mut_array_refs![output, 1, 32, 32, 2, 8];
+        is_initialized[0] = self.is_initialized as u8;
+        swap.copy_from_slice(self.swap.as_ref());
+        authority.copy_from_slice(self.authority.as_ref());
+        *fee_multiplier_bps = self.fee_multiplier_bps.to_le_bytes();
+        *activation_ts = self.activation_ts.to_le_bytes();
+    }
+}
+
+/// Gate of permissioned pools. Only the users on the allowlist of the gate may
+/// swap, deposit and withdraw in the pools that set it as their gate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gate {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// Key the gate address is derived from, the gatekeeper that created it
+    pub base: Pubkey,
+    /// Key that manages the allowlist of the gate
+    pub gatekeeper: Pubkey,
+}
+
+impl Sealed for Gate {}
+impl IsInitialized for Gate {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Gate {
+    const LEN: usize = 1 + 32 + 32;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, Gate::LEN];
+        let (is_initialized, base, gatekeeper) = array_refs![input, 1, 32, 32];
+        Ok(Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            base: Pubkey::new_from_array(*base),
+            gatekeeper: Pubkey::new_from_array(*gatekeeper),
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, Gate::LEN];
+        let (is_initialized, base, gatekeeper) = mut_array_refs![output, 1, 32, 32];
+        is_initialized[0] = self.is_initialized as u8;
+        base.copy_from_slice(self.base.as_ref());
+        gatekeeper.copy_from_slice(self.gatekeeper.as_ref());
+    }
+}
+
+/// Membership of a user in the allowlist of a [Gate].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AllowlistEntry {
+    /// Initialized state
+    pub is_initialized: bool,
+    /// The gate of the allowlist
+    pub gate: Pubkey,
+    /// The allowlisted user authority
+    pub authority: Pubkey,
+    /// Bump seed of the entry address
+    pub bump: u8,
+}
+
+impl Sealed for AllowlistEntry {}
+impl IsInitialized for AllowlistEntry {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for AllowlistEntry {
+    const LEN: usize = 1 + 32 + 32 + 1;
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, AllowlistEntry::LEN];
+        let (is_initialized, gate, authority, bump) = array_refs![input, 1, 32, 32, 1];
+        Ok(Self {
+            is_initialized: unpack_bool(is_initialized)?,
+            gate: Pubkey::new_from_array(*gate),
+            authority: Pubkey::new_from_array(*authority),
+            bump: bump[0],
+        })
+    }
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, AllowlistEntry::LEN];
+        let (is_initialized, gate, authority, bump) = mut_array_refs![output, 1, 32, 32, 1];
+        is_initialized[0] = self.is_initialized as u8;
+        gate.copy_from_slice(self.gate.as_ref());
+        authority.copy_from_slice(self.authority.as_ref());
+        bump[0] = self.bump;
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -231,10 +1223,10 @@ mod tests {
         };
 
         let is_initialized = true;
-        let is_paused = false;
+        let pause_flags = PauseFlags::PAUSE;
         let swap_info = SwapInfo {
             is_initialized,
-            is_paused,
+            pause_flags,
             nonce,
             initial_amp_factor,
             target_amp_factor,
@@ -248,15 +1240,36 @@ mod tests {
                 mint: token_a_mint,
                 admin_fees: admin_fee_key_a,
                 index: 0,
+                accrued_admin_fees: 0,
             },
             token_b: SwapTokenInfo {
                 reserves: token_b,
                 mint: token_b_mint,
                 admin_fees: admin_fee_key_b,
                 index: 1,
+                accrued_admin_fees: 0,
             },
             pool_mint,
             fees,
+            extra_fees: ExtraFees::default(),
+            admin_fee_mode: AdminFeeMode::Transfer,
+            lp_admin_fees: Pubkey::default(),
+            lp_admin_fee_last_d: 0,
+            lp_admin_fee_last_supply: 0,
+            lp_admin_fee_last_amp_factor: 0,
+            token_program_id: SPL_TOKEN_PROGRAM_ID,
+            guardian_key: Pubkey::default(),
+            roles: RoleKeys::default(),
+            timelock_delay: 0,
+            admin_queue: Pubkey::default(),
+            is_admin_renounced: false,
+            accepts_global_fee_tiers: false,
+            gate: Pubkey::default(),
+            circuit_breaker: CircuitBreaker::default(),
+            price_oracle: PriceOracle::default(),
+            last_d: 0,
+            last_amp_factor: 0,
+            last_sum_x: 0,
         };
 
         let mut packed = [0u8; SwapInfo::LEN];
@@ -266,7 +1279,7 @@ mod tests {
 
         let mut packed = vec![
             1_u8, // is_initialized
-            0_u8, // is_paused
+            1_u8, // is_paused
             nonce,
         ];
         packed.extend_from_slice(&initial_amp_factor.to_le_bytes());
@@ -291,7 +1304,377 @@ mod tests {
         packed.extend_from_slice(&trade_fee_denominator.to_le_bytes());
         packed.extend_from_slice(&withdraw_fee_numerator.to_le_bytes());
         packed.extend_from_slice(&withdraw_fee_denominator.to_le_bytes());
+        assert_eq!(packed.len(), SwapInfo::LEGACY_LEN);
         let unpacked = SwapInfo::unpack(&packed).unwrap();
         assert_eq!(swap_info, unpacked);
+
+        // legacy layout is packed in place
+        let mut legacy = [0u8; SwapInfo::LEGACY_LEN];
+        SwapInfo::pack(swap_info, &mut legacy).unwrap();
+        assert_eq!(legacy[..], packed[..]);
+
+        // the last invariant is dropped by the legacy layout
+        let with_last_d = SwapInfo {
+            last_d: 2_000_000,
+            last_amp_factor: 100,
+            last_sum_x: 2_000_000,
+            ..swap_info
+        };
+        SwapInfo::pack(with_last_d, &mut legacy).unwrap();
+        assert_eq!(SwapInfo::unpack(&legacy).unwrap(), swap_info);
+
+        // fields added since the legacy layout
+        let flash_loan_fee_numerator: u64 = 9;
+        let flash_loan_fee_denominator: u64 = 10;
+        let referral_fee_numerator: u64 = 18;
+        let referral_fee_denominator: u64 = 19;
+        let accrued_admin_fees_a: u64 = 11;
+        let accrued_admin_fees_b: u64 = 12;
+        let lp_admin_fees_raw = [10u8; 32];
+        let lp_admin_fees = Pubkey::new_from_array(lp_admin_fees_raw);
+        let lp_admin_fee_last_d: u64 = 13;
+        let lp_admin_fee_last_supply: u64 = 14;
+        let lp_admin_fee_last_amp_factor: u64 = 15;
+        let token_program_id_raw = [11u8; 32];
+        let token_program_id = Pubkey::new_from_array(token_program_id_raw);
+        let guardian_key_raw = [12u8; 32];
+        let guardian_key = Pubkey::new_from_array(guardian_key_raw);
+        let roles = RoleKeys {
+            fee_manager: Pubkey::new_from_array([13u8; 32]),
+            amp_manager: Pubkey::new_from_array([14u8; 32]),
+            fee_account_manager: Pubkey::new_from_array([15u8; 32]),
+            pause_manager: Pubkey::new_from_array([16u8; 32]),
+        };
+        let extended_info = SwapInfo {
+            token_a: SwapTokenInfo {
+                accrued_admin_fees: accrued_admin_fees_a,
+                ..swap_info.token_a
+            },
+            token_b: SwapTokenInfo {
+                accrued_admin_fees: accrued_admin_fees_b,
+                ..swap_info.token_b
+            },
+            extra_fees: ExtraFees {
+                flash_loan_fee_numerator,
+                flash_loan_fee_denominator,
+                referral_fee_numerator,
+                referral_fee_denominator,
+            },
+            admin_fee_mode: AdminFeeMode::Accrue,
+            lp_admin_fees,
+            lp_admin_fee_last_d,
+            lp_admin_fee_last_supply,
+            lp_admin_fee_last_amp_factor,
+            token_program_id,
+            pause_flags: PauseFlags::SWAP.union(PauseFlags::DEPOSIT),
+            guardian_key,
+            roles,
+            timelock_delay: 86400,
+            admin_queue: Pubkey::new_from_array([17u8; 32]),
+            is_admin_renounced: true,
+            accepts_global_fee_tiers: true,
+            gate: Pubkey::new_from_array([18u8; 32]),
+            circuit_breaker: CircuitBreaker {
+                max_price_deviation_bps: 300,
+                window_slots: 150,
+                reference_slot: 1_000,
+                reference_price: 1_000_000_000_000,
+            },
+            price_oracle: PriceOracle {
+                feed: Pubkey::new_from_array([19u8; 32]),
+                max_deviation_bps: 100,
+                max_staleness_slots: 25,
+                decimals_a: 6,
+                decimals_b: 9,
+            },
+            last_d: 2_000_000,
+            last_amp_factor: 100,
+            last_sum_x: 2_000_000,
+            ..swap_info
+        };
+        let mut extended = packed.clone();
+        extended.push(SwapInfo::VERSION);
+        extended.extend_from_slice(&flash_loan_fee_numerator.to_le_bytes());
+        extended.extend_from_slice(&flash_loan_fee_denominator.to_le_bytes());
+        extended.push(1_u8); // admin_fee_mode
+        extended.extend_from_slice(&accrued_admin_fees_a.to_le_bytes());
+        extended.extend_from_slice(&accrued_admin_fees_b.to_le_bytes());
+        extended.extend_from_slice(&lp_admin_fees_raw);
+        extended.extend_from_slice(&lp_admin_fee_last_d.to_le_bytes());
+        extended.extend_from_slice(&lp_admin_fee_last_supply.to_le_bytes());
+        extended.extend_from_slice(&lp_admin_fee_last_amp_factor.to_le_bytes());
+        extended.extend_from_slice(&token_program_id_raw);
+        extended.push(3_u8); // pause_flags
+        extended.extend_from_slice(&guardian_key_raw);
+        extended.extend_from_slice(&[13u8; 32]);
+        extended.extend_from_slice(&[14u8; 32]);
+        extended.extend_from_slice(&[15u8; 32]);
+        extended.extend_from_slice(&[16u8; 32]);
+        extended.extend_from_slice(&86400_i64.to_le_bytes());
+        extended.extend_from_slice(&[17u8; 32]);
+        extended.push(1_u8); // is_admin_renounced
+        extended.extend_from_slice(&referral_fee_numerator.to_le_bytes());
+        extended.extend_from_slice(&referral_fee_denominator.to_le_bytes());
+        extended.push(1_u8); // accepts_global_fee_tiers
+        extended.extend_from_slice(&[18u8; 32]);
+        extended.extend_from_slice(&300u16.to_le_bytes());
+        extended.extend_from_slice(&150u64.to_le_bytes());
+        extended.extend_from_slice(&1_000u64.to_le_bytes());
+        extended.extend_from_slice(&1_000_000_000_000u64.to_le_bytes());
+        extended.extend_from_slice(&[19u8; 32]);
+        extended.extend_from_slice(&100u16.to_le_bytes());
+        extended.extend_from_slice(&25u64.to_le_bytes());
+        extended.extend_from_slice(&[6u8, 9u8]);
+        extended.extend_from_slice(&2_000_000u64.to_le_bytes());
+        extended.extend_from_slice(&100u64.to_le_bytes());
+        extended.extend_from_slice(&2_000_000u64.to_le_bytes());
+        extended.extend_from_slice(&[0u8; 162]); // reserved
+        assert_eq!(extended.len(), SwapInfo::LEN);
+        let unpacked = SwapInfo::unpack(&extended).unwrap();
+        assert_eq!(extended_info, unpacked);
+        let mut repacked = [0u8; SwapInfo::LEN];
+        SwapInfo::pack(extended_info, &mut repacked).unwrap();
+        assert_eq!(repacked[..], extended[..]);
+
+        // the legacy layout cannot store them
+        let not_legacy = [
+            SwapInfo {
+                extra_fees: extended_info.extra_fees,
+                ..swap_info
+            },
+            SwapInfo {
+                extra_fees: ExtraFees {
+                    referral_fee_numerator,
+                    referral_fee_denominator,
+                    ..swap_info.extra_fees
+                },
+                ..swap_info
+            },
+            SwapInfo {
+                admin_fee_mode: AdminFeeMode::Accrue,
+                ..swap_info
+            },
+            SwapInfo {
+                token_a: extended_info.token_a,
+                ..swap_info
+            },
+            SwapInfo {
+                lp_admin_fees,
+                ..swap_info
+            },
+            SwapInfo {
+                token_program_id,
+                ..swap_info
+            },
+            // the legacy pause flag only holds a full pause
+            SwapInfo {
+                pause_flags: PauseFlags::SWAP,
+                ..swap_info
+            },
+            SwapInfo {
+                guardian_key,
+                ..swap_info
+            },
+            SwapInfo { roles, ..swap_info },
+            SwapInfo {
+                timelock_delay: extended_info.timelock_delay,
+                ..swap_info
+            },
+            SwapInfo {
+                admin_queue: extended_info.admin_queue,
+                ..swap_info
+            },
+            SwapInfo {
+                is_admin_renounced: true,
+                ..swap_info
+            },
+            SwapInfo {
+                accepts_global_fee_tiers: true,
+                ..swap_info
+            },
+            SwapInfo {
+                gate: extended_info.gate,
+                ..swap_info
+            },
+            SwapInfo {
+                circuit_breaker: extended_info.circuit_breaker,
+                ..swap_info
+            },
+            SwapInfo {
+                price_oracle: extended_info.price_oracle,
+                ..swap_info
+            },
+        ];
+        for info in not_legacy {
+            assert_eq!(
+                SwapInfo::pack(info, &mut legacy),
+                Err(SwapError::LegacySwapLayout.into())
+            );
+        }
+        assert_eq!(legacy[..], packed[..]);
+
+        // other lengths are rejected
+        assert_eq!(
+            SwapInfo::unpack(&extended[..SwapInfo::LEN - 1]),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // unknown version
+        let mut invalid = extended.clone();
+        invalid[SwapInfo::LEGACY_LEN] = SwapInfo::VERSION + 1;
+        assert_eq!(
+            SwapInfo::unpack(&invalid),
+            Err(ProgramError::InvalidAccountData)
+        );
+        // unset version of an initialized swap
+        invalid[SwapInfo::LEGACY_LEN] = 0;
+        assert_eq!(
+            SwapInfo::unpack(&invalid),
+            Err(ProgramError::InvalidAccountData)
+        );
+        // unset version of an uninitialized account
+        assert!(
+            !SwapInfo::unpack_unchecked(&[0u8; SwapInfo::LEN])
+                .unwrap()
+                .is_initialized
+        );
+
+        // invalid pause flags
+        let mut invalid = packed.clone();
+        invalid[1] = 2;
+        assert_eq!(
+            SwapInfo::unpack(&invalid),
+            Err(ProgramError::InvalidAccountData)
+        );
+        let mut invalid = extended.clone();
+        invalid[SwapInfo::LEGACY_LEN + 122] = 1 << 5;
+        assert_eq!(
+            SwapInfo::unpack(&invalid),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // invalid admin fee mode
+        let mode_offset = SwapInfo::LEGACY_LEN + 17;
+        extended[mode_offset] = 3;
+        assert_eq!(
+            SwapInfo::unpack(&extended),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_admin_queue_packing() {
+        let mut queue = AdminQueue {
+            is_initialized: true,
+            swap: Pubkey::new_from_array([1u8; 32]),
+            actions: [None; ADMIN_QUEUE_CAPACITY],
+        };
+        let action = crate::instruction::AdminInstruction::SetNewFees(Fees::default());
+        queue.actions[2] = Some(QueuedAdminAction {
+            eta: 1_000,
+            action: AdminActionData::new(&action).unwrap(),
+            accounts_hash: Hash::new_from_array([2u8; 32]),
+        });
+
+        let mut packed = [0u8; AdminQueue::LEN];
+        AdminQueue::pack(queue, &mut packed).unwrap();
+        let unpacked = AdminQueue::unpack(&packed).unwrap();
+        assert_eq!(unpacked, queue);
+        assert_eq!(
+            unpacked.actions[2].unwrap().action.unpack().unwrap(),
+            action
+        );
+
+        let queued = queue.actions[2].unwrap();
+        assert!(!queued.is_expired(1_000 + ADMIN_ACTION_GRACE_PERIOD));
+        assert!(queued.is_expired(1_001 + ADMIN_ACTION_GRACE_PERIOD));
+
+        // invalid queued flag
+        packed[1 + 32] = 2;
+        assert_eq!(
+            AdminQueue::unpack(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_factory_packing() {
+        let factory = Factory {
+            is_initialized: true,
+            admin: Pubkey::new_from_array([1u8; 32]),
+        };
+        let mut packed = [0u8; Factory::LEN];
+        Factory::pack(factory, &mut packed).unwrap();
+        assert_eq!(Factory::unpack(&packed).unwrap(), factory);
+
+        let template = PoolTemplate {
+            is_initialized: true,
+            is_enabled: true,
+            amp_tier: 2,
+            amp_factor: 100,
+            fees: Fees {
+                trade_fee_numerator: 4,
+                trade_fee_denominator: 10_000,
+                ..Fees::default()
+            },
+        };
+        let mut packed = [0u8; PoolTemplate::LEN];
+        PoolTemplate::pack(template, &mut packed).unwrap();
+        assert_eq!(PoolTemplate::unpack(&packed).unwrap(), template);
+        // invalid enabled flag
+        packed[1] = 2;
+        assert_eq!(
+            PoolTemplate::unpack(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let entry = RegistryEntry {
+            is_initialized: true,
+            token_a_mint: Pubkey::new_from_array([2u8; 32]),
+            token_b_mint: Pubkey::new_from_array([3u8; 32]),
+            amp_tier: 2,
+            swap: Pubkey::new_from_array([4u8; 32]),
+        };
+        let mut packed = [0u8; RegistryEntry::LEN];
+        RegistryEntry::pack(entry, &mut packed).unwrap();
+        assert_eq!(RegistryEntry::unpack(&packed).unwrap(), entry);
+
+        let tier = FeeTier {
+            is_initialized: true,
+            swap: Pubkey::new_from_array([5u8; 32]),
+            authority: Pubkey::new_from_array([6u8; 32]),
+            fee_multiplier_bps: 2_500,
+            activation_ts: 86400,
+        };
+        let mut packed = [0u8; FeeTier::LEN];
+        FeeTier::pack(tier, &mut packed).unwrap();
+        assert_eq!(FeeTier::unpack(&packed).unwrap(), tier);
+        assert!(!tier.is_global());
+        assert!(FeeTier {
+            swap: Pubkey::default(),
+            ..tier
+        }
+        .is_global());
+        assert!(!tier.is_active(86399));
+        assert!(tier.is_active(86400));
+
+        let gate = Gate {
+            is_initialized: true,
+            base: Pubkey::new_from_array([7u8; 32]),
+            gatekeeper: Pubkey::new_from_array([8u8; 32]),
+        };
+        let mut packed = [0u8; Gate::LEN];
+        Gate::pack(gate, &mut packed).unwrap();
+        assert_eq!(Gate::unpack(&packed).unwrap(), gate);
+
+        let entry = AllowlistEntry {
+            is_initialized: true,
+            gate: Pubkey::new_from_array([9u8; 32]),
+            authority: Pubkey::new_from_array([10u8; 32]),
+            bump: 254,
+        };
+        let mut packed = [0u8; AllowlistEntry::LEN];
+        AllowlistEntry::pack(entry, &mut packed).unwrap();
+        assert_eq!(AllowlistEntry::unpack(&packed).unwrap(), entry);
     }
 }
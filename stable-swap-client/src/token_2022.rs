@@ -0,0 +1,441 @@
+//! Token-2022 program support.
+//!
+//! Token-2022 accounts and mints share the spl_token base layout, followed by an
+//! account type byte and a list of type-length-value extensions. Only the
+//! extensions that the swap needs to understand are decoded here.
+
+use crate::error::SwapError;
+use arrayref::{array_ref, array_refs};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Length of a spl_token `Mint`.
+pub const MINT_LEN: usize = 82;
+/// Length of a spl_token `Account`. Token-2022 mints are padded to this length
+/// before their account type, so that they can never be confused with accounts.
+pub const ACCOUNT_LEN: usize = 165;
+
+/// Account type byte of an extended mint.
+const ACCOUNT_TYPE_MINT: u8 = 1;
+/// Account type byte of an extended token account.
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+/// Length of a token account with the `TransferFeeAmount` extension, which
+/// Token-2022 requires of the accounts of mints with transfer fees: the base,
+/// the account type, the type and length of the extension and the withheld
+/// amount.
+pub const TRANSFER_FEE_ACCOUNT_LEN: usize = ACCOUNT_LEN + 1 + 4 + 8;
+
+/// Maximum transfer fee, in basis points.
+const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+/// Instruction tag of the instructions of the transfer fee extension.
+pub const TRANSFER_FEE_EXTENSION: u8 = 26;
+/// Transfer fee instruction moving withheld fees from token accounts to their mint.
+pub const HARVEST_WITHHELD_TOKENS_TO_MINT: u8 = 4;
+
+/// Token-2022 extension types.
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionType {
+    /// Padding.
+    Uninitialized = 0,
+    /// Mint charges a fee on every transfer.
+    TransferFeeConfig = 1,
+    /// Fees withheld on a token account.
+    TransferFeeAmount = 2,
+    /// Mint can be closed by an authority.
+    MintCloseAuthority = 3,
+    /// Mint supports confidential transfers.
+    ConfidentialTransferMint = 4,
+    /// Account supports confidential transfers.
+    ConfidentialTransferAccount = 5,
+    /// New accounts of the mint start in a given state.
+    DefaultAccountState = 6,
+    /// Account owner cannot be changed.
+    ImmutableOwner = 7,
+    /// Incoming transfers to the account require a memo.
+    MemoTransfer = 8,
+    /// Tokens of the mint cannot be transferred.
+    NonTransferable = 9,
+    /// Mint displays amounts with accrued interest.
+    InterestBearingConfig = 10,
+    /// Account restricts actions taken through cross-program invocations.
+    CpiGuard = 11,
+    /// Mint has a delegate over every token account.
+    PermanentDelegate = 12,
+    /// Account of a non-transferable mint.
+    NonTransferableAccount = 13,
+    /// Mint calls a program on every transfer.
+    TransferHook = 14,
+    /// Account of a mint with a transfer hook.
+    TransferHookAccount = 15,
+    /// Mint withholds transfer fees confidentially.
+    ConfidentialTransferFeeConfig = 16,
+    /// Account withholds transfer fees confidentially.
+    ConfidentialTransferFeeAmount = 17,
+    /// Mint points to its metadata.
+    MetadataPointer = 18,
+    /// Mint stores its metadata.
+    TokenMetadata = 19,
+}
+
+impl ExtensionType {
+    /// Converts a packed extension type into an [ExtensionType].
+    pub fn from_u16(extension_type: u16) -> Option<Self> {
+        Some(match extension_type {
+            0 => Self::Uninitialized,
+            1 => Self::TransferFeeConfig,
+            2 => Self::TransferFeeAmount,
+            3 => Self::MintCloseAuthority,
+            4 => Self::ConfidentialTransferMint,
+            5 => Self::ConfidentialTransferAccount,
+            6 => Self::DefaultAccountState,
+            7 => Self::ImmutableOwner,
+            8 => Self::MemoTransfer,
+            9 => Self::NonTransferable,
+            10 => Self::InterestBearingConfig,
+            11 => Self::CpiGuard,
+            12 => Self::PermanentDelegate,
+            13 => Self::NonTransferableAccount,
+            14 => Self::TransferHook,
+            15 => Self::TransferHookAccount,
+            16 => Self::ConfidentialTransferFeeConfig,
+            17 => Self::ConfidentialTransferFeeAmount,
+            18 => Self::MetadataPointer,
+            19 => Self::TokenMetadata,
+            _ => return None,
+        })
+    }
+
+    /// Whether a pool can hold tokens of a mint with this extension.
+    ///
+    /// Interest-bearing mints only change how amounts are displayed, so the
+    /// invariant is computed on raw amounts like any other token.
+    pub fn is_supported_by_pool(&self) -> bool {
+        matches!(
+            self,
+            Self::Uninitialized
+                | Self::TransferFeeConfig
+                | Self::MintCloseAuthority
+                | Self::InterestBearingConfig
+                | Self::MetadataPointer
+                | Self::TokenMetadata
+        )
+    }
+}
+
+/// A transfer fee charged by a mint starting at an epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferFee {
+    /// First epoch where the fee takes effect.
+    pub epoch: u64,
+    /// Maximum fee charged per transfer.
+    pub maximum_fee: u64,
+    /// Fee charged per transfer, in basis points of the amount.
+    pub transfer_fee_basis_points: u16,
+}
+
+impl TransferFee {
+    const LEN: usize = 18;
+
+    fn unpack(input: &[u8; Self::LEN]) -> Self {
+        let (epoch, maximum_fee, transfer_fee_basis_points) = array_refs![input, 8, 8, 2];
+        Self {
+            epoch: u64::from_le_bytes(*epoch),
+            maximum_fee: u64::from_le_bytes(*maximum_fee),
+            transfer_fee_basis_points: u16::from_le_bytes(*transfer_fee_basis_points),
+        }
+    }
+
+    /// Fee charged on a transfer of `amount`, rounded up.
+    pub fn calculate_fee(&self, amount: u64) -> Option<u64> {
+        if self.transfer_fee_basis_points == 0 || amount == 0 {
+            return Some(0);
+        }
+        let bps = u128::from(self.transfer_fee_basis_points.min(MAX_FEE_BASIS_POINTS));
+        let fee = u128::from(amount)
+            .checked_mul(bps)?
+            .checked_add(u128::from(MAX_FEE_BASIS_POINTS))?
+            .checked_sub(1)?
+            .checked_div(u128::from(MAX_FEE_BASIS_POINTS))?;
+        Some(u64::try_from(fee).ok()?.min(self.maximum_fee))
+    }
+
+    /// Amount received by the destination of a transfer of `amount`.
+    pub fn calculate_post_fee_amount(&self, amount: u64) -> Option<u64> {
+        amount.checked_sub(self.calculate_fee(amount)?)
+    }
+}
+
+/// Transfer fee configuration of a mint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferFeeConfig {
+    /// Fee in effect before [TransferFeeConfig::newer_transfer_fee].
+    pub older_transfer_fee: TransferFee,
+    /// Fee in effect starting at its epoch.
+    pub newer_transfer_fee: TransferFee,
+}
+
+impl TransferFeeConfig {
+    const LEN: usize = 108;
+
+    fn unpack(input: &[u8; Self::LEN]) -> Self {
+        let (_authority, _withdraw_withheld_authority, _withheld_amount, older, newer) =
+            array_refs![input, 32, 32, 8, TransferFee::LEN, TransferFee::LEN];
+        Self {
+            older_transfer_fee: TransferFee::unpack(older),
+            newer_transfer_fee: TransferFee::unpack(newer),
+        }
+    }
+
+    /// Transfer fee in effect at `epoch`.
+    pub fn get_epoch_fee(&self, epoch: u64) -> &TransferFee {
+        if epoch >= self.newer_transfer_fee.epoch {
+            &self.newer_transfer_fee
+        } else {
+            &self.older_transfer_fee
+        }
+    }
+}
+
+/// Extensions of a Token-2022 mint that the swap depends on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MintExtensions {
+    /// Types of all extensions on the mint.
+    pub extension_types: Vec<ExtensionType>,
+    /// The transfer fee configuration, if the mint charges transfer fees.
+    pub transfer_fee_config: Option<TransferFeeConfig>,
+}
+
+impl MintExtensions {
+    /// Decodes the extensions of a mint. Mints without extensions have the
+    /// length of a spl_token `Mint`.
+    pub fn unpack(data: &[u8]) -> Result<Self, SwapError> {
+        let tlv_data = match data.len() {
+            MINT_LEN => return Ok(Self::default()),
+            len if len > ACCOUNT_LEN => {
+                if data[ACCOUNT_LEN] != ACCOUNT_TYPE_MINT {
+                    return Err(SwapError::ExpectedMint);
+                }
+                &data[ACCOUNT_LEN + 1..]
+            }
+            _ => return Err(SwapError::ExpectedMint),
+        };
+
+        let mut extensions = Self::default();
+        let mut rest = tlv_data;
+        while rest.len() >= 4 {
+            let (extension_type, length) = array_refs![array_ref![rest, 0, 4], 2, 2];
+            let extension_type = u16::from_le_bytes(*extension_type);
+            let length = usize::from(u16::from_le_bytes(*length));
+            let value_and_rest = &rest[4..];
+            let value = value_and_rest
+                .get(..length)
+                .ok_or(SwapError::ExpectedMint)?;
+            // an uninitialized entry marks the end of the extensions
+            if extension_type == ExtensionType::Uninitialized as u16 {
+                break;
+            }
+            let extension_type =
+                ExtensionType::from_u16(extension_type).ok_or(SwapError::UnsupportedMint)?;
+            if extension_type == ExtensionType::TransferFeeConfig {
+                if value.len() != TransferFeeConfig::LEN {
+                    return Err(SwapError::ExpectedMint);
+                }
+                extensions.transfer_fee_config = Some(TransferFeeConfig::unpack(array_ref![
+                    value,
+                    0,
+                    TransferFeeConfig::LEN
+                ]));
+            }
+            extensions.extension_types.push(extension_type);
+            rest = &value_and_rest[length..];
+        }
+        Ok(extensions)
+    }
+
+    /// Checks that a pool can hold tokens of the mint.
+    pub fn check_supported_by_pool(&self) -> Result<(), SwapError> {
+        if self
+            .extension_types
+            .iter()
+            .all(ExtensionType::is_supported_by_pool)
+        {
+            Ok(())
+        } else {
+            Err(SwapError::UnsupportedMint)
+        }
+    }
+
+    /// Length of a new token account of the mint.
+    pub fn account_len(&self) -> usize {
+        if self.transfer_fee_config.is_some() {
+            TRANSFER_FEE_ACCOUNT_LEN
+        } else {
+            ACCOUNT_LEN
+        }
+    }
+
+    /// Transfer fee in effect at `epoch`, if the mint charges transfer fees.
+    pub fn get_epoch_fee(&self, epoch: u64) -> Option<&TransferFee> {
+        self.transfer_fee_config
+            .as_ref()
+            .map(|config| config.get_epoch_fee(epoch))
+    }
+}
+
+/// Creates a `HarvestWithheldTokensToMint` instruction, which moves the
+/// transfer fees withheld in the `sources` token accounts to their mint.
+/// Anyone may harvest withheld fees. Accounts with withheld fees cannot be
+/// closed.
+pub fn harvest_withheld_tokens_to_mint(mint: &Pubkey, sources: &[&Pubkey]) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(*mint, false)];
+    accounts.extend(
+        sources
+            .iter()
+            .map(|source| AccountMeta::new(**source, false)),
+    );
+    Instruction {
+        program_id: id(),
+        accounts,
+        data: vec![TRANSFER_FEE_EXTENSION, HARVEST_WITHHELD_TOKENS_TO_MINT],
+    }
+}
+
+/// Returns the spl_token base of a mint, which may be followed by extensions.
+pub fn mint_base(data: &[u8]) -> Option<&[u8]> {
+    match data.len() {
+        MINT_LEN => Some(data),
+        len if len > ACCOUNT_LEN && data[ACCOUNT_LEN] == ACCOUNT_TYPE_MINT => data.get(..MINT_LEN),
+        _ => None,
+    }
+}
+
+/// Returns the spl_token base of a token account, which may be followed by extensions.
+pub fn account_base(data: &[u8]) -> Option<&[u8]> {
+    match data.len() {
+        ACCOUNT_LEN => Some(data),
+        len if len > ACCOUNT_LEN && data[ACCOUNT_LEN] == ACCOUNT_TYPE_ACCOUNT => {
+            data.get(..ACCOUNT_LEN)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn extended_mint(extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0u8; ACCOUNT_LEN];
+        data.push(ACCOUNT_TYPE_MINT);
+        for (extension_type, value) in extensions {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        data
+    }
+
+    fn transfer_fee_config(older: TransferFee, newer: TransferFee) -> Vec<u8> {
+        let mut value = vec![0u8; 72];
+        for fee in [older, newer] {
+            value.extend_from_slice(&fee.epoch.to_le_bytes());
+            value.extend_from_slice(&fee.maximum_fee.to_le_bytes());
+            value.extend_from_slice(&fee.transfer_fee_basis_points.to_le_bytes());
+        }
+        value
+    }
+
+    #[test]
+    fn test_transfer_fee() {
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: 1_000,
+            transfer_fee_basis_points: 100,
+        };
+        assert_eq!(fee.calculate_fee(0), Some(0));
+        assert_eq!(fee.calculate_fee(1), Some(1));
+        assert_eq!(fee.calculate_fee(10_000), Some(100));
+        assert_eq!(fee.calculate_fee(10_001), Some(101));
+        assert_eq!(fee.calculate_fee(u64::MAX), Some(1_000));
+        assert_eq!(fee.calculate_post_fee_amount(10_000), Some(9_900));
+
+        let no_fee = TransferFee::default();
+        assert_eq!(no_fee.calculate_fee(u64::MAX), Some(0));
+    }
+
+    #[test]
+    fn test_mint_extensions() {
+        let base = vec![0u8; MINT_LEN];
+        assert_eq!(MintExtensions::unpack(&base), Ok(MintExtensions::default()));
+        assert_eq!(mint_base(&base), Some(&base[..]));
+        assert_eq!(MintExtensions::default().account_len(), ACCOUNT_LEN);
+        assert_eq!(
+            MintExtensions::unpack(&[0u8; ACCOUNT_LEN]),
+            Err(SwapError::ExpectedMint)
+        );
+
+        let older = TransferFee {
+            epoch: 0,
+            maximum_fee: 10,
+            transfer_fee_basis_points: 50,
+        };
+        let newer = TransferFee {
+            epoch: 5,
+            maximum_fee: 20,
+            transfer_fee_basis_points: 100,
+        };
+        let data = extended_mint(&[
+            (
+                ExtensionType::TransferFeeConfig as u16,
+                transfer_fee_config(older, newer),
+            ),
+            (ExtensionType::InterestBearingConfig as u16, vec![0u8; 52]),
+        ]);
+        assert_eq!(mint_base(&data).unwrap().len(), MINT_LEN);
+        assert_eq!(account_base(&data), None);
+        let extensions = MintExtensions::unpack(&data).unwrap();
+        assert_eq!(
+            extensions.extension_types,
+            vec![
+                ExtensionType::TransferFeeConfig,
+                ExtensionType::InterestBearingConfig
+            ]
+        );
+        assert_eq!(extensions.get_epoch_fee(4), Some(&older));
+        assert_eq!(extensions.get_epoch_fee(5), Some(&newer));
+        assert_eq!(extensions.account_len(), TRANSFER_FEE_ACCOUNT_LEN);
+        assert_eq!(extensions.check_supported_by_pool(), Ok(()));
+
+        for unsupported in [
+            ExtensionType::NonTransferable,
+            ExtensionType::PermanentDelegate,
+        ] {
+            let data = extended_mint(&[(unsupported as u16, vec![0u8; 32])]);
+            let extensions = MintExtensions::unpack(&data).unwrap();
+            assert_eq!(extensions.get_epoch_fee(0), None);
+            assert_eq!(
+                extensions.check_supported_by_pool(),
+                Err(SwapError::UnsupportedMint)
+            );
+        }
+
+        // unknown extension
+        let data = extended_mint(&[(u16::MAX, vec![])]);
+        assert_eq!(
+            MintExtensions::unpack(&data),
+            Err(SwapError::UnsupportedMint)
+        );
+
+        // truncated extension
+        let mut data = extended_mint(&[(ExtensionType::MintCloseAuthority as u16, vec![0u8; 32])]);
+        data.truncate(data.len() - 1);
+        assert_eq!(MintExtensions::unpack(&data), Err(SwapError::ExpectedMint));
+    }
+}
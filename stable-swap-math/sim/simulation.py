@@ -51,6 +51,20 @@ class Curve:
             Dprev = D
             D = (Ann * S + D_P * self.n) * D // ((Ann - 1) * D + (self.n + 1) * D_P)
 
+        # Round down to the largest integer not exceeding D, which does not
+        # depend on the initial guess
+        P = self.n ** self.n
+        for x in xp:
+            P = P * x
+
+        def exceeds(D):
+            return D ** (self.n + 1) + (Ann - 1) * D * P > Ann * S * P
+
+        while D > 0 and exceeds(D):
+            D -= 1
+        while not exceeds(D + 1):
+            D += 1
+
         return D
 
     def y(self, i, j, x):
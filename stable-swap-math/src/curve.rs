@@ -1,6 +1,9 @@
 //! Swap calculations and curve invariant implementation
 
-use crate::{bn::U192, math::FeeCalculator};
+use crate::{
+    bn::{U192, U256},
+    math::FeeCalculator,
+};
 use num_traits::ToPrimitive;
 use stable_swap_client::{
     fees::Fees,
@@ -27,6 +30,14 @@ pub const MAX_AMP: u64 = 1_000_000;
 /// Maximum number of tokens to swap at once.
 pub const MAX_TOKENS_IN: u64 = u64::MAX >> 4;
 
+/// Maximum change of `sum(x_i)` since a previous invariant was computed for
+/// the previous invariant to be used as the initial guess of the invariant, in
+/// basis points of `sum(x_i)`. See [StableSwap::compute_d_with_guess].
+pub const D_GUESS_TOLERANCE_BPS: u64 = 300;
+
+/// Precision of marginal prices, see [StableSwap::compute_marginal_price].
+pub const PRICE_PRECISION: u64 = 1_000_000_000_000;
+
 /// Encodes all results of swapping from a source token to a destination token.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SwapResult {
@@ -187,18 +198,102 @@ impl StableSwap {
     ///
     /// *For more info on reserves, see [stable_swap_client::state::SwapTokenInfo::reserves].*
     pub fn compute_d(&self, amount_a: u64, amount_b: u64) -> Option<U192> {
+        let sum_x = amount_a.checked_add(amount_b)?; // sum(x_i), a.k.a S
+        self.compute_d_from(amount_a, amount_b, sum_x.into())
+    }
+
+    /// Computes the invariant (D) like [StableSwap::compute_d], reusing the
+    /// invariant `last_d` of reserves summing to `last_sum_x`, computed at the
+    /// amplification coefficient `last_amp_factor`, as the initial guess of
+    /// Newton's method.
+    ///
+    /// The guess is only used if it is set, the amplification coefficient is
+    /// unchanged and `sum(x_i)` moved by at most [D_GUESS_TOLERANCE_BPS] since,
+    /// i.e. the reserves have not changed drastically. Either way, the result
+    /// is exactly the one of [StableSwap::compute_d].
+    pub fn compute_d_with_guess(
+        &self,
+        amount_a: u64,
+        amount_b: u64,
+        last_d: u64,
+        last_sum_x: u64,
+        last_amp_factor: u64,
+    ) -> Option<U192> {
+        let sum_x = amount_a.checked_add(amount_b)?;
+        let tolerance = (sum_x as u128)
+            .checked_mul(D_GUESS_TOLERANCE_BPS.into())?
+            .checked_div(10_000)?;
+        if last_d != 0
+            && last_amp_factor == self.compute_amp_factor()?
+            && sum_x.abs_diff(last_sum_x) as u128 <= tolerance
+        {
+            self.compute_d_from(amount_a, amount_b, last_d.into())
+        } else {
+            self.compute_d(amount_a, amount_b)
+        }
+    }
+
+    /// Computes the invariant (D), starting Newton's method at `d_init`.
+    ///
+    /// Newton's method converges from any positive `d_init`, but needs fewer
+    /// iterations the closer `d_init` is to D, and none if `d_init` is D. It
+    /// stops within 1 of D, on a side depending on `d_init`, so the result is
+    /// rounded down to the largest integer not exceeding D, which does not
+    /// depend on `d_init`.
+    pub fn compute_d_from(&self, amount_a: u64, amount_b: u64, d_init: U192) -> Option<U192> {
+        self.compute_d_and_iterations(amount_a, amount_b, d_init)
+            .map(|(d, _)| d)
+    }
+
+    /// Returns the number of iterations of Newton's method
+    /// [StableSwap::compute_d_from] takes from `d_init`, which dominates its
+    /// compute cost.
+    pub fn compute_d_iterations(
+        &self,
+        amount_a: u64,
+        amount_b: u64,
+        d_init: U192,
+    ) -> Option<usize> {
+        self.compute_d_and_iterations(amount_a, amount_b, d_init)
+            .map(|(_, iterations)| iterations)
+    }
+
+    fn compute_d_and_iterations(
+        &self,
+        amount_a: u64,
+        amount_b: u64,
+        d_init: U192,
+    ) -> Option<(U192, usize)> {
         let sum_x = amount_a.checked_add(amount_b)?; // sum(x_i), a.k.a S
         if sum_x == 0 {
-            Some(0.into())
+            Some((0.into(), 0))
         } else {
             let amp_factor = self.compute_amp_factor()?;
+            // the reserves did not change since `d_init` was computed
+            if !d_init.is_zero()
+                && !self.exceeds_d(amp_factor, amount_a, amount_b, d_init)?
+                && self.exceeds_d(
+                    amp_factor,
+                    amount_a,
+                    amount_b,
+                    d_init.checked_add(1.into())?,
+                )?
+            {
+                return Some((d_init, 0));
+            }
             let amount_a_times_coins = amount_a.checked_mul(N_COINS.into())?;
             let amount_b_times_coins = amount_b.checked_mul(N_COINS.into())?;
 
             // Newton's method to approximate D
             let mut d_prev: U192;
-            let mut d: U192 = sum_x.into();
-            for _ in 0..256 {
+            let mut d: U192 = if d_init.is_zero() {
+                sum_x.into()
+            } else {
+                d_init
+            };
+            let mut iterations = 0;
+            for iteration in 1..=256 {
+                iterations = iteration;
                 let mut d_prod = d;
                 d_prod = d_prod
                     .checked_mul(d)?
@@ -218,10 +313,42 @@ impl StableSwap {
                 }
             }
 
-            Some(d)
+            // Round down to the largest integer not exceeding D
+            while !d.is_zero() && self.exceeds_d(amp_factor, amount_a, amount_b, d)? {
+                d = d.checked_sub(1.into())?;
+            }
+            while !self.exceeds_d(amp_factor, amount_a, amount_b, d.checked_add(1.into())?)? {
+                d = d.checked_add(1.into())?;
+            }
+
+            Some((d, iterations))
         }
     }
 
+    /// Returns true if `d` exceeds the invariant (D) of the reserves, i.e. with
+    /// `Ann = A * n**n`:
+    ///
+    /// ```text
+    /// d**(n+1) / (n**n * prod(x_i)) + (Ann - 1) * d > Ann * sum(x_i)
+    /// ```
+    fn exceeds_d(&self, amp_factor: u64, amount_a: u64, amount_b: u64, d: U192) -> Option<bool> {
+        let ann = amp_factor.checked_mul(N_COINS.into())?; // A * n ** n
+        let d = U256::from(d.to_u128()?);
+        let sum_x = amount_a.checked_add(amount_b)?;
+        // n**n * prod(x_i)
+        let prod_x = U256::from(amount_a)
+            .checked_mul(amount_b.into())?
+            .checked_mul(4.into())?;
+        let lhs = d.checked_mul(d)?.checked_mul(d)?.checked_add(
+            d.checked_mul(ann.checked_sub(1)?.into())?
+                .checked_mul(prod_x)?,
+        )?;
+        let rhs = U256::from(ann)
+            .checked_mul(sum_x.into())?
+            .checked_mul(prod_x)?;
+        Some(lhs > rhs)
+    }
+
     /// Computes the amount of pool tokens to mint after a deposit.
     pub fn compute_mint_amount_for_deposit(
         &self,
@@ -234,13 +361,42 @@ impl StableSwap {
     ) -> Option<u64> {
         // Initial invariant
         let d_0 = self.compute_d(swap_amount_a, swap_amount_b)?;
+        self.compute_mint_amount_for_deposit_with_d(
+            deposit_amount_a,
+            deposit_amount_b,
+            swap_amount_a,
+            swap_amount_b,
+            d_0,
+            pool_token_supply,
+            fees,
+        )
+        .map(|(mint_amount, _)| mint_amount)
+    }
+
+    /// Computes the amount of pool tokens to mint after a deposit like
+    /// [StableSwap::compute_mint_amount_for_deposit], given the invariant `d_0`
+    /// of the reserves before the deposit.
+    ///
+    /// Returns the amount of pool tokens to mint and the invariant of the
+    /// reserves after the deposit.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_mint_amount_for_deposit_with_d(
+        &self,
+        deposit_amount_a: u64,
+        deposit_amount_b: u64,
+        swap_amount_a: u64,
+        swap_amount_b: u64,
+        d_0: U192,
+        pool_token_supply: u64,
+        fees: &Fees,
+    ) -> Option<(u64, U192)> {
         let old_balances = [swap_amount_a, swap_amount_b];
         let mut new_balances = [
             swap_amount_a.checked_add(deposit_amount_a)?,
             swap_amount_b.checked_add(deposit_amount_b)?,
         ];
         // Invariant after change
-        let d_1 = self.compute_d(new_balances[0], new_balances[1])?;
+        let d_1 = self.compute_d_from(new_balances[0], new_balances[1], d_0)?;
         if d_1 <= d_0 {
             None
         } else {
@@ -259,11 +415,12 @@ impl StableSwap {
                 new_balances[i] = new_balances[i].checked_sub(fee)?;
             }
 
-            let d_2 = self.compute_d(new_balances[0], new_balances[1])?;
-            U192::from(pool_token_supply)
+            let d_2 = self.compute_d_from(new_balances[0], new_balances[1], d_1)?;
+            let mint_amount = U192::from(pool_token_supply)
                 .checked_mul(d_2.checked_sub(d_0)?)?
                 .checked_div(d_0)?
-                .to_u64()
+                .to_u64()?;
+            Some((mint_amount, d_1))
         }
     }
 
@@ -331,6 +488,28 @@ impl StableSwap {
         fees: &Fees,
     ) -> Option<(u64, u64)> {
         let d_0 = self.compute_d(swap_base_amount, swap_quote_amount)?;
+        self.compute_withdraw_one_with_d(
+            pool_token_amount,
+            pool_token_supply,
+            swap_base_amount,
+            swap_quote_amount,
+            d_0,
+            fees,
+        )
+    }
+
+    /// Computes the withdrawal of one token like
+    /// [StableSwap::compute_withdraw_one], given the invariant `d_0` of the
+    /// reserves before the withdrawal.
+    pub fn compute_withdraw_one_with_d(
+        &self,
+        pool_token_amount: u64,
+        pool_token_supply: u64,
+        swap_base_amount: u64,  // Same denomination of token to be withdrawn
+        swap_quote_amount: u64, // Counter denomination of token to be withdrawn
+        d_0: U192,
+        fees: &Fees,
+    ) -> Option<(u64, u64)> {
         let d_1 = d_0.checked_sub(
             U192::from(pool_token_amount)
                 .checked_mul(d_0)?
@@ -365,6 +544,57 @@ impl StableSwap {
         Some((dy, dy_0.checked_sub(dy)?))
     }
 
+    /// Computes the amount of pool tokens to mint to the admin for fees that were left in the pool.
+    ///
+    /// The growth of `D` per pool token since the last checkpoint (`last_d`, `last_supply`)
+    /// is attributed to fees, and the admin trade fee share of that growth is minted as
+    /// pool tokens such that the admin owns exactly that value after the mint. The growth
+    /// is measured at the amplification coefficient of the checkpoint (`last_amp_factor`),
+    /// so that ramping A is not mistaken for fees.
+    ///
+    /// Returns the amount of pool tokens to mint and the current `D`, which is the
+    /// new checkpoint together with the pool token supply after minting and the
+    /// current amplification coefficient.
+    /// If there is no checkpoint (`last_supply == 0`), nothing is minted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_admin_lp_fee(
+        &self,
+        swap_amount_a: u64,
+        swap_amount_b: u64,
+        pool_token_supply: u64,
+        last_d: u64,
+        last_supply: u64,
+        last_amp_factor: u64,
+        fees: &Fees,
+    ) -> Option<(u64, u64)> {
+        let d_1 = self.compute_d(swap_amount_a, swap_amount_b)?.to_u64()?;
+        if last_supply == 0 || last_amp_factor == 0 || pool_token_supply == 0 {
+            return Some((0, d_1));
+        }
+        let at_last_amp = StableSwap::new(
+            last_amp_factor,
+            last_amp_factor,
+            self.current_ts,
+            self.current_ts,
+            self.current_ts,
+        );
+        let d_grown = at_last_amp.compute_d(swap_amount_a, swap_amount_b)?;
+        // D the pool would have if its value per pool token had not changed
+        let d_0 = U192::from(pool_token_supply)
+            .checked_mul(last_d.into())?
+            .checked_div(last_supply.into())?;
+        if d_grown <= d_0 {
+            return Some((0, d_1));
+        }
+        let admin_value = fees.admin_trade_fee(d_grown.checked_sub(d_0)?.to_u64()?)?;
+        // admin_value / d_grown = mint_amount / (pool_token_supply + mint_amount)
+        let mint_amount = U192::from(admin_value)
+            .checked_mul(pool_token_supply.into())?
+            .checked_div(d_grown.checked_sub(admin_value.into())?)?
+            .to_u64()?;
+        Some((mint_amount, d_1))
+    }
+
     /// Compute SwapResult after an exchange
     pub fn swap_to(
         &self,
@@ -373,10 +603,26 @@ impl StableSwap {
         swap_destination_amount: u64,
         fees: &Fees,
     ) -> Option<SwapResult> {
-        let y = self.compute_y(
-            swap_source_amount.checked_add(source_amount)?,
+        self.swap_to_with_d(
+            source_amount,
+            swap_source_amount,
+            swap_destination_amount,
             self.compute_d(swap_source_amount, swap_destination_amount)?,
-        )?;
+            fees,
+        )
+    }
+
+    /// Compute SwapResult after an exchange, given the invariant `d` of the
+    /// reserves before the exchange, e.g. from [StableSwap::compute_d_with_guess].
+    pub fn swap_to_with_d(
+        &self,
+        source_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+        d: U192,
+        fees: &Fees,
+    ) -> Option<SwapResult> {
+        let y = self.compute_y(swap_source_amount.checked_add(source_amount)?, d)?;
         // https://github.com/curvefi/curve-contract/blob/b0bbf77f8f93c9c5f4e415bce9cd71f0cdee960e/contracts/pool-templates/base/SwapTemplateBase.vy#L466
         let dy = swap_destination_amount.checked_sub(y)?.checked_sub(1)?;
         let dy_fee = fees.trade_fee(dy)?;
@@ -396,6 +642,46 @@ impl StableSwap {
             fee: dy_fee,
         })
     }
+
+    /// Computes the marginal price of token A in token B, i.e. the amount of
+    /// token B an infinitesimal amount of token A swaps to before fees, scaled
+    /// by [PRICE_PRECISION].
+    ///
+    /// Differentiating the invariant with `Ann = A * n**n`:
+    ///
+    /// ```text
+    /// t = 4 * Ann * (x * y)**2 / D**3
+    /// price = (t + y) / (t + x)
+    /// ```
+    pub fn compute_marginal_price(&self, amount_a: u64, amount_b: u64) -> Option<u64> {
+        let d = self.compute_d(amount_a, amount_b)?;
+        self.compute_marginal_price_with_d(amount_a, amount_b, d)
+    }
+
+    /// Computes the marginal price like [StableSwap::compute_marginal_price],
+    /// given the invariant `d` of the reserves.
+    pub fn compute_marginal_price_with_d(
+        &self,
+        amount_a: u64,
+        amount_b: u64,
+        d: U192,
+    ) -> Option<u64> {
+        let amp_factor = self.compute_amp_factor()?;
+        let ann = amp_factor.checked_mul(N_COINS.into())?; // A * n ** n
+        let d = U256::from(d.to_u128()?);
+        let xy = U256::from(amount_a).checked_mul(amount_b.into())?;
+        // xy <= D**2 / 4, so t <= Ann * D
+        let t = U256::from(ann.checked_mul(4)?)
+            .checked_mul(xy)?
+            .checked_div(d)?
+            .checked_mul(xy)?
+            .checked_div(d)?
+            .checked_div(d)?;
+        t.checked_add(amount_b.into())?
+            .checked_mul(PRICE_PRECISION.into())?
+            .checked_div(t.checked_add(amount_a.into())?)?
+            .to_u64()
+    }
 }
 
 #[cfg(test)]
@@ -435,6 +721,169 @@ mod tests {
 
     const RAMP_TICKS: i64 = 100000;
 
+    #[test]
+    fn test_compute_marginal_price() {
+        let invariant = StableSwap::new(100, 100, ZERO_TS, ZERO_TS, ZERO_TS);
+
+        // balanced pools trade at par
+        assert_eq!(
+            invariant.compute_marginal_price(1_000_000, 1_000_000),
+            Some(PRICE_PRECISION)
+        );
+        // the abundant token is cheaper
+        let price = invariant
+            .compute_marginal_price(3_000_000_000, 1_000_000_000)
+            .unwrap();
+        assert!(price < PRICE_PRECISION);
+        let inverse = invariant
+            .compute_marginal_price(1_000_000_000, 3_000_000_000)
+            .unwrap();
+        let product = price as u128 * inverse as u128;
+        let one = PRICE_PRECISION as u128 * PRICE_PRECISION as u128;
+        assert!(one - product < one / 1_000_000);
+
+        // the marginal price matches the rate of a small swap
+        let dx = 1_000;
+        let result = invariant
+            .swap_to(dx, 3_000_000_000, 1_000_000_000, &ZERO_FEES)
+            .unwrap();
+        let rate = result.amount_swapped as u128 * PRICE_PRECISION as u128 / dx as u128;
+        assert!((price as u128).abs_diff(rate) < PRICE_PRECISION as u128 / 100);
+
+        // the invariant of the reserves gives the same price
+        let d = invariant.compute_d(3_000_000_000, 1_000_000_000).unwrap();
+        assert_eq!(
+            invariant.compute_marginal_price_with_d(3_000_000_000, 1_000_000_000, d),
+            Some(price)
+        );
+
+        // empty pools have no price
+        assert_eq!(invariant.compute_marginal_price(0, 1_000_000), None);
+    }
+
+    #[test]
+    fn test_compute_d_with_guess() {
+        let invariant = StableSwap::new(100, 100, ZERO_TS, ZERO_TS, ZERO_TS);
+        let last_sum_x = 4_000_000_000;
+        let last_d = invariant
+            .compute_d(1_000_000_000, 3_000_000_000)
+            .unwrap()
+            .to_u64()
+            .unwrap();
+        let result = invariant
+            .swap_to(10_000_000, 1_000_000_000, 3_000_000_000, &MODEL_FEES)
+            .unwrap();
+        let (amount_a, amount_b) = (result.new_source_amount, result.new_destination_amount);
+        let d = invariant.compute_d(amount_a, amount_b).unwrap();
+
+        // starting at the previous invariant finds the same invariant
+        assert_eq!(
+            invariant.compute_d_from(amount_a, amount_b, last_d.into()),
+            Some(d)
+        );
+        assert_eq!(
+            invariant.compute_d_with_guess(amount_a, amount_b, last_d, last_sum_x, 100),
+            Some(d)
+        );
+
+        // the guess is ignored once amp or the reserves changed too much
+        assert_eq!(
+            invariant.compute_d_with_guess(amount_a, amount_b, last_d, last_sum_x, 200),
+            Some(d)
+        );
+        assert_eq!(
+            invariant.compute_d_with_guess(amount_a, amount_b, last_d, last_sum_x / 2, 100),
+            Some(d)
+        );
+        assert_eq!(
+            invariant.compute_d_with_guess(amount_a, amount_b, 0, last_sum_x, 100),
+            Some(d)
+        );
+
+        // the swap result only depends on the invariant
+        assert_eq!(
+            invariant.swap_to_with_d(1_000, amount_a, amount_b, d, &MODEL_FEES),
+            invariant.swap_to(1_000, amount_a, amount_b, &MODEL_FEES)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_compute_d_from_any_guess(
+            amp_factor in MIN_AMP..=MAX_AMP,
+            amount_a in 1..MAX_TOKENS_IN,
+            amount_b in 1..MAX_TOKENS_IN,
+            guess_percent in 50u64..200,
+        ) {
+            let invariant = StableSwap::new(amp_factor, amp_factor, ZERO_TS, ZERO_TS, ZERO_TS);
+            let d = invariant.compute_d(amount_a, amount_b).unwrap();
+            let d_init = d * guess_percent / 100;
+            prop_assert_eq!(invariant.compute_d_from(amount_a, amount_b, d_init), Some(d));
+        }
+    }
+
+    #[test]
+    fn test_compute_admin_lp_fee() {
+        let fees = Fees {
+            admin_trade_fee_numerator: 1,
+            admin_trade_fee_denominator: 2,
+            ..MODEL_FEES
+        };
+        let invariant = StableSwap::new(100, 100, ZERO_TS, ZERO_TS, ZERO_TS);
+        let d = invariant
+            .compute_d(1_000_000, 1_000_000)
+            .unwrap()
+            .to_u64()
+            .unwrap();
+        let supply = d;
+
+        // no checkpoint
+        assert_eq!(
+            invariant.compute_admin_lp_fee(1_000_000, 1_000_000, supply, 0, 0, 0, &fees),
+            Some((0, d))
+        );
+        // no growth
+        assert_eq!(
+            invariant.compute_admin_lp_fee(1_000_000, 1_000_000, supply, d, supply, 100, &fees),
+            Some((0, d))
+        );
+
+        // fees grew the pool
+        let (mint_amount, new_d) = invariant
+            .compute_admin_lp_fee(1_010_000, 1_010_000, supply, d, supply, 100, &fees)
+            .unwrap();
+        let growth = new_d - d;
+        let admin_value = growth / 2;
+        assert!(mint_amount > 0);
+        // the admin owns the admin share of the growth after minting
+        let admin_owned = new_d as u128 * mint_amount as u128 / (supply + mint_amount) as u128;
+        assert!(admin_value as u128 - admin_owned <= 1);
+
+        // growth is measured per pool token
+        let (mint_amount_2, _) = invariant
+            .compute_admin_lp_fee(1_010_000, 1_010_000, supply, d / 2, supply / 2, 100, &fees)
+            .unwrap();
+        assert_eq!(mint_amount, mint_amount_2);
+
+        // ramping A up grows D of imbalanced reserves without fees
+        let d = invariant
+            .compute_d(1_000_000, 500_000)
+            .unwrap()
+            .to_u64()
+            .unwrap();
+        let ramped = StableSwap::new(100, 200, ZERO_TS + 86400, ZERO_TS, ZERO_TS + 86400);
+        let ramped_d = ramped
+            .compute_d(1_000_000, 500_000)
+            .unwrap()
+            .to_u64()
+            .unwrap();
+        assert!(ramped_d > d);
+        assert_eq!(
+            ramped.compute_admin_lp_fee(1_000_000, 500_000, d, d, d, 100, &fees),
+            Some((0, ramped_d))
+        );
+    }
+
     #[test]
     fn test_ramp_amp_up() {
         let mut rng = rand::thread_rng();
@@ -1,7 +1,7 @@
 //! Math helpers
 
 use num_traits::ToPrimitive;
-use stable_swap_client::fees::Fees;
+use stable_swap_client::fees::{ExtraFees, Fees};
 
 const MAX: u64 = 1 << 32;
 const MAX_BIG: u64 = 1 << 48;
@@ -49,6 +49,14 @@ pub trait FeeCalculator {
     fn normalized_trade_fee(&self, n_coins: u8, amount: u64) -> Option<u64>;
 }
 
+/// Calculates the flash loan and referral fees.
+pub trait ExtraFeeCalculator {
+    /// Applies the flash loan fee.
+    fn flash_loan_fee(&self, loan_amount: u64) -> Option<u64>;
+    /// Applies the referral fee.
+    fn referral_fee(&self, fee_amount: u64) -> Option<u64>;
+}
+
 impl FeeCalculator for Fees {
     /// Apply admin trade fee
     fn admin_trade_fee(&self, fee_amount: u64) -> Option<u64> {
@@ -105,6 +113,30 @@ impl FeeCalculator for Fees {
     }
 }
 
+impl ExtraFeeCalculator for ExtraFees {
+    /// Compute flash loan fee from amount
+    fn flash_loan_fee(&self, loan_amount: u64) -> Option<u64> {
+        mul_div_imbalanced(
+            loan_amount,
+            self.flash_loan_fee_numerator,
+            self.flash_loan_fee_denominator,
+        )
+    }
+
+    /// Apply referral fee
+    fn referral_fee(&self, fee_amount: u64) -> Option<u64> {
+        // swaps that never set a referral fee store a zero denominator
+        if self.referral_fee_numerator == 0 {
+            return Some(0);
+        }
+        mul_div_imbalanced(
+            fee_amount,
+            self.referral_fee_numerator,
+            self.referral_fee_denominator,
+        )
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -164,4 +196,39 @@ mod tests {
             expected_normalized_fee
         );
     }
+
+    #[test]
+    fn extra_fee_results() {
+        let flash_loan_fee_numerator = 9;
+        let flash_loan_fee_denominator = 1000;
+        let referral_fee_numerator = 1;
+        let referral_fee_denominator = 4;
+        let extra_fees = ExtraFees {
+            flash_loan_fee_numerator,
+            flash_loan_fee_denominator,
+            referral_fee_numerator,
+            referral_fee_denominator,
+        };
+
+        let loan_amount = 5_000_000_000;
+        let expected_flash_loan_fee =
+            loan_amount * flash_loan_fee_numerator / flash_loan_fee_denominator;
+        assert_eq!(
+            extra_fees.flash_loan_fee(loan_amount).unwrap(),
+            expected_flash_loan_fee
+        );
+
+        let trade_fee = 1_000_000;
+        let expected_referral_fee = trade_fee * referral_fee_numerator / referral_fee_denominator;
+        assert_eq!(
+            extra_fees.referral_fee(trade_fee).unwrap(),
+            expected_referral_fee
+        );
+        let no_referral_fee = ExtraFees {
+            referral_fee_numerator: 0,
+            referral_fee_denominator: 0,
+            ..extra_fees
+        };
+        assert_eq!(no_referral_fee.referral_fee(trade_fee).unwrap(), 0);
+    }
 }
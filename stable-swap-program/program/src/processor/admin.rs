@@ -2,27 +2,42 @@
 
 use crate::{
     error::SwapError,
-    instruction::{AdminInstruction, RampAData},
+    fees::BPS_DENOMINATOR,
+    instruction::{
+        AdminActionData, AdminInstruction, CircuitBreakerData, PriceOracleData, RampAData,
+    },
     processor::utils,
-    state::SwapInfo,
+    state::{
+        AdminFeeMode, AdminQueue, AdminRole, CircuitBreaker, PauseFlags, PriceOracle,
+        QueuedAdminAction, RoleKeys, SwapInfo, ADMIN_QUEUE_CAPACITY, MAX_TIMELOCK_DELAY,
+    },
 };
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    hash::{hashv, Hash},
     msg,
+    program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
+    system_program,
     sysvar::{clock::Clock, Sysvar},
 };
-use stable_swap_client::fees::Fees;
+use stable_swap_client::fees::{ExtraFees, Fees};
 use stable_swap_math::curve::{StableSwap, MAX_AMP, MIN_AMP, MIN_RAMP_DURATION, ZERO_TS};
 
-use super::checks::check_has_admin_signer;
+use super::checks::{
+    check_has_admin_signer, check_swap_authority, check_token_program,
+    check_withdraw_token_accounts,
+};
+use super::logging::emit_admin_event;
+use super::{fee_tier, gate, oracle, system, token};
 
 const ADMIN_TRANSFER_DELAY: i64 = 259200; // 3 days
 
 /// Process admin instruction
 pub fn process_admin_instruction(
+    program_id: &Pubkey,
     instruction: &AdminInstruction,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
@@ -31,9 +46,77 @@ pub fn process_admin_instruction(
     let admin_info = next_account_info(account_info_iter)?;
 
     let token_swap = &mut SwapInfo::unpack(&swap_info.data.borrow_mut())?;
-    check_has_admin_signer(&token_swap.admin_key, admin_info)?;
+    if token_swap.is_admin_renounced {
+        return Err(SwapError::AdminRenounced.into());
+    }
+    if let AdminInstruction::AcceptAdmin = instruction {
+        msg!("Instruction: AcceptAdmin");
+        accept_admin(token_swap, admin_info)?;
+        SwapInfo::pack(*token_swap, &mut swap_info.data.borrow_mut())?;
+        return emit_admin_event(swap_info.key, admin_info.key, instruction);
+    }
+    let role = check_has_admin_signer(token_swap, instruction.allowed_roles(), admin_info)?;
+    if token_swap.timelock_delay > 0 && instruction.requires_timelock() {
+        return Err(SwapError::TimelockRequired.into());
+    }
 
     (match *instruction {
+        AdminInstruction::InitializeAdminQueue => {
+            msg!("Instruction: InitializeAdminQueue");
+            initialize_admin_queue(program_id, swap_info.key, token_swap, account_info_iter)
+        }
+        AdminInstruction::QueueAdminAction(action) => {
+            msg!("Instruction: QueueAdminAction");
+            queue_admin_action(token_swap, &action, account_info_iter)
+        }
+        AdminInstruction::CancelAdminAction(index) => {
+            msg!("Instruction: CancelAdminAction");
+            cancel_admin_action(token_swap, index, account_info_iter)
+        }
+        AdminInstruction::ExecuteAdminAction(index) => {
+            msg!("Instruction: ExecuteAdminAction");
+            execute_admin_action(
+                program_id,
+                swap_info,
+                admin_info,
+                token_swap,
+                index,
+                account_info_iter,
+            )
+        }
+        AdminInstruction::MigrateSwapInfo => {
+            msg!("Instruction: MigrateSwapInfo");
+            migrate_swap_info(swap_info, account_info_iter)
+        }
+        _ => process_admin_action(
+            program_id,
+            swap_info,
+            admin_info,
+            token_swap,
+            role,
+            instruction,
+            account_info_iter,
+        ),
+    })?;
+
+    // a closed swap has nothing to pack
+    if token_swap.is_initialized {
+        SwapInfo::pack(*token_swap, &mut swap_info.data.borrow_mut())?;
+    }
+    emit_admin_event(swap_info.key, admin_info.key, instruction)
+}
+
+/// Process an admin instruction that is run directly or from the admin queue
+fn process_admin_action<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    swap_info: &AccountInfo<'b>,
+    admin_info: &AccountInfo<'b>,
+    token_swap: &mut SwapInfo,
+    role: AdminRole,
+    instruction: &AdminInstruction,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    match *instruction {
         AdminInstruction::RampA(RampAData {
             target_amp,
             stop_ramp_ts,
@@ -59,19 +142,98 @@ pub fn process_admin_instruction(
         }
         AdminInstruction::ApplyNewAdmin => {
             msg!("Instruction: ApplyNewAdmin");
-            apply_new_admin(token_swap)
+            apply_new_admin(token_swap, account_info_iter)
         }
         AdminInstruction::CommitNewAdmin => {
             msg!("Instruction: CommitNewAdmin");
             commit_new_admin(token_swap, account_info_iter)
         }
+        AdminInstruction::CancelAdminTransfer => {
+            msg!("Instruction: CancelAdminTransfer");
+            cancel_admin_transfer(token_swap)
+        }
+        AdminInstruction::RenounceAdmin => {
+            msg!("Instruction: RenounceAdmin");
+            renounce_admin(token_swap)
+        }
         AdminInstruction::SetNewFees(new_fees) => {
             msg!("Instruction: SetNewFees");
             set_new_fees(token_swap, &new_fees)
         }
-    })?;
-
-    SwapInfo::pack(*token_swap, &mut swap_info.data.borrow_mut())
+        AdminInstruction::SetExtraFees(extra_fees) => {
+            msg!("Instruction: SetExtraFees");
+            set_extra_fees(token_swap, &extra_fees)
+        }
+        AdminInstruction::SetAdminFeeMode(mode) => {
+            msg!("Instruction: SetAdminFeeMode");
+            set_admin_fee_mode(token_swap, mode, account_info_iter)
+        }
+        AdminInstruction::SetPauseFlags(flags) => {
+            msg!("Instruction: SetPauseFlags");
+            set_pause_flags(token_swap, role, flags)
+        }
+        AdminInstruction::SetGuardian => {
+            msg!("Instruction: SetGuardian");
+            set_guardian(token_swap, account_info_iter)
+        }
+        AdminInstruction::GrantRole(role) => {
+            msg!("Instruction: GrantRole");
+            grant_role(token_swap, role, account_info_iter)
+        }
+        AdminInstruction::RevokeRole(role) => {
+            msg!("Instruction: RevokeRole");
+            revoke_role(token_swap, role)
+        }
+        AdminInstruction::SetTimelockDelay(delay) => {
+            msg!("Instruction: SetTimelockDelay");
+            set_timelock_delay(token_swap, delay)
+        }
+        AdminInstruction::SetGate => {
+            msg!("Instruction: SetGate");
+            set_gate(program_id, token_swap, account_info_iter)
+        }
+        AdminInstruction::SetCircuitBreaker(data) => {
+            msg!("Instruction: SetCircuitBreaker");
+            set_circuit_breaker(token_swap, &data)
+        }
+        AdminInstruction::ResetCircuitBreaker => {
+            msg!("Instruction: ResetCircuitBreaker");
+            reset_circuit_breaker(token_swap)
+        }
+        AdminInstruction::SetPriceOracle(data) => {
+            msg!("Instruction: SetPriceOracle");
+            set_price_oracle(token_swap, &data, account_info_iter)
+        }
+        AdminInstruction::SetFeeTier(fee_multiplier_bps) => {
+            msg!("Instruction: SetFeeTier");
+            set_fee_tier(
+                program_id,
+                swap_info.key,
+                fee_multiplier_bps,
+                account_info_iter,
+            )
+        }
+        AdminInstruction::RemoveFeeTier => {
+            msg!("Instruction: RemoveFeeTier");
+            remove_fee_tier(program_id, swap_info.key, account_info_iter)
+        }
+        AdminInstruction::ClosePool => {
+            msg!("Instruction: ClosePool");
+            close_pool(
+                program_id,
+                swap_info,
+                admin_info,
+                token_swap,
+                account_info_iter,
+            )
+        }
+        AdminInstruction::InitializeAdminQueue
+        | AdminInstruction::QueueAdminAction(_)
+        | AdminInstruction::CancelAdminAction(_)
+        | AdminInstruction::ExecuteAdminAction(_)
+        | AdminInstruction::AcceptAdmin
+        | AdminInstruction::MigrateSwapInfo => Err(SwapError::InvalidInstruction.into()),
+    }
 }
 
 /// Ramp to future a
@@ -163,18 +325,383 @@ fn stop_ramp_a(token_swap: &mut SwapInfo) -> ProgramResult {
 
 /// Pause swap
 fn pause(token_swap: &mut SwapInfo) -> ProgramResult {
-    token_swap.is_paused = true;
+    // keeps operations paused beyond `PauseFlags::PAUSE`, such as withdrawals
+    token_swap.pause_flags = token_swap.pause_flags.union(PauseFlags::PAUSE);
     msg!("Admin: Program paused");
     Ok(())
 }
 
 /// Unpause swap
 fn unpause(token_swap: &mut SwapInfo) -> ProgramResult {
-    token_swap.is_paused = false;
+    token_swap.pause_flags = PauseFlags::NONE;
     msg!("Admin: Program unpaused");
     Ok(())
 }
 
+/// Set paused operations
+fn set_pause_flags(token_swap: &mut SwapInfo, role: AdminRole, flags: PauseFlags) -> ProgramResult {
+    // the guardian may only pause operations
+    if role == AdminRole::Guardian && !flags.contains(token_swap.pause_flags) {
+        return Err(SwapError::Unauthorized.into());
+    }
+    token_swap.pause_flags = flags;
+    msg!("Admin: Pause flags set to {}", flags.bits());
+    Ok(())
+}
+
+/// Set guardian
+fn set_guardian<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &mut SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let guardian_info = next_account_info(account_info_iter)?;
+    token_swap.guardian_key = *guardian_info.key;
+    msg!("Admin: Guardian set to {}", token_swap.guardian_key);
+    Ok(())
+}
+
+/// Grant role
+fn grant_role<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &mut SwapInfo,
+    role: AdminRole,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    // the admin is transferred with CommitNewAdmin and ApplyNewAdmin
+    if role == AdminRole::Admin {
+        return Err(SwapError::InvalidInput.into());
+    }
+    let grantee_info = next_account_info(account_info_iter)?;
+    *token_swap.role_key_mut(role) = *grantee_info.key;
+    msg!("Admin: Granted {:?} to {}", role, grantee_info.key);
+    Ok(())
+}
+
+/// Revoke role
+fn revoke_role(token_swap: &mut SwapInfo, role: AdminRole) -> ProgramResult {
+    if role == AdminRole::Admin {
+        return Err(SwapError::InvalidInput.into());
+    }
+    *token_swap.role_key_mut(role) = Pubkey::default();
+    msg!("Admin: Revoked {:?}", role);
+    Ok(())
+}
+
+/// Set timelock delay
+fn set_timelock_delay(token_swap: &mut SwapInfo, delay: i64) -> ProgramResult {
+    if !(0..=MAX_TIMELOCK_DELAY).contains(&delay) {
+        return Err(SwapError::InvalidInput.into());
+    }
+    if delay > 0 && token_swap.admin_queue == Pubkey::default() {
+        msg!("Admin: Admin queue is not initialized");
+        return Err(SwapError::InvalidInput.into());
+    }
+    token_swap.timelock_delay = delay;
+    msg!("Admin: Timelock delay set to {}", delay);
+    Ok(())
+}
+
+/// Initialize admin queue
+fn initialize_admin_queue<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    swap_key: &Pubkey,
+    token_swap: &mut SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let admin_queue_info = next_account_info(account_info_iter)?;
+    if admin_queue_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if token_swap.admin_queue != Pubkey::default() {
+        return Err(SwapError::AlreadyInUse.into());
+    }
+    let admin_queue = AdminQueue::unpack_unchecked(&admin_queue_info.data.borrow())?;
+    if admin_queue.is_initialized {
+        return Err(SwapError::AlreadyInUse.into());
+    }
+
+    AdminQueue::pack(
+        AdminQueue {
+            is_initialized: true,
+            swap: *swap_key,
+            actions: [None; ADMIN_QUEUE_CAPACITY],
+        },
+        &mut admin_queue_info.data.borrow_mut(),
+    )?;
+    token_swap.admin_queue = *admin_queue_info.key;
+    msg!("Admin: Initialized admin queue {}", token_swap.admin_queue);
+    Ok(())
+}
+
+/// Migrate swap info to the versioned layout. The swap is packed in the new
+/// layout once it is reallocated.
+fn migrate_swap_info<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    swap_info: &AccountInfo<'b>,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let payer_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    if *system_program_info.key != system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !SwapInfo::is_legacy_layout(&swap_info.data.borrow()) {
+        msg!("Swap info is already migrated");
+        return Err(SwapError::InvalidInput.into());
+    }
+
+    system::realloc_account(
+        payer_info.clone(),
+        swap_info.clone(),
+        system_program_info.clone(),
+        SwapInfo::LEN,
+    )?;
+    msg!("Admin: Migrated swap info to version {}", SwapInfo::VERSION);
+    Ok(())
+}
+
+/// Set fee tier
+fn set_fee_tier<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    swap: &Pubkey,
+    fee_multiplier_bps: u16,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let payer_info = next_account_info(account_info_iter)?;
+    let fee_tier_info = next_account_info(account_info_iter)?;
+    let user_authority_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    fee_tier::set_fee_tier(
+        program_id,
+        Some(swap),
+        fee_multiplier_bps,
+        payer_info,
+        fee_tier_info,
+        user_authority_info,
+        system_program_info,
+    )
+}
+
+/// Remove fee tier
+fn remove_fee_tier<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    swap: &Pubkey,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let fee_tier_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    fee_tier::remove_fee_tier(program_id, Some(swap), fee_tier_info, destination_info)
+}
+
+/// Checks that the account is the admin queue of the swap
+fn check_admin_queue(token_swap: &SwapInfo, admin_queue_info: &AccountInfo) -> ProgramResult {
+    check_keys_equal!(
+        token_swap.admin_queue,
+        *admin_queue_info.key,
+        "Admin queue",
+        SwapError::IncorrectSwapAccount
+    );
+    Ok(())
+}
+
+/// Hashes the keys of the accounts of a queued admin action
+fn hash_action_accounts<'a, 'b: 'a>(accounts: impl Iterator<Item = &'a AccountInfo<'b>>) -> Hash {
+    let keys: Vec<&[u8]> = accounts.map(|account| account.key.as_ref()).collect();
+    hashv(&keys)
+}
+
+/// Queue admin action
+fn queue_admin_action<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &SwapInfo,
+    action: &AdminActionData,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let admin_queue_info = next_account_info(account_info_iter)?;
+    check_admin_queue(token_swap, admin_queue_info)?;
+    let mut admin_queue = AdminQueue::unpack(&admin_queue_info.data.borrow())?;
+
+    let current_ts = Clock::get()?.unix_timestamp;
+    let eta = current_ts
+        .checked_add(token_swap.timelock_delay)
+        .ok_or(SwapError::CalculationFailure)?;
+    // expired actions can no longer be executed, so their slots are reused
+    let index = admin_queue
+        .actions
+        .iter()
+        .position(|queued| match queued {
+            None => true,
+            Some(queued) => queued.is_expired(current_ts),
+        })
+        .ok_or(SwapError::AdminQueueFull)?;
+    admin_queue.actions[index] = Some(QueuedAdminAction {
+        eta,
+        action: *action,
+        accounts_hash: hash_action_accounts(account_info_iter),
+    });
+
+    AdminQueue::pack(admin_queue, &mut admin_queue_info.data.borrow_mut())?;
+    msg!("Admin: Queued admin action {} executable at {}", index, eta);
+    Ok(())
+}
+
+/// Cancel admin action
+fn cancel_admin_action<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &SwapInfo,
+    index: u8,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let admin_queue_info = next_account_info(account_info_iter)?;
+    check_admin_queue(token_swap, admin_queue_info)?;
+    let mut admin_queue = AdminQueue::unpack(&admin_queue_info.data.borrow())?;
+
+    admin_queue
+        .actions
+        .get_mut(usize::from(index))
+        .and_then(|queued| queued.take())
+        .ok_or(SwapError::InvalidInput)?;
+
+    AdminQueue::pack(admin_queue, &mut admin_queue_info.data.borrow_mut())?;
+    msg!("Admin: Cancelled admin action {}", index);
+    Ok(())
+}
+
+/// Execute admin action
+fn execute_admin_action<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    swap_info: &AccountInfo<'b>,
+    admin_info: &AccountInfo<'b>,
+    token_swap: &mut SwapInfo,
+    index: u8,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let admin_queue_info = next_account_info(account_info_iter)?;
+    check_admin_queue(token_swap, admin_queue_info)?;
+    let mut admin_queue = AdminQueue::unpack(&admin_queue_info.data.borrow())?;
+
+    let queued = admin_queue
+        .actions
+        .get_mut(usize::from(index))
+        .and_then(|queued| queued.take())
+        .ok_or(SwapError::InvalidInput)?;
+    let current_ts = Clock::get()?.unix_timestamp;
+    if current_ts < queued.eta {
+        return Err(SwapError::AdminActionNotReady.into());
+    }
+    if queued.is_expired(current_ts) {
+        return Err(SwapError::AdminActionExpired.into());
+    }
+    let action_accounts: Vec<&AccountInfo> = account_info_iter.collect();
+    if hash_action_accounts(action_accounts.iter().copied()) != queued.accounts_hash {
+        msg!("Admin: Accounts do not match the queued admin action");
+        return Err(SwapError::InvalidInput.into());
+    }
+
+    // the signer must be allowed to run the queued instruction
+    let action = queued.action.unpack()?;
+    let role = check_has_admin_signer(token_swap, action.allowed_roles(), admin_info)?;
+
+    AdminQueue::pack(admin_queue, &mut admin_queue_info.data.borrow_mut())?;
+    msg!("Admin: Executing admin action {}", index);
+    process_admin_action(
+        program_id,
+        swap_info,
+        admin_info,
+        token_swap,
+        role,
+        &action,
+        &mut action_accounts.into_iter(),
+    )
+}
+
+/// Set circuit breaker
+fn set_circuit_breaker(token_swap: &mut SwapInfo, data: &CircuitBreakerData) -> ProgramResult {
+    if u64::from(data.max_price_deviation_bps) > BPS_DENOMINATOR
+        || (data.max_price_deviation_bps != 0 && data.window_slots == 0)
+    {
+        return Err(SwapError::InvalidInput.into());
+    }
+    token_swap.circuit_breaker = CircuitBreaker {
+        max_price_deviation_bps: data.max_price_deviation_bps,
+        window_slots: data.window_slots,
+        ..CircuitBreaker::default()
+    };
+    msg!(
+        "Admin: Circuit breaker set to {} bps per {} slots",
+        data.max_price_deviation_bps,
+        data.window_slots
+    );
+    Ok(())
+}
+
+/// Reset circuit breaker
+fn reset_circuit_breaker(token_swap: &mut SwapInfo) -> ProgramResult {
+    token_swap.circuit_breaker.reference_slot = 0;
+    token_swap.circuit_breaker.reference_price = 0;
+    msg!("Admin: Circuit breaker reset");
+    Ok(())
+}
+
+/// Set price oracle
+fn set_price_oracle<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &mut SwapInfo,
+    data: &PriceOracleData,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let feed_info = next_account_info(account_info_iter)?;
+    if *feed_info.key == Pubkey::default() {
+        token_swap.price_oracle = PriceOracle::default();
+        msg!("Admin: Price oracle removed");
+        return Ok(());
+    }
+    if data.max_deviation_bps == 0
+        || u64::from(data.max_deviation_bps) > BPS_DENOMINATOR
+        || data.max_staleness_slots == 0
+    {
+        return Err(SwapError::InvalidInput.into());
+    }
+    oracle::load_price_feed(feed_info)?;
+    let token_a_mint_info = next_account_info(account_info_iter)?;
+    let token_b_mint_info = next_account_info(account_info_iter)?;
+    check_keys_equal!(
+        *token_a_mint_info.key,
+        token_swap.token_a.mint,
+        "Mint A",
+        SwapError::IncorrectMint
+    );
+    check_keys_equal!(
+        *token_b_mint_info.key,
+        token_swap.token_b.mint,
+        "Mint B",
+        SwapError::IncorrectMint
+    );
+    token_swap.price_oracle = PriceOracle {
+        feed: *feed_info.key,
+        max_deviation_bps: data.max_deviation_bps,
+        max_staleness_slots: data.max_staleness_slots,
+        decimals_a: utils::unpack_mint(&token_a_mint_info.data.borrow())?.decimals,
+        decimals_b: utils::unpack_mint(&token_b_mint_info.data.borrow())?.decimals,
+    };
+    msg!(
+        "Admin: Price oracle set to {} within {} bps",
+        feed_info.key,
+        data.max_deviation_bps
+    );
+    Ok(())
+}
+
+/// Set gate
+fn set_gate<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    token_swap: &mut SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let gate_info = next_account_info(account_info_iter)?;
+    if *gate_info.key != Pubkey::default() {
+        gate::load_gate(program_id, gate_info)?;
+    }
+    token_swap.gate = *gate_info.key;
+    msg!("Admin: Gate set to {}", token_swap.gate);
+    Ok(())
+}
+
 /// Set fee account
 fn set_fee_account<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
     token_swap: &mut SwapInfo,
@@ -202,6 +729,10 @@ fn set_fee_account<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
             "Admin: Fee account B set to {}",
             token_swap.token_b.admin_fees
         );
+    } else if new_admin_fee_account.mint == token_swap.pool_mint {
+        msg!("Admin: Old fee account LP {}", token_swap.lp_admin_fees);
+        token_swap.lp_admin_fees = *new_fee_account_info.key;
+        msg!("Admin: Fee account LP set to {}", token_swap.lp_admin_fees);
     } else {
         return Err(SwapError::InvalidAdmin.into());
     }
@@ -209,11 +740,35 @@ fn set_fee_account<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
     Ok(())
 }
 
-/// Apply new admin (finalize admin transfer)
-fn apply_new_admin(token_swap: &mut SwapInfo) -> ProgramResult {
+/// Apply new admin (finalize admin transfer), signed by the admin and the
+/// future admin
+fn apply_new_admin<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &mut SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    if token_swap.future_admin_deadline == ZERO_TS {
+        return Err(SwapError::NoActiveTransfer.into());
+    }
+    let new_admin_info = next_account_info(account_info_iter)?;
+    accept_admin(token_swap, new_admin_info)
+}
+
+/// Accept admin transfer, signed by the future admin
+fn accept_admin(token_swap: &mut SwapInfo, new_admin_info: &AccountInfo) -> ProgramResult {
     if token_swap.future_admin_deadline == ZERO_TS {
         return Err(SwapError::NoActiveTransfer.into());
     }
+    if *new_admin_info.key != token_swap.future_admin_key {
+        return Err(SwapError::Unauthorized.into());
+    }
+    if !new_admin_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    finalize_admin_transfer(token_swap)
+}
+
+/// Hands the swap to the future admin if the deadline has not passed
+fn finalize_admin_transfer(token_swap: &mut SwapInfo) -> ProgramResult {
     let clock = Clock::get()?;
     if clock.unix_timestamp > token_swap.future_admin_deadline {
         return Err(SwapError::AdminDeadlineExceeded.into());
@@ -227,6 +782,20 @@ fn apply_new_admin(token_swap: &mut SwapInfo) -> ProgramResult {
     Ok(())
 }
 
+/// Cancel admin transfer
+fn cancel_admin_transfer(token_swap: &mut SwapInfo) -> ProgramResult {
+    if token_swap.future_admin_deadline == ZERO_TS {
+        return Err(SwapError::NoActiveTransfer.into());
+    }
+    msg!(
+        "Admin: Cancelled admin transfer to {}",
+        token_swap.future_admin_key
+    );
+    token_swap.future_admin_key = Pubkey::default();
+    token_swap.future_admin_deadline = ZERO_TS;
+    Ok(())
+}
+
 /// Commit new admin (initiate admin transfer)
 fn commit_new_admin<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
     token_swap: &mut SwapInfo,
@@ -252,21 +821,275 @@ fn commit_new_admin<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
     Ok(())
 }
 
+/// Renounce admin, leaving the swap without admin
+fn renounce_admin(token_swap: &mut SwapInfo) -> ProgramResult {
+    // nobody could unpause the swap afterwards
+    if token_swap.pause_flags != PauseFlags::NONE {
+        return Err(SwapError::IsPaused.into());
+    }
+    msg!("Admin: Renouncing admin {}", token_swap.admin_key);
+    token_swap.is_admin_renounced = true;
+    token_swap.admin_key = Pubkey::default();
+    token_swap.future_admin_key = Pubkey::default();
+    token_swap.future_admin_deadline = ZERO_TS;
+    token_swap.guardian_key = Pubkey::default();
+    token_swap.roles = RoleKeys::default();
+    // the circuit breaker stays as configured; without an admin to reset it,
+    // it restarts from the current price at the start of every window
+    token_swap.circuit_breaker.reference_slot = 0;
+    token_swap.circuit_breaker.reference_price = 0;
+    Ok(())
+}
+
 /// Set new fees
 fn set_new_fees(token_swap: &mut SwapInfo, new_fees: &Fees) -> ProgramResult {
+    new_fees.validate()?;
+    token_swap.extra_fees.validate(new_fees)?;
     msg!("Admin: Old fees {:?}", token_swap.fees);
     token_swap.fees = *new_fees;
     msg!("Admin: New fees {:?}", token_swap.fees);
     Ok(())
 }
 
+/// Set extra fees
+fn set_extra_fees(token_swap: &mut SwapInfo, extra_fees: &ExtraFees) -> ProgramResult {
+    extra_fees.validate(&token_swap.fees)?;
+    msg!("Admin: Old extra fees {:?}", token_swap.extra_fees);
+    token_swap.extra_fees = *extra_fees;
+    msg!("Admin: New extra fees {:?}", token_swap.extra_fees);
+    Ok(())
+}
+
+/// Set admin fee mode
+fn set_admin_fee_mode<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &mut SwapInfo,
+    mode: AdminFeeMode,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    if mode == AdminFeeMode::LpMint && token_swap.lp_admin_fees == Pubkey::default() {
+        msg!("Admin: LP fee account not set");
+        return Err(SwapError::InvalidAdmin.into());
+    }
+    msg!("Admin: Old admin fee mode {:?}", token_swap.admin_fee_mode);
+    if token_swap.admin_fee_mode != mode && mode == AdminFeeMode::LpMint {
+        // fee growth is measured from the switch
+        checkpoint_lp_admin_fee(token_swap, account_info_iter)?;
+    } else if token_swap.admin_fee_mode == AdminFeeMode::LpMint && mode != AdminFeeMode::LpMint {
+        // fee growth since the checkpoint would be lost with the switch
+        check_lp_admin_fee_claimed(token_swap, account_info_iter)?;
+    }
+    token_swap.admin_fee_mode = mode;
+    msg!("Admin: New admin fee mode {:?}", token_swap.admin_fee_mode);
+    Ok(())
+}
+
+/// Checks that no pool tokens are owed as admin fees for the growth of the
+/// pool since the last checkpoint
+fn check_lp_admin_fee_claimed<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let (invariant, token_a_amount, token_b_amount, pool_token_supply) =
+        read_lp_admin_fee_accounts(token_swap, account_info_iter)?;
+    let (mint_amount, _) = invariant
+        .compute_admin_lp_fee(
+            token_a_amount,
+            token_b_amount,
+            pool_token_supply,
+            token_swap.lp_admin_fee_last_d,
+            token_swap.lp_admin_fee_last_supply,
+            token_swap.lp_admin_fee_last_amp_factor,
+            &token_swap.fees,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    if mint_amount != 0 {
+        msg!(
+            "Admin: {} pool tokens of admin fees are unclaimed",
+            mint_amount
+        );
+        return Err(SwapError::UnclaimedAdminFees.into());
+    }
+    Ok(())
+}
+
+/// Records the invariant, pool token supply and amplification coefficient
+/// that pool token admin fees are measured from
+fn checkpoint_lp_admin_fee<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &mut SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let (invariant, token_a_amount, token_b_amount, pool_token_supply) =
+        read_lp_admin_fee_accounts(token_swap, account_info_iter)?;
+    token_swap.lp_admin_fee_last_d = invariant
+        .compute_d(token_a_amount, token_b_amount)
+        .and_then(|d| d.to_u64())
+        .ok_or(SwapError::CalculationFailure)?;
+    token_swap.lp_admin_fee_last_supply = pool_token_supply;
+    token_swap.lp_admin_fee_last_amp_factor = invariant
+        .compute_amp_factor()
+        .ok_or(SwapError::CalculationFailure)?;
+    Ok(())
+}
+
+/// Reads the reserves and pool token supply that pool token admin fees are
+/// measured with, and returns them with the invariant of the swap
+fn read_lp_admin_fee_accounts<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    token_swap: &SwapInfo,
+    account_info_iter: &mut I,
+) -> Result<(StableSwap, u64, u64, u64), ProgramError> {
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+    check_lp_admin_fee_accounts(token_swap, token_a_info, token_b_info, pool_mint_info)?;
+
+    let token_a_amount = token_swap
+        .token_a
+        .pool_reserves(utils::unpack_token_account(&token_a_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let token_b_amount = token_swap
+        .token_b
+        .pool_reserves(utils::unpack_token_account(&token_b_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
+    let invariant = StableSwap::new_from_swap_info(token_swap, Clock::get()?.unix_timestamp);
+    Ok((invariant, token_a_amount, token_b_amount, pool_mint.supply))
+}
+
+/// Checks the reserves and pool mint accounts of the swap
+fn check_lp_admin_fee_accounts(
+    token_swap: &SwapInfo,
+    token_a_info: &AccountInfo,
+    token_b_info: &AccountInfo,
+    pool_mint_info: &AccountInfo,
+) -> ProgramResult {
+    check_keys_equal!(
+        *token_a_info.key,
+        token_swap.token_a.reserves,
+        "Token A",
+        SwapError::IncorrectSwapAccount
+    );
+    check_keys_equal!(
+        *token_b_info.key,
+        token_swap.token_b.reserves,
+        "Token B",
+        SwapError::IncorrectSwapAccount
+    );
+    check_keys_equal!(
+        *pool_mint_info.key,
+        token_swap.pool_mint,
+        "Pool mint",
+        SwapError::IncorrectMint
+    );
+    Ok(())
+}
+
+/// Close pool
+fn close_pool<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    swap_info: &AccountInfo<'b>,
+    admin_info: &AccountInfo<'b>,
+    token_swap: &mut SwapInfo,
+    account_info_iter: &mut I,
+) -> ProgramResult {
+    let authority_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let admin_fee_a_info = next_account_info(account_info_iter)?;
+    let admin_fee_b_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    check_token_program(token_swap, token_program_info.key)?;
+    check_swap_authority(token_swap, swap_info.key, program_id, authority_info.key)?;
+    check_keys_equal!(
+        *pool_mint_info.key,
+        token_swap.pool_mint,
+        "Pool mint",
+        SwapError::IncorrectMint
+    );
+    check_withdraw_token_accounts(&token_swap.token_a, token_a_info.key, admin_fee_a_info.key)?;
+    check_withdraw_token_accounts(&token_swap.token_b, token_b_info.key, admin_fee_b_info.key)?;
+
+    let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
+    if pool_mint.supply != 0 {
+        return Err(SwapError::InvalidSupply.into());
+    }
+
+    let mint_infos: Vec<AccountInfo> = account_info_iter.cloned().collect();
+    let (mint_a, mint_b) = token::load_transfer_mints(token_swap, &mint_infos)?;
+    for (token, reserves_info) in [
+        (&token_swap.token_a, token_a_info),
+        (&token_swap.token_b, token_b_info),
+    ] {
+        let reserves = utils::unpack_token_account(&reserves_info.data.borrow())?;
+        if token.pool_reserves(reserves.amount) != Some(0) {
+            return Err(SwapError::NonEmptyReserves.into());
+        }
+    }
+    for (token, reserves_info, admin_fee_info, mint) in [
+        (&token_swap.token_a, token_a_info, admin_fee_a_info, mint_a),
+        (&token_swap.token_b, token_b_info, admin_fee_b_info, mint_b),
+    ] {
+        // only the accrued admin fees are left in the reserves
+        if token.accrued_admin_fees > 0 {
+            token::transfer_as_swap(
+                swap_info.key,
+                token_program_info.clone(),
+                reserves_info.clone(),
+                admin_fee_info.clone(),
+                authority_info.clone(),
+                token_swap.nonce,
+                token.accrued_admin_fees,
+                mint.as_ref(),
+            )?;
+        }
+        // Token-2022 accounts holding withheld transfer fees cannot be closed
+        if let Some(mint) = mint.as_ref().filter(|mint| mint.transfer_fee.is_some()) {
+            token::harvest_withheld_tokens_to_mint(
+                token_program_info.clone(),
+                mint,
+                reserves_info.clone(),
+            )?;
+        }
+        token::close_account_as_swap(
+            swap_info.key,
+            token_program_info.clone(),
+            reserves_info.clone(),
+            admin_info.clone(),
+            authority_info.clone(),
+            token_swap.nonce,
+        )?;
+    }
+
+    let swap_lamports = swap_info.lamports();
+    **admin_info.lamports.borrow_mut() = admin_info
+        .lamports()
+        .checked_add(swap_lamports)
+        .ok_or(SwapError::CalculationFailure)?;
+    **swap_info.lamports.borrow_mut() = 0;
+    swap_info.data.borrow_mut().fill(0);
+    token_swap.is_initialized = false;
+
+    msg!("Admin: Closed pool, returned {} lamports", swap_lamports);
+    Ok(())
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::integer_arithmetic)]
 mod tests {
     use super::*;
-    use crate::{curve::ZERO_TS, processor::test_utils::*};
-    use solana_program::clock::Epoch;
-    use solana_program::program_error::ProgramError;
+    use crate::{
+        curve::ZERO_TS,
+        events::{AdminEvent, Event},
+        instruction::{close_pool, with_token_2022_mints},
+        pda::find_fee_tier_address,
+        processor::test_utils::*,
+        state::{FeeTier, ADMIN_ACTION_GRACE_PERIOD, ADMIN_QUEUE_CAPACITY},
+        token_2022,
+    };
+    use solana_program::{clock::Epoch, instruction::AccountMeta, rent::Rent, system_program};
+    use solana_sdk::account::Account;
+    use spl_token::state::{Account as SplAccount, Mint as SplMint};
 
     const DEFAULT_TOKEN_A_AMOUNT: u64 = 1_000_000_000;
     const DEFAULT_TOKEN_B_AMOUNT: u64 = 1_000_000_000;
@@ -275,7 +1098,19 @@ mod tests {
     #[test]
     fn test_is_admin() {
         let admin_key = pubkey_rand();
+        let guardian_key = pubkey_rand();
         let admin_owner = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &admin_key,
+            MIN_AMP,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let mut token_swap = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        token_swap.admin_key = admin_key;
+
         let mut lamports = 0;
         let mut admin_account_data = vec![];
         let mut admin_account_info = AccountInfo::new(
@@ -288,11 +1123,13 @@ mod tests {
             false,
             Epoch::default(),
         );
+        let admin_only = &[AdminRole::Admin];
+        let admin_or_guardian = &[AdminRole::Admin, AdminRole::Guardian];
 
         // Correct admin
         assert_eq!(
-            Ok(()),
-            check_has_admin_signer(&admin_key, &admin_account_info)
+            Ok(AdminRole::Admin),
+            check_has_admin_signer(&token_swap, admin_only, &admin_account_info)
         );
 
         // Unauthorized account
@@ -301,14 +1138,27 @@ mod tests {
         fake_admin_account.key = &fake_admin_key;
         assert_eq!(
             Err(SwapError::Unauthorized.into()),
-            check_has_admin_signer(&admin_key, &fake_admin_account)
+            check_has_admin_signer(&token_swap, admin_or_guardian, &fake_admin_account)
         );
 
-        // Admin did not sign
-        admin_account_info.is_signer = false;
+        // Guardian
+        token_swap.guardian_key = guardian_key;
+        let mut guardian_account = admin_account_info.clone();
+        guardian_account.key = &guardian_key;
         assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            check_has_admin_signer(&admin_key, &admin_account_info)
+            Err(SwapError::Unauthorized.into()),
+            check_has_admin_signer(&token_swap, admin_only, &guardian_account)
+        );
+        assert_eq!(
+            Ok(AdminRole::Guardian),
+            check_has_admin_signer(&token_swap, admin_or_guardian, &guardian_account)
+        );
+
+        // Admin did not sign
+        admin_account_info.is_signer = false;
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            check_has_admin_signer(&token_swap, admin_only, &admin_account_info)
         );
     }
 
@@ -507,7 +1357,7 @@ mod tests {
             accounts.pause().unwrap();
 
             let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
-            assert!(swap_info.is_paused);
+            assert_eq!(swap_info.pause_flags, PauseFlags::PAUSE);
         }
     }
 
@@ -543,15 +1393,513 @@ mod tests {
             // Pause swap pool
             accounts.pause().unwrap();
             let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
-            assert!(swap_info.is_paused);
+            assert!(swap_info.is_paused());
 
             // Unpause swap pool
             accounts.unpause().unwrap();
             let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
-            assert!(!swap_info.is_paused);
+            assert!(!swap_info.is_paused());
+        }
+    }
+
+    #[test]
+    fn test_set_pause_flags() {
+        let user_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.set_pause_flags(PauseFlags::SWAP)
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // unauthorized account
+        {
+            let old_admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_pause_flags(PauseFlags::SWAP)
+            );
+            accounts.admin_key = old_admin_key;
+        }
+
+        // valid call
+        {
+            let flags = PauseFlags::SWAP.union(PauseFlags::DEPOSIT);
+            accounts.set_pause_flags(flags).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.pause_flags, flags);
+            assert!(swap_info.pause_flags.contains(PauseFlags::SWAP));
+            assert!(!swap_info.pause_flags.contains(PauseFlags::WITHDRAW));
+
+            accounts.set_pause_flags(PauseFlags::NONE).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert!(!swap_info.is_paused());
+        }
+    }
+
+    #[test]
+    fn test_guardian() {
+        let user_key = pubkey_rand();
+        let guardian_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+
+        // unauthorized account
+        {
+            let old_admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_guardian(&guardian_key)
+            );
+            accounts.admin_key = old_admin_key;
+        }
+
+        accounts.set_guardian(&guardian_key).unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.guardian_key, guardian_key);
+
+        let admin_key = accounts.admin_key;
+        accounts.admin_key = guardian_key;
+
+        // guardian cannot run other admin instructions
+        {
+            assert_eq!(Err(SwapError::Unauthorized.into()), accounts.unpause());
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_new_fees(DEFAULT_TEST_FEES)
+            );
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_guardian(&pubkey_rand())
+            );
+        }
+
+        // guardian can pause
+        {
+            accounts.set_pause_flags(PauseFlags::SWAP).unwrap();
+            accounts.pause().unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.pause_flags, PauseFlags::PAUSE);
+
+            accounts.set_pause_flags(PauseFlags::ALL).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.pause_flags, PauseFlags::ALL);
+
+            // pausing a fully paused pool keeps every flag
+            accounts.pause().unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.pause_flags, PauseFlags::ALL);
+        }
+
+        // guardian cannot unpause
+        {
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_pause_flags(PauseFlags::PAUSE)
+            );
+        }
+
+        // admin can remove the guardian
+        {
+            accounts.admin_key = admin_key;
+            accounts.unpause().unwrap();
+            accounts.set_guardian(&Pubkey::default()).unwrap();
+            accounts.admin_key = guardian_key;
+            assert_eq!(Err(SwapError::Unauthorized.into()), accounts.pause());
+        }
+    }
+
+    #[test]
+    fn test_roles() {
+        let user_key = pubkey_rand();
+        let fee_manager_key = pubkey_rand();
+        let amp_manager_key = pubkey_rand();
+        let mut accounts = init_accounts_ramp_a();
+
+        // unauthorized account
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = user_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.grant_role(AdminRole::FeeManager, &fee_manager_key)
+            );
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.revoke_role(AdminRole::FeeManager)
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // admin role cannot be delegated
+        {
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.grant_role(AdminRole::Admin, &fee_manager_key)
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.revoke_role(AdminRole::Admin)
+            );
+        }
+
+        accounts
+            .grant_role(AdminRole::FeeManager, &fee_manager_key)
+            .unwrap();
+        accounts
+            .grant_role(AdminRole::AmpManager, &amp_manager_key)
+            .unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.roles.fee_manager, fee_manager_key);
+        assert_eq!(swap_info.roles.amp_manager, amp_manager_key);
+        let admin_key = accounts.admin_key;
+
+        // fee manager can only manage fees
+        {
+            accounts.admin_key = fee_manager_key;
+            let new_fees = Fees {
+                trade_fee_numerator: 1,
+                trade_fee_denominator: 1000,
+                ..DEFAULT_TEST_FEES
+            };
+            accounts.set_new_fees(new_fees).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.fees, new_fees);
+            assert_eq!(Err(SwapError::Unauthorized.into()), accounts.pause());
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.stop_ramp_a(ZERO_TS)
+            );
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.grant_role(AdminRole::FeeManager, &user_key)
+            );
+        }
+
+        // amp manager can only ramp A
+        {
+            accounts.admin_key = amp_manager_key;
+            accounts.stop_ramp_a(ZERO_TS).unwrap();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_new_fees(DEFAULT_TEST_FEES)
+            );
+        }
+
+        // revoked roles can no longer sign
+        {
+            accounts.admin_key = admin_key;
+            accounts.revoke_role(AdminRole::FeeManager).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.roles.fee_manager, Pubkey::default());
+            accounts.admin_key = fee_manager_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_new_fees(DEFAULT_TEST_FEES)
+            );
+        }
+    }
+
+    #[test]
+    fn test_admin_queue() {
+        const DELAY: i64 = 86400;
+        let admin_queue_key = pubkey_rand();
+        let mut admin_queue_account = Account::new(0, AdminQueue::LEN, &SWAP_PROGRAM_ID);
+        let mut accounts = init_accounts_ramp_a();
+        let new_fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 1000,
+            ..DEFAULT_TEST_FEES
+        };
+        let set_new_fees = AdminInstruction::SetNewFees(new_fees);
+
+        // timelock requires an admin queue
+        assert_eq!(
+            Err(SwapError::InvalidInput.into()),
+            accounts.set_timelock_delay(DELAY)
+        );
+
+        // admin queue must be owned by the program
+        {
+            let mut wrong_owner = Account::new(0, AdminQueue::LEN, &pubkey_rand());
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.initialize_admin_queue(&admin_queue_key, &mut wrong_owner)
+            );
+        }
+
+        accounts
+            .initialize_admin_queue(&admin_queue_key, &mut admin_queue_account)
+            .unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.admin_queue, admin_queue_key);
+        assert_eq!(
+            Err(SwapError::AlreadyInUse.into()),
+            accounts.initialize_admin_queue(&admin_queue_key, &mut admin_queue_account)
+        );
+
+        assert_eq!(
+            Err(SwapError::InvalidInput.into()),
+            accounts.set_timelock_delay(MAX_TIMELOCK_DELAY + 1)
+        );
+        accounts.set_timelock_delay(DELAY).unwrap();
+
+        // parameter changes must be queued
+        assert_eq!(
+            Err(SwapError::TimelockRequired.into()),
+            accounts.set_new_fees(new_fees)
+        );
+        assert_eq!(
+            Err(SwapError::TimelockRequired.into()),
+            accounts.set_timelock_delay(0)
+        );
+        // pausing is not delayed
+        accounts.pause().unwrap();
+        accounts.unpause().unwrap();
+
+        // unauthorized account
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.queue_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    &set_new_fees,
+                    &mut [],
+                    ZERO_TS,
+                )
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // queue and execute
+        {
+            accounts
+                .queue_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    &set_new_fees,
+                    &mut [],
+                    ZERO_TS,
+                )
+                .unwrap();
+            let admin_queue = AdminQueue::unpack(&admin_queue_account.data).unwrap();
+            let queued = admin_queue.actions[0].unwrap();
+            assert_eq!(queued.eta, ZERO_TS + DELAY);
+            assert_eq!(queued.action.unpack().unwrap(), set_new_fees);
+
+            assert_eq!(
+                Err(SwapError::AdminActionNotReady.into()),
+                accounts.execute_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    0,
+                    &mut [],
+                    ZERO_TS + DELAY - 1,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.execute_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    0,
+                    &mut [(AccountMeta::new(pubkey_rand(), false), Account::default())],
+                    ZERO_TS + DELAY,
+                )
+            );
+            accounts
+                .execute_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    0,
+                    &mut [],
+                    ZERO_TS + DELAY,
+                )
+                .unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.fees, new_fees);
+            let admin_queue = AdminQueue::unpack(&admin_queue_account.data).unwrap();
+            assert_eq!(admin_queue.actions[0], None);
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.execute_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    0,
+                    &mut [],
+                    ZERO_TS + DELAY,
+                )
+            );
+        }
+
+        // cancel
+        {
+            accounts
+                .queue_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    &set_new_fees,
+                    &mut [],
+                    ZERO_TS,
+                )
+                .unwrap();
+            accounts
+                .cancel_admin_action(&admin_queue_key, &mut admin_queue_account, 0)
+                .unwrap();
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.execute_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    0,
+                    &mut [],
+                    ZERO_TS + DELAY,
+                )
+            );
+        }
+
+        // expire
+        let expiry_ts = ZERO_TS + DELAY + ADMIN_ACTION_GRACE_PERIOD + 1;
+        {
+            accounts
+                .queue_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    &set_new_fees,
+                    &mut [],
+                    ZERO_TS,
+                )
+                .unwrap();
+            assert_eq!(
+                Err(SwapError::AdminActionExpired.into()),
+                accounts.execute_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    0,
+                    &mut [],
+                    expiry_ts,
+                )
+            );
+        }
+
+        // expired actions free their slot
+        {
+            for _ in 0..ADMIN_QUEUE_CAPACITY {
+                accounts
+                    .queue_admin_action(
+                        &admin_queue_key,
+                        &mut admin_queue_account,
+                        &set_new_fees,
+                        &mut [],
+                        expiry_ts,
+                    )
+                    .unwrap();
+            }
+            assert_eq!(
+                Err(SwapError::AdminQueueFull.into()),
+                accounts.queue_admin_action(
+                    &admin_queue_key,
+                    &mut admin_queue_account,
+                    &set_new_fees,
+                    &mut [],
+                    expiry_ts,
+                )
+            );
         }
     }
 
+    #[test]
+    fn test_queue_fee_tier() {
+        const DELAY: i64 = 86400;
+        let admin_queue_key = pubkey_rand();
+        let mut admin_queue_account = Account::new(0, AdminQueue::LEN, &SWAP_PROGRAM_ID);
+        let mut accounts = init_accounts_ramp_a();
+        let user_key = pubkey_rand();
+        let payer_key = pubkey_rand();
+        let mut payer_account = Account::new(1_000_000_000, 0, &system_program::id());
+        let (fee_tier_key, _) =
+            find_fee_tier_address(&SWAP_PROGRAM_ID, Some(&accounts.swap_key), &user_key);
+        let mut fee_tier_account = Account::new(0, FeeTier::LEN, &system_program::id());
+        accounts
+            .initialize_admin_queue(&admin_queue_key, &mut admin_queue_account)
+            .unwrap();
+        accounts.set_timelock_delay(DELAY).unwrap();
+
+        // fee tiers must be queued
+        assert_eq!(
+            Err(SwapError::TimelockRequired.into()),
+            accounts.set_fee_tier(
+                &payer_key,
+                &mut payer_account,
+                &user_key,
+                &mut fee_tier_account,
+                5_000
+            )
+        );
+
+        let set_fee_tier = AdminInstruction::SetFeeTier(5_000);
+        accounts
+            .queue_admin_action(
+                &admin_queue_key,
+                &mut admin_queue_account,
+                &set_fee_tier,
+                &mut [
+                    (payer_key, Account::default()),
+                    (fee_tier_key, Account::default()),
+                    (user_key, Account::default()),
+                    (system_program::id(), Account::default()),
+                ],
+                ZERO_TS,
+            )
+            .unwrap();
+        let mut action_accounts = [
+            (AccountMeta::new(payer_key, true), payer_account),
+            (AccountMeta::new(fee_tier_key, false), fee_tier_account),
+            (
+                AccountMeta::new_readonly(user_key, false),
+                Account::default(),
+            ),
+            (
+                AccountMeta::new_readonly(system_program::id(), false),
+                Account::default(),
+            ),
+        ];
+        accounts
+            .execute_admin_action(
+                &admin_queue_key,
+                &mut admin_queue_account,
+                0,
+                &mut action_accounts,
+                ZERO_TS + DELAY,
+            )
+            .unwrap();
+        let fee_tier = FeeTier::unpack(&action_accounts[1].1.data).unwrap();
+        assert_eq!(fee_tier.authority, user_key);
+        assert_eq!(fee_tier.fee_multiplier_bps, 5_000);
+    }
+
     #[test]
     fn test_set_fee_account() {
         let user_key = pubkey_rand();
@@ -569,8 +1917,8 @@ mod tests {
             admin_fee_account_a,
             admin_fee_key_b,
             admin_fee_account_b,
-            wrong_admin_fee_key,
-            wrong_admin_fee_account,
+            lp_admin_fee_key,
+            lp_admin_fee_account,
         ) = accounts.setup_token_accounts(
             &user_key,
             &owner_key,
@@ -603,6 +1951,16 @@ mod tests {
 
         // wrong admin account
         {
+            let (wrong_mint_key, mut wrong_mint_account) =
+                create_mint(&spl_token::id(), &user_key, DEFAULT_TOKEN_DECIMALS, None);
+            let (wrong_admin_fee_key, wrong_admin_fee_account) = mint_token(
+                &spl_token::id(),
+                &wrong_mint_key,
+                &mut wrong_mint_account,
+                &user_key,
+                &owner_key,
+                0,
+            );
             assert_eq!(
                 Err(SwapError::InvalidAdmin.into()),
                 accounts.set_admin_fee_account(&wrong_admin_fee_key, &wrong_admin_fee_account)
@@ -623,12 +1981,19 @@ mod tests {
                 .unwrap();
             let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
             assert_eq!(swap_info.token_b.admin_fees, admin_fee_key_b);
+            // set LP fee account
+            accounts
+                .set_admin_fee_account(&lp_admin_fee_key, &lp_admin_fee_account)
+                .unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.lp_admin_fees, lp_admin_fee_key);
         }
     }
 
     #[test]
     fn test_apply_new_admin() {
         let user_key = pubkey_rand();
+        let new_admin_key = pubkey_rand();
         let amp_factor = MIN_AMP * 100;
         let mut accounts = SwapAccountInfo::new(
             &user_key,
@@ -642,7 +2007,7 @@ mod tests {
         {
             assert_eq!(
                 Err(ProgramError::UninitializedAccount),
-                accounts.apply_new_admin(ZERO_TS)
+                accounts.apply_new_admin(&new_admin_key, ZERO_TS)
             );
         }
 
@@ -655,7 +2020,7 @@ mod tests {
             accounts.admin_key = fake_admin_key;
             assert_eq!(
                 Err(SwapError::Unauthorized.into()),
-                accounts.apply_new_admin(ZERO_TS)
+                accounts.apply_new_admin(&new_admin_key, ZERO_TS)
             );
             accounts.admin_key = old_admin_key;
         }
@@ -664,13 +2029,12 @@ mod tests {
         {
             assert_eq!(
                 Err(SwapError::NoActiveTransfer.into()),
-                accounts.apply_new_admin(ZERO_TS)
+                accounts.apply_new_admin(&new_admin_key, ZERO_TS)
             );
         }
 
         // apply new admin
         {
-            let new_admin_key = pubkey_rand();
             let current_ts = MIN_RAMP_DURATION;
 
             // Commit to initiate admin transfer
@@ -678,15 +2042,23 @@ mod tests {
                 .commit_new_admin(&new_admin_key, current_ts)
                 .unwrap();
 
+            // Applying transfer without the future admin's signature should fail
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.apply_new_admin(&pubkey_rand(), current_ts + 1)
+            );
+
             // Applying transfer past deadline should fail
             let apply_deadline = current_ts + MIN_RAMP_DURATION * 3;
             assert_eq!(
                 Err(SwapError::AdminDeadlineExceeded.into()),
-                accounts.apply_new_admin(apply_deadline + 1)
+                accounts.apply_new_admin(&new_admin_key, apply_deadline + 1)
             );
 
             // Apply to finalize admin transfer
-            accounts.apply_new_admin(current_ts + 1).unwrap();
+            accounts
+                .apply_new_admin(&new_admin_key, current_ts + 1)
+                .unwrap();
             let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
             assert_eq!(swap_info.admin_key, new_admin_key);
             assert_eq!(swap_info.future_admin_key, Pubkey::default());
@@ -762,9 +2134,329 @@ mod tests {
     }
 
     #[test]
-    fn test_set_new_fees() {
+    fn test_accept_admin() {
         let user_key = pubkey_rand();
-        let amp_factor = MIN_AMP * 100;
+        let new_admin_key = pubkey_rand();
+        let current_ts = MIN_RAMP_DURATION;
+        let amp_factor = MIN_AMP * 100;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.accept_admin(&new_admin_key, current_ts)
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // no active transfer
+        {
+            assert_eq!(
+                Err(SwapError::NoActiveTransfer.into()),
+                accounts.accept_admin(&new_admin_key, current_ts)
+            );
+        }
+
+        accounts
+            .commit_new_admin(&new_admin_key, current_ts)
+            .unwrap();
+
+        // only the future admin can accept
+        {
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.accept_admin(&pubkey_rand(), current_ts + 1)
+            );
+            let admin_key = accounts.admin_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.accept_admin(&admin_key, current_ts + 1)
+            );
+        }
+
+        // accepting past deadline should fail
+        {
+            let accept_deadline = current_ts + MIN_RAMP_DURATION * 3;
+            assert_eq!(
+                Err(SwapError::AdminDeadlineExceeded.into()),
+                accounts.accept_admin(&new_admin_key, accept_deadline + 1)
+            );
+        }
+
+        // accept to finalize admin transfer
+        {
+            accounts
+                .accept_admin(&new_admin_key, current_ts + 1)
+                .unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.admin_key, new_admin_key);
+            assert_eq!(swap_info.future_admin_key, Pubkey::default());
+            assert_eq!(swap_info.future_admin_deadline, ZERO_TS);
+        }
+    }
+
+    #[test]
+    fn test_cancel_admin_transfer() {
+        let user_key = pubkey_rand();
+        let new_admin_key = pubkey_rand();
+        let current_ts = MIN_RAMP_DURATION;
+        let amp_factor = MIN_AMP * 100;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+
+        // no active transfer
+        {
+            assert_eq!(
+                Err(SwapError::NoActiveTransfer.into()),
+                accounts.cancel_admin_transfer()
+            );
+        }
+
+        accounts
+            .commit_new_admin(&new_admin_key, current_ts)
+            .unwrap();
+
+        // unauthorized account
+        {
+            let old_admin_key = accounts.admin_key;
+            accounts.admin_key = new_admin_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.cancel_admin_transfer()
+            );
+            accounts.admin_key = old_admin_key;
+        }
+
+        // cancelled transfer can no longer be accepted
+        {
+            accounts.cancel_admin_transfer().unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.future_admin_key, Pubkey::default());
+            assert_eq!(swap_info.future_admin_deadline, ZERO_TS);
+            assert_eq!(
+                Err(SwapError::NoActiveTransfer.into()),
+                accounts.accept_admin(&new_admin_key, current_ts + 1)
+            );
+
+            // a new transfer can be started right away
+            accounts
+                .commit_new_admin(&new_admin_key, current_ts + 1)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_migrate_swap_info() {
+        let user_key = pubkey_rand();
+        let payer_key = pubkey_rand();
+        let mut payer_account = Account::new(1_000_000_000, 0, &system_program::id());
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+
+        // swap is already in the versioned layout
+        {
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.migrate_swap_info(&payer_key, &mut payer_account)
+            );
+        }
+
+        // swap in the legacy layout of deployed swaps, a prefix of the
+        // versioned layout
+        let rent = Rent::default();
+        accounts.swap_account.data.truncate(SwapInfo::LEGACY_LEN);
+        accounts.swap_account.lamports = rent.minimum_balance(SwapInfo::LEGACY_LEN);
+        // which has no last invariant
+        let swap_info = SwapInfo {
+            last_d: 0,
+            last_amp_factor: 0,
+            last_sum_x: 0,
+            ..swap_info
+        };
+        assert_eq!(SwapInfo::unpack(&accounts.swap_account.data), Ok(swap_info));
+
+        // legacy swaps can still be traded
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &user_key, 1_000, 0, 0);
+            let swap_token_a_key = accounts.token_a_key;
+            let swap_token_b_key = accounts.token_b_key;
+            accounts
+                .swap(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    1_000,
+                    0,
+                )
+                .unwrap();
+            assert_eq!(accounts.swap_account.data.len(), SwapInfo::LEGACY_LEN);
+            assert_eq!(SwapInfo::unpack(&accounts.swap_account.data), Ok(swap_info));
+        }
+
+        // legacy swaps keep their layout until they are migrated
+        {
+            accounts.pause().unwrap();
+            accounts.unpause().unwrap();
+            assert_eq!(accounts.swap_account.data.len(), SwapInfo::LEGACY_LEN);
+            assert_eq!(SwapInfo::unpack(&accounts.swap_account.data), Ok(swap_info));
+            assert_eq!(
+                Err(SwapError::LegacySwapLayout.into()),
+                accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES)
+            );
+        }
+
+        // only the admin can migrate
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.migrate_swap_info(&payer_key, &mut payer_account)
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        accounts
+            .migrate_swap_info(&payer_key, &mut payer_account)
+            .unwrap();
+        let data = &accounts.swap_account.data;
+        assert_eq!(data.len(), SwapInfo::LEN);
+        assert_eq!(data[SwapInfo::LEGACY_LEN], SwapInfo::VERSION);
+        assert_eq!(SwapInfo::unpack(data), Ok(swap_info));
+        assert_eq!(
+            accounts.swap_account.lamports,
+            rent.minimum_balance(SwapInfo::LEN)
+        );
+        assert_eq!(
+            payer_account.lamports,
+            1_000_000_000 - rent.minimum_balance(SwapInfo::LEN)
+                + rent.minimum_balance(SwapInfo::LEGACY_LEN)
+        );
+
+        // the migrated swap can be used and charge a referral fee
+        accounts.pause().unwrap();
+        accounts.set_new_fees(DEFAULT_TEST_FEES).unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_info.is_paused());
+        assert_eq!(swap_info.fees, DEFAULT_TEST_FEES);
+    }
+
+    #[test]
+    fn test_renounce_admin() {
+        let user_key = pubkey_rand();
+        let guardian_key = pubkey_rand();
+        let fee_manager_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.renounce_admin()
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+        accounts.set_guardian(&guardian_key).unwrap();
+        accounts
+            .grant_role(AdminRole::FeeManager, &fee_manager_key)
+            .unwrap();
+        accounts
+            .commit_new_admin(&pubkey_rand(), MIN_RAMP_DURATION)
+            .unwrap();
+
+        // only the admin can renounce
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = guardian_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.renounce_admin()
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // paused swaps cannot be renounced, since nobody could unpause them
+        {
+            accounts.set_pause_flags(PauseFlags::WITHDRAW).unwrap();
+            assert_eq!(Err(SwapError::IsPaused.into()), accounts.renounce_admin());
+            accounts.pause().unwrap();
+            assert_eq!(Err(SwapError::IsPaused.into()), accounts.renounce_admin());
+            accounts.unpause().unwrap();
+        }
+
+        accounts.renounce_admin().unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_info.is_admin_renounced);
+        assert_eq!(swap_info.admin_key, Pubkey::default());
+        assert_eq!(swap_info.future_admin_key, Pubkey::default());
+        assert_eq!(swap_info.future_admin_deadline, ZERO_TS);
+        assert_eq!(swap_info.guardian_key, Pubkey::default());
+        assert_eq!(swap_info.roles, RoleKeys::default());
+
+        // admin instructions fail for every key
+        {
+            assert_eq!(
+                Err(SwapError::AdminRenounced.into()),
+                accounts.renounce_admin()
+            );
+            assert_eq!(Err(SwapError::AdminRenounced.into()), accounts.pause());
+            assert_eq!(
+                Err(SwapError::AdminRenounced.into()),
+                accounts.commit_new_admin(&pubkey_rand(), MIN_RAMP_DURATION)
+            );
+            accounts.admin_key = guardian_key;
+            assert_eq!(Err(SwapError::AdminRenounced.into()), accounts.pause());
+            accounts.admin_key = fee_manager_key;
+            assert_eq!(
+                Err(SwapError::AdminRenounced.into()),
+                accounts.set_new_fees(DEFAULT_TEST_FEES)
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_new_fees() {
+        let user_key = pubkey_rand();
+        let amp_factor = MIN_AMP * 100;
         let mut accounts = SwapAccountInfo::new(
             &user_key,
             amp_factor,
@@ -775,13 +2467,13 @@ mod tests {
 
         let new_fees: Fees = Fees {
             admin_trade_fee_numerator: 0,
-            admin_trade_fee_denominator: 0,
+            admin_trade_fee_denominator: 1,
             admin_withdraw_fee_numerator: 0,
-            admin_withdraw_fee_denominator: 0,
+            admin_withdraw_fee_denominator: 1,
             trade_fee_numerator: 0,
-            trade_fee_denominator: 0,
+            trade_fee_denominator: 1,
             withdraw_fee_numerator: 0,
-            withdraw_fee_denominator: 0,
+            withdraw_fee_denominator: 1,
         };
 
         // swap not initialized
@@ -806,12 +2498,574 @@ mod tests {
             accounts.admin_key = old_admin_key;
         }
 
+        // invalid fees
+        {
+            assert_eq!(
+                Err(SwapError::ZeroFeeDenominator.into()),
+                accounts.set_new_fees(Fees {
+                    trade_fee_denominator: 0,
+                    ..new_fees
+                })
+            );
+            assert_eq!(
+                Err(SwapError::TradeFeeTooHigh.into()),
+                accounts.set_new_fees(Fees {
+                    trade_fee_numerator: 1,
+                    trade_fee_denominator: 2,
+                    ..new_fees
+                })
+            );
+            assert_eq!(
+                Err(SwapError::WithdrawFeeTooHigh.into()),
+                accounts.set_new_fees(Fees {
+                    withdraw_fee_numerator: 1,
+                    withdraw_fee_denominator: 2,
+                    ..new_fees
+                })
+            );
+            assert_eq!(
+                Err(SwapError::AdminFeeTooHigh.into()),
+                accounts.set_new_fees(Fees {
+                    admin_withdraw_fee_numerator: 2,
+                    ..new_fees
+                })
+            );
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.fees, DEFAULT_TEST_FEES);
+        }
+
         // valid call
         {
             accounts.set_new_fees(new_fees).unwrap();
 
             let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
             assert_eq!(swap_info.fees, new_fees);
+            assert_eq!(
+                take_events(),
+                vec![Event::Admin(AdminEvent {
+                    swap: accounts.swap_key,
+                    signer: accounts.admin_key,
+                    instruction: AdminInstruction::SetNewFees(new_fees).pack(),
+                })]
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_extra_fees() {
+        let user_key = pubkey_rand();
+        let amp_factor = MIN_AMP * 100;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES)
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.extra_fees, ExtraFees::default());
+
+        // unauthorized account
+        {
+            let old_admin_key = accounts.admin_key;
+            let fake_admin_key = pubkey_rand();
+            accounts.admin_key = fake_admin_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES)
+            );
+            accounts.admin_key = old_admin_key;
+        }
+
+        // invalid fees
+        {
+            assert_eq!(
+                Err(SwapError::ZeroFeeDenominator.into()),
+                accounts.set_extra_fees(ExtraFees {
+                    flash_loan_fee_denominator: 0,
+                    ..DEFAULT_TEST_EXTRA_FEES
+                })
+            );
+            assert_eq!(
+                Err(SwapError::FlashLoanFeeTooHigh.into()),
+                accounts.set_extra_fees(ExtraFees {
+                    flash_loan_fee_numerator: 1,
+                    flash_loan_fee_denominator: 2,
+                    ..DEFAULT_TEST_EXTRA_FEES
+                })
+            );
+            assert_eq!(
+                Err(SwapError::ReferralFeeTooHigh.into()),
+                accounts.set_extra_fees(ExtraFees {
+                    referral_fee_numerator: 1,
+                    referral_fee_denominator: 1,
+                    ..DEFAULT_TEST_EXTRA_FEES
+                })
+            );
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.extra_fees, ExtraFees::default());
+        }
+
+        // swap in the legacy layout
+        {
+            let swap_data = accounts.swap_account.data.clone();
+            let mut legacy = vec![0u8; SwapInfo::LEGACY_LEN];
+            SwapInfo::pack(swap_info, &mut legacy).unwrap();
+            accounts.swap_account.data = legacy;
+            assert_eq!(
+                Err(SwapError::LegacySwapLayout.into()),
+                accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES)
+            );
+            // other admin instructions keep working
+            accounts.set_new_fees(DEFAULT_TEST_FEES).unwrap();
+            assert_eq!(accounts.swap_account.data.len(), SwapInfo::LEGACY_LEN);
+            accounts.swap_account.data = swap_data;
+        }
+
+        // valid call
+        {
+            accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES).unwrap();
+
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.extra_fees, DEFAULT_TEST_EXTRA_FEES);
+            assert_eq!(swap_info.fees, DEFAULT_TEST_FEES);
+        }
+    }
+
+    #[test]
+    fn test_set_admin_fee_mode() {
+        let user_key = pubkey_rand();
+        let amp_factor = MIN_AMP * 100;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.set_admin_fee_mode(AdminFeeMode::Accrue)
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.admin_fee_mode, AdminFeeMode::Transfer);
+
+        // unauthorized account
+        {
+            let old_admin_key = accounts.admin_key;
+            let fake_admin_key = pubkey_rand();
+            accounts.admin_key = fake_admin_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_admin_fee_mode(AdminFeeMode::Accrue)
+            );
+            accounts.admin_key = old_admin_key;
+        }
+
+        // LP fee account not set
+        {
+            assert_eq!(
+                Err(SwapError::InvalidAdmin.into()),
+                accounts.set_admin_fee_mode(AdminFeeMode::LpMint)
+            );
+        }
+
+        // valid call
+        {
+            accounts.set_admin_fee_mode(AdminFeeMode::Accrue).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.admin_fee_mode, AdminFeeMode::Accrue);
+
+            accounts.set_admin_fee_mode(AdminFeeMode::Transfer).unwrap();
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.admin_fee_mode, AdminFeeMode::Transfer);
         }
     }
+
+    #[test]
+    fn test_close_pool() {
+        let user_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.close_pool()
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // unauthorized account
+        {
+            let old_admin_key = accounts.admin_key;
+            let fake_admin_key = pubkey_rand();
+            accounts.admin_key = fake_admin_key;
+            assert_eq!(Err(SwapError::Unauthorized.into()), accounts.close_pool());
+            accounts.admin_key = old_admin_key;
+        }
+
+        // wrong admin fee account
+        {
+            let old_admin_fee_a_key = accounts.admin_fee_a_key;
+            accounts.admin_fee_a_key = pubkey_rand();
+            assert_eq!(Err(SwapError::InvalidAdmin.into()), accounts.close_pool());
+            accounts.admin_fee_a_key = old_admin_fee_a_key;
+        }
+
+        // outstanding pool tokens
+        {
+            assert_eq!(Err(SwapError::InvalidSupply.into()), accounts.close_pool());
+        }
+
+        // admin fees of swaps are left in the reserves
+        accounts.set_admin_fee_mode(AdminFeeMode::Accrue).unwrap();
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &user_key, 1_000_000, 0, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        accounts
+            .swap(
+                &user_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                1_000_000,
+                0,
+            )
+            .unwrap();
+        let accrued_admin_fee_b = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .token_b
+            .accrued_admin_fees;
+        assert!(accrued_admin_fee_b > 0);
+
+        // the last withdraw pays no withdraw fee and leaves the accrued admin fees
+        let pool_token_key = accounts.pool_token_key;
+        let mut pool_token_account = accounts.pool_token_account.clone();
+        let pool_amount = utils::unpack_token_account(&pool_token_account.data)
+            .unwrap()
+            .amount;
+        accounts
+            .withdraw(
+                &user_key,
+                &pool_token_key,
+                &mut pool_token_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                pool_amount,
+                0,
+                0,
+            )
+            .unwrap();
+        assert_eq!(
+            utils::unpack_token_account(&accounts.token_a_account.data)
+                .unwrap()
+                .amount,
+            0
+        );
+        assert_eq!(
+            utils::unpack_token_account(&accounts.token_b_account.data)
+                .unwrap()
+                .amount,
+            accrued_admin_fee_b
+        );
+
+        // reserves holding more than the accrued admin fees
+        {
+            let token_a_account = accounts.token_a_account.clone();
+            let mut reserves_a = SplAccount::unpack(&token_a_account.data).unwrap();
+            reserves_a.amount = 1;
+            SplAccount::pack(reserves_a, &mut accounts.token_a_account.data).unwrap();
+            assert_eq!(
+                Err(SwapError::NonEmptyReserves.into()),
+                accounts.close_pool()
+            );
+            accounts.token_a_account = token_a_account;
+        }
+
+        // valid call
+        {
+            let swap_lamports = 1_000_000;
+            accounts.swap_account.lamports = swap_lamports;
+            let reserve_lamports =
+                accounts.token_a_account.lamports + accounts.token_b_account.lamports;
+            let admin_fee_a = utils::unpack_token_account(&accounts.admin_fee_a_account.data)
+                .unwrap()
+                .amount;
+            let admin_fee_b = utils::unpack_token_account(&accounts.admin_fee_b_account.data)
+                .unwrap()
+                .amount;
+            let admin_lamports = accounts.admin_account.lamports;
+
+            accounts.close_pool().unwrap();
+
+            assert_eq!(
+                accounts.admin_account.lamports,
+                admin_lamports + swap_lamports + reserve_lamports
+            );
+            assert_eq!(accounts.swap_account.lamports, 0);
+            assert!(accounts.swap_account.data.iter().all(|&b| b == 0));
+            assert_eq!(accounts.token_a_account.lamports, 0);
+            assert_eq!(accounts.token_b_account.lamports, 0);
+            assert_eq!(
+                utils::unpack_token_account(&accounts.admin_fee_a_account.data)
+                    .unwrap()
+                    .amount,
+                admin_fee_a
+            );
+            assert_eq!(
+                utils::unpack_token_account(&accounts.admin_fee_b_account.data)
+                    .unwrap()
+                    .amount,
+                admin_fee_b + accrued_admin_fee_b
+            );
+        }
+    }
+
+    #[test]
+    fn test_close_pool_token_2022() {
+        const WITHHELD: u64 = 25;
+        let user_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.convert_to_token_2022();
+        accounts
+            .initialize_swap_with_token_program(&token_2022::id())
+            .unwrap();
+
+        // burn the pool tokens
+        let mut pool_mint = SplMint::unpack(&accounts.pool_mint_account.data).unwrap();
+        pool_mint.supply = 0;
+        SplMint::pack(pool_mint, &mut accounts.pool_mint_account.data).unwrap();
+
+        // the reserves of token A only hold accrued admin fees, token B is empty
+        let mut swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        swap_info.token_a.accrued_admin_fees =
+            utils::unpack_token_account(&accounts.token_a_account.data)
+                .unwrap()
+                .amount;
+        SwapInfo::pack(swap_info, &mut accounts.swap_account.data).unwrap();
+        let mut reserves_b = SplAccount::unpack(&accounts.token_b_account.data).unwrap();
+        reserves_b.amount = 0;
+        SplAccount::pack(reserves_b, &mut accounts.token_b_account.data).unwrap();
+
+        // token A charges transfer fees, some of which are withheld in the reserves
+        let extend = |data: &mut Vec<u8>, account_type: u8, extension: (u16, Vec<u8>)| {
+            data.resize(token_2022::ACCOUNT_LEN, 0);
+            data.push(account_type);
+            data.extend_from_slice(&extension.0.to_le_bytes());
+            data.extend_from_slice(&(extension.1.len() as u16).to_le_bytes());
+            data.extend(extension.1);
+        };
+        extend(
+            &mut accounts.token_a_mint_account.data,
+            1,
+            (
+                token_2022::ExtensionType::TransferFeeConfig as u16,
+                vec![0; 108],
+            ),
+        );
+        extend(
+            &mut accounts.token_a_account.data,
+            2,
+            (
+                token_2022::ExtensionType::TransferFeeAmount as u16,
+                WITHHELD.to_le_bytes().to_vec(),
+            ),
+        );
+        let remaining_a = utils::unpack_token_account(&accounts.token_a_account.data)
+            .unwrap()
+            .amount;
+        let admin_fee_a = utils::unpack_token_account(&accounts.admin_fee_a_account.data)
+            .unwrap()
+            .amount;
+
+        let ix = with_token_2022_mints(
+            close_pool(
+                &token_2022::id(),
+                &accounts.swap_key,
+                &accounts.admin_key,
+                &accounts.authority_key,
+                &accounts.pool_mint_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &accounts.admin_fee_a_key,
+                &accounts.admin_fee_b_key,
+            )
+            .unwrap(),
+            &accounts.token_a_mint_key,
+            &accounts.token_b_mint_key,
+        );
+        assert!(ix.accounts[ix.accounts.len() - 2..]
+            .iter()
+            .all(|meta| meta.is_writable));
+        do_process_instruction_at_time(
+            ix,
+            vec![
+                &mut accounts.swap_account,
+                &mut accounts.admin_account,
+                &mut Account::default(),
+                &mut accounts.pool_mint_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut accounts.admin_fee_a_account,
+                &mut accounts.admin_fee_b_account,
+                &mut Account::default(),
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+            ],
+            ZERO_TS,
+        )
+        .unwrap();
+
+        let config = &accounts.token_a_mint_account.data[token_2022::ACCOUNT_LEN + 5..];
+        assert_eq!(config[64..72], WITHHELD.to_le_bytes());
+        assert_eq!(accounts.token_a_account.lamports, 0);
+        assert_eq!(accounts.token_b_account.lamports, 0);
+        assert_eq!(accounts.swap_account.lamports, 0);
+        assert_eq!(
+            utils::unpack_token_account(&accounts.admin_fee_a_account.data)
+                .unwrap()
+                .amount,
+            admin_fee_a + remaining_a
+        );
+        // the pool mint stays open
+        assert!(accounts.pool_mint_account.lamports > 0);
+    }
+    #[test]
+    fn test_close_pool_queued() {
+        const DELAY: i64 = 86400;
+        let user_key = pubkey_rand();
+        let admin_queue_key = pubkey_rand();
+        let mut admin_queue_account = Account::new(0, AdminQueue::LEN, &SWAP_PROGRAM_ID);
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP,
+            DEFAULT_TOKEN_A_AMOUNT,
+            DEFAULT_TOKEN_B_AMOUNT,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let pool_token_key = accounts.pool_token_key;
+        let mut pool_token_account = accounts.pool_token_account.clone();
+        let pool_amount = utils::unpack_token_account(&pool_token_account.data)
+            .unwrap()
+            .amount;
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &user_key, 0, 0, 0);
+        accounts
+            .withdraw(
+                &user_key,
+                &pool_token_key,
+                &mut pool_token_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                pool_amount,
+                0,
+                0,
+            )
+            .unwrap();
+        accounts
+            .initialize_admin_queue(&admin_queue_key, &mut admin_queue_account)
+            .unwrap();
+        accounts.set_timelock_delay(DELAY).unwrap();
+
+        // closing the pool must be queued
+        assert_eq!(
+            Err(SwapError::TimelockRequired.into()),
+            accounts.close_pool()
+        );
+
+        let mut action_accounts = [
+            (
+                AccountMeta::new_readonly(accounts.authority_key, false),
+                Account::default(),
+            ),
+            (
+                AccountMeta::new_readonly(accounts.pool_mint_key, false),
+                accounts.pool_mint_account.clone(),
+            ),
+            (
+                AccountMeta::new(accounts.token_a_key, false),
+                accounts.token_a_account.clone(),
+            ),
+            (
+                AccountMeta::new(accounts.token_b_key, false),
+                accounts.token_b_account.clone(),
+            ),
+            (
+                AccountMeta::new(accounts.admin_fee_a_key, false),
+                accounts.admin_fee_a_account.clone(),
+            ),
+            (
+                AccountMeta::new(accounts.admin_fee_b_key, false),
+                accounts.admin_fee_b_account.clone(),
+            ),
+            (
+                AccountMeta::new_readonly(spl_token::id(), false),
+                Account::default(),
+            ),
+        ];
+        accounts
+            .queue_admin_action(
+                &admin_queue_key,
+                &mut admin_queue_account,
+                &AdminInstruction::ClosePool,
+                &mut action_accounts
+                    .iter()
+                    .map(|(meta, _)| (meta.pubkey, Account::default()))
+                    .collect::<Vec<_>>(),
+                ZERO_TS,
+            )
+            .unwrap();
+        accounts
+            .execute_admin_action(
+                &admin_queue_key,
+                &mut admin_queue_account,
+                0,
+                &mut action_accounts,
+                ZERO_TS + DELAY,
+            )
+            .unwrap();
+        assert_eq!(accounts.swap_account.lamports, 0);
+        assert!(accounts.swap_account.data.iter().all(|&b| b == 0));
+        assert_eq!(action_accounts[2].1.lamports, 0);
+        assert_eq!(action_accounts[3].1.lamports, 0);
+    }
 }
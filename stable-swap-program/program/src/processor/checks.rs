@@ -3,18 +3,19 @@
 use crate::{
     error::SwapError,
     processor::utils,
-    state::{SwapInfo, SwapTokenInfo},
+    state::{AdminRole, PauseFlags, SwapInfo, SwapTokenInfo},
 };
 
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
     pubkey::Pubkey,
 };
 
 use super::logging::log_slippage_error;
+use super::token::{self, TransferMint};
 
 /// Checks if the reserve of the swap is the given key.
-fn check_reserves_match(token: &SwapTokenInfo, reserves_info_key: &Pubkey) -> ProgramResult {
+pub fn check_reserves_match(token: &SwapTokenInfo, reserves_info_key: &Pubkey) -> ProgramResult {
     check_token_keys_equal!(
         token,
         *reserves_info_key,
@@ -25,21 +26,41 @@ fn check_reserves_match(token: &SwapTokenInfo, reserves_info_key: &Pubkey) -> Pr
     Ok(())
 }
 
-/// Access control for admin only instructions
+/// Checks that the operation is not paused.
+pub fn check_not_paused(token_swap: &SwapInfo, operation: PauseFlags) -> ProgramResult {
+    if token_swap.pause_flags.contains(operation) {
+        return Err(SwapError::IsPaused.into());
+    }
+    Ok(())
+}
+
+/// Access control for admin only instructions. Returns the role of the signer
+/// among the allowed roles.
 pub fn check_has_admin_signer(
-    expected_admin_key: &Pubkey,
+    token_swap: &SwapInfo,
+    allowed_roles: &[AdminRole],
     admin_account_info: &AccountInfo,
-) -> ProgramResult {
-    check_keys_equal!(
-        *expected_admin_key,
-        *admin_account_info.key,
-        "Admin signer",
-        SwapError::Unauthorized
-    );
+) -> Result<AdminRole, ProgramError> {
+    // unassigned roles hold the default pubkey, which cannot sign
+    let role = allowed_roles
+        .iter()
+        .copied()
+        .find(|role| token_swap.role_key(*role) == admin_account_info.key);
+    let role = match role {
+        Some(role) => role,
+        None => {
+            msg!(
+                "Admin signer {} does not have any of the roles {:?}",
+                admin_account_info.key,
+                allowed_roles
+            );
+            return Err(SwapError::Unauthorized.into());
+        }
+    };
     if !admin_account_info.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
-    Ok(())
+    Ok(role)
 }
 
 pub fn check_deposit_token_accounts(
@@ -61,10 +82,12 @@ pub fn check_deposit_token_accounts(
 pub fn check_can_withdraw_token(
     rate: Option<(u64, u64, u64)>,
     minimum_token_amount: u64,
+    mint: Option<&TransferMint>,
 ) -> Result<(u64, u64, u64), ProgramError> {
     let (amount, fee, admin_fee) = rate.ok_or(SwapError::CalculationFailure)?;
-    if amount < minimum_token_amount {
-        log_slippage_error(minimum_token_amount, amount);
+    let received_amount = token::post_fee_amount(mint, amount)?;
+    if received_amount < minimum_token_amount {
+        log_slippage_error(minimum_token_amount, received_amount);
         return Err(SwapError::ExceededSlippage.into());
     }
 
@@ -87,6 +110,17 @@ pub fn check_withdraw_token_accounts(
     Ok(())
 }
 
+/// Checks that the token program is the one used by the swap.
+pub fn check_token_program(token_swap: &SwapInfo, token_program_key: &Pubkey) -> ProgramResult {
+    check_keys_equal!(
+        *token_program_key,
+        token_swap.token_program_id,
+        "Token program",
+        ProgramError::IncorrectProgramId
+    );
+    Ok(())
+}
+
 pub fn check_swap_authority(
     token_swap: &SwapInfo,
     swap_info_key: &Pubkey,
@@ -103,11 +137,12 @@ pub fn check_swap_authority(
     Ok(())
 }
 
-/// Checks that the destination of the swap has the correct accounts.
+/// Checks that the destination of the swap has the correct accounts. The
+/// admin fee account is only checked if it is passed.
 pub fn check_swap_token_destination_accounts(
     token: &SwapTokenInfo,
     swap_destination_info_key: &Pubkey,
-    admin_destination_info_key: &Pubkey,
+    admin_destination_info_key: Option<&Pubkey>,
 ) -> ProgramResult {
     check_keys_equal!(
         *swap_destination_info_key,
@@ -115,11 +150,13 @@ pub fn check_swap_token_destination_accounts(
         "Incorrect destination, expected",
         SwapError::IncorrectSwapAccount
     );
-    check_keys_equal!(
-        *admin_destination_info_key,
-        token.admin_fees,
-        "Admin fee",
-        SwapError::InvalidAdmin
-    );
+    if let Some(admin_destination_info_key) = admin_destination_info_key {
+        check_keys_equal!(
+            *admin_destination_info_key,
+            token.admin_fees,
+            "Admin fee",
+            SwapError::InvalidAdmin
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,407 @@
+//! Circuit breaker rejecting swaps, deposits and single sided withdrawals
+//! that move the price of a swap too far within a window of slots.
+
+use crate::{error::SwapError, fees::BPS_DENOMINATOR, state::SwapInfo};
+
+use solana_program::{clock::Slot, entrypoint::ProgramResult, msg};
+use stable_swap_math::{bn::U192, curve::StableSwap};
+
+/// Checks that an operation moving the reserves of the pool from `reserves_before`
+/// to `reserves_after` keeps the marginal price within the band of the circuit
+/// breaker. The price before the first operation of a window is recorded as the
+/// reference of the window.
+///
+/// `d_before` and `d_after` are the invariants of the reserves, which the
+/// caller has usually computed already.
+pub fn check_price_deviation(
+    token_swap: &mut SwapInfo,
+    invariant: &StableSwap,
+    slot: Slot,
+    reserves_before: (u64, u64),
+    d_before: U192,
+    reserves_after: (u64, u64),
+    d_after: U192,
+) -> ProgramResult {
+    let circuit_breaker = &mut token_swap.circuit_breaker;
+    if !circuit_breaker.is_enabled() {
+        return Ok(());
+    }
+    if circuit_breaker.is_window_expired(slot) {
+        circuit_breaker.reference_price = invariant
+            .compute_marginal_price_with_d(reserves_before.0, reserves_before.1, d_before)
+            .ok_or(SwapError::CalculationFailure)?;
+        circuit_breaker.reference_slot = slot;
+    }
+
+    let reference_price = circuit_breaker.reference_price;
+    let price = invariant
+        .compute_marginal_price_with_d(reserves_after.0, reserves_after.1, d_after)
+        .ok_or(SwapError::CalculationFailure)?;
+    let deviation_bps =
+        price_deviation_bps(price, reference_price).ok_or(SwapError::CalculationFailure)?;
+    if deviation_bps > u128::from(circuit_breaker.max_price_deviation_bps) {
+        msg!(
+            "Circuit breaker: Price {} deviates {} bps from {}",
+            price,
+            deviation_bps,
+            reference_price
+        );
+        return Err(SwapError::PriceDeviationExceeded.into());
+    }
+    Ok(())
+}
+
+/// Deviation of `price` from `reference_price`, in bps.
+pub fn price_deviation_bps(price: u64, reference_price: u64) -> Option<u128> {
+    u128::from(price.abs_diff(reference_price))
+        .checked_mul(BPS_DENOMINATOR.into())?
+        .checked_div(reference_price.into())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::integer_arithmetic)]
+mod tests {
+    use super::*;
+    use crate::{
+        curve::{PRICE_PRECISION, ZERO_TS},
+        processor::test_utils::*,
+        results::SwapResult,
+    };
+    use solana_program::{program_error::ProgramError, program_pack::Pack};
+
+    #[test]
+    fn test_circuit_breaker() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let guardian_key = pubkey_rand();
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, 100, 1_000_000, 1_000_000, DEFAULT_TEST_FEES);
+        accounts.initialize_swap().unwrap();
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, 1_000_000, 0, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let mut swap_a_to_b =
+            |accounts: &mut SwapAccountInfo, amount_in: u64| -> Result<(), ProgramError> {
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+            };
+
+        // only the admin and pause manager can configure the circuit breaker
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_circuit_breaker(30, 10)
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // the band cannot exceed 100% and windows cannot be empty
+        {
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.set_circuit_breaker(10_001, 10)
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.set_circuit_breaker(30, 0)
+            );
+        }
+
+        accounts.set_circuit_breaker(30, 10).unwrap();
+        set_current_slot(100);
+
+        // the first swap of a window records the price before it
+        swap_a_to_b(&mut accounts, 100_000).unwrap();
+        let circuit_breaker = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .circuit_breaker;
+        assert_eq!(circuit_breaker.reference_slot, 100);
+        assert_eq!(circuit_breaker.reference_price, PRICE_PRECISION);
+
+        // swaps and quotes are rejected once the price moved beyond the band within the window
+        {
+            set_current_slot(109);
+            assert_eq!(
+                Err(SwapError::PriceDeviationExceeded.into()),
+                swap_a_to_b(&mut accounts, 100_000)
+            );
+            assert_eq!(
+                Err(SwapError::PriceDeviationExceeded.into()),
+                accounts.quote_swap(&swap_token_a_key, &swap_token_b_key, 100_000)
+            );
+        }
+
+        // a new window starts at the current price, quotes do not record it
+        set_current_slot(110);
+        accounts
+            .quote_swap(&swap_token_a_key, &swap_token_b_key, 100_000)
+            .unwrap();
+        assert_eq!(
+            SwapInfo::unpack(&accounts.swap_account.data)
+                .unwrap()
+                .circuit_breaker
+                .reference_slot,
+            100
+        );
+        swap_a_to_b(&mut accounts, 100_000).unwrap();
+        let circuit_breaker = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .circuit_breaker;
+        assert_eq!(circuit_breaker.reference_slot, 110);
+        assert!(circuit_breaker.reference_price < PRICE_PRECISION);
+
+        // large swaps are rejected at once
+        {
+            set_current_slot(200);
+            assert_eq!(
+                Err(SwapError::PriceDeviationExceeded.into()),
+                swap_a_to_b(&mut accounts, 300_000)
+            );
+        }
+
+        // the guardian can reset the circuit breaker within a window
+        swap_a_to_b(&mut accounts, 50_000).unwrap();
+        assert_eq!(
+            Err(SwapError::PriceDeviationExceeded.into()),
+            swap_a_to_b(&mut accounts, 100_000)
+        );
+        accounts.set_guardian(&guardian_key).unwrap();
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = guardian_key;
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_circuit_breaker(0, 0)
+            );
+            accounts.reset_circuit_breaker().unwrap();
+            accounts.admin_key = admin_key;
+        }
+        let circuit_breaker = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .circuit_breaker;
+        assert_eq!(circuit_breaker.reference_price, 0);
+        swap_a_to_b(&mut accounts, 50_000).unwrap();
+
+        // disabling the circuit breaker clears its state
+        accounts.set_circuit_breaker(0, 0).unwrap();
+        let circuit_breaker = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .circuit_breaker;
+        assert_eq!(circuit_breaker, Default::default());
+        swap_a_to_b(&mut accounts, 300_000).unwrap();
+        set_current_slot(0);
+    }
+
+    #[test]
+    fn test_circuit_breaker_with_referral() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let referrer_key = pubkey_rand();
+        let amp_factor = 1;
+        let swap_with_referral = |max_price_deviation_bps: Option<u16>| {
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                amp_factor,
+                1_000_000,
+                1_000_000,
+                DEFAULT_TEST_FEES,
+            );
+            accounts.initialize_swap().unwrap();
+            accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES).unwrap();
+            if let Some(max_price_deviation_bps) = max_price_deviation_bps {
+                accounts
+                    .set_circuit_breaker(max_price_deviation_bps, 10)
+                    .unwrap();
+            }
+            let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+                accounts.setup_token_accounts(&user_key, &swapper_key, 100_000, 0, 0);
+            let (_, _, referrer_key, mut referrer_account, _, _) =
+                accounts.setup_token_accounts(&user_key, &referrer_key, 0, 0, 0);
+            let swap_token_a_key = accounts.token_a_key;
+            let swap_token_b_key = accounts.token_b_key;
+            accounts.swap_with_referral(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                Some((&referrer_key, &mut referrer_account)),
+                100_000,
+                0,
+            )
+        };
+        set_current_slot(100);
+
+        swap_with_referral(None).unwrap();
+        let result = take_result::<SwapResult>();
+        assert!(result.referral_fee > 0);
+        let invariant = StableSwap::new(amp_factor, amp_factor, ZERO_TS, ZERO_TS, ZERO_TS);
+        let price = invariant
+            .compute_marginal_price(result.reserves.token_a, result.reserves.token_b)
+            .unwrap();
+        let deviation_bps = price_deviation_bps(price, PRICE_PRECISION).unwrap() as u16;
+
+        // the band is checked against the reserves after the referral fee left
+        assert_eq!(
+            Err(SwapError::PriceDeviationExceeded.into()),
+            swap_with_referral(Some(deviation_bps - 1))
+        );
+        swap_with_referral(Some(deviation_bps)).unwrap();
+        set_current_slot(0);
+    }
+
+    #[test]
+    fn test_circuit_breaker_deposit_and_withdraw_one() {
+        let user_key = pubkey_rand();
+        let depositor_key = pubkey_rand();
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, 100, 1_000_000, 1_000_000, DEFAULT_TEST_FEES);
+        accounts.initialize_swap().unwrap();
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &depositor_key, 1_000_000, 0, 1_000_000);
+        accounts.set_circuit_breaker(30, 10).unwrap();
+        set_current_slot(100);
+
+        // imbalanced deposits are rejected beyond the band
+        assert_eq!(
+            Err(SwapError::PriceDeviationExceeded.into()),
+            accounts.deposit(
+                &depositor_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                600_000,
+                0,
+                0,
+            )
+        );
+        accounts
+            .deposit(
+                &depositor_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                100_000,
+                0,
+                0,
+            )
+            .unwrap();
+        let circuit_breaker = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .circuit_breaker;
+        assert_eq!(circuit_breaker.reference_slot, 100);
+        assert_eq!(circuit_breaker.reference_price, PRICE_PRECISION);
+
+        // and so are single sided withdrawals
+        assert_eq!(
+            Err(SwapError::PriceDeviationExceeded.into()),
+            accounts.withdraw_one(
+                &depositor_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                800_000,
+                0,
+            )
+        );
+        accounts
+            .withdraw_one(
+                &depositor_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                100_000,
+                0,
+            )
+            .unwrap();
+
+        set_current_slot(0);
+    }
+
+    #[test]
+    fn test_circuit_breaker_after_renounce() {
+        for is_enabled in [true, false] {
+            let user_key = pubkey_rand();
+            let swapper_key = pubkey_rand();
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, 100, 1_000_000, 1_000_000, DEFAULT_TEST_FEES);
+            accounts.initialize_swap().unwrap();
+            let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+                accounts.setup_token_accounts(&user_key, &swapper_key, 1_000_000, 0, 0);
+            let swap_token_a_key = accounts.token_a_key;
+            let swap_token_b_key = accounts.token_b_key;
+            let mut swap_a_to_b =
+                |accounts: &mut SwapAccountInfo, amount_in: u64| -> Result<(), ProgramError> {
+                    accounts.swap(
+                        &swapper_key,
+                        &token_a_key,
+                        &mut token_a_account,
+                        &swap_token_a_key,
+                        &swap_token_b_key,
+                        &token_b_key,
+                        &mut token_b_account,
+                        amount_in,
+                        0,
+                    )
+                };
+
+            if is_enabled {
+                accounts.set_circuit_breaker(30, 10).unwrap();
+                set_current_slot(100);
+                swap_a_to_b(&mut accounts, 100_000).unwrap();
+            }
+            accounts.renounce_admin().unwrap();
+            let circuit_breaker = SwapInfo::unpack(&accounts.swap_account.data)
+                .unwrap()
+                .circuit_breaker;
+            assert_eq!(circuit_breaker.is_enabled(), is_enabled);
+            assert_eq!(circuit_breaker.reference_price, 0);
+
+            if is_enabled {
+                // the band still applies within a window
+                set_current_slot(105);
+                swap_a_to_b(&mut accounts, 100_000).unwrap();
+                assert_eq!(
+                    Err(SwapError::PriceDeviationExceeded.into()),
+                    swap_a_to_b(&mut accounts, 100_000)
+                );
+
+                // and resets without an admin once the window expires
+                set_current_slot(115);
+                swap_a_to_b(&mut accounts, 100_000).unwrap();
+            } else {
+                // a disabled circuit breaker stays disabled
+                swap_a_to_b(&mut accounts, 300_000).unwrap();
+            }
+            set_current_slot(0);
+        }
+    }
+}
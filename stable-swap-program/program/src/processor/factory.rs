@@ -0,0 +1,676 @@
+//! Module for processing pool factory instructions.
+
+use crate::{
+    error::SwapError,
+    instruction::{CreatePoolData, FactoryInstruction, PoolTemplateData},
+    pda,
+    state::{Factory, PoolTemplate, RegistryEntry, SwapInfo},
+};
+use stable_swap_math::curve::{MAX_AMP, MIN_AMP};
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    program_pack::Pack,
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+};
+
+use super::fee_tier;
+use super::swap;
+use super::system;
+use super::utils;
+
+/// Process factory instruction
+pub fn process_factory_instruction(
+    program_id: &Pubkey,
+    instruction: &FactoryInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    match *instruction {
+        FactoryInstruction::InitializeFactory => {
+            msg!("Instruction: InitializeFactory");
+            initialize_factory(program_id, accounts)
+        }
+        FactoryInstruction::SetFactoryAdmin => {
+            msg!("Instruction: SetFactoryAdmin");
+            set_factory_admin(program_id, accounts)
+        }
+        FactoryInstruction::SetPoolTemplate(data) => {
+            msg!("Instruction: SetPoolTemplate");
+            set_pool_template(program_id, &data, accounts)
+        }
+        FactoryInstruction::CreatePool(data) => {
+            msg!("Instruction: CreatePool");
+            create_pool(program_id, &data, accounts)
+        }
+        FactoryInstruction::SetGlobalFeeTier(fee_multiplier_bps) => {
+            msg!("Instruction: SetGlobalFeeTier");
+            set_global_fee_tier(program_id, fee_multiplier_bps, accounts)
+        }
+        FactoryInstruction::RemoveGlobalFeeTier => {
+            msg!("Instruction: RemoveGlobalFeeTier");
+            remove_global_fee_tier(program_id, accounts)
+        }
+    }
+}
+
+/// Checks that the signer is the upgrade authority of the program
+fn check_upgrade_authority(
+    program_id: &Pubkey,
+    program_data_info: &AccountInfo,
+    upgrade_authority_info: &AccountInfo,
+) -> ProgramResult {
+    let (program_data_key, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    check_keys_equal!(
+        *program_data_info.key,
+        program_data_key,
+        "Program data",
+        SwapError::InvalidProgramAddress
+    );
+    if *program_data_info.owner != bpf_loader_upgradeable::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let upgrade_authority = match limited_deserialize(&program_data_info.data.borrow(), 64) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        }) => upgrade_authority_address,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    if upgrade_authority != Some(*upgrade_authority_info.key) {
+        return Err(SwapError::Unauthorized.into());
+    }
+    if !upgrade_authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// Checks that the account is the factory
+fn check_factory_address(program_id: &Pubkey, factory_info: &AccountInfo) -> ProgramResult {
+    check_keys_equal!(
+        *factory_info.key,
+        pda::find_factory_address(program_id).0,
+        "Factory",
+        SwapError::InvalidProgramAddress
+    );
+    Ok(())
+}
+
+/// Loads the factory
+fn load_factory(program_id: &Pubkey, factory_info: &AccountInfo) -> Result<Factory, ProgramError> {
+    check_factory_address(program_id, factory_info)?;
+    if factory_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Factory::unpack(&factory_info.data.borrow())
+}
+
+/// Access control for instructions of the factory admin
+fn check_factory_admin(factory: &Factory, admin_info: &AccountInfo) -> ProgramResult {
+    check_keys_equal!(
+        *admin_info.key,
+        factory.admin,
+        "Factory admin",
+        SwapError::Unauthorized
+    );
+    if !admin_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// Initialize factory
+fn initialize_factory(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer_info = next_account_info(account_info_iter)?;
+    let factory_info = next_account_info(account_info_iter)?;
+    let upgrade_authority_info = next_account_info(account_info_iter)?;
+    let program_data_info = next_account_info(account_info_iter)?;
+    let admin_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    check_upgrade_authority(program_id, program_data_info, upgrade_authority_info)?;
+    let (factory_key, factory_bump) = pda::find_factory_address(program_id);
+    check_keys_equal!(
+        *factory_info.key,
+        factory_key,
+        "Factory",
+        SwapError::InvalidProgramAddress
+    );
+
+    system::create_account(
+        payer_info.clone(),
+        factory_info.clone(),
+        system_program_info.clone(),
+        program_id,
+        Factory::LEN,
+        &[&[pda::FACTORY_SEED, &[factory_bump]]],
+    )?;
+    Factory::pack(
+        Factory {
+            is_initialized: true,
+            admin: *admin_info.key,
+        },
+        &mut factory_info.data.borrow_mut(),
+    )?;
+    msg!("Factory: Admin set to {}", admin_info.key);
+    Ok(())
+}
+
+/// Set factory admin
+fn set_factory_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let factory_info = next_account_info(account_info_iter)?;
+    let admin_info = next_account_info(account_info_iter)?;
+    let new_admin_info = next_account_info(account_info_iter)?;
+
+    let mut factory = load_factory(program_id, factory_info)?;
+    check_factory_admin(&factory, admin_info)?;
+    factory.admin = *new_admin_info.key;
+    Factory::pack(factory, &mut factory_info.data.borrow_mut())?;
+    msg!("Factory: Admin set to {}", factory.admin);
+    Ok(())
+}
+
+/// Set pool template
+fn set_pool_template(
+    program_id: &Pubkey,
+    data: &PoolTemplateData,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let admin_info = next_account_info(account_info_iter)?;
+    let factory_info = next_account_info(account_info_iter)?;
+    let template_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let factory = load_factory(program_id, factory_info)?;
+    check_factory_admin(&factory, admin_info)?;
+    if !(MIN_AMP..=MAX_AMP).contains(&data.amp_factor) {
+        msg!("Invalid amp factor: {}", data.amp_factor);
+        return Err(SwapError::InvalidInput.into());
+    }
+    data.fees.validate()?;
+
+    let (template_key, template_bump) = pda::find_template_address(program_id, data.amp_tier);
+    check_keys_equal!(
+        *template_info.key,
+        template_key,
+        "Pool template",
+        SwapError::InvalidProgramAddress
+    );
+    if template_info.owner != program_id {
+        system::create_account(
+            admin_info.clone(),
+            template_info.clone(),
+            system_program_info.clone(),
+            program_id,
+            PoolTemplate::LEN,
+            &[&[pda::TEMPLATE_SEED, &[data.amp_tier], &[template_bump]]],
+        )?;
+    }
+    PoolTemplate::pack(
+        PoolTemplate {
+            is_initialized: true,
+            is_enabled: data.is_enabled,
+            amp_tier: data.amp_tier,
+            amp_factor: data.amp_factor,
+            fees: data.fees,
+        },
+        &mut template_info.data.borrow_mut(),
+    )?;
+    msg!(
+        "Factory: Template {} set to A {}, enabled {}",
+        data.amp_tier,
+        data.amp_factor,
+        data.is_enabled
+    );
+    Ok(())
+}
+
+/// Create pool
+fn create_pool(
+    program_id: &Pubkey,
+    data: &CreatePoolData,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let factory_info = next_account_info(account_info_iter)?;
+    let template_info = next_account_info(account_info_iter)?;
+    let registry_entry_info = next_account_info(account_info_iter)?;
+    let pool_accounts = accounts
+        .get(3..)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let swap_info = next_account_info(account_info_iter)?;
+    let _authority_info = next_account_info(account_info_iter)?;
+    let admin_info = next_account_info(account_info_iter)?;
+    let admin_fee_a_info = next_account_info(account_info_iter)?;
+    let admin_fee_b_info = next_account_info(account_info_iter)?;
+    let token_a_mint_info = next_account_info(account_info_iter)?;
+    let _token_a_info = next_account_info(account_info_iter)?;
+    let token_b_mint_info = next_account_info(account_info_iter)?;
+    let _token_b_info = next_account_info(account_info_iter)?;
+    let _pool_mint_info = next_account_info(account_info_iter)?;
+    let _source_a_info = next_account_info(account_info_iter)?;
+    let _source_b_info = next_account_info(account_info_iter)?;
+    let _destination_info = next_account_info(account_info_iter)?;
+    let _token_program_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let factory = load_factory(program_id, factory_info)?;
+    check_keys_equal!(
+        *template_info.key,
+        pda::find_template_address(program_id, data.amp_tier).0,
+        "Pool template",
+        SwapError::InvalidProgramAddress
+    );
+    if template_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let template = PoolTemplate::unpack(&template_info.data.borrow())?;
+    if !template.is_enabled {
+        return Err(SwapError::TemplateDisabled.into());
+    }
+    if token_a_mint_info.key >= token_b_mint_info.key {
+        return Err(SwapError::UnsortedMints.into());
+    }
+    check_keys_equal!(
+        *admin_info.key,
+        factory.admin,
+        "Pool admin",
+        SwapError::InvalidAdmin
+    );
+    for admin_fee_info in [admin_fee_a_info, admin_fee_b_info] {
+        let admin_fee_account = utils::unpack_token_account(&admin_fee_info.data.borrow())?;
+        check_keys_equal!(
+            admin_fee_account.owner,
+            factory.admin,
+            "Admin fee account owner",
+            SwapError::InvalidAdmin
+        );
+    }
+
+    swap::create_pool(
+        program_id,
+        Some(data.amp_tier),
+        template.amp_factor,
+        template.fees,
+        data.token_a_amount,
+        data.token_b_amount,
+        pool_accounts,
+    )?;
+    // global fee tiers only discount swaps of pools created by the factory
+    let mut token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    token_swap.accepts_global_fee_tiers = true;
+    SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+
+    let (registry_entry_key, registry_entry_bump) = pda::find_registry_address(
+        program_id,
+        token_a_mint_info.key,
+        token_b_mint_info.key,
+        data.amp_tier,
+    );
+    check_keys_equal!(
+        *registry_entry_info.key,
+        registry_entry_key,
+        "Registry entry",
+        SwapError::InvalidProgramAddress
+    );
+    system::create_account(
+        payer_info.clone(),
+        registry_entry_info.clone(),
+        system_program_info.clone(),
+        program_id,
+        RegistryEntry::LEN,
+        &[&[
+            pda::REGISTRY_SEED,
+            token_a_mint_info.key.as_ref(),
+            token_b_mint_info.key.as_ref(),
+            &[data.amp_tier],
+            &[registry_entry_bump],
+        ]],
+    )?;
+    RegistryEntry::pack(
+        RegistryEntry {
+            is_initialized: true,
+            token_a_mint: *token_a_mint_info.key,
+            token_b_mint: *token_b_mint_info.key,
+            amp_tier: data.amp_tier,
+            swap: *swap_info.key,
+        },
+        &mut registry_entry_info.data.borrow_mut(),
+    )?;
+    msg!(
+        "Factory: Registered pool {} in tier {}",
+        swap_info.key,
+        data.amp_tier
+    );
+    Ok(())
+}
+
+/// Set global fee tier
+fn set_global_fee_tier(
+    program_id: &Pubkey,
+    fee_multiplier_bps: u16,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let admin_info = next_account_info(account_info_iter)?;
+    let factory_info = next_account_info(account_info_iter)?;
+    let fee_tier_info = next_account_info(account_info_iter)?;
+    let user_authority_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let factory = load_factory(program_id, factory_info)?;
+    check_factory_admin(&factory, admin_info)?;
+    fee_tier::set_fee_tier(
+        program_id,
+        None,
+        fee_multiplier_bps,
+        admin_info,
+        fee_tier_info,
+        user_authority_info,
+        system_program_info,
+    )
+}
+
+/// Remove global fee tier
+fn remove_global_fee_tier(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let admin_info = next_account_info(account_info_iter)?;
+    let factory_info = next_account_info(account_info_iter)?;
+    let fee_tier_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+
+    let factory = load_factory(program_id, factory_info)?;
+    check_factory_admin(&factory, admin_info)?;
+    fee_tier::remove_fee_tier(program_id, None, fee_tier_info, destination_info)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::integer_arithmetic)]
+mod tests {
+    use super::*;
+    use crate::{
+        curve::ZERO_TS,
+        fees::Fees,
+        instruction::{
+            initialize_factory, remove_global_fee_tier, set_factory_admin, set_global_fee_tier,
+            set_pool_template,
+        },
+        pda::PoolAddresses,
+        processor::test_utils::*,
+        state::{FeeTier, GLOBAL_FEE_TIER_DELAY},
+    };
+    use solana_program::system_program;
+    use solana_sdk::account::Account;
+
+    const AMP_TIER: u8 = 1;
+
+    fn set_template(
+        admin_key: &Pubkey,
+        admin_account: &mut Account,
+        factory_account: &mut Account,
+        template_account: &mut Account,
+        amp_factor: u64,
+        fees: Fees,
+        is_enabled: bool,
+    ) -> ProgramResult {
+        do_process_instruction(
+            set_pool_template(admin_key, AMP_TIER, amp_factor, fees, is_enabled).unwrap(),
+            vec![
+                admin_account,
+                factory_account,
+                template_account,
+                &mut Account::default(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_factory() {
+        let upgrade_authority_key = pubkey_rand();
+        let admin_key = pubkey_rand();
+        let mut program_data_account = create_program_data(Some(&upgrade_authority_key));
+        let payer_key = pubkey_rand();
+        let mut payer_account = Account::new(1_000_000_000, 0, &system_program::id());
+        let mut factory_account = Account::new(0, Factory::LEN, &system_program::id());
+
+        // only the upgrade authority may create the factory
+        {
+            let ix = initialize_factory(&payer_key, &admin_key, &admin_key).unwrap();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                do_process_instruction(
+                    ix,
+                    vec![
+                        &mut payer_account,
+                        &mut factory_account,
+                        &mut Account::default(),
+                        &mut program_data_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+        do_process_instruction(
+            initialize_factory(&payer_key, &upgrade_authority_key, &upgrade_authority_key).unwrap(),
+            vec![
+                &mut payer_account,
+                &mut factory_account,
+                &mut Account::default(),
+                &mut program_data_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(factory_account.owner, SWAP_PROGRAM_ID);
+
+        // transfer the factory to the admin
+        {
+            let ix = set_factory_admin(&admin_key, &admin_key).unwrap();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                do_process_instruction(
+                    ix,
+                    vec![
+                        &mut factory_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+        do_process_instruction(
+            set_factory_admin(&upgrade_authority_key, &admin_key).unwrap(),
+            vec![
+                &mut factory_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+        .unwrap();
+        let factory = Factory::unpack(&factory_account.data).unwrap();
+        assert_eq!(factory.admin, admin_key);
+
+        // set the template of the tier
+        let mut admin_account = Account::new(1_000_000_000, 0, &system_program::id());
+        let mut template_account = Account::new(0, PoolTemplate::LEN, &system_program::id());
+        assert_eq!(
+            Err(SwapError::InvalidInput.into()),
+            set_template(
+                &admin_key,
+                &mut admin_account,
+                &mut factory_account,
+                &mut template_account,
+                MAX_AMP + 1,
+                DEFAULT_TEST_FEES,
+                true
+            )
+        );
+        assert_eq!(
+            Err(SwapError::ZeroFeeDenominator.into()),
+            set_template(
+                &admin_key,
+                &mut admin_account,
+                &mut factory_account,
+                &mut template_account,
+                MIN_AMP,
+                Fees::default(),
+                true
+            )
+        );
+        set_template(
+            &admin_key,
+            &mut admin_account,
+            &mut factory_account,
+            &mut template_account,
+            MIN_AMP,
+            DEFAULT_TEST_FEES,
+            false,
+        )
+        .unwrap();
+        // templates can be updated
+        set_template(
+            &admin_key,
+            &mut admin_account,
+            &mut factory_account,
+            &mut template_account,
+            100,
+            DEFAULT_TEST_FEES,
+            false,
+        )
+        .unwrap();
+        let template = PoolTemplate::unpack(&template_account.data).unwrap();
+        assert_eq!(template.amp_factor, 100);
+        assert!(!template.is_enabled);
+
+        // create a pool with the template
+        let mut pool = FactoryPoolAccounts::new(&payer_key, &admin_key);
+        assert_eq!(
+            Err(SwapError::TemplateDisabled.into()),
+            pool.create(
+                &mut factory_account,
+                &mut template_account,
+                &mut payer_account,
+                false
+            )
+        );
+        set_template(
+            &admin_key,
+            &mut admin_account,
+            &mut factory_account,
+            &mut template_account,
+            100,
+            DEFAULT_TEST_FEES,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(SwapError::UnsortedMints.into()),
+            pool.create(
+                &mut factory_account,
+                &mut template_account,
+                &mut payer_account,
+                true
+            )
+        );
+        pool.create(
+            &mut factory_account,
+            &mut template_account,
+            &mut payer_account,
+            false,
+        )
+        .unwrap();
+
+        let addresses = PoolAddresses::find_for_factory(
+            &SWAP_PROGRAM_ID,
+            &pool.token_a_mint.0,
+            &pool.token_b_mint.0,
+            AMP_TIER,
+        );
+        let entry = RegistryEntry::unpack(&pool.registry_entry.data).unwrap();
+        assert_eq!(entry.swap, addresses.swap);
+        assert_eq!(entry.token_a_mint, pool.token_a_mint.0);
+        assert_eq!(entry.token_b_mint, pool.token_b_mint.0);
+        assert_eq!(entry.amp_tier, AMP_TIER);
+        let swap_info = SwapInfo::unpack(&pool.swap.data).unwrap();
+        assert_eq!(swap_info.admin_key, admin_key);
+        assert_eq!(swap_info.initial_amp_factor, 100);
+        assert_eq!(swap_info.fees, DEFAULT_TEST_FEES);
+        assert!(swap_info.accepts_global_fee_tiers);
+
+        // the canonical pool can only be created once
+        assert!(pool
+            .create(
+                &mut factory_account,
+                &mut template_account,
+                &mut payer_account,
+                false
+            )
+            .is_err());
+
+        // global fee tiers are managed by the factory admin
+        let trader_key = pubkey_rand();
+        let mut fee_tier_account = Account::new(0, FeeTier::LEN, &system_program::id());
+        {
+            let ix = set_global_fee_tier(&payer_key, &trader_key, 5_000).unwrap();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                do_process_instruction(
+                    ix,
+                    vec![
+                        &mut payer_account,
+                        &mut factory_account,
+                        &mut fee_tier_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+        do_process_instruction_at_time(
+            set_global_fee_tier(&admin_key, &trader_key, 5_000).unwrap(),
+            vec![
+                &mut admin_account,
+                &mut factory_account,
+                &mut fee_tier_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+            ZERO_TS,
+        )
+        .unwrap();
+        let fee_tier = FeeTier::unpack(&fee_tier_account.data).unwrap();
+        assert!(fee_tier.is_global());
+        assert_eq!(fee_tier.authority, trader_key);
+        assert_eq!(fee_tier.fee_multiplier_bps, 5_000);
+        // global tiers only apply after a delay
+        assert_eq!(fee_tier.activation_ts, ZERO_TS + GLOBAL_FEE_TIER_DELAY);
+
+        let rent_destination_key = pubkey_rand();
+        let mut rent_destination_account = Account::default();
+        let fee_tier_lamports = fee_tier_account.lamports;
+        do_process_instruction(
+            remove_global_fee_tier(&admin_key, &trader_key, &rent_destination_key).unwrap(),
+            vec![
+                &mut admin_account,
+                &mut factory_account,
+                &mut fee_tier_account,
+                &mut rent_destination_account,
+            ],
+        )
+        .unwrap();
+        assert_eq!(fee_tier_account.lamports, 0);
+        assert_eq!(rent_destination_account.lamports, fee_tier_lamports);
+    }
+}
@@ -0,0 +1,165 @@
+//! Fee tiers of users, set by the admin of a swap or of the factory.
+
+use crate::{
+    error::SwapError,
+    fees::{Fees, BPS_DENOMINATOR},
+    pda,
+    state::{FeeTier, SwapInfo, GLOBAL_FEE_TIER_DELAY},
+};
+
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, msg,
+    program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, sysvar::Sysvar,
+};
+
+use super::system;
+
+/// Creates or updates the fee tier of `user_authority_info` for `swap`, or
+/// the global fee tier if `swap` is `None`. Global tiers only apply after
+/// [GLOBAL_FEE_TIER_DELAY], giving the LPs of the pools they discount time to
+/// react. Tiers of a swap are subject to the timelock of the swap instead.
+pub fn set_fee_tier<'a>(
+    program_id: &Pubkey,
+    swap: Option<&Pubkey>,
+    fee_multiplier_bps: u16,
+    payer_info: &AccountInfo<'a>,
+    fee_tier_info: &AccountInfo<'a>,
+    user_authority_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    if u64::from(fee_multiplier_bps) > BPS_DENOMINATOR {
+        msg!("Invalid fee multiplier: {}", fee_multiplier_bps);
+        return Err(SwapError::InvalidInput.into());
+    }
+    let scope = swap.copied().unwrap_or_default();
+    let (fee_tier_key, fee_tier_bump) =
+        pda::find_fee_tier_address(program_id, swap, user_authority_info.key);
+    check_keys_equal!(
+        *fee_tier_info.key,
+        fee_tier_key,
+        "Fee tier",
+        SwapError::InvalidProgramAddress
+    );
+    if fee_tier_info.owner != program_id {
+        system::create_account(
+            payer_info.clone(),
+            fee_tier_info.clone(),
+            system_program_info.clone(),
+            program_id,
+            FeeTier::LEN,
+            &[&[
+                pda::FEE_TIER_SEED,
+                scope.as_ref(),
+                user_authority_info.key.as_ref(),
+                &[fee_tier_bump],
+            ]],
+        )?;
+    }
+    let activation_ts = match swap {
+        Some(_) => 0,
+        None => Clock::get()?
+            .unix_timestamp
+            .checked_add(GLOBAL_FEE_TIER_DELAY)
+            .ok_or(SwapError::CalculationFailure)?,
+    };
+    FeeTier::pack(
+        FeeTier {
+            is_initialized: true,
+            swap: scope,
+            authority: *user_authority_info.key,
+            fee_multiplier_bps,
+            activation_ts,
+        },
+        &mut fee_tier_info.data.borrow_mut(),
+    )?;
+    msg!(
+        "Fee tier: {} pays {} bps of the trade fee from {}",
+        user_authority_info.key,
+        fee_multiplier_bps,
+        activation_ts
+    );
+    Ok(())
+}
+
+/// Closes a fee tier of `swap`, or a global fee tier if `swap` is `None`,
+/// and returns its rent to `destination_info`.
+pub fn remove_fee_tier(
+    program_id: &Pubkey,
+    swap: Option<&Pubkey>,
+    fee_tier_info: &AccountInfo,
+    destination_info: &AccountInfo,
+) -> ProgramResult {
+    let fee_tier = load_fee_tier(program_id, fee_tier_info)?;
+    if fee_tier.swap != swap.copied().unwrap_or_default() {
+        return Err(SwapError::InvalidFeeTier.into());
+    }
+
+    let fee_tier_lamports = fee_tier_info.lamports();
+    **destination_info.lamports.borrow_mut() = destination_info
+        .lamports()
+        .checked_add(fee_tier_lamports)
+        .ok_or(SwapError::CalculationFailure)?;
+    **fee_tier_info.lamports.borrow_mut() = 0;
+    fee_tier_info.data.borrow_mut().fill(0);
+    msg!("Fee tier: Removed tier of {}", fee_tier.authority);
+    Ok(())
+}
+
+/// Loads a fee tier. Fee tiers are only created by the program at their
+/// derived addresses, so the owner check is sufficient.
+fn load_fee_tier(
+    program_id: &Pubkey,
+    fee_tier_info: &AccountInfo,
+) -> Result<FeeTier, ProgramError> {
+    if fee_tier_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    FeeTier::unpack(&fee_tier_info.data.borrow())
+}
+
+/// Returns the fees of a swap of `user_authority` in `swap`, discounted by
+/// the fee tier of the user if one is passed. Global tiers only apply to swaps
+/// that accept them, and neither discount before their activation.
+pub fn effective_fees(
+    program_id: &Pubkey,
+    swap: &Pubkey,
+    token_swap: &SwapInfo,
+    user_authority: &Pubkey,
+    fee_tier_info: Option<&AccountInfo>,
+) -> Result<Fees, ProgramError> {
+    let fees = &token_swap.fees;
+    let fee_tier_info = match fee_tier_info {
+        Some(fee_tier_info) => fee_tier_info,
+        None => return Ok(*fees),
+    };
+    let fee_tier = load_fee_tier(program_id, fee_tier_info)?;
+    let is_applicable = if fee_tier.is_global() {
+        token_swap.accepts_global_fee_tiers
+    } else {
+        fee_tier.swap == *swap
+    };
+    if fee_tier.authority != *user_authority || !is_applicable {
+        return Err(SwapError::InvalidFeeTier.into());
+    }
+    if !fee_tier.is_active(Clock::get()?.unix_timestamp) {
+        msg!("Fee tier: Not active before {}", fee_tier.activation_ts);
+        return Ok(*fees);
+    }
+    fees.with_trade_fee_multiplier(fee_tier.fee_multiplier_bps)
+        .ok_or_else(|| SwapError::CalculationFailure.into())
+}
+
+/// Returns the fees of a quoted swap in `swap`, discounted by the fee tier
+/// passed. Quotes have no user authority, so the tier applies on its own.
+pub fn quote_fees(
+    program_id: &Pubkey,
+    swap: &Pubkey,
+    token_swap: &SwapInfo,
+    fee_tier_info: Option<&AccountInfo>,
+) -> Result<Fees, ProgramError> {
+    let user_authority = match fee_tier_info {
+        Some(fee_tier_info) => load_fee_tier(program_id, fee_tier_info)?.authority,
+        None => return Ok(token_swap.fees),
+    };
+    effective_fees(program_id, swap, token_swap, &user_authority, fee_tier_info)
+}
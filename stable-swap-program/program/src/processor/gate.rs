@@ -0,0 +1,597 @@
+//! Module for processing the instructions of the gates of permissioned pools.
+
+use crate::{
+    error::SwapError,
+    instruction::GateInstruction,
+    pda,
+    state::{AllowlistEntry, Gate, SwapInfo},
+};
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+use super::system;
+
+/// Process gate instruction
+pub fn process_gate_instruction(
+    program_id: &Pubkey,
+    instruction: &GateInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    match *instruction {
+        GateInstruction::InitializeGate => {
+            msg!("Instruction: InitializeGate");
+            initialize_gate(program_id, accounts)
+        }
+        GateInstruction::SetGatekeeper => {
+            msg!("Instruction: SetGatekeeper");
+            set_gatekeeper(program_id, accounts)
+        }
+        GateInstruction::AddToAllowlist => {
+            msg!("Instruction: AddToAllowlist");
+            add_to_allowlist(program_id, accounts)
+        }
+        GateInstruction::RemoveFromAllowlist => {
+            msg!("Instruction: RemoveFromAllowlist");
+            remove_from_allowlist(program_id, accounts)
+        }
+    }
+}
+
+/// Loads a gate, checking that it was created by the program
+pub fn load_gate(program_id: &Pubkey, gate_info: &AccountInfo) -> Result<Gate, ProgramError> {
+    if gate_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let gate = Gate::unpack(&gate_info.data.borrow())?;
+    if *gate_info.key != pda::find_gate_address(program_id, &gate.base).0 {
+        msg!("Invalid gate: {}", gate_info.key);
+        return Err(SwapError::InvalidProgramAddress.into());
+    }
+    Ok(gate)
+}
+
+/// Access control for instructions of the gatekeeper
+fn check_gatekeeper(gate: &Gate, gatekeeper_info: &AccountInfo) -> ProgramResult {
+    check_keys_equal!(
+        *gatekeeper_info.key,
+        gate.gatekeeper,
+        "Gatekeeper",
+        SwapError::Unauthorized
+    );
+    if !gatekeeper_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// Checks that the user authority may use a gated swap with the allowlist
+/// entry passed in the optional accounts of the instruction.
+pub fn check_allowlisted(
+    program_id: &Pubkey,
+    token_swap: &SwapInfo,
+    user_authority: &Pubkey,
+    entry_info: Option<&AccountInfo>,
+) -> ProgramResult {
+    if token_swap.gate == Pubkey::default() {
+        return Ok(());
+    }
+    let entry_info = entry_info.ok_or(SwapError::NotAllowlisted)?;
+    if entry_info.owner != program_id {
+        return Err(SwapError::NotAllowlisted.into());
+    }
+    let entry = AllowlistEntry::unpack(&entry_info.data.borrow())?;
+    if entry.gate != token_swap.gate || entry.authority != *user_authority {
+        return Err(SwapError::NotAllowlisted.into());
+    }
+    let entry_key = Pubkey::create_program_address(
+        &[
+            pda::ALLOWLIST_SEED,
+            entry.gate.as_ref(),
+            entry.authority.as_ref(),
+            &[entry.bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| SwapError::InvalidProgramAddress)?;
+    check_keys_equal!(
+        *entry_info.key,
+        entry_key,
+        "Allowlist entry",
+        SwapError::InvalidProgramAddress
+    );
+    Ok(())
+}
+
+/// Checks that a quote of a gated swap is for an allowlisted user authority,
+/// which is the authority of the allowlist entry passed to the quote.
+pub fn check_quote_allowlisted(
+    program_id: &Pubkey,
+    token_swap: &SwapInfo,
+    entry_info: Option<&AccountInfo>,
+) -> ProgramResult {
+    if token_swap.gate == Pubkey::default() {
+        return Ok(());
+    }
+    let entry_info = entry_info.ok_or(SwapError::NotAllowlisted)?;
+    if entry_info.owner != program_id {
+        return Err(SwapError::NotAllowlisted.into());
+    }
+    let user_authority = AllowlistEntry::unpack(&entry_info.data.borrow())?.authority;
+    check_allowlisted(program_id, token_swap, &user_authority, Some(entry_info))
+}
+
+/// Initialize gate
+fn initialize_gate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer_info = next_account_info(account_info_iter)?;
+    let gate_info = next_account_info(account_info_iter)?;
+    let gatekeeper_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !gatekeeper_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (gate_key, gate_bump) = pda::find_gate_address(program_id, gatekeeper_info.key);
+    check_keys_equal!(
+        *gate_info.key,
+        gate_key,
+        "Gate",
+        SwapError::InvalidProgramAddress
+    );
+
+    system::create_account(
+        payer_info.clone(),
+        gate_info.clone(),
+        system_program_info.clone(),
+        program_id,
+        Gate::LEN,
+        &[&[pda::GATE_SEED, gatekeeper_info.key.as_ref(), &[gate_bump]]],
+    )?;
+    Gate::pack(
+        Gate {
+            is_initialized: true,
+            base: *gatekeeper_info.key,
+            gatekeeper: *gatekeeper_info.key,
+        },
+        &mut gate_info.data.borrow_mut(),
+    )?;
+    msg!("Gate: Gatekeeper set to {}", gatekeeper_info.key);
+    Ok(())
+}
+
+/// Set gatekeeper
+fn set_gatekeeper(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let gate_info = next_account_info(account_info_iter)?;
+    let gatekeeper_info = next_account_info(account_info_iter)?;
+    let new_gatekeeper_info = next_account_info(account_info_iter)?;
+
+    let mut gate = load_gate(program_id, gate_info)?;
+    check_gatekeeper(&gate, gatekeeper_info)?;
+    gate.gatekeeper = *new_gatekeeper_info.key;
+    Gate::pack(gate, &mut gate_info.data.borrow_mut())?;
+    msg!("Gate: Gatekeeper set to {}", gate.gatekeeper);
+    Ok(())
+}
+
+/// Add to allowlist
+fn add_to_allowlist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let gate_info = next_account_info(account_info_iter)?;
+    let gatekeeper_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let entry_info = next_account_info(account_info_iter)?;
+    let user_authority_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let gate = load_gate(program_id, gate_info)?;
+    check_gatekeeper(&gate, gatekeeper_info)?;
+    let (entry_key, entry_bump) =
+        pda::find_allowlist_entry_address(program_id, gate_info.key, user_authority_info.key);
+    check_keys_equal!(
+        *entry_info.key,
+        entry_key,
+        "Allowlist entry",
+        SwapError::InvalidProgramAddress
+    );
+
+    system::create_account(
+        payer_info.clone(),
+        entry_info.clone(),
+        system_program_info.clone(),
+        program_id,
+        AllowlistEntry::LEN,
+        &[&[
+            pda::ALLOWLIST_SEED,
+            gate_info.key.as_ref(),
+            user_authority_info.key.as_ref(),
+            &[entry_bump],
+        ]],
+    )?;
+    AllowlistEntry::pack(
+        AllowlistEntry {
+            is_initialized: true,
+            gate: *gate_info.key,
+            authority: *user_authority_info.key,
+            bump: entry_bump,
+        },
+        &mut entry_info.data.borrow_mut(),
+    )?;
+    msg!("Gate: Added {}", user_authority_info.key);
+    Ok(())
+}
+
+/// Remove from allowlist
+fn remove_from_allowlist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let gate_info = next_account_info(account_info_iter)?;
+    let gatekeeper_info = next_account_info(account_info_iter)?;
+    let entry_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+
+    let gate = load_gate(program_id, gate_info)?;
+    check_gatekeeper(&gate, gatekeeper_info)?;
+    if entry_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let entry = AllowlistEntry::unpack(&entry_info.data.borrow())?;
+    check_keys_equal!(
+        entry.gate,
+        *gate_info.key,
+        "Allowlist entry gate",
+        SwapError::InvalidInput
+    );
+
+    let entry_lamports = entry_info.lamports();
+    **destination_info.lamports.borrow_mut() = destination_info
+        .lamports()
+        .checked_add(entry_lamports)
+        .ok_or(SwapError::CalculationFailure)?;
+    **entry_info.lamports.borrow_mut() = 0;
+    entry_info.data.borrow_mut().fill(0);
+    msg!("Gate: Removed {}", entry.authority);
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        curve::MIN_AMP,
+        instruction::{remove_from_allowlist, set_gatekeeper},
+        pda::find_allowlist_entry_address,
+        processor::test_utils::*,
+    };
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn test_gatekeeper() {
+        let gatekeeper_key = pubkey_rand();
+        let user_key = pubkey_rand();
+        let (gate_key, mut gate_account) = create_gate(&gatekeeper_key);
+        let gate = Gate::unpack(&gate_account.data).unwrap();
+        assert_eq!(gate.base, gatekeeper_key);
+        assert_eq!(gate.gatekeeper, gatekeeper_key);
+
+        // only the gatekeeper can add users
+        {
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                create_allowlist_entry(&gate_key, &mut gate_account, &pubkey_rand(), &user_key)
+            );
+        }
+
+        let (entry_key, mut entry_account) =
+            create_allowlist_entry(&gate_key, &mut gate_account, &gatekeeper_key, &user_key)
+                .unwrap();
+        let entry = AllowlistEntry::unpack(&entry_account.data).unwrap();
+        assert_eq!(
+            entry_key,
+            find_allowlist_entry_address(&SWAP_PROGRAM_ID, &gate_key, &user_key).0
+        );
+        assert_eq!(entry.gate, gate_key);
+        assert_eq!(entry.authority, user_key);
+
+        // hand over the gate
+        let new_gatekeeper_key = pubkey_rand();
+        {
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                do_process_instruction(
+                    set_gatekeeper(&gate_key, &new_gatekeeper_key, &new_gatekeeper_key).unwrap(),
+                    vec![
+                        &mut gate_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+        do_process_instruction(
+            set_gatekeeper(&gate_key, &gatekeeper_key, &new_gatekeeper_key).unwrap(),
+            vec![
+                &mut gate_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+        .unwrap();
+        let gate = Gate::unpack(&gate_account.data).unwrap();
+        assert_eq!(gate.base, gatekeeper_key);
+        assert_eq!(gate.gatekeeper, new_gatekeeper_key);
+
+        // the entry is closed by the new gatekeeper
+        let destination_key = pubkey_rand();
+        let mut destination_account = Account::default();
+        {
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                do_process_instruction(
+                    remove_from_allowlist(&gate_key, &gatekeeper_key, &user_key, &destination_key)
+                        .unwrap(),
+                    vec![
+                        &mut gate_account,
+                        &mut Account::default(),
+                        &mut entry_account,
+                        &mut destination_account,
+                    ],
+                )
+            );
+        }
+        let entry_lamports = entry_account.lamports;
+        do_process_instruction(
+            remove_from_allowlist(&gate_key, &new_gatekeeper_key, &user_key, &destination_key)
+                .unwrap(),
+            vec![
+                &mut gate_account,
+                &mut Account::default(),
+                &mut entry_account,
+                &mut destination_account,
+            ],
+        )
+        .unwrap();
+        assert_eq!(entry_account.lamports, 0);
+        assert_eq!(destination_account.lamports, entry_lamports);
+    }
+
+    #[test]
+    fn test_gated_pool() {
+        let user_key = pubkey_rand();
+        let trader_key = pubkey_rand();
+        let gatekeeper_key = pubkey_rand();
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, MIN_AMP, 1_000_000, 1_000_000, DEFAULT_TEST_FEES);
+        accounts.initialize_swap().unwrap();
+        let (gate_key, mut gate_account) = create_gate(&gatekeeper_key);
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &trader_key, 10_000, 10_000, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // only the admin can gate a pool
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_gate(&gate_key, &mut gate_account)
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // the gate must be created by the program
+        {
+            let mut fake_gate_account = gate_account.clone();
+            fake_gate_account.owner = pubkey_rand();
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.set_gate(&gate_key, &mut fake_gate_account)
+            );
+        }
+
+        accounts.set_gate(&gate_key, &mut gate_account).unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.gate, gate_key);
+
+        // users without an entry are rejected
+        {
+            assert_eq!(
+                Err(SwapError::NotAllowlisted.into()),
+                accounts.deposit(
+                    &trader_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    1_000,
+                    1_000,
+                    0,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::NotAllowlisted.into()),
+                accounts.swap(
+                    &trader_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    1_000,
+                    0,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::NotAllowlisted.into()),
+                accounts.quote_swap(&swap_token_a_key, &swap_token_b_key, 1_000)
+            );
+        }
+
+        // entries of other users are rejected
+        {
+            let other_entry =
+                create_allowlist_entry(&gate_key, &mut gate_account, &gatekeeper_key, &user_key)
+                    .unwrap();
+            accounts.allowlist_entry = Some(other_entry);
+            assert_eq!(
+                Err(SwapError::NotAllowlisted.into()),
+                accounts.swap(
+                    &trader_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // entries of other gates are rejected
+        {
+            let other_gatekeeper_key = pubkey_rand();
+            let (other_gate_key, mut other_gate_account) = create_gate(&other_gatekeeper_key);
+            let other_entry = create_allowlist_entry(
+                &other_gate_key,
+                &mut other_gate_account,
+                &other_gatekeeper_key,
+                &trader_key,
+            )
+            .unwrap();
+            accounts.allowlist_entry = Some(other_entry);
+            assert_eq!(
+                Err(SwapError::NotAllowlisted.into()),
+                accounts.swap(
+                    &trader_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // entries passed in the position of another optional account are rejected
+        let (entry_key, entry_account) =
+            create_allowlist_entry(&gate_key, &mut gate_account, &gatekeeper_key, &trader_key)
+                .unwrap();
+        {
+            accounts.allowlist_entry = None;
+            assert_eq!(
+                Err(SwapError::NotAllowlisted.into()),
+                accounts.swap_with_fee_tier(
+                    &trader_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    Some((&entry_key, &mut entry_account.clone())),
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // allowlisted users can provide liquidity, swap and withdraw
+        accounts.allowlist_entry = Some((entry_key, entry_account));
+        accounts
+            .quote_swap(&swap_token_a_key, &swap_token_b_key, 1_000)
+            .unwrap();
+        accounts
+            .deposit(
+                &trader_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                1_000,
+                1_000,
+                0,
+            )
+            .unwrap();
+        accounts
+            .swap(
+                &trader_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                1_000,
+                0,
+            )
+            .unwrap();
+        accounts
+            .withdraw(
+                &trader_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                100,
+                0,
+                0,
+            )
+            .unwrap();
+        accounts
+            .withdraw_one(
+                &trader_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                100,
+                0,
+            )
+            .unwrap();
+
+        // removing the gate opens the pool
+        accounts.allowlist_entry = None;
+        accounts
+            .set_gate(&Pubkey::default(), &mut Account::default())
+            .unwrap();
+        accounts
+            .swap(
+                &trader_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                1_000,
+                0,
+            )
+            .unwrap();
+    }
+}
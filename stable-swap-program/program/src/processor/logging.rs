@@ -1,8 +1,21 @@
 //! Logging related helpers.
 
-use solana_program::log::sol_log_64;
+use crate::{
+    error::SwapError,
+    events::{self, AdminEvent, PoolState, Reserves, VIRTUAL_PRICE_DECIMALS},
+    instruction::AdminInstruction,
+    processor::utils,
+    results::InstructionResult,
+    state::SwapInfo,
+};
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::log::{sol_log_64, sol_log_data};
 use solana_program::msg;
+use solana_program::program::set_return_data;
+use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
+use stable_swap_math::bn::U192;
 
 /// Event enum
 #[derive(Debug)]
@@ -19,6 +32,10 @@ pub enum Event {
     WithdrawA,
     /// Withdraw event (B)
     WithdrawB,
+    /// Flash loan event
+    FlashLoan,
+    /// Accrued admin fees claimed event
+    ClaimAdminFees,
 }
 
 /// Log event
@@ -36,6 +53,8 @@ pub fn log_event(
         Event::SwapBToA => "Event: SwapBToA",
         Event::WithdrawA => "Event: WithdrawA",
         Event::WithdrawB => "Event: WithdrawB",
+        Event::FlashLoan => "Event: FlashLoan",
+        Event::ClaimAdminFees => "Event: ClaimAdminFees",
     });
 
     solana_program::log::sol_log_64(
@@ -47,6 +66,83 @@ pub fn log_event(
     );
 }
 
+/// Log a Borsh-encoded event with `sol_log_data`
+pub fn emit_event(event: &events::Event) -> ProgramResult {
+    sol_log_data(&[&event.encode()?]);
+    Ok(())
+}
+
+/// Set a Borsh-encoded result as the return data of the instruction. Must be
+/// called after the last CPI of the instruction, which resets the return data.
+pub fn set_result<T: InstructionResult>(result: &T) -> ProgramResult {
+    set_return_data(&result.encode()?);
+    Ok(())
+}
+
+/// Log an [AdminEvent] for an admin instruction that was run
+pub fn emit_admin_event(
+    swap: &Pubkey,
+    signer: &Pubkey,
+    instruction: &AdminInstruction,
+) -> ProgramResult {
+    emit_event(&events::Event::Admin(AdminEvent {
+        swap: *swap,
+        signer: *signer,
+        instruction: instruction.pack(),
+    }))
+}
+
+/// Reads the reserves of the pool, excluding unclaimed admin fees
+pub fn read_reserves(
+    token_swap: &SwapInfo,
+    token_a_info: &AccountInfo,
+    token_b_info: &AccountInfo,
+) -> Result<Reserves, ProgramError> {
+    Ok(Reserves {
+        token_a: token_swap
+            .token_a
+            .pool_reserves(utils::unpack_token_account(&token_a_info.data.borrow())?.amount)
+            .ok_or(SwapError::CalculationFailure)?,
+        token_b: token_swap
+            .token_b
+            .pool_reserves(utils::unpack_token_account(&token_b_info.data.borrow())?.amount)
+            .ok_or(SwapError::CalculationFailure)?,
+    })
+}
+
+/// Reads the pool token supply and computes the virtual price of a pool with
+/// the given reserves and their invariant `d`
+pub fn read_pool_state(
+    reserves: Reserves,
+    d: U192,
+    pool_mint_info: &AccountInfo,
+) -> Result<PoolState, ProgramError> {
+    let pool_token_supply = utils::unpack_mint(&pool_mint_info.data.borrow())?.supply;
+    compute_pool_state(reserves, d, pool_token_supply)
+}
+
+/// Computes the virtual price of a pool with the given reserves, their
+/// invariant `d` and pool token supply
+pub fn compute_pool_state(
+    reserves: Reserves,
+    d: U192,
+    pool_token_supply: u64,
+) -> Result<PoolState, ProgramError> {
+    let virtual_price = if pool_token_supply == 0 {
+        0
+    } else {
+        d.checked_mul(U192::exp10(VIRTUAL_PRICE_DECIMALS.into()))
+            .and_then(|d| d.checked_div(pool_token_supply.into()))
+            .and_then(|virtual_price| virtual_price.to_u64())
+            .ok_or(SwapError::CalculationFailure)?
+    };
+    Ok(PoolState {
+        reserves,
+        pool_token_supply,
+        virtual_price,
+    })
+}
+
 pub fn log_keys_mismatch(msg: &str, left: Pubkey, right: Pubkey) {
     msg!(msg);
     msg!("Left:");
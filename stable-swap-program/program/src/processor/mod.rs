@@ -5,8 +5,15 @@ mod macros;
 
 mod admin;
 mod checks;
+mod circuit_breaker;
+mod factory;
+mod fee_tier;
+mod gate;
 mod logging;
+mod oracle;
+mod quote;
 mod swap;
+mod system;
 mod token;
 mod utils;
 
@@ -14,7 +21,7 @@ mod utils;
 #[allow(clippy::unwrap_used)]
 mod test_utils;
 
-use crate::instruction::AdminInstruction;
+use crate::instruction::{AdminInstruction, FactoryInstruction, GateInstruction};
 
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
@@ -26,9 +33,19 @@ impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = AdminInstruction::unpack(input)?;
         match instruction {
-            None => swap::process_swap_instruction(program_id, accounts, input),
+            None => match FactoryInstruction::unpack(input)? {
+                None => match GateInstruction::unpack(input)? {
+                    None => swap::process_swap_instruction(program_id, accounts, input),
+                    Some(gate_instruction) => {
+                        gate::process_gate_instruction(program_id, &gate_instruction, accounts)
+                    }
+                },
+                Some(factory_instruction) => {
+                    factory::process_factory_instruction(program_id, &factory_instruction, accounts)
+                }
+            },
             Some(admin_instruction) => {
-                admin::process_admin_instruction(&admin_instruction, accounts)
+                admin::process_admin_instruction(program_id, &admin_instruction, accounts)
             }
         }
     }
@@ -0,0 +1,293 @@
+//! Checks of swaps against the external price feed of the swap.
+
+use crate::{
+    curve::{StableSwap, PRICE_PRECISION},
+    error::SwapError,
+    oracle::{pyth_program, PriceFeed},
+    state::PriceOracle,
+};
+
+use solana_program::{
+    account_info::AccountInfo, clock::Slot, entrypoint::ProgramResult, msg,
+    program_error::ProgramError,
+};
+use stable_swap_math::bn::U192;
+
+use super::circuit_breaker::price_deviation_bps;
+
+/// Decodes the price feed of a price account owned by the Pyth program.
+pub fn load_price_feed(feed_info: &AccountInfo) -> Result<PriceFeed, ProgramError> {
+    if *feed_info.owner != pyth_program::id() {
+        msg!("Oracle: Price feed is not owned by the Pyth program");
+        return Err(SwapError::InvalidOracle.into());
+    }
+    Ok(PriceFeed::unpack(&feed_info.data.borrow())?)
+}
+
+/// Checks that the marginal price of the pool after a swap, given by the
+/// reserves `reserves_after` and their invariant `d_after`, is within the band
+/// of the price oracle, widened by the confidence interval of the feed. The
+/// feed of the oracle is passed in the optional accounts of the instruction.
+///
+/// Swaps leaving the price outside of the band are still accepted if they move
+/// it closer to the price of the feed than the reserves `reserves_before` with
+/// the invariant `d_before`, so that a pool outside of the band is not frozen.
+///
+/// A price outside of the band only rejects the swap. The failed transaction
+/// rolls back all of its writes, so the swap cannot be paused from here.
+#[allow(clippy::too_many_arguments)]
+pub fn check_oracle_price(
+    price_oracle: &PriceOracle,
+    feed_info: Option<&AccountInfo>,
+    invariant: &StableSwap,
+    slot: Slot,
+    reserves_before: (u64, u64),
+    d_before: U192,
+    reserves_after: (u64, u64),
+    d_after: U192,
+) -> ProgramResult {
+    if !price_oracle.is_enabled() {
+        return Ok(());
+    }
+    let feed_info = feed_info
+        .filter(|info| *info.key == price_oracle.feed)
+        .ok_or(SwapError::InvalidOracle)?;
+    let feed = load_price_feed(feed_info)?;
+    if !feed.is_trading || slot.saturating_sub(feed.pub_slot) > price_oracle.max_staleness_slots {
+        msg!(
+            "Oracle: Price of slot {} is stale at slot {}",
+            feed.pub_slot,
+            slot
+        );
+        return Err(SwapError::StaleOracle.into());
+    }
+
+    let oracle_price = feed
+        .scaled_price(
+            PRICE_PRECISION,
+            price_oracle.decimals_a,
+            price_oracle.decimals_b,
+        )
+        .ok_or(SwapError::InvalidOracle)?;
+    let band_bps = feed
+        .confidence_bps()
+        .and_then(|confidence_bps| {
+            confidence_bps.checked_add(price_oracle.max_deviation_bps.into())
+        })
+        .ok_or(SwapError::InvalidOracle)?;
+    let price = invariant
+        .compute_marginal_price_with_d(reserves_after.0, reserves_after.1, d_after)
+        .ok_or(SwapError::CalculationFailure)?;
+    let deviation_bps =
+        price_deviation_bps(price, oracle_price).ok_or(SwapError::CalculationFailure)?;
+    if deviation_bps <= u128::from(band_bps) {
+        return Ok(());
+    }
+
+    let price_before = invariant
+        .compute_marginal_price_with_d(reserves_before.0, reserves_before.1, d_before)
+        .ok_or(SwapError::CalculationFailure)?;
+    let deviation_before_bps =
+        price_deviation_bps(price_before, oracle_price).ok_or(SwapError::CalculationFailure)?;
+    if deviation_bps >= deviation_before_bps {
+        msg!(
+            "Oracle: Price {} deviates {} bps from {}",
+            price,
+            deviation_bps,
+            oracle_price
+        );
+        return Err(SwapError::OracleDeviationExceeded.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{processor::test_utils::*, state::SwapInfo};
+    use solana_program::{program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn test_price_oracle() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, 100, 1_000_000, 1_000_000, DEFAULT_TEST_FEES);
+        accounts.initialize_swap().unwrap();
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, 1_000_000, 0, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let mut swap_a_to_b =
+            |accounts: &mut SwapAccountInfo, amount_in: u64| -> Result<(), ProgramError> {
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+            };
+        let feed = PriceFeed {
+            price: 100_000_000,
+            conf: 10_000,
+            expo: -8,
+            is_trading: true,
+            pub_slot: 100,
+        };
+        let feed_key = pubkey_rand();
+        let mut feed_account = create_price_feed(&feed);
+
+        // only the admin can configure the price oracle
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_price_oracle(&feed_key, &mut feed_account, 30, 10)
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // the band and staleness must be set, and the feed must be a price account
+        {
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.set_price_oracle(&feed_key, &mut feed_account, 0, 10)
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.set_price_oracle(&feed_key, &mut feed_account, 30, 0)
+            );
+            assert_eq!(
+                Err(SwapError::InvalidOracle.into()),
+                accounts.set_price_oracle(&feed_key, &mut Account::default(), 30, 10)
+            );
+            let mut fake_feed_account = Account {
+                owner: pubkey_rand(),
+                ..feed_account.clone()
+            };
+            assert_eq!(
+                Err(SwapError::InvalidOracle.into()),
+                accounts.set_price_oracle(&feed_key, &mut fake_feed_account, 30, 10)
+            );
+        }
+
+        accounts
+            .set_price_oracle(&feed_key, &mut feed_account, 30, 10)
+            .unwrap();
+        let price_oracle = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .price_oracle;
+        assert_eq!(price_oracle.feed, feed_key);
+        assert_eq!(price_oracle.decimals_a, DEFAULT_TOKEN_DECIMALS);
+        assert_eq!(price_oracle.decimals_b, DEFAULT_TOKEN_DECIMALS);
+        set_current_slot(105);
+
+        // swaps and their quotes must pass the feed
+        assert_eq!(
+            Err(SwapError::InvalidOracle.into()),
+            swap_a_to_b(&mut accounts, 100_000)
+        );
+        assert_eq!(
+            Err(SwapError::InvalidOracle.into()),
+            accounts.quote_swap(&swap_token_a_key, &swap_token_b_key, 100_000)
+        );
+        accounts.price_feed = Some((feed_key, feed_account));
+        accounts
+            .quote_swap(&swap_token_a_key, &swap_token_b_key, 100_000)
+            .unwrap();
+        swap_a_to_b(&mut accounts, 100_000).unwrap();
+
+        // stale and halted prices are rejected
+        {
+            set_current_slot(111);
+            assert_eq!(
+                Err(SwapError::StaleOracle.into()),
+                swap_a_to_b(&mut accounts, 10_000)
+            );
+            accounts.price_feed = Some((
+                feed_key,
+                create_price_feed(&PriceFeed {
+                    is_trading: false,
+                    pub_slot: 111,
+                    ..feed
+                }),
+            ));
+            assert_eq!(
+                Err(SwapError::StaleOracle.into()),
+                swap_a_to_b(&mut accounts, 10_000)
+            );
+        }
+
+        // swaps moving the price beyond the band are rejected
+        accounts.price_feed = Some((
+            feed_key,
+            create_price_feed(&PriceFeed {
+                pub_slot: 111,
+                ..feed
+            }),
+        ));
+        assert_eq!(
+            Err(SwapError::OracleDeviationExceeded.into()),
+            swap_a_to_b(&mut accounts, 100_000)
+        );
+        assert_eq!(
+            Err(SwapError::OracleDeviationExceeded.into()),
+            accounts.quote_swap(&swap_token_a_key, &swap_token_b_key, 100_000)
+        );
+        swap_a_to_b(&mut accounts, 10_000).unwrap();
+
+        // the band is widened by the confidence interval of the feed
+        accounts.price_feed = Some((
+            feed_key,
+            create_price_feed(&PriceFeed {
+                conf: 2_000_000,
+                pub_slot: 111,
+                ..feed
+            }),
+        ));
+        swap_a_to_b(&mut accounts, 100_000).unwrap();
+
+        // outside of the band, swaps moving the price towards the feed are accepted
+        accounts.price_feed = Some((
+            feed_key,
+            create_price_feed(&PriceFeed {
+                price: 110_000_000,
+                pub_slot: 111,
+                ..feed
+            }),
+        ));
+        assert_eq!(
+            Err(SwapError::OracleDeviationExceeded.into()),
+            swap_a_to_b(&mut accounts, 10_000)
+        );
+        accounts.price_feed = Some((
+            feed_key,
+            create_price_feed(&PriceFeed {
+                price: 90_000_000,
+                pub_slot: 111,
+                ..feed
+            }),
+        ));
+        swap_a_to_b(&mut accounts, 10_000).unwrap();
+
+        // swaps are unchecked once the price oracle is removed
+        accounts
+            .set_price_oracle(&Pubkey::default(), &mut Account::default(), 0, 0)
+            .unwrap();
+        let price_oracle = SwapInfo::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .price_oracle;
+        assert_eq!(price_oracle, Default::default());
+        accounts.price_feed = None;
+        swap_a_to_b(&mut accounts, 100_000).unwrap();
+        set_current_slot(0);
+    }
+}
@@ -0,0 +1,681 @@
+//! Module for processing read-only quote instructions.
+//!
+//! Quotes validate the pool accounts like the instructions they quote and
+//! compute the same amounts, but make no state changes. The result is set as
+//! the return data of the instruction.
+
+use crate::{
+    error::SwapError,
+    events::Reserves,
+    processor::utils,
+    results::{DepositResult, SwapResult, WithdrawOneResult},
+    state::{AdminFeeMode, PauseFlags, SwapInfo},
+};
+use stable_swap_math::curve::StableSwap;
+use stable_swap_math::math::FeeCalculator;
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+
+use super::checks::*;
+use super::circuit_breaker;
+use super::fee_tier;
+use super::gate;
+use super::logging::*;
+use super::oracle;
+use super::token;
+
+/// Loads the swap, which must be owned by the program
+fn load_swap(program_id: &Pubkey, swap_info: &AccountInfo) -> Result<SwapInfo, ProgramError> {
+    if swap_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    SwapInfo::unpack(&swap_info.data.borrow())
+}
+
+/// Creates the invariant of the swap at the current time
+fn load_invariant(token_swap: &SwapInfo) -> Result<StableSwap, ProgramError> {
+    let clock = Clock::get()?;
+    Ok(StableSwap::new(
+        token_swap.initial_amp_factor,
+        token_swap.target_amp_factor,
+        clock.unix_timestamp,
+        token_swap.start_ramp_ts,
+        token_swap.stop_ramp_ts,
+    ))
+}
+
+/// Amount leaving the reserves with a withdrawal of `amount` charging `admin_fee`
+fn reserves_outflow(token_swap: &SwapInfo, amount: u64, admin_fee: u64) -> Option<u64> {
+    match token_swap.admin_fee_mode {
+        AdminFeeMode::Transfer | AdminFeeMode::Accrue => amount.checked_add(admin_fee),
+        // left in the pool until pool tokens are minted for it
+        AdminFeeMode::LpMint => Some(amount),
+    }
+}
+
+/// Processes a [QuoteSwap](enum.Instruction.html).
+pub fn process_quote_swap(
+    program_id: &Pubkey,
+    amount_in: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let swap_source_info = next_account_info(account_info_iter)?;
+    let swap_destination_info = next_account_info(account_info_iter)?;
+
+    if *swap_source_info.key == *swap_destination_info.key {
+        return Err(SwapError::InvalidInput.into());
+    }
+
+    let mut token_swap = load_swap(program_id, swap_info)?;
+    check_not_paused(&token_swap, PauseFlags::SWAP)?;
+    // optional accounts have the positions of those of swaps
+    let optional_accounts = token::optional_accounts(&token_swap, accounts, 3);
+    gate::check_quote_allowlisted(
+        program_id,
+        &token_swap,
+        token::optional_account(program_id, optional_accounts, 1),
+    )?;
+
+    let is_a_to_b = *swap_source_info.key == token_swap.token_a.reserves;
+    let (source_token, destination_token) = if is_a_to_b {
+        (&token_swap.token_a, &token_swap.token_b)
+    } else if *swap_source_info.key == token_swap.token_b.reserves {
+        (&token_swap.token_b, &token_swap.token_a)
+    } else {
+        return Err(SwapError::IncorrectSwapAccount.into());
+    };
+    check_reserves_match(destination_token, swap_destination_info.key)?;
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+    let (source_mint, destination_mint) = if is_a_to_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    };
+
+    let swap_source_amount = source_token
+        .pool_reserves(utils::unpack_token_account(&swap_source_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let swap_destination_amount = destination_token
+        .pool_reserves(utils::unpack_token_account(&swap_destination_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+
+    let fees = fee_tier::quote_fees(
+        program_id,
+        swap_info.key,
+        &token_swap,
+        token::optional_account(program_id, optional_accounts, 0),
+    )?;
+    let invariant = load_invariant(&token_swap)?;
+    let d = invariant
+        .compute_d_with_guess(
+            swap_source_amount,
+            swap_destination_amount,
+            token_swap.last_d,
+            token_swap.last_sum_x,
+            token_swap.last_amp_factor,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    let amount_in_received = token::post_fee_amount(source_mint.as_ref(), amount_in)?;
+    let result = invariant
+        .swap_to_with_d(
+            amount_in_received,
+            swap_source_amount,
+            swap_destination_amount,
+            d,
+            &fees,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+
+    let source_amount_after = swap_source_amount
+        .checked_add(amount_in_received)
+        .ok_or(SwapError::CalculationFailure)?;
+    let destination_amount_after =
+        reserves_outflow(&token_swap, result.amount_swapped, result.admin_fee)
+            .and_then(|outflow| swap_destination_amount.checked_sub(outflow))
+            .ok_or(SwapError::CalculationFailure)?;
+    let (reserves_before, reserves_after) = if is_a_to_b {
+        (
+            (swap_source_amount, swap_destination_amount),
+            (source_amount_after, destination_amount_after),
+        )
+    } else {
+        (
+            (swap_destination_amount, swap_source_amount),
+            (destination_amount_after, source_amount_after),
+        )
+    };
+    // quotes fail like the swap would on the circuit breaker and the price
+    // oracle, without recording the reference price of a new window
+    let d_after = invariant
+        .compute_d_from(reserves_after.0, reserves_after.1, d)
+        .ok_or(SwapError::CalculationFailure)?;
+    let slot = Clock::get()?.slot;
+    circuit_breaker::check_price_deviation(
+        &mut token_swap,
+        &invariant,
+        slot,
+        reserves_before,
+        d,
+        reserves_after,
+        d_after,
+    )?;
+    oracle::check_oracle_price(
+        &token_swap.price_oracle,
+        token::optional_account(program_id, optional_accounts, 2),
+        &invariant,
+        slot,
+        reserves_before,
+        d,
+        reserves_after,
+        d_after,
+    )?;
+
+    // the user only receives the output net of any transfer fee
+    let amount_out_received =
+        token::post_fee_amount(destination_mint.as_ref(), result.amount_swapped)?;
+    set_result(&SwapResult {
+        amount_in,
+        amount_out: amount_out_received,
+        fee: result.fee,
+        admin_fee: result.admin_fee,
+        // quotes a swap without a referrer
+        referral_fee: 0,
+        reserves: reserves_after.into(),
+    })
+}
+
+/// Processes a [QuoteDeposit](enum.Instruction.html).
+pub fn process_quote_deposit(
+    program_id: &Pubkey,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+
+    let token_swap = load_swap(program_id, swap_info)?;
+    check_not_paused(&token_swap, PauseFlags::DEPOSIT)?;
+    check_reserves_match(&token_swap.token_a, token_a_info.key)?;
+    check_reserves_match(&token_swap.token_b, token_b_info.key)?;
+    check_keys_equal!(
+        *pool_mint_info.key,
+        token_swap.pool_mint,
+        "Pool mint",
+        SwapError::IncorrectMint
+    );
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+
+    let reserves_before = read_reserves(&token_swap, token_a_info, token_b_info)?;
+    let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
+
+    let invariant = load_invariant(&token_swap)?;
+    let token_a_received = token::post_fee_amount(mint_a.as_ref(), token_a_amount)?;
+    let token_b_received = token::post_fee_amount(mint_b.as_ref(), token_b_amount)?;
+    let d_before = invariant
+        .compute_d(reserves_before.token_a, reserves_before.token_b)
+        .ok_or(SwapError::CalculationFailure)?;
+    let (mint_amount, d_after) = invariant
+        .compute_mint_amount_for_deposit_with_d(
+            token_a_received,
+            token_b_received,
+            reserves_before.token_a,
+            reserves_before.token_b,
+            d_before,
+            pool_mint.supply,
+            &token_swap.fees,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+
+    let reserves = Reserves {
+        token_a: reserves_before
+            .token_a
+            .checked_add(token_a_received)
+            .ok_or(SwapError::CalculationFailure)?,
+        token_b: reserves_before
+            .token_b
+            .checked_add(token_b_received)
+            .ok_or(SwapError::CalculationFailure)?,
+    };
+    let pool_token_supply = pool_mint
+        .supply
+        .checked_add(mint_amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    set_result(&DepositResult {
+        token_a_amount,
+        token_b_amount,
+        pool_token_amount: mint_amount,
+        pool: compute_pool_state(reserves, d_after, pool_token_supply)?,
+    })
+}
+
+/// Processes a [QuoteWithdrawOne](enum.Instruction.html).
+pub fn process_quote_withdraw_one(
+    program_id: &Pubkey,
+    pool_token_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let base_token_info = next_account_info(account_info_iter)?;
+    let quote_token_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+
+    if *base_token_info.key == *quote_token_info.key {
+        return Err(SwapError::InvalidInput.into());
+    }
+
+    let token_swap = load_swap(program_id, swap_info)?;
+    check_not_paused(&token_swap, PauseFlags::WITHDRAW_ONE)?;
+
+    let is_base_a = *base_token_info.key == token_swap.token_a.reserves;
+    let (base_token, quote_token) = if is_base_a {
+        (&token_swap.token_a, &token_swap.token_b)
+    } else if *base_token_info.key == token_swap.token_b.reserves {
+        (&token_swap.token_b, &token_swap.token_a)
+    } else {
+        msg!("Unknown base token:");
+        base_token_info.key.log();
+        return Err(SwapError::IncorrectSwapAccount.into());
+    };
+    check_reserves_match(quote_token, quote_token_info.key)?;
+    check_keys_equal!(
+        *pool_mint_info.key,
+        token_swap.pool_mint,
+        "Pool mint",
+        SwapError::IncorrectMint
+    );
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+    let base_mint = if is_base_a { mint_a } else { mint_b };
+
+    let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
+    let base_token_amount = base_token
+        .pool_reserves(utils::unpack_token_account(&base_token_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let quote_token_amount = quote_token
+        .pool_reserves(utils::unpack_token_account(&quote_token_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+
+    let invariant = load_invariant(&token_swap)?;
+    let d_before = invariant
+        .compute_d(base_token_amount, quote_token_amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let (dy, dy_fee) = invariant
+        .compute_withdraw_one_with_d(
+            pool_token_amount,
+            pool_mint.supply,
+            base_token_amount,
+            quote_token_amount,
+            d_before,
+            &token_swap.fees,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    let withdraw_fee = token_swap
+        .fees
+        .withdraw_fee(dy)
+        .ok_or(SwapError::CalculationFailure)?;
+    let token_amount = dy
+        .checked_sub(withdraw_fee)
+        .ok_or(SwapError::CalculationFailure)?;
+    let admin_trade_fee = token_swap
+        .fees
+        .admin_trade_fee(dy_fee)
+        .ok_or(SwapError::CalculationFailure)?;
+    let admin_withdraw_fee = token_swap
+        .fees
+        .admin_withdraw_fee(withdraw_fee)
+        .ok_or(SwapError::CalculationFailure)?;
+    let admin_fee = admin_trade_fee
+        .checked_add(admin_withdraw_fee)
+        .ok_or(SwapError::CalculationFailure)?;
+
+    let base_amount_after = reserves_outflow(&token_swap, token_amount, admin_fee)
+        .and_then(|outflow| base_token_amount.checked_sub(outflow))
+        .ok_or(SwapError::CalculationFailure)?;
+    let reserves = if is_base_a {
+        Reserves {
+            token_a: base_amount_after,
+            token_b: quote_token_amount,
+        }
+    } else {
+        Reserves {
+            token_a: quote_token_amount,
+            token_b: base_amount_after,
+        }
+    };
+    let d_after = invariant
+        .compute_d_from(reserves.token_a, reserves.token_b, d_before)
+        .ok_or(SwapError::CalculationFailure)?;
+    let pool_token_supply = pool_mint
+        .supply
+        .checked_sub(pool_token_amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    set_result(&WithdrawOneResult {
+        token_amount: token::post_fee_amount(base_mint.as_ref(), token_amount)?,
+        fee: dy_fee,
+        pool_token_amount,
+        pool: compute_pool_state(reserves, d_after, pool_token_supply)?,
+    })
+}
+
+/// Processes a [GetVirtualPrice](enum.Instruction.html).
+pub fn process_get_virtual_price(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+
+    let token_swap = load_swap(program_id, swap_info)?;
+    check_reserves_match(&token_swap.token_a, token_a_info.key)?;
+    check_reserves_match(&token_swap.token_b, token_b_info.key)?;
+    check_keys_equal!(
+        *pool_mint_info.key,
+        token_swap.pool_mint,
+        "Pool mint",
+        SwapError::IncorrectMint
+    );
+
+    let reserves = read_reserves(&token_swap, token_a_info, token_b_info)?;
+    let invariant = load_invariant(&token_swap)?;
+    let d = invariant
+        .compute_d_with_guess(
+            reserves.token_a,
+            reserves.token_b,
+            token_swap.last_d,
+            token_swap.last_sum_x,
+            token_swap.last_amp_factor,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    set_result(&read_pool_state(reserves, d, pool_mint_info)?)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        curve::{MIN_AMP, ZERO_TS},
+        events::PoolState,
+        instruction::{quote_swap, quote_withdraw_one, with_token_2022_mints},
+        processor::test_utils::*,
+        results::{InstructionResult, WithdrawOneResult},
+        token_2022::{self, TransferFee},
+    };
+    use solana_program::program_error::ProgramError;
+
+    fn run_quotes_match_execution(admin_fee_mode: AdminFeeMode) {
+        let user_key = pubkey_rand();
+        let depositor_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000,
+            1_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let (_, _, _, _, lp_admin_fee_key, lp_admin_fee_account) =
+            accounts.setup_token_accounts(&user_key, &user_key, 0, 0, 0);
+        accounts
+            .set_admin_fee_account(&lp_admin_fee_key, &lp_admin_fee_account)
+            .unwrap();
+        accounts.set_admin_fee_mode(admin_fee_mode).unwrap();
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &depositor_key, 11_000, 10_000, 0);
+
+        // quotes make no state changes
+        let swap_account = accounts.swap_account.clone();
+        accounts.quote_deposit(10_000, 5_000).unwrap();
+        let quote = take_result::<DepositResult>();
+        assert_eq!(accounts.swap_account, swap_account);
+        accounts
+            .deposit(
+                &depositor_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                10_000,
+                5_000,
+                0,
+            )
+            .unwrap();
+        assert_eq!(quote, take_result::<DepositResult>());
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        accounts
+            .quote_swap(&swap_token_a_key, &swap_token_b_key, 1_000)
+            .unwrap();
+        let quote = take_result::<SwapResult>();
+        accounts
+            .swap(
+                &depositor_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                1_000,
+                0,
+            )
+            .unwrap();
+        assert_eq!(quote, take_result::<SwapResult>());
+
+        accounts.quote_withdraw_one(1_000).unwrap();
+        let quote = take_result::<WithdrawOneResult>();
+        accounts
+            .withdraw_one(
+                &depositor_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                1_000,
+                0,
+            )
+            .unwrap();
+        assert_eq!(quote, take_result::<WithdrawOneResult>());
+
+        accounts.get_virtual_price().unwrap();
+        assert_eq!(take_result::<PoolState>(), quote.pool);
+        assert!(quote.pool.virtual_price > 10_u64.pow(12));
+    }
+
+    #[test]
+    fn test_quotes_match_execution() {
+        run_quotes_match_execution(AdminFeeMode::Transfer);
+        run_quotes_match_execution(AdminFeeMode::Accrue);
+        run_quotes_match_execution(AdminFeeMode::LpMint);
+    }
+
+    #[test]
+    fn test_quote_transfer_fees() {
+        let user_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000,
+            1_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.convert_to_token_2022();
+        accounts
+            .initialize_swap_with_token_program(&token_2022::id())
+            .unwrap();
+
+        let quote = |accounts: &mut SwapAccountInfo| {
+            let mut mint_a_account = accounts.token_a_mint_account.clone();
+            let mut mint_b_account = accounts.token_b_mint_account.clone();
+            do_process_instruction_at_time(
+                with_token_2022_mints(
+                    quote_swap(
+                        &accounts.swap_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        1_000,
+                    )
+                    .unwrap(),
+                    &accounts.token_a_mint_key,
+                    &accounts.token_b_mint_key,
+                ),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut accounts.token_a_account.clone(),
+                    &mut accounts.token_b_account.clone(),
+                    &mut mint_a_account.clone(),
+                    &mut mint_b_account.clone(),
+                ],
+                ZERO_TS,
+            )
+            .unwrap();
+            let swap = take_result::<SwapResult>();
+            do_process_instruction_at_time(
+                with_token_2022_mints(
+                    quote_withdraw_one(
+                        &accounts.swap_key,
+                        &accounts.token_b_key,
+                        &accounts.token_a_key,
+                        &accounts.pool_mint_key,
+                        1_000,
+                    )
+                    .unwrap(),
+                    &accounts.token_a_mint_key,
+                    &accounts.token_b_mint_key,
+                ),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut accounts.token_b_account.clone(),
+                    &mut accounts.token_a_account.clone(),
+                    &mut accounts.pool_mint_account.clone(),
+                    &mut mint_a_account,
+                    &mut mint_b_account,
+                ],
+                ZERO_TS,
+            )
+            .unwrap();
+            (swap, take_result::<WithdrawOneResult>())
+        };
+        let (gross_swap, gross_withdraw_one) = quote(&mut accounts);
+
+        // token B charges a transfer fee on the amounts paid out
+        let transfer_fee = TransferFee {
+            epoch: 0,
+            maximum_fee: u64::MAX,
+            transfer_fee_basis_points: 100,
+        };
+        set_transfer_fee(&mut accounts.token_b_mint_account, &transfer_fee);
+
+        let (swap, withdraw_one) = quote(&mut accounts);
+        assert_eq!(
+            swap.amount_out,
+            transfer_fee
+                .calculate_post_fee_amount(gross_swap.amount_out)
+                .unwrap()
+        );
+        assert!(swap.amount_out < gross_swap.amount_out);
+        assert_eq!(swap.fee, gross_swap.fee);
+        assert_eq!(
+            withdraw_one.token_amount,
+            transfer_fee
+                .calculate_post_fee_amount(gross_withdraw_one.token_amount)
+                .unwrap()
+        );
+        assert!(withdraw_one.token_amount < gross_withdraw_one.token_amount);
+        assert_eq!(withdraw_one.fee, gross_withdraw_one.fee);
+    }
+
+    #[test]
+    fn test_quote_validation() {
+        let user_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000,
+            1_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // same reserves on both sides
+        assert_eq!(
+            Err(SwapError::InvalidInput.into()),
+            accounts.quote_swap(&swap_token_a_key, &swap_token_a_key, 100)
+        );
+
+        // reserves not of the swap
+        {
+            let wrong_key = pubkey_rand();
+            let old_key = accounts.token_b_key;
+            accounts.token_b_key = wrong_key;
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.quote_deposit(100, 100)
+            );
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.get_virtual_price()
+            );
+            accounts.token_b_key = old_key;
+        }
+
+        // pool mint not of the swap
+        {
+            let old_key = accounts.pool_mint_key;
+            accounts.pool_mint_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::IncorrectMint.into()),
+                accounts.quote_withdraw_one(100)
+            );
+            accounts.pool_mint_key = old_key;
+        }
+
+        // swap not owned by the program
+        {
+            let old_owner = accounts.swap_account.owner;
+            accounts.swap_account.owner = pubkey_rand();
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.get_virtual_price()
+            );
+            accounts.swap_account.owner = old_owner;
+        }
+
+        // paused operations cannot be quoted
+        accounts.set_pause_flags(PauseFlags::SWAP).unwrap();
+        assert_eq!(
+            Err(SwapError::IsPaused.into()),
+            accounts.quote_swap(&swap_token_a_key, &swap_token_b_key, 100)
+        );
+        accounts.quote_deposit(100, 100).unwrap();
+        accounts.get_virtual_price().unwrap();
+        let pool = take_result::<PoolState>();
+        assert_eq!(pool.reserves.token_a, 1_000_000);
+        assert_eq!(PoolState::decode(&pool.encode().unwrap()).unwrap(), pool);
+    }
+}
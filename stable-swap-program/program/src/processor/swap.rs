@@ -2,31 +2,49 @@
 
 use crate::{
     error::SwapError,
-    fees::Fees,
+    events::{self, DepositEvent, Reserves, SwapEvent, WithdrawEvent, WithdrawOneEvent},
+    fees::{ExtraFees, Fees},
     instruction::{
-        DepositData, InitializeData, SwapData, SwapInstruction, WithdrawData, WithdrawOneData,
+        DepositData, FlashLoanData, InitializeData, InitializeV2Data, QuoteDepositData,
+        QuoteSwapData, QuoteWithdrawOneData, ReceiveFlashLoanData, SwapData, SwapInstruction,
+        WithdrawData, WithdrawOneData,
     },
+    pda,
     pool_converter::PoolTokenConverter,
     processor::utils,
-    state::{SwapInfo, SwapTokenInfo},
+    results::{DepositResult, FlashLoanResult, SwapResult, WithdrawOneResult, WithdrawResult},
+    state::{
+        AdminFeeMode, CircuitBreaker, PauseFlags, PriceOracle, RoleKeys, SwapInfo, SwapTokenInfo,
+    },
+    token_2022::{self, MintExtensions},
 };
+use stable_swap_math::bn::U192;
 use stable_swap_math::curve::{StableSwap, MAX_AMP, MIN_AMP, ZERO_TS};
-use stable_swap_math::math::FeeCalculator;
+use stable_swap_math::math::{ExtraFeeCalculator, FeeCalculator};
 
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     msg,
+    program::invoke,
     program_error::ProgramError,
     program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
+    system_program,
     sysvar::{clock::Clock, Sysvar},
 };
 
 use super::checks::*;
+use super::circuit_breaker;
+use super::fee_tier;
+use super::gate;
 use super::logging::*;
-use super::token;
+use super::oracle;
+use super::quote;
+use super::system;
+use super::token::{self, TransferMint};
 
 pub fn process_swap_instruction(
     program_id: &Pubkey,
@@ -48,7 +66,7 @@ pub fn process_swap_instruction(
             minimum_amount_out,
         }) => {
             msg!("Instruction: Swap");
-            process_swap(program_id, amount_in, minimum_amount_out, accounts)
+            process_swap(program_id, amount_in, minimum_amount_out, false, accounts)
         }
         SwapInstruction::Deposit(DepositData {
             token_a_amount,
@@ -90,6 +108,56 @@ pub fn process_swap_instruction(
                 accounts,
             )
         }
+        SwapInstruction::FlashLoan(FlashLoanData { amount }) => {
+            msg!("Instruction: FlashLoan");
+            process_flash_loan(program_id, amount, accounts)
+        }
+        SwapInstruction::ClaimAdminFees => {
+            msg!("Instruction: ClaimAdminFees");
+            process_claim_admin_fees(program_id, accounts)
+        }
+        SwapInstruction::InitializeV2(InitializeV2Data {
+            amp_factor,
+            fees,
+            token_a_amount,
+            token_b_amount,
+        }) => {
+            msg!("Instruction: InitV2");
+            process_initialize_v2(
+                program_id,
+                amp_factor,
+                fees,
+                token_a_amount,
+                token_b_amount,
+                accounts,
+            )
+        }
+        SwapInstruction::QuoteSwap(QuoteSwapData { amount_in }) => {
+            msg!("Instruction: QuoteSwap");
+            quote::process_quote_swap(program_id, amount_in, accounts)
+        }
+        SwapInstruction::QuoteDeposit(QuoteDepositData {
+            token_a_amount,
+            token_b_amount,
+        }) => {
+            msg!("Instruction: QuoteDeposit");
+            quote::process_quote_deposit(program_id, token_a_amount, token_b_amount, accounts)
+        }
+        SwapInstruction::QuoteWithdrawOne(QuoteWithdrawOneData { pool_token_amount }) => {
+            msg!("Instruction: QuoteWithdrawOne");
+            quote::process_quote_withdraw_one(program_id, pool_token_amount, accounts)
+        }
+        SwapInstruction::GetVirtualPrice => {
+            msg!("Instruction: GetVirtualPrice");
+            quote::process_get_virtual_price(program_id, accounts)
+        }
+        SwapInstruction::SwapWithReferral(SwapData {
+            amount_in,
+            minimum_amount_out,
+        }) => {
+            msg!("Instruction: SwapWithReferral");
+            process_swap(program_id, amount_in, minimum_amount_out, true, accounts)
+        }
     }
 }
 
@@ -119,7 +187,15 @@ fn process_initialize(
         msg!("Invalid amp factor: {}", amp_factor);
         return Err(SwapError::InvalidInput.into());
     }
+    if let Err(err) = fees.validate() {
+        msg!("Invalid fees: {:?}", fees);
+        return Err(err.into());
+    }
 
+    // new swaps use the versioned layout
+    if SwapInfo::is_legacy_layout(&swap_info.data.borrow()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
     let token_swap = SwapInfo::unpack_unchecked(&swap_info.data.borrow())?;
     if token_swap.is_initialized {
         return Err(SwapError::AlreadyInUse.into());
@@ -225,6 +301,30 @@ fn process_initialize(
         SwapError::InvalidAdmin
     );
 
+    token::check_supported_program(token_program_info.key)?;
+    for info in [
+        token_a_mint_info,
+        token_a_info,
+        token_b_mint_info,
+        token_b_info,
+        pool_mint_info,
+        admin_fee_a_info,
+        admin_fee_b_info,
+        destination_info,
+    ] {
+        check_keys_equal!(
+            *info.owner,
+            *token_program_info.key,
+            "Token program",
+            ProgramError::IncorrectProgramId
+        );
+    }
+    if *token_program_info.key == token_2022::id() {
+        for mint_info in [token_a_mint_info, token_b_mint_info, pool_mint_info] {
+            MintExtensions::unpack(&mint_info.data.borrow())?.check_supported_by_pool()?;
+        }
+    }
+
     // amp_factor == initial_amp_factor == target_amp_factor on init
     let invariant = StableSwap::new(amp_factor, amp_factor, ZERO_TS, ZERO_TS, ZERO_TS);
     // Compute amount of LP tokens to mint for bootstrapper
@@ -244,7 +344,7 @@ fn process_initialize(
 
     let obj = SwapInfo {
         is_initialized: true,
-        is_paused: false,
+        pause_flags: PauseFlags::NONE,
         nonce,
         initial_amp_factor: amp_factor,
         target_amp_factor: amp_factor,
@@ -258,15 +358,40 @@ fn process_initialize(
             mint: token_a.mint,
             admin_fees: *admin_fee_a_info.key,
             index: 0,
+            accrued_admin_fees: 0,
         },
         token_b: SwapTokenInfo {
             reserves: *token_b_info.key,
             mint: token_b.mint,
             admin_fees: *admin_fee_b_info.key,
             index: 1,
+            accrued_admin_fees: 0,
         },
         pool_mint: *pool_mint_info.key,
         fees,
+        // flash loans are enabled with SetExtraFees
+        extra_fees: ExtraFees::default(),
+        admin_fee_mode: AdminFeeMode::Transfer,
+        lp_admin_fees: Pubkey::default(),
+        lp_admin_fee_last_d: 0,
+        lp_admin_fee_last_supply: 0,
+        lp_admin_fee_last_amp_factor: 0,
+        token_program_id: *token_program_info.key,
+        guardian_key: Pubkey::default(),
+        roles: RoleKeys::default(),
+        timelock_delay: 0,
+        admin_queue: Pubkey::default(),
+        is_admin_renounced: false,
+        accepts_global_fee_tiers: false,
+        gate: Pubkey::default(),
+        circuit_breaker: CircuitBreaker::default(),
+        price_oracle: PriceOracle::default(),
+        last_d: mint_amount,
+        last_amp_factor: amp_factor,
+        last_sum_x: token_a
+            .amount
+            .checked_add(token_b.amount)
+            .ok_or(SwapError::CalculationFailure)?,
     };
     SwapInfo::pack(obj, &mut swap_info.data.borrow_mut())?;
 
@@ -280,11 +405,240 @@ fn process_initialize(
     Ok(())
 }
 
+/// Processes an [InitializeV2](enum.Instruction.html).
+fn process_initialize_v2(
+    program_id: &Pubkey,
+    amp_factor: u64,
+    fees: Fees,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    create_pool(
+        program_id,
+        None,
+        amp_factor,
+        fees,
+        token_a_amount,
+        token_b_amount,
+        accounts,
+    )
+}
+
+/// Creates the accounts of a pool at program derived addresses and initializes
+/// the pool. The swap of a pool created by the factory is also derived from
+/// its amp tier.
+pub fn create_pool(
+    program_id: &Pubkey,
+    amp_tier: Option<u8>,
+    amp_factor: u64,
+    fees: Fees,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer_info = next_account_info(account_info_iter)?;
+    let swap_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let admin_key_info = next_account_info(account_info_iter)?;
+    let admin_fee_a_info = next_account_info(account_info_iter)?;
+    let admin_fee_b_info = next_account_info(account_info_iter)?;
+    let token_a_mint_info = next_account_info(account_info_iter)?;
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_mint_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+    let source_a_info = next_account_info(account_info_iter)?;
+    let source_b_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    token::check_supported_program(token_program_info.key)?;
+    check_keys_equal!(
+        *system_program_info.key,
+        system_program::id(),
+        "System program",
+        ProgramError::IncorrectProgramId
+    );
+
+    if token_a_mint_info.key >= token_b_mint_info.key {
+        return Err(SwapError::UnsortedMints.into());
+    }
+    let (swap_key, swap_bump) = match amp_tier {
+        None => pda::find_swap_address(
+            program_id,
+            token_a_mint_info.key,
+            token_b_mint_info.key,
+            admin_key_info.key,
+            amp_factor,
+        ),
+        Some(amp_tier) => pda::find_factory_swap_address(
+            program_id,
+            token_a_mint_info.key,
+            token_b_mint_info.key,
+            amp_tier,
+        ),
+    };
+    check_keys_equal!(
+        *swap_info.key,
+        swap_key,
+        "Swap",
+        SwapError::InvalidProgramAddress
+    );
+    let (authority_key, nonce) = pda::find_swap_authority(program_id, &swap_key);
+    check_keys_equal!(
+        *authority_info.key,
+        authority_key,
+        "Swap authority",
+        SwapError::InvalidProgramAddress
+    );
+    let (token_a_key, token_a_bump) =
+        pda::find_reserves_address(program_id, &swap_key, token_a_mint_info.key);
+    check_keys_equal!(
+        *token_a_info.key,
+        token_a_key,
+        "Token A reserves",
+        SwapError::InvalidProgramAddress
+    );
+    let (token_b_key, token_b_bump) =
+        pda::find_reserves_address(program_id, &swap_key, token_b_mint_info.key);
+    check_keys_equal!(
+        *token_b_info.key,
+        token_b_key,
+        "Token B reserves",
+        SwapError::InvalidProgramAddress
+    );
+    let (pool_mint_key, pool_mint_bump) = pda::find_pool_mint_address(program_id, &swap_key);
+    check_keys_equal!(
+        *pool_mint_info.key,
+        pool_mint_key,
+        "Pool mint",
+        SwapError::InvalidProgramAddress
+    );
+    let token_a_mint = utils::unpack_mint(&token_a_mint_info.data.borrow())?;
+
+    let tier_seed = amp_tier.map(|amp_tier| [amp_tier]);
+    let amp_seed = amp_factor.to_le_bytes();
+    let swap_bump_seed = [swap_bump];
+    let mut swap_seeds = vec![
+        pda::SWAP_SEED,
+        token_a_mint_info.key.as_ref(),
+        token_b_mint_info.key.as_ref(),
+    ];
+    match tier_seed.as_ref() {
+        Some(seed) => swap_seeds.push(&seed[..]),
+        None => swap_seeds.extend([admin_key_info.key.as_ref(), &amp_seed[..]]),
+    }
+    swap_seeds.push(&swap_bump_seed);
+    system::create_account(
+        payer_info.clone(),
+        swap_info.clone(),
+        system_program_info.clone(),
+        program_id,
+        SwapInfo::LEN,
+        &[&swap_seeds],
+    )?;
+    for (reserves_info, mint_info, bump) in [
+        (token_a_info, token_a_mint_info, token_a_bump),
+        (token_b_info, token_b_mint_info, token_b_bump),
+    ] {
+        system::create_account(
+            payer_info.clone(),
+            reserves_info.clone(),
+            system_program_info.clone(),
+            token_program_info.key,
+            token::account_len(token_program_info.key, mint_info)?,
+            &[&[
+                pda::RESERVES_SEED,
+                swap_key.as_ref(),
+                mint_info.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+        token::initialize_account(
+            token_program_info.clone(),
+            reserves_info.clone(),
+            mint_info.clone(),
+            &authority_key,
+        )?;
+    }
+    // the pool token mint and its accounts have no extensions
+    system::create_account(
+        payer_info.clone(),
+        pool_mint_info.clone(),
+        system_program_info.clone(),
+        token_program_info.key,
+        spl_token::state::Mint::LEN,
+        &[&[pda::POOL_MINT_SEED, swap_key.as_ref(), &[pool_mint_bump]]],
+    )?;
+    token::initialize_mint(
+        token_program_info.clone(),
+        pool_mint_info.clone(),
+        &authority_key,
+        token_a_mint.decimals,
+    )?;
+    system::create_account(
+        payer_info.clone(),
+        destination_info.clone(),
+        system_program_info.clone(),
+        token_program_info.key,
+        spl_token::state::Account::LEN,
+        &[],
+    )?;
+    token::initialize_account(
+        token_program_info.clone(),
+        destination_info.clone(),
+        pool_mint_info.clone(),
+        payer_info.key,
+    )?;
+
+    token::transfer_as_user(
+        token_program_info.clone(),
+        source_a_info.clone(),
+        token_a_info.clone(),
+        payer_info.clone(),
+        token_a_amount,
+        token::load_transfer_mint(token_program_info.key, token_a_mint_info)?.as_ref(),
+    )?;
+    token::transfer_as_user(
+        token_program_info.clone(),
+        source_b_info.clone(),
+        token_b_info.clone(),
+        payer_info.clone(),
+        token_b_amount,
+        token::load_transfer_mint(token_program_info.key, token_b_mint_info)?.as_ref(),
+    )?;
+
+    process_initialize(
+        program_id,
+        nonce,
+        amp_factor,
+        fees,
+        &[
+            swap_info.clone(),
+            authority_info.clone(),
+            admin_key_info.clone(),
+            admin_fee_a_info.clone(),
+            admin_fee_b_info.clone(),
+            token_a_mint_info.clone(),
+            token_a_info.clone(),
+            token_b_mint_info.clone(),
+            token_b_info.clone(),
+            pool_mint_info.clone(),
+            destination_info.clone(),
+            token_program_info.clone(),
+        ],
+    )
+}
+
 /// Processes an [Swap](enum.Instruction.html).
 fn process_swap(
     program_id: &Pubkey,
     amount_in: u64,
     minimum_amount_out: u64,
+    with_referral: bool,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
     if amount_in == 0 {
@@ -306,10 +660,58 @@ fn process_swap(
         return Err(SwapError::InvalidInput.into());
     }
 
-    let token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
-    if token_swap.is_paused {
-        return Err(SwapError::IsPaused.into());
+    let mut token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    check_not_paused(&token_swap, PauseFlags::SWAP)?;
+    check_token_program(&token_swap, token_program_info.key)?;
+    // optional accounts have fixed positions, see [SwapInstruction::Swap]
+    let optional_accounts = token::optional_accounts(&token_swap, accounts, 9);
+    let (referrer_info, optional_accounts) = if with_referral {
+        (
+            token::optional_account(program_id, optional_accounts, 0),
+            optional_accounts.get(1..).unwrap_or_default(),
+        )
+    } else {
+        (None, optional_accounts)
+    };
+    gate::check_allowlisted(
+        program_id,
+        &token_swap,
+        user_authority_info.key,
+        token::optional_account(program_id, optional_accounts, 1),
+    )?;
+    let fee_tier_info = token::optional_account(program_id, optional_accounts, 0);
+    let pool_mint_info = token::optional_account(program_id, optional_accounts, 3);
+    if let Some(pool_mint_info) = pool_mint_info {
+        check_keys_equal!(
+            *pool_mint_info.key,
+            token_swap.pool_mint,
+            "Pool mint",
+            SwapError::IncorrectMint
+        );
+    }
+    if let Some(referrer_info) = referrer_info {
+        check_token_keys_not_equal!(
+            token_swap.token_a,
+            *referrer_info.key,
+            token_swap.token_a.reserves,
+            "Referrer cannot be one of swap's token accounts for token",
+            SwapError::InvalidInput
+        );
+        check_token_keys_not_equal!(
+            token_swap.token_b,
+            *referrer_info.key,
+            token_swap.token_b.reserves,
+            "Referrer cannot be one of swap's token accounts for token",
+            SwapError::InvalidInput
+        );
     }
+    let fees = fee_tier::effective_fees(
+        program_id,
+        swap_info.key,
+        &token_swap,
+        user_authority_info.key,
+        fee_tier_info,
+    )?;
 
     check_token_keys_not_equal!(
         token_swap.token_a,
@@ -334,28 +736,39 @@ fn process_swap(
         swap_authority_info.key,
     )?;
 
-    if *swap_source_info.key == token_swap.token_a.reserves {
+    let is_a_to_b = *swap_source_info.key == token_swap.token_a.reserves;
+    let (source_token, destination_token) = if is_a_to_b {
         // Swap A to B
-        check_swap_token_destination_accounts(
-            &token_swap.token_b,
-            swap_destination_info.key,
-            admin_destination_info.key,
-        )?;
+        (&token_swap.token_a, &token_swap.token_b)
     } else if *swap_source_info.key == token_swap.token_b.reserves {
         // Swap B to A
-        check_swap_token_destination_accounts(
-            &token_swap.token_a,
-            swap_destination_info.key,
-            admin_destination_info.key,
-        )?;
+        (&token_swap.token_b, &token_swap.token_a)
     } else {
         return Err(SwapError::IncorrectSwapAccount.into());
-    }
+    };
+    // the admin fee account is only used if admin fees are transferred
+    let admin_destination_info = Some(admin_destination_info).filter(|info| {
+        token_swap.admin_fee_mode == AdminFeeMode::Transfer || info.key != program_id
+    });
+    check_swap_token_destination_accounts(
+        destination_token,
+        swap_destination_info.key,
+        admin_destination_info.map(|info| info.key),
+    )?;
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+    let (source_mint, destination_mint) = if is_a_to_b {
+        (mint_a.as_ref(), mint_b.as_ref())
+    } else {
+        (mint_b.as_ref(), mint_a.as_ref())
+    };
 
     let clock = Clock::get()?;
-    let swap_source_account = utils::unpack_token_account(&swap_source_info.data.borrow())?;
-    let swap_destination_account =
-        utils::unpack_token_account(&swap_destination_info.data.borrow())?;
+    let swap_source_amount = source_token
+        .pool_reserves(utils::unpack_token_account(&swap_source_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let swap_destination_amount = destination_token
+        .pool_reserves(utils::unpack_token_account(&swap_destination_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
 
     let invariant = StableSwap::new(
         token_swap.initial_amp_factor,
@@ -364,19 +777,82 @@ fn process_swap(
         token_swap.start_ramp_ts,
         token_swap.stop_ramp_ts,
     );
+    // the invariant stored by the last instruction is a close initial guess
+    let d = invariant
+        .compute_d_with_guess(
+            swap_source_amount,
+            swap_destination_amount,
+            token_swap.last_d,
+            token_swap.last_sum_x,
+            token_swap.last_amp_factor,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    // the pool only receives the input net of any transfer fee
+    let amount_in_received = token::post_fee_amount(source_mint, amount_in)?;
     let result = invariant
-        .swap_to(
-            amount_in,
-            swap_source_account.amount,
-            swap_destination_account.amount,
-            &token_swap.fees,
+        .swap_to_with_d(
+            amount_in_received,
+            swap_source_amount,
+            swap_destination_amount,
+            d,
+            &fees,
         )
         .ok_or(SwapError::CalculationFailure)?;
     let amount_swapped = result.amount_swapped;
-    if amount_swapped < minimum_amount_out {
-        log_slippage_error(minimum_amount_out, amount_swapped);
+    let amount_out_received = token::post_fee_amount(destination_mint, amount_swapped)?;
+    if amount_out_received < minimum_amount_out {
+        log_slippage_error(minimum_amount_out, amount_out_received);
         return Err(SwapError::ExceededSlippage.into());
     }
+    let referral_fee = match referrer_info {
+        Some(_) => token_swap
+            .extra_fees
+            .referral_fee(result.fee)
+            .ok_or(SwapError::CalculationFailure)?,
+        None => 0,
+    };
+    // admin fees minted as pool tokens stay in the pool, the referral fee
+    // also leaves the destination reserves
+    let new_destination_amount = if token_swap.admin_fee_mode == AdminFeeMode::LpMint {
+        swap_destination_amount.checked_sub(amount_swapped)
+    } else {
+        Some(result.new_destination_amount)
+    }
+    .and_then(|amount| amount.checked_sub(referral_fee))
+    .ok_or(SwapError::CalculationFailure)?;
+    let (reserves_before, reserves_after) = if is_a_to_b {
+        (
+            (swap_source_amount, swap_destination_amount),
+            (result.new_source_amount, new_destination_amount),
+        )
+    } else {
+        (
+            (swap_destination_amount, swap_source_amount),
+            (new_destination_amount, result.new_source_amount),
+        )
+    };
+    // the invariant barely moves in a swap, so Newton's method converges
+    // within a few iterations from the invariant before it
+    let d_after = compute_d_after(&invariant, reserves_after, d)?;
+    circuit_breaker::check_price_deviation(
+        &mut token_swap,
+        &invariant,
+        clock.slot,
+        reserves_before,
+        d,
+        reserves_after,
+        d_after,
+    )?;
+    oracle::check_oracle_price(
+        &token_swap.price_oracle,
+        token::optional_account(program_id, optional_accounts, 2),
+        &invariant,
+        clock.slot,
+        reserves_before,
+        d,
+        reserves_after,
+        d_after,
+    )?;
 
     // from user to swap
     token::transfer_as_user(
@@ -385,6 +861,7 @@ fn process_swap(
         swap_source_info.clone(),
         user_authority_info.clone(),
         amount_in,
+        source_mint,
     )?;
     // from swap to user
     token::transfer_as_swap(
@@ -395,24 +872,92 @@ fn process_swap(
         swap_authority_info.clone(),
         token_swap.nonce,
         amount_swapped,
+        destination_mint,
     )?;
     // from swap to fees
-    token::transfer_as_swap(
-        swap_info.key,
-        token_program_info.clone(),
-        swap_destination_info.clone(),
-        admin_destination_info.clone(),
-        swap_authority_info.clone(),
-        token_swap.nonce,
-        result.admin_fee,
-    )?;
+    match token_swap.admin_fee_mode {
+        AdminFeeMode::Transfer => {
+            let admin_destination_info = admin_destination_info.ok_or(SwapError::InvalidAdmin)?;
+            token::transfer_as_swap(
+                swap_info.key,
+                token_program_info.clone(),
+                swap_destination_info.clone(),
+                admin_destination_info.clone(),
+                swap_authority_info.clone(),
+                token_swap.nonce,
+                result.admin_fee,
+                destination_mint,
+            )?;
+        }
+        AdminFeeMode::Accrue => {
+            let destination_token = if is_a_to_b {
+                &mut token_swap.token_b
+            } else {
+                &mut token_swap.token_a
+            };
+            accrue_admin_fee(destination_token, result.admin_fee)?;
+        }
+        // left in the pool until pool tokens are minted for it
+        AdminFeeMode::LpMint => {}
+    }
+    // from swap to referrer
+    if let Some(referrer_info) = referrer_info {
+        token::transfer_as_swap(
+            swap_info.key,
+            token_program_info.clone(),
+            swap_destination_info.clone(),
+            referrer_info.clone(),
+            swap_authority_info.clone(),
+            token_swap.nonce,
+            referral_fee,
+            destination_mint,
+        )?;
+    }
 
-    if *swap_source_info.key == token_swap.token_a.reserves {
-        log_event(Event::SwapAToB, amount_in, amount_swapped, 0, result.fee);
+    if is_a_to_b {
+        log_event(
+            Event::SwapAToB,
+            amount_in,
+            amount_out_received,
+            0,
+            result.fee,
+        );
     } else {
-        log_event(Event::SwapBToA, amount_swapped, amount_in, 0, result.fee);
+        log_event(
+            Event::SwapBToA,
+            amount_out_received,
+            amount_in,
+            0,
+            result.fee,
+        );
     };
-    Ok(())
+    let reserves = Reserves::from(reserves_after);
+    let virtual_price = pool_mint_info
+        .map(|pool_mint_info| read_pool_state(reserves, d_after, pool_mint_info))
+        .transpose()?
+        .map(|pool| pool.virtual_price);
+    record_last_d(&mut token_swap, &invariant, reserves_after, d_after)?;
+    SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+    set_result(&SwapResult {
+        amount_in,
+        amount_out: amount_out_received,
+        fee: result.fee,
+        admin_fee: result.admin_fee,
+        referral_fee,
+        reserves,
+    })?;
+    emit_event(&events::Event::Swap(SwapEvent {
+        swap: *swap_info.key,
+        user: *user_authority_info.key,
+        a_to_b: is_a_to_b,
+        amount_in,
+        amount_out: amount_out_received,
+        fee: result.fee,
+        admin_fee: result.admin_fee,
+        referral_fee,
+        reserves,
+        virtual_price,
+    }))
 }
 
 /// Processes an [Deposit](enum.Instruction.html).
@@ -439,10 +984,19 @@ fn process_deposit(
     let dest_info = next_account_info(account_info_iter)?;
     let token_program_info = next_account_info(account_info_iter)?;
 
-    let token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
-    if token_swap.is_paused {
-        return Err(SwapError::IsPaused.into());
-    }
+    let mut token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    check_not_paused(&token_swap, PauseFlags::DEPOSIT)?;
+    gate::check_allowlisted(
+        program_id,
+        &token_swap,
+        user_authority_info.key,
+        token::optional_account(
+            program_id,
+            token::optional_accounts(&token_swap, accounts, 10),
+            0,
+        ),
+    )?;
+    check_token_program(&token_swap, token_program_info.key)?;
     check_swap_authority(
         &token_swap,
         swap_info.key,
@@ -452,6 +1006,7 @@ fn process_deposit(
 
     check_deposit_token_accounts(&token_swap.token_a, source_a_info.key, token_a_info.key)?;
     check_deposit_token_accounts(&token_swap.token_b, source_b_info.key, token_b_info.key)?;
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
 
     check_keys_equal!(
         *pool_mint_info.key,
@@ -461,8 +1016,14 @@ fn process_deposit(
     );
 
     let clock = Clock::get()?;
-    let token_a = utils::unpack_token_account(&token_a_info.data.borrow())?;
-    let token_b = utils::unpack_token_account(&token_b_info.data.borrow())?;
+    let token_a_amount_before = token_swap
+        .token_a
+        .pool_reserves(utils::unpack_token_account(&token_a_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let token_b_amount_before = token_swap
+        .token_b
+        .pool_reserves(utils::unpack_token_account(&token_b_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
     let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
 
     let invariant = StableSwap::new(
@@ -472,12 +1033,26 @@ fn process_deposit(
         token_swap.start_ramp_ts,
         token_swap.stop_ramp_ts,
     );
-    let mint_amount = invariant
-        .compute_mint_amount_for_deposit(
-            token_a_amount,
-            token_b_amount,
-            token_a.amount,
-            token_b.amount,
+    // the pool only receives the deposits net of any transfer fees
+    let token_a_amount_received = token::post_fee_amount(mint_a.as_ref(), token_a_amount)?;
+    let token_b_amount_received = token::post_fee_amount(mint_b.as_ref(), token_b_amount)?;
+    // the invariant stored by the last instruction is a close initial guess
+    let d_before = invariant
+        .compute_d_with_guess(
+            token_a_amount_before,
+            token_b_amount_before,
+            token_swap.last_d,
+            token_swap.last_sum_x,
+            token_swap.last_amp_factor,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    let (mint_amount, d_after) = invariant
+        .compute_mint_amount_for_deposit_with_d(
+            token_a_amount_received,
+            token_b_amount_received,
+            token_a_amount_before,
+            token_b_amount_before,
+            d_before,
             pool_mint.supply,
             &token_swap.fees,
         )
@@ -486,6 +1061,24 @@ fn process_deposit(
         log_slippage_error(min_mint_amount, mint_amount);
         return Err(SwapError::ExceededSlippage.into());
     }
+    let reserves_after = (
+        token_a_amount_before
+            .checked_add(token_a_amount_received)
+            .ok_or(SwapError::CalculationFailure)?,
+        token_b_amount_before
+            .checked_add(token_b_amount_received)
+            .ok_or(SwapError::CalculationFailure)?,
+    );
+    // imbalanced deposits move the price like a swap
+    circuit_breaker::check_price_deviation(
+        &mut token_swap,
+        &invariant,
+        clock.slot,
+        (token_a_amount_before, token_b_amount_before),
+        d_before,
+        reserves_after,
+        d_after,
+    )?;
 
     // from user to swap
     token::transfer_as_user(
@@ -494,6 +1087,7 @@ fn process_deposit(
         token_a_info.clone(),
         user_authority_info.clone(),
         token_a_amount,
+        mint_a.as_ref(),
     )?;
     // from user to swap
     token::transfer_as_user(
@@ -502,6 +1096,7 @@ fn process_deposit(
         token_b_info.clone(),
         user_authority_info.clone(),
         token_b_amount,
+        mint_b.as_ref(),
     )?;
     // mint lp to user
     token::mint_to(
@@ -521,7 +1116,23 @@ fn process_deposit(
         mint_amount,
         0,
     );
-    Ok(())
+    let pool = read_pool_state(reserves_after.into(), d_after, pool_mint_info)?;
+    record_last_d(&mut token_swap, &invariant, reserves_after, d_after)?;
+    SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+    set_result(&DepositResult {
+        token_a_amount,
+        token_b_amount,
+        pool_token_amount: mint_amount,
+        pool,
+    })?;
+    emit_event(&events::Event::Deposit(DepositEvent {
+        swap: *swap_info.key,
+        user: *user_authority_info.key,
+        token_a_amount,
+        token_b_amount,
+        pool_token_amount: mint_amount,
+        pool,
+    }))
 }
 
 struct WithdrawContext<'a, 'b: 'a> {
@@ -537,7 +1148,14 @@ fn handle_token_withdraw<'a, 'b: 'a>(
     reserves_info: &'a AccountInfo<'b>,
     dest_token_info: &'a AccountInfo<'b>,
     admin_fee_dest_info: &'a AccountInfo<'b>,
+    mint: Option<&TransferMint<'b>>,
 ) -> ProgramResult {
+    // admin fees that are not transferred are recorded by the caller
+    let admin_fee = if ctx.token_swap.admin_fee_mode == AdminFeeMode::Transfer {
+        admin_fee
+    } else {
+        0
+    };
     // from swap to user
     token::transfer_as_swap(
         ctx.swap_info.key,
@@ -547,6 +1165,7 @@ fn handle_token_withdraw<'a, 'b: 'a>(
         ctx.swap_authority_info.clone(),
         ctx.token_swap.nonce,
         amount,
+        mint,
     )?;
     // from swap to fee
     token::transfer_as_swap(
@@ -557,6 +1176,7 @@ fn handle_token_withdraw<'a, 'b: 'a>(
         ctx.swap_authority_info.clone(),
         ctx.token_swap.nonce,
         admin_fee,
+        mint,
     )?;
 
     Ok(())
@@ -589,6 +1209,18 @@ fn process_withdraw(
     let token_program_info = next_account_info(account_info_iter)?;
 
     let token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    check_not_paused(&token_swap, PauseFlags::WITHDRAW)?;
+    gate::check_allowlisted(
+        program_id,
+        &token_swap,
+        user_authority_info.key,
+        token::optional_account(
+            program_id,
+            token::optional_accounts(&token_swap, accounts, 12),
+            0,
+        ),
+    )?;
+    check_token_program(&token_swap, token_program_info.key)?;
     check_swap_authority(
         &token_swap,
         swap_info.key,
@@ -607,6 +1239,7 @@ fn process_withdraw(
         token_b_info.key,
         admin_fee_dest_b_info.key,
     )?;
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
 
     check_keys_equal!(
         *pool_mint_info.key,
@@ -620,14 +1253,30 @@ fn process_withdraw(
         return Err(SwapError::EmptyPool.into());
     }
 
-    let token_a = utils::unpack_token_account(&token_a_info.data.borrow())?;
-    let token_b = utils::unpack_token_account(&token_b_info.data.borrow())?;
+    let token_a_amount = token_swap
+        .token_a
+        .pool_reserves(utils::unpack_token_account(&token_a_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let token_b_amount = token_swap
+        .token_b
+        .pool_reserves(utils::unpack_token_account(&token_b_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
 
+    // the last withdraw pays no withdraw fee, which no liquidity provider would
+    // be left to own and would keep the pool from being closed
+    let fees = if pool_token_amount == pool_mint.supply {
+        Fees {
+            withdraw_fee_numerator: 0,
+            ..token_swap.fees
+        }
+    } else {
+        token_swap.fees
+    };
     let converter = PoolTokenConverter {
         supply: (pool_mint.supply),
-        token_a: (token_a.amount),
-        token_b: (token_b.amount),
-        fees: &token_swap.fees,
+        token_a: token_a_amount,
+        token_b: token_b_amount,
+        fees: &fees,
     };
     let pool_token_amount_u256 = pool_token_amount;
 
@@ -641,10 +1290,12 @@ fn process_withdraw(
     let (a_amount, a_fee, a_admin_fee) = check_can_withdraw_token(
         converter.token_a_rate(pool_token_amount_u256),
         minimum_token_a_amount,
+        mint_a.as_ref(),
     )?;
     let (b_amount, b_fee, b_admin_fee) = check_can_withdraw_token(
         converter.token_b_rate(pool_token_amount_u256),
         minimum_token_b_amount,
+        mint_b.as_ref(),
     )?;
 
     handle_token_withdraw(
@@ -653,6 +1304,7 @@ fn process_withdraw(
         token_a_info,
         dest_token_a_info,
         admin_fee_dest_a_info,
+        mint_a.as_ref(),
     )?;
     handle_token_withdraw(
         &ctx,
@@ -660,7 +1312,13 @@ fn process_withdraw(
         token_b_info,
         dest_token_b_info,
         admin_fee_dest_b_info,
+        mint_b.as_ref(),
     )?;
+    let mut token_swap = ctx.token_swap;
+    if token_swap.admin_fee_mode == AdminFeeMode::Accrue {
+        accrue_admin_fee(&mut token_swap.token_a, a_admin_fee)?;
+        accrue_admin_fee(&mut token_swap.token_b, b_admin_fee)?;
+    }
 
     // burn LP tokens withdrawn
     token::burn(
@@ -674,7 +1332,46 @@ fn process_withdraw(
     log_event(Event::WithdrawA, a_amount, 0, 0, a_fee);
     log_event(Event::WithdrawB, 0, b_amount, 0, b_fee);
     log_event(Event::Burn, 0, 0, pool_token_amount, 0);
-    Ok(())
+    let clock = Clock::get()?;
+    let invariant = StableSwap::new(
+        token_swap.initial_amp_factor,
+        token_swap.target_amp_factor,
+        clock.unix_timestamp,
+        token_swap.start_ramp_ts,
+        token_swap.stop_ramp_ts,
+    );
+    let reserves = read_reserves(&token_swap, token_a_info, token_b_info)?;
+    let reserves_after = (reserves.token_a, reserves.token_b);
+    let d_after = invariant
+        .compute_d_with_guess(
+            reserves_after.0,
+            reserves_after.1,
+            token_swap.last_d,
+            token_swap.last_sum_x,
+            token_swap.last_amp_factor,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
+    let pool = read_pool_state(reserves, d_after, pool_mint_info)?;
+    record_last_d(&mut token_swap, &invariant, reserves_after, d_after)?;
+    SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+    set_result(&WithdrawResult {
+        token_a_amount: a_amount,
+        token_b_amount: b_amount,
+        token_a_fee: a_fee,
+        token_b_fee: b_fee,
+        pool_token_amount,
+        pool,
+    })?;
+    emit_event(&events::Event::Withdraw(WithdrawEvent {
+        swap: *swap_info.key,
+        user: *user_authority_info.key,
+        token_a_amount: a_amount,
+        token_b_amount: b_amount,
+        token_a_fee: a_fee,
+        token_b_fee: b_fee,
+        pool_token_amount,
+        pool,
+    }))
 }
 
 /// Processes an [WithdrawOne](enum.Instruction.html).
@@ -705,10 +1402,19 @@ fn process_withdraw_one(
         return Err(SwapError::InvalidInput.into());
     }
 
-    let token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
-    if token_swap.is_paused {
-        return Err(SwapError::IsPaused.into());
-    }
+    let mut token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    check_not_paused(&token_swap, PauseFlags::WITHDRAW_ONE)?;
+    gate::check_allowlisted(
+        program_id,
+        &token_swap,
+        user_authority_info.key,
+        token::optional_account(
+            program_id,
+            token::optional_accounts(&token_swap, accounts, 10),
+            0,
+        ),
+    )?;
+    check_token_program(&token_swap, token_program_info.key)?;
     check_swap_authority(
         &token_swap,
         swap_info.key,
@@ -716,7 +1422,8 @@ fn process_withdraw_one(
         swap_authority_info.key,
     )?;
 
-    if *base_token_info.key == token_swap.token_a.reserves {
+    let is_base_a = *base_token_info.key == token_swap.token_a.reserves;
+    if is_base_a {
         check_keys_equal!(
             *quote_token_info.key,
             token_swap.token_b.reserves,
@@ -755,10 +1462,22 @@ fn process_withdraw_one(
         SwapError::IncorrectMint
     );
 
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+    let base_mint = if is_base_a { mint_a } else { mint_b };
+
     let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
     let clock = Clock::get()?;
-    let base_token = utils::unpack_token_account(&base_token_info.data.borrow())?;
-    let quote_token = utils::unpack_token_account(&quote_token_info.data.borrow())?;
+    let (base_token, quote_token) = if is_base_a {
+        (&token_swap.token_a, &token_swap.token_b)
+    } else {
+        (&token_swap.token_b, &token_swap.token_a)
+    };
+    let base_token_amount = base_token
+        .pool_reserves(utils::unpack_token_account(&base_token_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let quote_token_amount = quote_token
+        .pool_reserves(utils::unpack_token_account(&quote_token_info.data.borrow())?.amount)
+        .ok_or(SwapError::CalculationFailure)?;
 
     let invariant = StableSwap::new(
         token_swap.initial_amp_factor,
@@ -767,12 +1486,28 @@ fn process_withdraw_one(
         token_swap.start_ramp_ts,
         token_swap.stop_ramp_ts,
     );
+    let reserves_before = if is_base_a {
+        (base_token_amount, quote_token_amount)
+    } else {
+        (quote_token_amount, base_token_amount)
+    };
+    // the invariant stored by the last instruction is a close initial guess
+    let d_before = invariant
+        .compute_d_with_guess(
+            reserves_before.0,
+            reserves_before.1,
+            token_swap.last_d,
+            token_swap.last_sum_x,
+            token_swap.last_amp_factor,
+        )
+        .ok_or(SwapError::CalculationFailure)?;
     let (dy, dy_fee) = invariant
-        .compute_withdraw_one(
+        .compute_withdraw_one_with_d(
             pool_token_amount,
             pool_mint.supply,
-            base_token.amount,
-            quote_token.amount,
+            base_token_amount,
+            quote_token_amount,
+            d_before,
             &token_swap.fees,
         )
         .ok_or(SwapError::CalculationFailure)?;
@@ -783,8 +1518,9 @@ fn process_withdraw_one(
     let token_amount = dy
         .checked_sub(withdraw_fee)
         .ok_or(SwapError::CalculationFailure)?;
-    if token_amount < minimum_token_amount {
-        log_slippage_error(minimum_token_amount, token_amount);
+    let token_amount_received = token::post_fee_amount(base_mint.as_ref(), token_amount)?;
+    if token_amount_received < minimum_token_amount {
+        log_slippage_error(minimum_token_amount, token_amount_received);
         return Err(SwapError::ExceededSlippage.into());
     }
 
@@ -800,7 +1536,34 @@ fn process_withdraw_one(
         .checked_add(admin_withdraw_fee)
         .ok_or(SwapError::CalculationFailure)?;
 
-    // from swap to user
+    // admin fees minted as pool tokens stay in the pool
+    let base_token_removed = if token_swap.admin_fee_mode == AdminFeeMode::LpMint {
+        Some(token_amount)
+    } else {
+        token_amount.checked_add(admin_fee)
+    }
+    .ok_or(SwapError::CalculationFailure)?;
+    let base_token_amount_after = base_token_amount
+        .checked_sub(base_token_removed)
+        .ok_or(SwapError::CalculationFailure)?;
+    let reserves_after = if is_base_a {
+        (base_token_amount_after, quote_token_amount)
+    } else {
+        (quote_token_amount, base_token_amount_after)
+    };
+    let d_after = compute_d_after(&invariant, reserves_after, d_before)?;
+    // single sided withdrawals move the price like a swap
+    circuit_breaker::check_price_deviation(
+        &mut token_swap,
+        &invariant,
+        clock.slot,
+        reserves_before,
+        d_before,
+        reserves_after,
+        d_after,
+    )?;
+
+    // from swap to user
     token::transfer_as_swap(
         swap_info.key,
         token_program_info.clone(),
@@ -809,17 +1572,33 @@ fn process_withdraw_one(
         swap_authority_info.clone(),
         token_swap.nonce,
         token_amount,
+        base_mint.as_ref(),
     )?;
     // from swap to fee
-    token::transfer_as_swap(
-        swap_info.key,
-        token_program_info.clone(),
-        base_token_info.clone(),
-        admin_destination_info.clone(),
-        swap_authority_info.clone(),
-        token_swap.nonce,
-        admin_fee,
-    )?;
+    match token_swap.admin_fee_mode {
+        AdminFeeMode::Transfer => {
+            token::transfer_as_swap(
+                swap_info.key,
+                token_program_info.clone(),
+                base_token_info.clone(),
+                admin_destination_info.clone(),
+                swap_authority_info.clone(),
+                token_swap.nonce,
+                admin_fee,
+                base_mint.as_ref(),
+            )?;
+        }
+        AdminFeeMode::Accrue => {
+            let base_token = if is_base_a {
+                &mut token_swap.token_a
+            } else {
+                &mut token_swap.token_b
+            };
+            accrue_admin_fee(base_token, admin_fee)?;
+        }
+        // left in the pool until pool tokens are minted for it
+        AdminFeeMode::LpMint => {}
+    }
     token::burn(
         token_program_info.clone(),
         source_info.clone(),
@@ -828,12 +1607,335 @@ fn process_withdraw_one(
         pool_token_amount,
     )?;
 
-    if *base_token_info.key == token_swap.token_a.reserves {
-        log_event(Event::WithdrawA, token_amount, 0, 0, dy_fee);
+    if is_base_a {
+        log_event(Event::WithdrawA, token_amount_received, 0, 0, dy_fee);
     } else {
-        log_event(Event::WithdrawB, 0, token_amount, 0, dy_fee);
+        log_event(Event::WithdrawB, 0, token_amount_received, 0, dy_fee);
     };
     log_event(Event::Burn, 0, 0, pool_token_amount, 0);
+    let pool = read_pool_state(reserves_after.into(), d_after, pool_mint_info)?;
+    record_last_d(&mut token_swap, &invariant, reserves_after, d_after)?;
+    SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+    set_result(&WithdrawOneResult {
+        token_amount: token_amount_received,
+        fee: dy_fee,
+        pool_token_amount,
+        pool,
+    })?;
+    emit_event(&events::Event::WithdrawOne(WithdrawOneEvent {
+        swap: *swap_info.key,
+        user: *user_authority_info.key,
+        token_index: if is_base_a { 0 } else { 1 },
+        token_amount: token_amount_received,
+        fee: dy_fee,
+        pool_token_amount,
+        pool,
+    }))
+}
+
+/// Processes a [FlashLoan](enum.Instruction.html).
+fn process_flash_loan(program_id: &Pubkey, amount: u64, accounts: &[AccountInfo]) -> ProgramResult {
+    if amount == 0 {
+        // noop
+        return Ok(());
+    }
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let swap_authority_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let admin_destination_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let receiver_program_info = next_account_info(account_info_iter)?;
+
+    let mut token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    check_not_paused(&token_swap, PauseFlags::FLASH_LOAN)?;
+    check_token_program(&token_swap, token_program_info.key)?;
+    // the mints of Token-2022 pools are not forwarded to the receiver
+    let remaining_account_infos = account_info_iter.as_slice();
+    let receiver_account_infos = &remaining_account_infos[..remaining_account_infos
+        .len()
+        .saturating_sub(token::transfer_mint_account_count(&token_swap))];
+    check_swap_authority(
+        &token_swap,
+        swap_info.key,
+        program_id,
+        swap_authority_info.key,
+    )?;
+
+    let is_token_a = *reserve_info.key == token_swap.token_a.reserves;
+    let token = if is_token_a {
+        &token_swap.token_a
+    } else {
+        &token_swap.token_b
+    };
+    check_withdraw_token_accounts(token, reserve_info.key, admin_destination_info.key)?;
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+    let mint = if is_token_a { mint_a } else { mint_b };
+    if *destination_info.key == *reserve_info.key
+        || *receiver_program_info.key == *program_id
+        || *receiver_program_info.key == *token_program_info.key
+    {
+        return Err(SwapError::InvalidInput.into());
+    }
+
+    if !token_swap.extra_fees.is_flash_loan_enabled() {
+        return Err(SwapError::FlashLoansDisabled.into());
+    }
+    let fee = token_swap
+        .extra_fees
+        .flash_loan_fee(amount)
+        .ok_or(SwapError::CalculationFailure)?;
+    let admin_fee = token_swap
+        .fees
+        .admin_trade_fee(fee)
+        .ok_or(SwapError::CalculationFailure)?;
+    let reserve_before = utils::unpack_token_account(&reserve_info.data.borrow())?.amount;
+    let expected_reserve = reserve_before
+        .checked_add(fee)
+        .ok_or(SwapError::CalculationFailure)?;
+
+    // from swap to borrower
+    token::transfer_as_swap(
+        swap_info.key,
+        token_program_info.clone(),
+        reserve_info.clone(),
+        destination_info.clone(),
+        swap_authority_info.clone(),
+        token_swap.nonce,
+        amount,
+        mint.as_ref(),
+    )?;
+
+    let mut receiver_accounts = vec![
+        AccountMeta::new(*destination_info.key, false),
+        AccountMeta::new(*reserve_info.key, false),
+        AccountMeta::new_readonly(*token_program_info.key, false),
+    ];
+    receiver_accounts.extend(receiver_account_infos.iter().map(|info| AccountMeta {
+        pubkey: *info.key,
+        is_signer: info.is_signer,
+        is_writable: info.is_writable,
+    }));
+    let receiver_ix = Instruction {
+        program_id: *receiver_program_info.key,
+        accounts: receiver_accounts,
+        data: ReceiveFlashLoanData { amount, fee }.pack(),
+    };
+    let mut invoke_account_infos = vec![
+        destination_info.clone(),
+        reserve_info.clone(),
+        token_program_info.clone(),
+        receiver_program_info.clone(),
+    ];
+    invoke_account_infos.extend_from_slice(receiver_account_infos);
+    invoke(&receiver_ix, &invoke_account_infos)?;
+
+    let reserve_after = utils::unpack_token_account(&reserve_info.data.borrow())?.amount;
+    if reserve_after < expected_reserve {
+        msg!("Flash loan not repaid");
+        log_slippage_error(expected_reserve, reserve_after);
+        return Err(SwapError::FlashLoanNotRepaid.into());
+    }
+
+    // from swap to fees
+    match token_swap.admin_fee_mode {
+        AdminFeeMode::Transfer => {
+            token::transfer_as_swap(
+                swap_info.key,
+                token_program_info.clone(),
+                reserve_info.clone(),
+                admin_destination_info.clone(),
+                swap_authority_info.clone(),
+                token_swap.nonce,
+                admin_fee,
+                mint.as_ref(),
+            )?;
+        }
+        AdminFeeMode::Accrue => {
+            let token = if is_token_a {
+                &mut token_swap.token_a
+            } else {
+                &mut token_swap.token_b
+            };
+            accrue_admin_fee(token, admin_fee)?;
+            SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+        }
+        // left in the pool until pool tokens are minted for it
+        AdminFeeMode::LpMint => {}
+    }
+
+    if is_token_a {
+        log_event(Event::FlashLoan, amount, 0, 0, fee);
+    } else {
+        log_event(Event::FlashLoan, 0, amount, 0, fee);
+    }
+    set_result(&FlashLoanResult { amount, fee })
+}
+
+/// Processes a [ClaimAdminFees](enum.Instruction.html).
+fn process_claim_admin_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let swap_authority_info = next_account_info(account_info_iter)?;
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let admin_fee_dest_a_info = next_account_info(account_info_iter)?;
+    let admin_fee_dest_b_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let mut token_swap = SwapInfo::unpack(&swap_info.data.borrow())?;
+    check_token_program(&token_swap, token_program_info.key)?;
+    check_swap_authority(
+        &token_swap,
+        swap_info.key,
+        program_id,
+        swap_authority_info.key,
+    )?;
+    check_withdraw_token_accounts(
+        &token_swap.token_a,
+        token_a_info.key,
+        admin_fee_dest_a_info.key,
+    )?;
+    check_withdraw_token_accounts(
+        &token_swap.token_b,
+        token_b_info.key,
+        admin_fee_dest_b_info.key,
+    )?;
+    let (mint_a, mint_b) = token::load_transfer_mints(&token_swap, accounts)?;
+
+    let a_admin_fee = token_swap.token_a.accrued_admin_fees;
+    let b_admin_fee = token_swap.token_b.accrued_admin_fees;
+    let mut lp_admin_fee = 0;
+    if token_swap.admin_fee_mode == AdminFeeMode::LpMint {
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let lp_admin_fee_dest_info = next_account_info(account_info_iter)?;
+        check_keys_equal!(
+            *pool_mint_info.key,
+            token_swap.pool_mint,
+            "Pool mint",
+            SwapError::IncorrectMint
+        );
+        check_keys_equal!(
+            *lp_admin_fee_dest_info.key,
+            token_swap.lp_admin_fees,
+            "LP admin fee dest",
+            SwapError::InvalidAdmin
+        );
+
+        let token_a_amount = token_swap
+            .token_a
+            .pool_reserves(utils::unpack_token_account(&token_a_info.data.borrow())?.amount)
+            .ok_or(SwapError::CalculationFailure)?;
+        let token_b_amount = token_swap
+            .token_b
+            .pool_reserves(utils::unpack_token_account(&token_b_info.data.borrow())?.amount)
+            .ok_or(SwapError::CalculationFailure)?;
+        let pool_mint = utils::unpack_mint(&pool_mint_info.data.borrow())?;
+        let invariant = StableSwap::new_from_swap_info(&token_swap, Clock::get()?.unix_timestamp);
+        let (mint_amount, d) = invariant
+            .compute_admin_lp_fee(
+                token_a_amount,
+                token_b_amount,
+                pool_mint.supply,
+                token_swap.lp_admin_fee_last_d,
+                token_swap.lp_admin_fee_last_supply,
+                token_swap.lp_admin_fee_last_amp_factor,
+                &token_swap.fees,
+            )
+            .ok_or(SwapError::CalculationFailure)?;
+        token::mint_to(
+            swap_info.key,
+            token_program_info.clone(),
+            pool_mint_info.clone(),
+            lp_admin_fee_dest_info.clone(),
+            swap_authority_info.clone(),
+            token_swap.nonce,
+            mint_amount,
+        )?;
+        token_swap.lp_admin_fee_last_d = d;
+        token_swap.lp_admin_fee_last_supply = pool_mint
+            .supply
+            .checked_add(mint_amount)
+            .ok_or(SwapError::CalculationFailure)?;
+        token_swap.lp_admin_fee_last_amp_factor = invariant
+            .compute_amp_factor()
+            .ok_or(SwapError::CalculationFailure)?;
+        lp_admin_fee = mint_amount;
+    }
+
+    token::transfer_as_swap(
+        swap_info.key,
+        token_program_info.clone(),
+        token_a_info.clone(),
+        admin_fee_dest_a_info.clone(),
+        swap_authority_info.clone(),
+        token_swap.nonce,
+        a_admin_fee,
+        mint_a.as_ref(),
+    )?;
+    token::transfer_as_swap(
+        swap_info.key,
+        token_program_info.clone(),
+        token_b_info.clone(),
+        admin_fee_dest_b_info.clone(),
+        swap_authority_info.clone(),
+        token_swap.nonce,
+        b_admin_fee,
+        mint_b.as_ref(),
+    )?;
+
+    token_swap.token_a.accrued_admin_fees = 0;
+    token_swap.token_b.accrued_admin_fees = 0;
+    SwapInfo::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+
+    log_event(
+        Event::ClaimAdminFees,
+        a_admin_fee,
+        b_admin_fee,
+        lp_admin_fee,
+        0,
+    );
+    Ok(())
+}
+
+/// Records the invariant `d` of the reserves after an instruction changed
+/// them, which the next instruction starts Newton's method at.
+fn record_last_d(
+    token_swap: &mut SwapInfo,
+    invariant: &StableSwap,
+    reserves: (u64, u64),
+    d: U192,
+) -> ProgramResult {
+    token_swap.last_d = d.try_to_u64()?;
+    token_swap.last_sum_x = reserves
+        .0
+        .checked_add(reserves.1)
+        .ok_or(SwapError::CalculationFailure)?;
+    token_swap.last_amp_factor = invariant
+        .compute_amp_factor()
+        .ok_or(SwapError::CalculationFailure)?;
+    Ok(())
+}
+
+/// Computes the invariant of the reserves after an instruction, starting
+/// Newton's method at the invariant `d_before` of the reserves before it.
+fn compute_d_after(
+    invariant: &StableSwap,
+    reserves_after: (u64, u64),
+    d_before: U192,
+) -> Result<U192, ProgramError> {
+    invariant
+        .compute_d_from(reserves_after.0, reserves_after.1, d_before)
+        .ok_or_else(|| SwapError::CalculationFailure.into())
+}
+
+/// Records an admin fee that is left in the reserves.
+fn accrue_admin_fee(token: &mut SwapTokenInfo, admin_fee: u64) -> ProgramResult {
+    token.accrued_admin_fees = token
+        .accrued_admin_fees
+        .checked_add(admin_fee)
+        .ok_or(SwapError::CalculationFailure)?;
     Ok(())
 }
 
@@ -842,10 +1944,16 @@ fn process_withdraw_one(
 mod tests {
     use super::*;
     use crate::{
-        instruction::{deposit, swap, withdraw, withdraw_one},
+        instruction::{
+            deposit, initialize_v2, swap, with_token_2022_mints, withdraw, withdraw_one,
+        },
+        pda::{find_fee_tier_address, PoolAddresses},
         processor::test_utils::*,
+        state::FeeTier,
+    };
+    use solana_program::{
+        program_error::ProgramError, rent::Rent, system_instruction::SystemError, system_program,
     };
-    use solana_program::program_error::ProgramError;
     use solana_sdk::account::Account;
     use spl_token::{
         error::TokenError,
@@ -901,6 +2009,29 @@ mod tests {
             accounts.initial_amp_factor = old_initial_amp_factor;
         }
 
+        // invalid fees
+        {
+            let old_fees = accounts.fees;
+            accounts.fees = Fees {
+                trade_fee_denominator: 0,
+                ..old_fees
+            };
+            assert_eq!(
+                Err(SwapError::ZeroFeeDenominator.into()),
+                accounts.initialize_swap()
+            );
+            accounts.fees = Fees {
+                admin_trade_fee_numerator: 2,
+                admin_trade_fee_denominator: 1,
+                ..old_fees
+            };
+            assert_eq!(
+                Err(SwapError::AdminFeeTooHigh.into()),
+                accounts.initialize_swap()
+            );
+            accounts.fees = old_fees;
+        }
+
         // uninitialized token a account
         {
             let old_account = accounts.token_a_account;
@@ -1298,7 +2429,7 @@ mod tests {
         }
         let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
         assert!(swap_info.is_initialized);
-        assert!(!swap_info.is_paused);
+        assert!(!swap_info.is_paused());
         assert_eq!(swap_info.nonce, accounts.nonce);
         assert_eq!(swap_info.initial_amp_factor, amp_factor);
         assert_eq!(swap_info.target_amp_factor, amp_factor);
@@ -1324,6 +2455,360 @@ mod tests {
         assert_eq!(pool_mint.supply, pool_account.amount);
     }
 
+    #[test]
+    fn test_initialize_v2() {
+        let payer_key = pubkey_rand();
+        let admin_key = pubkey_rand();
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
+        let mut mint_x = create_mint(&spl_token::id(), &payer_key, DEFAULT_TOKEN_DECIMALS, None);
+        let mut mint_y = create_mint(&spl_token::id(), &payer_key, DEFAULT_TOKEN_DECIMALS, None);
+        if mint_x.0 > mint_y.0 {
+            std::mem::swap(&mut mint_x, &mut mint_y);
+        }
+        let (token_a_mint_key, mut token_a_mint_account) = mint_x;
+        let (token_b_mint_key, mut token_b_mint_account) = mint_y;
+        let (source_a_key, mut source_a_account) = mint_token(
+            &spl_token::id(),
+            &token_a_mint_key,
+            &mut token_a_mint_account,
+            &payer_key,
+            &payer_key,
+            token_a_amount,
+        );
+        let (source_b_key, mut source_b_account) = mint_token(
+            &spl_token::id(),
+            &token_b_mint_key,
+            &mut token_b_mint_account,
+            &payer_key,
+            &payer_key,
+            token_b_amount,
+        );
+        let (admin_fee_a_key, mut admin_fee_a_account) = mint_token(
+            &spl_token::id(),
+            &token_a_mint_key,
+            &mut token_a_mint_account,
+            &payer_key,
+            &admin_key,
+            0,
+        );
+        let (admin_fee_b_key, mut admin_fee_b_account) = mint_token(
+            &spl_token::id(),
+            &token_b_mint_key,
+            &mut token_b_mint_account,
+            &payer_key,
+            &admin_key,
+            0,
+        );
+        let pool = PoolAddresses::find(
+            &SWAP_PROGRAM_ID,
+            &token_a_mint_key,
+            &token_b_mint_key,
+            &admin_key,
+            MIN_AMP,
+        );
+        let destination_key = pubkey_rand();
+
+        let payer_lamports = 1_000_000_000;
+        let mut payer_account = Account::new(payer_lamports, 0, &system_program::id());
+        let mut swap_account = Account::new(0, SwapInfo::LEN, &system_program::id());
+        // funding an address must not prevent the pool from being created
+        let mut token_a_account =
+            Account::new(1, spl_token::state::Account::LEN, &system_program::id());
+        let mut token_b_account =
+            Account::new(0, spl_token::state::Account::LEN, &system_program::id());
+        let mut pool_mint_account =
+            Account::new(0, spl_token::state::Mint::LEN, &system_program::id());
+        let mut destination_account =
+            Account::new(0, spl_token::state::Account::LEN, &system_program::id());
+
+        let initialize_ix = |admin_key: &Pubkey,
+                             token_a_mint_key: &Pubkey,
+                             token_b_mint_key: &Pubkey,
+                             amp_factor: u64| {
+            initialize_v2(
+                &spl_token::id(),
+                &payer_key,
+                admin_key,
+                &admin_fee_a_key,
+                &admin_fee_b_key,
+                token_a_mint_key,
+                token_b_mint_key,
+                &source_a_key,
+                &source_b_key,
+                &destination_key,
+                amp_factor,
+                DEFAULT_TEST_FEES,
+                token_a_amount,
+                token_b_amount,
+            )
+            .unwrap()
+        };
+        let ix = initialize_ix(&admin_key, &token_a_mint_key, &token_b_mint_key, MIN_AMP);
+        let mut process = |ix: Instruction| {
+            do_process_instruction(
+                ix,
+                vec![
+                    &mut payer_account,
+                    &mut swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut admin_fee_a_account,
+                    &mut admin_fee_b_account,
+                    &mut token_a_mint_account,
+                    &mut token_a_account,
+                    &mut token_b_mint_account,
+                    &mut token_b_account,
+                    &mut pool_mint_account,
+                    &mut source_a_account,
+                    &mut source_b_account,
+                    &mut destination_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
+            )
+        };
+
+        // swap not derived from the mints
+        {
+            let mut ix = ix.clone();
+            ix.accounts[1].pubkey = pubkey_rand();
+            assert_eq!(Err(SwapError::InvalidProgramAddress.into()), process(ix));
+        }
+
+        // mints must be sorted
+        {
+            let mut ix = ix.clone();
+            ix.accounts.swap(6, 8);
+            assert_eq!(Err(SwapError::UnsortedMints.into()), process(ix));
+        }
+
+        // another creator cannot take over the address of the pool with their
+        // own admin or amp
+        {
+            let squatter_key = pubkey_rand();
+            for mut squat_ix in [
+                initialize_ix(&squatter_key, &token_a_mint_key, &token_b_mint_key, MIN_AMP),
+                initialize_ix(
+                    &admin_key,
+                    &token_a_mint_key,
+                    &token_b_mint_key,
+                    MIN_AMP + 1,
+                ),
+            ] {
+                assert_ne!(squat_ix.accounts[1].pubkey, pool.swap);
+                for index in [1, 2, 7, 9, 10] {
+                    squat_ix.accounts[index] = ix.accounts[index].clone();
+                }
+                assert_eq!(
+                    Err(SwapError::InvalidProgramAddress.into()),
+                    process(squat_ix)
+                );
+            }
+        }
+
+        // pool token mint not derived from the swap
+        {
+            let mut ix = ix.clone();
+            ix.accounts[10].pubkey = pubkey_rand();
+            assert_eq!(Err(SwapError::InvalidProgramAddress.into()), process(ix));
+        }
+
+        // unsupported token program
+        {
+            let mut ix = ix.clone();
+            ix.accounts[14].pubkey = pubkey_rand();
+            assert_eq!(Err(ProgramError::IncorrectProgramId), process(ix));
+        }
+
+        // invalid fees
+        {
+            let mut ix = ix.clone();
+            ix.data = SwapInstruction::InitializeV2(InitializeV2Data {
+                amp_factor: MIN_AMP,
+                fees: Fees::default(),
+                token_a_amount,
+                token_b_amount,
+            })
+            .pack();
+            assert_eq!(Err(SwapError::ZeroFeeDenominator.into()), process(ix));
+        }
+
+        process(ix.clone()).unwrap();
+
+        // pool can only be created once
+        assert_eq!(
+            Err(ProgramError::Custom(
+                SystemError::AccountAlreadyInUse as u32
+            )),
+            process(ix)
+        );
+
+        assert_eq!(swap_account.owner, SWAP_PROGRAM_ID);
+        let swap_info = SwapInfo::unpack(&swap_account.data).unwrap();
+        assert!(swap_info.is_initialized);
+        assert_eq!(swap_info.nonce, pool.nonce);
+        assert_eq!(swap_info.admin_key, admin_key);
+        assert_eq!(swap_info.token_a.reserves, pool.token_a_reserves);
+        assert_eq!(swap_info.token_b.reserves, pool.token_b_reserves);
+        assert_eq!(swap_info.pool_mint, pool.pool_mint);
+        assert_eq!(swap_info.token_a.admin_fees, admin_fee_a_key);
+        assert_eq!(swap_info.token_b.admin_fees, admin_fee_b_key);
+        assert_eq!(swap_info.fees, DEFAULT_TEST_FEES);
+
+        let rent = Rent::default();
+        let token_a = utils::unpack_token_account(&token_a_account.data).unwrap();
+        assert_eq!(token_a.owner, pool.authority);
+        assert_eq!(token_a.amount, token_a_amount);
+        assert_eq!(
+            token_a_account.lamports,
+            rent.minimum_balance(spl_token::state::Account::LEN)
+        );
+        let token_b = utils::unpack_token_account(&token_b_account.data).unwrap();
+        assert_eq!(token_b.owner, pool.authority);
+        assert_eq!(token_b.amount, token_b_amount);
+        let source_a = utils::unpack_token_account(&source_a_account.data).unwrap();
+        assert_eq!(source_a.amount, 0);
+        let pool_mint = utils::unpack_mint(&pool_mint_account.data).unwrap();
+        assert_eq!(pool_mint.mint_authority, COption::Some(pool.authority));
+        assert_eq!(pool_mint.decimals, DEFAULT_TOKEN_DECIMALS);
+        let destination = utils::unpack_token_account(&destination_account.data).unwrap();
+        assert_eq!(destination.owner, payer_key);
+        assert_eq!(destination.mint, pool.pool_mint);
+        assert!(destination.amount > 0);
+        assert_eq!(pool_mint.supply, destination.amount);
+        assert_eq!(
+            payer_account.lamports,
+            payer_lamports
+                - rent.minimum_balance(SwapInfo::LEN)
+                - (rent.minimum_balance(spl_token::state::Account::LEN) - 1)
+                - rent.minimum_balance(spl_token::state::Account::LEN) * 2
+                - rent.minimum_balance(spl_token::state::Mint::LEN)
+        );
+    }
+
+    #[test]
+    fn test_initialize_v2_token_2022() {
+        let payer_key = pubkey_rand();
+        let admin_key = pubkey_rand();
+        let mut mint_x = create_mint(&spl_token::id(), &payer_key, DEFAULT_TOKEN_DECIMALS, None);
+        let mut mint_y = create_mint(&spl_token::id(), &payer_key, DEFAULT_TOKEN_DECIMALS, None);
+        if mint_x.0 > mint_y.0 {
+            std::mem::swap(&mut mint_x, &mut mint_y);
+        }
+        let (token_a_mint_key, mut token_a_mint_account) = mint_x;
+        let (token_b_mint_key, mut token_b_mint_account) = mint_y;
+        let token_account = |mint_key: &Pubkey, mint_account: &mut Account, owner, amount| {
+            mint_token(
+                &spl_token::id(),
+                mint_key,
+                mint_account,
+                &payer_key,
+                owner,
+                amount,
+            )
+        };
+        let (source_a_key, mut source_a_account) = token_account(
+            &token_a_mint_key,
+            &mut token_a_mint_account,
+            &payer_key,
+            1_000,
+        );
+        let (source_b_key, mut source_b_account) = token_account(
+            &token_b_mint_key,
+            &mut token_b_mint_account,
+            &payer_key,
+            2_000,
+        );
+        let (admin_fee_a_key, mut admin_fee_a_account) =
+            token_account(&token_a_mint_key, &mut token_a_mint_account, &admin_key, 0);
+        let (admin_fee_b_key, mut admin_fee_b_account) =
+            token_account(&token_b_mint_key, &mut token_b_mint_account, &admin_key, 0);
+        // the mint and accounts are moved to Token-2022 once created
+        for account in [
+            &mut token_a_mint_account,
+            &mut token_b_mint_account,
+            &mut source_a_account,
+            &mut source_b_account,
+            &mut admin_fee_a_account,
+            &mut admin_fee_b_account,
+        ] {
+            account.owner = token_2022::id();
+        }
+        set_transfer_fee(
+            &mut token_a_mint_account,
+            &token_2022::TransferFee {
+                epoch: 0,
+                maximum_fee: 0,
+                transfer_fee_basis_points: 0,
+            },
+        );
+        let destination_key = pubkey_rand();
+
+        let ix = initialize_v2(
+            &token_2022::id(),
+            &payer_key,
+            &admin_key,
+            &admin_fee_a_key,
+            &admin_fee_b_key,
+            &token_a_mint_key,
+            &token_b_mint_key,
+            &source_a_key,
+            &source_b_key,
+            &destination_key,
+            MIN_AMP,
+            DEFAULT_TEST_FEES,
+            1_000,
+            2_000,
+        )
+        .unwrap();
+        let mut payer_account = Account::new(1_000_000_000, 0, &system_program::id());
+        let mut swap_account = Account::new(0, SwapInfo::LEN, &system_program::id());
+        // accounts are allocated with the length they are created with, so the
+        // reserves of token A must be created with room for the withheld fees
+        let mut token_a_account = Account::new(
+            0,
+            token_2022::TRANSFER_FEE_ACCOUNT_LEN,
+            &system_program::id(),
+        );
+        let mut token_b_account = Account::new(0, token_2022::ACCOUNT_LEN, &system_program::id());
+        let mut pool_mint_account = Account::new(0, token_2022::MINT_LEN, &system_program::id());
+        let mut destination_account =
+            Account::new(0, token_2022::ACCOUNT_LEN, &system_program::id());
+        // transfers of Token-2022 pools load the transfer fee of the epoch
+        do_process_instruction_at_time(
+            ix,
+            vec![
+                &mut payer_account,
+                &mut swap_account,
+                &mut Account::default(),
+                &mut Account::default(),
+                &mut admin_fee_a_account,
+                &mut admin_fee_b_account,
+                &mut token_a_mint_account,
+                &mut token_a_account,
+                &mut token_b_mint_account,
+                &mut token_b_account,
+                &mut pool_mint_account,
+                &mut source_a_account,
+                &mut source_b_account,
+                &mut destination_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+            ZERO_TS,
+        )
+        .unwrap();
+
+        let swap_info = SwapInfo::unpack(&swap_account.data).unwrap();
+        assert_eq!(swap_info.token_program_id, token_2022::id());
+        assert_eq!(token_a_account.owner, token_2022::id());
+        let token_a = utils::unpack_token_account(&token_a_account.data).unwrap();
+        assert_eq!(token_a.amount, 1_000);
+        assert_eq!(pool_mint_account.owner, token_2022::id());
+        assert!(utils::unpack_mint(&pool_mint_account.data).unwrap().supply > 0);
+    }
+
     #[test]
     fn test_deposit() {
         let user_key = pubkey_rand();
@@ -2370,7 +3855,7 @@ mod tests {
             );
         }
 
-        // correct withdrawal
+        // withdrawals paused
         {
             let (
                 token_a_key,
@@ -2386,19 +3871,10 @@ mod tests {
                 initial_b,
                 initial_pool,
             );
-
-            let original_reserve_a = utils::unpack_token_account(&accounts.token_a_account.data)
-                .unwrap()
-                .amount;
-            let original_reserve_b = utils::unpack_token_account(&accounts.token_b_account.data)
-                .unwrap()
-                .amount;
-            let original_lp_supply = utils::unpack_mint(&accounts.pool_mint_account.data)
-                .unwrap()
-                .supply;
-
-            accounts
-                .withdraw(
+            accounts.set_pause_flags(PauseFlags::ALL).unwrap();
+            assert_eq!(
+                Err(SwapError::IsPaused.into()),
+                accounts.withdraw(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
@@ -2410,21 +3886,67 @@ mod tests {
                     minimum_a_amount,
                     minimum_b_amount,
                 )
-                .unwrap();
+            );
+        }
 
-            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
-            let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
-            let pool_converter = PoolTokenConverter {
-                supply: original_lp_supply,
-                token_a: original_reserve_a,
-                token_b: original_reserve_b,
-                fees: &DEFAULT_TEST_FEES,
-            };
+        // LPs can still exit while the pool is paused
+        accounts.set_pause_flags(PauseFlags::PAUSE).unwrap();
 
-            let (withdrawn_a, _, admin_fee_a) =
-                pool_converter.token_a_rate(withdraw_amount).unwrap();
-            let withdrawn_total_a = withdrawn_a + admin_fee_a;
-            assert_eq!(swap_token_a.amount, token_a_amount - withdrawn_total_a);
+        // correct withdrawal
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool,
+            );
+
+            let original_reserve_a = utils::unpack_token_account(&accounts.token_a_account.data)
+                .unwrap()
+                .amount;
+            let original_reserve_b = utils::unpack_token_account(&accounts.token_b_account.data)
+                .unwrap()
+                .amount;
+            let original_lp_supply = utils::unpack_mint(&accounts.pool_mint_account.data)
+                .unwrap()
+                .supply;
+
+            accounts
+                .withdraw(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount,
+                    minimum_a_amount,
+                    minimum_b_amount,
+                )
+                .unwrap();
+
+            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+            let pool_converter = PoolTokenConverter {
+                supply: original_lp_supply,
+                token_a: original_reserve_a,
+                token_b: original_reserve_b,
+                fees: &DEFAULT_TEST_FEES,
+            };
+
+            let (withdrawn_a, _, admin_fee_a) =
+                pool_converter.token_a_rate(withdraw_amount).unwrap();
+            let withdrawn_total_a = withdrawn_a + admin_fee_a;
+            assert_eq!(swap_token_a.amount, token_a_amount - withdrawn_total_a);
             let (withdrawn_b, _, admin_fee_b) =
                 pool_converter.token_b_rate(withdraw_amount).unwrap();
             let withdrawn_total_b = withdrawn_b + admin_fee_b;
@@ -3472,4 +4994,1505 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_flash_loan() {
+        let user_key = pubkey_rand();
+        let borrower_key = pubkey_rand();
+        let amp_factor = 85;
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 1_000_000;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            token_a_amount,
+            token_b_amount,
+            DEFAULT_TEST_FEES,
+        );
+        let initial_a = 10_000;
+        let initial_b = 10_000;
+        let loan_amount = 100_000;
+        let expected_fee = DEFAULT_TEST_EXTRA_FEES.flash_loan_fee(loan_amount).unwrap();
+        let expected_admin_fee = DEFAULT_TEST_FEES.admin_trade_fee(expected_fee).unwrap();
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // swap not initialized
+        {
+            let (token_a_key, mut token_a_account, _, _, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.flash_loan(
+                    &borrower_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &FLASH_LOAN_RECEIVER_ID,
+                    loan_amount,
+                )
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // flash loans are disabled until a flash loan fee is set
+        {
+            let (token_a_key, mut token_a_account, _, _, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::FlashLoansDisabled.into()),
+                accounts.flash_loan(
+                    &borrower_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &FLASH_LOAN_RECEIVER_ID,
+                    loan_amount,
+                )
+            );
+        }
+
+        accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES).unwrap();
+
+        // receiver cannot be the swap program
+        {
+            let (token_a_key, mut token_a_account, _, _, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.flash_loan(
+                    &borrower_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &SWAP_PROGRAM_ID,
+                    loan_amount,
+                )
+            );
+        }
+
+        // loan not repaid
+        {
+            let (token_a_key, mut token_a_account, _, _, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::FlashLoanNotRepaid.into()),
+                accounts.flash_loan(
+                    &borrower_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &FLASH_LOAN_DEFAULTER_ID,
+                    loan_amount,
+                )
+            );
+            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            assert_eq!(swap_token_a.amount, token_a_amount);
+        }
+
+        // correct flash loan of token a
+        {
+            let (token_a_key, mut token_a_account, _, _, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            accounts
+                .flash_loan(
+                    &borrower_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &FLASH_LOAN_RECEIVER_ID,
+                    loan_amount,
+                )
+                .unwrap();
+            assert_eq!(
+                take_result::<FlashLoanResult>(),
+                FlashLoanResult {
+                    amount: loan_amount,
+                    fee: expected_fee,
+                }
+            );
+
+            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            assert_eq!(
+                swap_token_a.amount,
+                token_a_amount + expected_fee - expected_admin_fee
+            );
+            let admin_fee_a =
+                utils::unpack_token_account(&accounts.admin_fee_a_account.data).unwrap();
+            assert_eq!(admin_fee_a.amount, expected_admin_fee);
+            let token_a = utils::unpack_token_account(&token_a_account.data).unwrap();
+            assert_eq!(token_a.amount, initial_a - expected_fee);
+        }
+
+        // correct flash loan of token b
+        {
+            let (_, _, token_b_key, mut token_b_account, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            accounts
+                .flash_loan(
+                    &borrower_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &FLASH_LOAN_RECEIVER_ID,
+                    loan_amount,
+                )
+                .unwrap();
+
+            let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+            assert_eq!(
+                swap_token_b.amount,
+                token_b_amount + expected_fee - expected_admin_fee
+            );
+            let admin_fee_b =
+                utils::unpack_token_account(&accounts.admin_fee_b_account.data).unwrap();
+            assert_eq!(admin_fee_b.amount, expected_admin_fee);
+        }
+
+        // pool is paused
+        {
+            let (token_a_key, mut token_a_account, _, _, _, _) =
+                accounts.setup_token_accounts(&user_key, &borrower_key, initial_a, initial_b, 0);
+            accounts.pause().unwrap();
+            assert_eq!(
+                Err(SwapError::IsPaused.into()),
+                accounts.flash_loan(
+                    &borrower_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &FLASH_LOAN_RECEIVER_ID,
+                    loan_amount,
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_claim_admin_fees() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let amp_factor = 85;
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 1_000_000;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            token_a_amount,
+            token_b_amount,
+            DEFAULT_TEST_FEES,
+        );
+        let initial_a = 100_000;
+        let initial_b = 100_000;
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // swap not initialized
+        {
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.claim_admin_fees()
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+        accounts.set_admin_fee_mode(AdminFeeMode::Accrue).unwrap();
+
+        let invariant = StableSwap::new(
+            accounts.initial_amp_factor,
+            accounts.target_amp_factor,
+            ZERO_TS,
+            ZERO_TS,
+            ZERO_TS,
+        );
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+
+        // admin fees accrue in the reserves
+        let a_to_b_amount = initial_a / 10;
+        let a_to_b = invariant
+            .swap_to(
+                a_to_b_amount,
+                token_a_amount,
+                token_b_amount,
+                &DEFAULT_TEST_FEES,
+            )
+            .unwrap();
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                0,
+            )
+            .unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.token_a.accrued_admin_fees, 0);
+        assert_eq!(swap_info.token_b.accrued_admin_fees, a_to_b.admin_fee);
+        let admin_fee_b = utils::unpack_token_account(&accounts.admin_fee_b_account.data).unwrap();
+        assert_eq!(admin_fee_b.amount, 0);
+        let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.amount, token_b_amount - a_to_b.amount_swapped);
+
+        // accrued admin fees are excluded from the pool reserves
+        let b_to_a_amount = initial_b / 10;
+        let b_to_a = invariant
+            .swap_to(
+                b_to_a_amount,
+                swap_token_b.amount - a_to_b.admin_fee,
+                a_to_b.new_source_amount,
+                &DEFAULT_TEST_FEES,
+            )
+            .unwrap();
+        accounts
+            .swap(
+                &swapper_key,
+                &token_b_key,
+                &mut token_b_account,
+                &swap_token_b_key,
+                &swap_token_a_key,
+                &token_a_key,
+                &mut token_a_account,
+                b_to_a_amount,
+                0,
+            )
+            .unwrap();
+        let token_a = utils::unpack_token_account(&token_a_account.data).unwrap();
+        assert_eq!(
+            token_a.amount,
+            initial_a - a_to_b_amount + b_to_a.amount_swapped
+        );
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.token_a.accrued_admin_fees, b_to_a.admin_fee);
+        assert_eq!(swap_info.token_b.accrued_admin_fees, a_to_b.admin_fee);
+
+        // wrong admin fee account
+        {
+            let old_admin_fee_a_key = accounts.admin_fee_a_key;
+            accounts.admin_fee_a_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::InvalidAdmin.into()),
+                accounts.claim_admin_fees()
+            );
+            accounts.admin_fee_a_key = old_admin_fee_a_key;
+        }
+
+        // claim accrued fees
+        {
+            let old_swap_token_a =
+                utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            let old_swap_token_b =
+                utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+            accounts.claim_admin_fees().unwrap();
+
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_info.token_a.accrued_admin_fees, 0);
+            assert_eq!(swap_info.token_b.accrued_admin_fees, 0);
+            let admin_fee_a =
+                utils::unpack_token_account(&accounts.admin_fee_a_account.data).unwrap();
+            assert_eq!(admin_fee_a.amount, b_to_a.admin_fee);
+            let admin_fee_b =
+                utils::unpack_token_account(&accounts.admin_fee_b_account.data).unwrap();
+            assert_eq!(admin_fee_b.amount, a_to_b.admin_fee);
+            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            assert_eq!(
+                swap_token_a.amount,
+                old_swap_token_a.amount - b_to_a.admin_fee
+            );
+            let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+            assert_eq!(
+                swap_token_b.amount,
+                old_swap_token_b.amount - a_to_b.admin_fee
+            );
+
+            // nothing left to claim
+            accounts.claim_admin_fees().unwrap();
+            let admin_fee_a =
+                utils::unpack_token_account(&accounts.admin_fee_a_account.data).unwrap();
+            assert_eq!(admin_fee_a.amount, b_to_a.admin_fee);
+        }
+
+        // the program id may be passed instead of the admin fee account
+        {
+            let old_admin_fee_b_key = accounts.admin_fee_b_key;
+            accounts.admin_fee_b_key = SWAP_PROGRAM_ID;
+            accounts
+                .swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    a_to_b_amount,
+                    0,
+                )
+                .unwrap();
+            accounts.admin_fee_b_key = old_admin_fee_b_key;
+            let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            assert!(swap_info.token_b.accrued_admin_fees > 0);
+        }
+    }
+
+    #[test]
+    fn test_claim_admin_fees_as_lp() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let admin_owner_key = pubkey_rand();
+        let amp_factor = 85;
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 1_000_000;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            token_a_amount,
+            token_b_amount,
+            DEFAULT_TEST_FEES,
+        );
+        let initial_a = 100_000;
+        let initial_b = 100_000;
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        accounts.initialize_swap().unwrap();
+        let (_, _, _, _, lp_admin_fee_key, mut lp_admin_fee_account) =
+            accounts.setup_token_accounts(&user_key, &admin_owner_key, 0, 0, 0);
+        accounts
+            .set_admin_fee_account(&lp_admin_fee_key, &lp_admin_fee_account)
+            .unwrap();
+        accounts.set_admin_fee_mode(AdminFeeMode::LpMint).unwrap();
+
+        let invariant = StableSwap::new(
+            accounts.initial_amp_factor,
+            accounts.target_amp_factor,
+            ZERO_TS,
+            ZERO_TS,
+            ZERO_TS,
+        );
+
+        // missing pool token accounts
+        {
+            assert_eq!(
+                Err(ProgramError::NotEnoughAccountKeys),
+                accounts.claim_admin_fees()
+            );
+        }
+
+        // wrong LP admin fee account
+        {
+            let (_, _, _, _, wrong_key, mut wrong_account) =
+                accounts.setup_token_accounts(&user_key, &admin_owner_key, 0, 0, 0);
+            assert_eq!(
+                Err(SwapError::InvalidAdmin.into()),
+                accounts.claim_admin_fees_as_lp(&wrong_key, &mut wrong_account)
+            );
+        }
+
+        // switching the mode records the checkpoint
+        let pool_mint = utils::unpack_mint(&accounts.pool_mint_account.data).unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.lp_admin_fee_last_supply, pool_mint.supply);
+        assert_eq!(
+            swap_info.lp_admin_fee_last_amp_factor,
+            invariant.compute_amp_factor().unwrap()
+        );
+        assert_eq!(
+            swap_info.lp_admin_fee_last_d,
+            invariant
+                .compute_d(token_a_amount, token_b_amount)
+                .unwrap()
+                .to_u64()
+                .unwrap()
+        );
+
+        // nothing to mint before the pool grows
+        accounts
+            .claim_admin_fees_as_lp(&lp_admin_fee_key, &mut lp_admin_fee_account)
+            .unwrap();
+        let lp_admin_fee = utils::unpack_token_account(&lp_admin_fee_account.data).unwrap();
+        assert_eq!(lp_admin_fee.amount, 0);
+
+        // admin fees stay in the pool
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+        let a_to_b_amount = initial_a / 2;
+        let result = invariant
+            .swap_to(
+                a_to_b_amount,
+                token_a_amount,
+                token_b_amount,
+                &DEFAULT_TEST_FEES,
+            )
+            .unwrap();
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                0,
+            )
+            .unwrap();
+        let admin_fee_b = utils::unpack_token_account(&accounts.admin_fee_b_account.data).unwrap();
+        assert_eq!(admin_fee_b.amount, 0);
+        let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.amount, token_b_amount - result.amount_swapped);
+
+        // the mode cannot be switched before the growth is claimed
+        assert_eq!(
+            Err(SwapError::UnclaimedAdminFees.into()),
+            accounts.set_admin_fee_mode(AdminFeeMode::Accrue)
+        );
+
+        // admin share of the growth is minted as pool tokens
+        let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+        let (expected_mint_amount, expected_d) = invariant
+            .compute_admin_lp_fee(
+                swap_token_a.amount,
+                swap_token_b.amount,
+                pool_mint.supply,
+                swap_info.lp_admin_fee_last_d,
+                swap_info.lp_admin_fee_last_supply,
+                swap_info.lp_admin_fee_last_amp_factor,
+                &DEFAULT_TEST_FEES,
+            )
+            .unwrap();
+        assert!(expected_mint_amount > 0);
+        accounts
+            .claim_admin_fees_as_lp(&lp_admin_fee_key, &mut lp_admin_fee_account)
+            .unwrap();
+        let lp_admin_fee = utils::unpack_token_account(&lp_admin_fee_account.data).unwrap();
+        assert_eq!(lp_admin_fee.amount, expected_mint_amount);
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.lp_admin_fee_last_d, expected_d);
+        assert_eq!(
+            swap_info.lp_admin_fee_last_supply,
+            pool_mint.supply + expected_mint_amount
+        );
+
+        // nothing to mint without further growth
+        accounts
+            .claim_admin_fees_as_lp(&lp_admin_fee_key, &mut lp_admin_fee_account)
+            .unwrap();
+        let lp_admin_fee = utils::unpack_token_account(&lp_admin_fee_account.data).unwrap();
+        assert_eq!(lp_admin_fee.amount, expected_mint_amount);
+
+        // the mode can be switched once the growth is claimed
+        accounts.set_admin_fee_mode(AdminFeeMode::Accrue).unwrap();
+    }
+
+    #[test]
+    fn test_token_2022_pool() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let amp_factor = 85;
+        let token_a_amount = 5000;
+        let token_b_amount = 5000;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            token_a_amount,
+            token_b_amount,
+            DEFAULT_TEST_FEES,
+        );
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+        token_a_account.owner = token_2022::id();
+        token_b_account.owner = token_2022::id();
+        accounts.convert_to_token_2022();
+
+        // token program does not own the accounts
+        assert_eq!(
+            Err(ProgramError::IncorrectProgramId),
+            accounts.initialize_swap()
+        );
+
+        accounts
+            .initialize_swap_with_token_program(&token_2022::id())
+            .unwrap();
+        let swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_info.token_program_id, token_2022::id());
+
+        let swap_ix = |token_program_id: &Pubkey, accounts: &SwapAccountInfo| {
+            swap(
+                token_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &swapper_key,
+                &token_a_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &token_b_key,
+                &accounts.admin_fee_b_key,
+                initial_a,
+                0,
+            )
+            .unwrap()
+        };
+
+        // wrong token program
+        {
+            let mut swap_token_a_account = accounts.token_a_account.clone();
+            let mut swap_token_b_account = accounts.token_b_account.clone();
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                do_process_instruction_at_time(
+                    swap_ix(&spl_token::id(), &accounts),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account,
+                        &mut swap_token_a_account,
+                        &mut swap_token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.admin_fee_b_account,
+                        &mut Account::default(),
+                    ],
+                    ZERO_TS,
+                )
+            );
+        }
+
+        // mints must be passed in order
+        {
+            let mut swap_token_a_account = accounts.token_a_account.clone();
+            let mut swap_token_b_account = accounts.token_b_account.clone();
+            assert_eq!(
+                Err(SwapError::IncorrectMint.into()),
+                do_process_instruction_at_time(
+                    with_token_2022_mints(
+                        swap_ix(&token_2022::id(), &accounts),
+                        &accounts.token_b_mint_key,
+                        &accounts.token_a_mint_key,
+                    ),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account,
+                        &mut swap_token_a_account,
+                        &mut swap_token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.admin_fee_b_account,
+                        &mut Account::default(),
+                        &mut accounts.token_b_mint_account.clone(),
+                        &mut accounts.token_a_mint_account.clone(),
+                    ],
+                    ZERO_TS,
+                )
+            );
+        }
+
+        // swap with checked transfers
+        {
+            let invariant = StableSwap::new(amp_factor, amp_factor, ZERO_TS, ZERO_TS, ZERO_TS);
+            let result = invariant
+                .swap_to(
+                    initial_a,
+                    token_a_amount,
+                    token_b_amount,
+                    &DEFAULT_TEST_FEES,
+                )
+                .unwrap();
+            do_process_instruction_at_time(
+                with_token_2022_mints(
+                    swap_ix(&token_2022::id(), &accounts),
+                    &accounts.token_a_mint_key,
+                    &accounts.token_b_mint_key,
+                ),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut token_a_account,
+                    &mut accounts.token_a_account,
+                    &mut accounts.token_b_account,
+                    &mut token_b_account,
+                    &mut accounts.admin_fee_b_account,
+                    &mut Account::default(),
+                    &mut accounts.token_a_mint_account.clone(),
+                    &mut accounts.token_b_mint_account.clone(),
+                ],
+                ZERO_TS,
+            )
+            .unwrap();
+
+            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            assert_eq!(swap_token_a.amount, token_a_amount + initial_a);
+            let token_b = utils::unpack_token_account(&token_b_account.data).unwrap();
+            assert_eq!(token_b.amount, initial_b + result.amount_swapped);
+        }
+
+        // results and events report the output net of the transfer fee
+        {
+            let transfer_fee = token_2022::TransferFee {
+                epoch: 0,
+                maximum_fee: u64::MAX,
+                transfer_fee_basis_points: 100,
+            };
+            set_transfer_fee(&mut accounts.token_a_mint_account, &transfer_fee);
+            let amount_in = initial_b / 2;
+            let swap_token_a = utils::unpack_token_account(&accounts.token_a_account.data).unwrap();
+            let swap_token_b = utils::unpack_token_account(&accounts.token_b_account.data).unwrap();
+            let invariant = StableSwap::new(amp_factor, amp_factor, ZERO_TS, ZERO_TS, ZERO_TS);
+            let result = invariant
+                .swap_to(
+                    amount_in,
+                    swap_token_b.amount,
+                    swap_token_a.amount,
+                    &DEFAULT_TEST_FEES,
+                )
+                .unwrap();
+            let amount_out = transfer_fee
+                .calculate_post_fee_amount(result.amount_swapped)
+                .unwrap();
+            assert!(amount_out < result.amount_swapped);
+            take_events();
+            do_process_instruction_at_time(
+                with_token_2022_mints(
+                    swap(
+                        &token_2022::id(),
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &swapper_key,
+                        &token_b_key,
+                        &accounts.token_b_key,
+                        &accounts.token_a_key,
+                        &token_a_key,
+                        &accounts.admin_fee_a_key,
+                        amount_in,
+                        amount_out,
+                    )
+                    .unwrap(),
+                    &accounts.token_a_mint_key,
+                    &accounts.token_b_mint_key,
+                ),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut token_b_account,
+                    &mut accounts.token_b_account,
+                    &mut accounts.token_a_account,
+                    &mut token_a_account,
+                    &mut accounts.admin_fee_a_account,
+                    &mut Account::default(),
+                    &mut accounts.token_a_mint_account.clone(),
+                    &mut accounts.token_b_mint_account.clone(),
+                ],
+                ZERO_TS,
+            )
+            .unwrap();
+            assert_eq!(take_result::<SwapResult>().amount_out, amount_out);
+            match take_events().as_slice() {
+                [events::Event::Swap(event)] => {
+                    assert_eq!(event.amount_out, amount_out);
+                    // the pool mint was not passed
+                    assert_eq!(event.virtual_price, None);
+                }
+                events => panic!("unexpected events {:?}", events),
+            }
+        }
+    }
+
+    #[test]
+    fn test_initialize_token_2022_extensions() {
+        let user_key = pubkey_rand();
+        let amp_factor = 85;
+        let token_a_amount = 5000;
+        let token_b_amount = 5000;
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            amp_factor,
+            token_a_amount,
+            token_b_amount,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.convert_to_token_2022();
+
+        // extensions are appended to the mint after the account type
+        let base_mint_data = accounts.token_a_mint_account.data.clone();
+        let extend_mint = |extensions: &[(u16, usize)]| {
+            let mut data = base_mint_data.clone();
+            data.resize(token_2022::ACCOUNT_LEN, 0);
+            data.push(1);
+            for (extension_type, len) in extensions {
+                data.extend_from_slice(&extension_type.to_le_bytes());
+                data.extend_from_slice(&(*len as u16).to_le_bytes());
+                data.extend(vec![0u8; *len]);
+            }
+            data
+        };
+
+        for unsupported in [(9, 0), (12, 32)] {
+            accounts.token_a_mint_account.data = extend_mint(&[unsupported]);
+            assert_eq!(
+                Err(SwapError::UnsupportedMint.into()),
+                accounts.initialize_swap_with_token_program(&token_2022::id())
+            );
+        }
+
+        // transfer fees and interest are supported
+        accounts.token_a_mint_account.data = extend_mint(&[(1, 108), (10, 52)]);
+        accounts
+            .initialize_swap_with_token_program(&token_2022::id())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_events() {
+        let user_key = pubkey_rand();
+        let depositor_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000,
+            1_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &depositor_key, 1_100, 1_000, 0);
+        let reserves = |accounts: &SwapAccountInfo| events::Reserves {
+            token_a: utils::unpack_token_account(&accounts.token_a_account.data)
+                .unwrap()
+                .amount,
+            token_b: utils::unpack_token_account(&accounts.token_b_account.data)
+                .unwrap()
+                .amount,
+        };
+        let pool_token_supply = |accounts: &SwapAccountInfo| {
+            utils::unpack_mint(&accounts.pool_mint_account.data)
+                .unwrap()
+                .supply
+        };
+
+        accounts
+            .deposit(
+                &depositor_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                1_000,
+                1_000,
+                0,
+            )
+            .unwrap();
+        let pool_token_amount = utils::unpack_token_account(&pool_account.data)
+            .unwrap()
+            .amount;
+        match take_events().as_slice() {
+            [events::Event::Deposit(event)] => {
+                assert_eq!(event.swap, accounts.swap_key);
+                assert_eq!(event.user, depositor_key);
+                assert_eq!(event.token_a_amount, 1_000);
+                assert_eq!(event.token_b_amount, 1_000);
+                assert_eq!(event.pool_token_amount, pool_token_amount);
+                assert_eq!(event.pool.reserves, reserves(&accounts));
+                assert_eq!(event.pool.pool_token_supply, pool_token_supply(&accounts));
+                // balanced pool with no fees earned yet
+                assert_eq!(event.pool.virtual_price, 10_u64.pow(12));
+                assert_eq!(
+                    take_result::<DepositResult>(),
+                    DepositResult {
+                        token_a_amount: 1_000,
+                        token_b_amount: 1_000,
+                        pool_token_amount,
+                        pool: event.pool,
+                    }
+                );
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        accounts
+            .swap(
+                &depositor_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                100,
+                0,
+            )
+            .unwrap();
+        match take_events().as_slice() {
+            [events::Event::Swap(event)] => {
+                assert_eq!(event.swap, accounts.swap_key);
+                assert_eq!(event.user, depositor_key);
+                assert!(event.a_to_b);
+                assert_eq!(event.amount_in, 100);
+                assert_eq!(
+                    event.amount_out,
+                    utils::unpack_token_account(&token_b_account.data)
+                        .unwrap()
+                        .amount
+                );
+                assert!(event.fee > 0);
+                assert_eq!(event.admin_fee, event.fee / 2);
+                assert_eq!(event.reserves, reserves(&accounts));
+                assert_eq!(
+                    take_result::<SwapResult>(),
+                    SwapResult {
+                        amount_in: 100,
+                        amount_out: event.amount_out,
+                        fee: event.fee,
+                        admin_fee: event.admin_fee,
+                        referral_fee: event.referral_fee,
+                        reserves: event.reserves,
+                    }
+                );
+                assert_eq!(event.referral_fee, 0);
+                accounts.get_virtual_price().unwrap();
+                let pool = take_result::<events::PoolState>();
+                assert_eq!(event.virtual_price, Some(pool.virtual_price));
+                assert!(pool.virtual_price > 10_u64.pow(12));
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        accounts
+            .withdraw_one(
+                &depositor_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                100,
+                0,
+            )
+            .unwrap();
+        match take_events().as_slice() {
+            [events::Event::WithdrawOne(event)] => {
+                assert_eq!(event.user, depositor_key);
+                assert_eq!(event.token_index, 0);
+                assert_eq!(
+                    event.token_amount,
+                    utils::unpack_token_account(&token_a_account.data)
+                        .unwrap()
+                        .amount
+                );
+                assert_eq!(event.pool_token_amount, 100);
+                assert_eq!(event.pool.reserves, reserves(&accounts));
+                assert_eq!(event.pool.pool_token_supply, pool_token_supply(&accounts));
+                // fees earned by the pool increase the virtual price
+                assert!(event.pool.virtual_price > 10_u64.pow(12));
+                assert_eq!(
+                    take_result::<WithdrawOneResult>(),
+                    WithdrawOneResult {
+                        token_amount: event.token_amount,
+                        fee: event.fee,
+                        pool_token_amount: 100,
+                        pool: event.pool,
+                    }
+                );
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        accounts
+            .withdraw(
+                &depositor_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                100,
+                0,
+                0,
+            )
+            .unwrap();
+        match take_events().as_slice() {
+            [events::Event::Withdraw(event)] => {
+                assert_eq!(event.user, depositor_key);
+                assert_eq!(event.pool_token_amount, 100);
+                assert!(event.token_a_amount > 0 && event.token_b_amount > 0);
+                assert_eq!(event.pool.reserves, reserves(&accounts));
+                assert_eq!(event.pool.pool_token_supply, pool_token_supply(&accounts));
+                assert_eq!(
+                    take_result::<WithdrawResult>(),
+                    WithdrawResult {
+                        token_a_amount: event.token_a_amount,
+                        token_b_amount: event.token_b_amount,
+                        token_a_fee: event.token_a_fee,
+                        token_b_fee: event.token_b_fee,
+                        pool_token_amount: 100,
+                        pool: event.pool,
+                    }
+                );
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+    }
+
+    #[test]
+    fn test_swap_with_referral() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let referrer_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000,
+            1_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        accounts.set_extra_fees(DEFAULT_TEST_EXTRA_FEES).unwrap();
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, 10_000, 0, 0);
+        let (_, _, referrer_token_b_key, mut referrer_token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &referrer_key, 0, 0, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // referrer cannot be the reserves of the swap
+        {
+            let mut swap_token_b_account = accounts.token_b_account.clone();
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap_with_referral(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    Some((&swap_token_b_key, &mut swap_token_b_account)),
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // the referrer receives its share of the trade fee
+        accounts
+            .swap_with_referral(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                Some((&referrer_token_b_key, &mut referrer_token_b_account)),
+                1_000,
+                0,
+            )
+            .unwrap();
+        let result = take_result::<SwapResult>();
+        assert!(result.fee > 0);
+        assert_eq!(result.admin_fee, result.fee / 2);
+        assert_eq!(result.referral_fee, result.fee / 4);
+        match take_events().as_slice() {
+            [events::Event::Swap(event)] => assert_eq!(event.referral_fee, result.referral_fee),
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert_eq!(
+            utils::unpack_token_account(&referrer_token_b_account.data)
+                .unwrap()
+                .amount,
+            result.referral_fee
+        );
+        assert_eq!(
+            utils::unpack_token_account(&token_b_account.data)
+                .unwrap()
+                .amount,
+            result.amount_out
+        );
+        assert_eq!(
+            utils::unpack_token_account(&accounts.token_b_account.data)
+                .unwrap()
+                .amount,
+            1_000_000 - result.amount_out - result.admin_fee - result.referral_fee
+        );
+        assert_eq!(
+            result.reserves.token_b,
+            1_000_000 - result.amount_out - result.admin_fee - result.referral_fee
+        );
+
+        // without a referrer, no referral fee is charged
+        accounts
+            .swap_with_referral(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                None,
+                1_000,
+                0,
+            )
+            .unwrap();
+        let result = take_result::<SwapResult>();
+        assert!(result.fee > 0);
+        assert_eq!(result.referral_fee, 0);
+    }
+
+    #[test]
+    fn test_swap_with_fee_tier() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let payer_key = pubkey_rand();
+        let mut payer_account = Account::new(1_000_000_000, 0, &system_program::id());
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000,
+            1_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, 10_000, 0, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let (fee_tier_key, _) =
+            find_fee_tier_address(&SWAP_PROGRAM_ID, Some(&accounts.swap_key), &swapper_key);
+        let mut fee_tier_account = Account::new(0, FeeTier::LEN, &system_program::id());
+
+        // only the admin and fee manager can set fee tiers
+        {
+            let admin_key = accounts.admin_key;
+            accounts.admin_key = pubkey_rand();
+            assert_eq!(
+                Err(SwapError::Unauthorized.into()),
+                accounts.set_fee_tier(
+                    &payer_key,
+                    &mut payer_account,
+                    &swapper_key,
+                    &mut fee_tier_account,
+                    5_000
+                )
+            );
+            accounts.admin_key = admin_key;
+        }
+
+        // the multiplier cannot exceed 100%
+        {
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.set_fee_tier(
+                    &payer_key,
+                    &mut payer_account,
+                    &swapper_key,
+                    &mut fee_tier_account,
+                    10_001
+                )
+            );
+        }
+
+        accounts
+            .set_fee_tier(
+                &payer_key,
+                &mut payer_account,
+                &swapper_key,
+                &mut fee_tier_account,
+                5_000,
+            )
+            .unwrap();
+        let fee_tier = FeeTier::unpack(&fee_tier_account.data).unwrap();
+        assert_eq!(fee_tier.swap, accounts.swap_key);
+        assert_eq!(fee_tier.authority, swapper_key);
+        assert_eq!(fee_tier.fee_multiplier_bps, 5_000);
+
+        // the swapper pays half of the trade fee
+        accounts
+            .quote_swap(&swap_token_a_key, &swap_token_b_key, 1_000)
+            .unwrap();
+        let quoted = take_result::<SwapResult>();
+        accounts
+            .quote_swap_with_fee_tier(
+                &swap_token_a_key,
+                &swap_token_b_key,
+                Some((&fee_tier_key, &mut fee_tier_account)),
+                1_000,
+            )
+            .unwrap();
+        let quoted_with_fee_tier = take_result::<SwapResult>();
+        accounts
+            .swap_with_fee_tier(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                Some((&fee_tier_key, &mut fee_tier_account)),
+                1_000,
+                0,
+            )
+            .unwrap();
+        let result = take_result::<SwapResult>();
+        let dy = quoted.amount_out + quoted.fee;
+        assert_eq!(result.amount_out + result.fee, dy);
+        assert_eq!(result.fee, dy * 3 / 100);
+        assert!(result.fee < quoted.fee);
+        assert_eq!(result.admin_fee, result.fee / 2);
+        assert_eq!(result, quoted_with_fee_tier);
+
+        // the fee tier only applies to its user
+        {
+            let other_key = pubkey_rand();
+            let (other_a_key, mut other_a_account, other_b_key, mut other_b_account, _, _) =
+                accounts.setup_token_accounts(&user_key, &other_key, 10_000, 0, 0);
+            assert_eq!(
+                Err(SwapError::InvalidFeeTier.into()),
+                accounts.swap_with_fee_tier(
+                    &other_key,
+                    &other_a_key,
+                    &mut other_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &other_b_key,
+                    &mut other_b_account,
+                    Some((&fee_tier_key, &mut fee_tier_account)),
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // the account in the position of the fee tier must be a fee tier
+        {
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.swap_with_fee_tier(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    Some((&pubkey_rand(), &mut Account::default())),
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // the fee tier only applies to its swap, and global tiers only to
+        // swaps that accept them
+        for (swap, expected) in [
+            (pubkey_rand(), Err(SwapError::InvalidFeeTier.into())),
+            (Pubkey::default(), Err(SwapError::InvalidFeeTier.into())),
+        ] {
+            let mut other_fee_tier_account = fee_tier_account.clone();
+            FeeTier::pack(
+                FeeTier { swap, ..fee_tier },
+                &mut other_fee_tier_account.data,
+            )
+            .unwrap();
+            assert_eq!(
+                expected,
+                accounts.swap_with_fee_tier(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    Some((&fee_tier_key, &mut other_fee_tier_account)),
+                    1_000,
+                    0,
+                )
+            );
+        }
+
+        // global tiers discount swaps that accept them once active
+        {
+            let mut swap_info = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            swap_info.accepts_global_fee_tiers = true;
+            SwapInfo::pack(swap_info, &mut accounts.swap_account.data).unwrap();
+            accounts
+                .quote_swap(&swap_token_a_key, &swap_token_b_key, 1_000)
+                .unwrap();
+            let quoted = take_result::<SwapResult>();
+            for (activation_ts, is_active) in [(ZERO_TS + 1, false), (ZERO_TS, true)] {
+                let mut global_fee_tier_account = fee_tier_account.clone();
+                FeeTier::pack(
+                    FeeTier {
+                        swap: Pubkey::default(),
+                        activation_ts,
+                        ..fee_tier
+                    },
+                    &mut global_fee_tier_account.data,
+                )
+                .unwrap();
+                accounts
+                    .quote_swap_with_fee_tier(
+                        &swap_token_a_key,
+                        &swap_token_b_key,
+                        Some((&fee_tier_key, &mut global_fee_tier_account)),
+                        1_000,
+                    )
+                    .unwrap();
+                let quoted_with_global_tier = take_result::<SwapResult>();
+                assert_eq!(quoted_with_global_tier.fee < quoted.fee, is_active);
+            }
+        }
+
+        // removing the fee tier returns its rent
+        let rent_destination_key = pubkey_rand();
+        let mut rent_destination_account = Account::default();
+        let fee_tier_lamports = fee_tier_account.lamports;
+        accounts
+            .remove_fee_tier(
+                &swapper_key,
+                &mut fee_tier_account,
+                &rent_destination_key,
+                &mut rent_destination_account,
+            )
+            .unwrap();
+        assert_eq!(fee_tier_account.lamports, 0);
+        assert_eq!(rent_destination_account.lamports, fee_tier_lamports);
+        assert!(fee_tier_account.data.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_last_d() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000_000,
+            3_000_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, 1_000_000_000, 1_000_000_000, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        // the stored invariant is the one of the reserves after the last instruction
+        let check_last_d = |accounts: &SwapAccountInfo| {
+            let token_swap = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            let amount_a = utils::unpack_token_account(&accounts.token_a_account.data)
+                .unwrap()
+                .amount;
+            let amount_b = utils::unpack_token_account(&accounts.token_b_account.data)
+                .unwrap()
+                .amount;
+            let invariant = StableSwap::new_from_swap_info(&token_swap, ZERO_TS);
+            let d = invariant.compute_d(amount_a, amount_b).unwrap();
+            assert_eq!(d, token_swap.last_d.into());
+            assert_eq!(token_swap.last_sum_x, amount_a + amount_b);
+            assert_eq!(token_swap.last_amp_factor, MIN_AMP * 100);
+        };
+        check_last_d(&accounts);
+
+        for (i, amount_in) in [1_000, 100_000, 10_000_000, 1_000_000, 50_000_000, 10]
+            .into_iter()
+            .enumerate()
+        {
+            if i % 2 == 0 {
+                accounts
+                    .swap(
+                        &swapper_key,
+                        &token_a_key,
+                        &mut token_a_account,
+                        &swap_token_a_key,
+                        &swap_token_b_key,
+                        &token_b_key,
+                        &mut token_b_account,
+                        amount_in,
+                        0,
+                    )
+                    .unwrap();
+            } else {
+                accounts
+                    .swap(
+                        &swapper_key,
+                        &token_b_key,
+                        &mut token_b_account,
+                        &swap_token_b_key,
+                        &swap_token_a_key,
+                        &token_a_key,
+                        &mut token_a_account,
+                        amount_in,
+                        0,
+                    )
+                    .unwrap();
+            }
+            check_last_d(&accounts);
+        }
+
+        accounts
+            .deposit(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                100_000_000,
+                0,
+                0,
+            )
+            .unwrap();
+        check_last_d(&accounts);
+
+        let pool_token_amount = utils::unpack_token_account(&pool_account.data)
+            .unwrap()
+            .amount;
+        accounts
+            .withdraw_one(
+                &swapper_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                pool_token_amount / 2,
+                0,
+            )
+            .unwrap();
+        check_last_d(&accounts);
+
+        accounts
+            .withdraw(
+                &swapper_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                pool_token_amount / 4,
+                0,
+                0,
+            )
+            .unwrap();
+        check_last_d(&accounts);
+    }
+
+    /// Native tests have no compute meter, so this compares the iterations of
+    /// Newton's method for D, which dominate the compute units of a swap.
+    #[test]
+    fn test_last_d_benchmark() {
+        let user_key = pubkey_rand();
+        let swapper_key = pubkey_rand();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            MIN_AMP * 100,
+            1_000_000_000,
+            3_000_000_000,
+            DEFAULT_TEST_FEES,
+        );
+        accounts.initialize_swap().unwrap();
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, 1_000_000_000, 1_000_000_000, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let read_state = |accounts: &SwapAccountInfo| {
+            let token_swap = SwapInfo::unpack(&accounts.swap_account.data).unwrap();
+            let amount_a = utils::unpack_token_account(&accounts.token_a_account.data)
+                .unwrap()
+                .amount;
+            let amount_b = utils::unpack_token_account(&accounts.token_b_account.data)
+                .unwrap()
+                .amount;
+            (token_swap, amount_a, amount_b)
+        };
+
+        let (mut cold, mut warm) = (0, 0);
+        for i in 0..100u64 {
+            let (token_swap, amount_a, amount_b) = read_state(&accounts);
+            let invariant = StableSwap::new_from_swap_info(&token_swap, ZERO_TS);
+            // before the invariant was stored, swaps started at sum(x_i) and
+            // computed no invariant after the trade
+            cold += invariant
+                .compute_d_iterations(amount_a, amount_b, 0.into())
+                .unwrap();
+            let d_before = invariant.compute_d(amount_a, amount_b).unwrap();
+            warm += invariant
+                .compute_d_iterations(amount_a, amount_b, token_swap.last_d.into())
+                .unwrap();
+
+            let amount_in = 1_000 * (i % 7 + 1).pow(6);
+            if i % 2 == 0 {
+                accounts
+                    .swap(
+                        &swapper_key,
+                        &token_a_key,
+                        &mut token_a_account,
+                        &swap_token_a_key,
+                        &swap_token_b_key,
+                        &token_b_key,
+                        &mut token_b_account,
+                        amount_in,
+                        0,
+                    )
+                    .unwrap();
+            } else {
+                accounts
+                    .swap(
+                        &swapper_key,
+                        &token_b_key,
+                        &mut token_b_account,
+                        &swap_token_b_key,
+                        &swap_token_a_key,
+                        &token_a_key,
+                        &mut token_a_account,
+                        amount_in,
+                        0,
+                    )
+                    .unwrap();
+            }
+            // the invariant after the swap starts at the one before it
+            let (_, amount_a, amount_b) = read_state(&accounts);
+            warm += invariant
+                .compute_d_iterations(amount_a, amount_b, d_before)
+                .unwrap();
+        }
+        println!(
+            "Newton iterations of D for 100 swaps: {} before, {} after",
+            cold, warm
+        );
+    }
 }
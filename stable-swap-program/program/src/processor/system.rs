@@ -0,0 +1,80 @@
+//! System program helpers
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+/// Creates a rent-exempt account of `space` bytes owned by `owner`, signed by
+/// `signer_seeds` if the account is a program derived address.
+///
+/// Accounts that already hold lamports cannot be created with `CreateAccount`,
+/// so they are topped up, allocated and assigned instead. This prevents anyone
+/// from blocking the creation of a program derived address by funding it.
+pub fn create_account<'a>(
+    payer: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    owner: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = account.lamports();
+    if current_lamports == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                required_lamports,
+                space as u64,
+                owner,
+            ),
+            &[payer, account, system_program],
+            signer_seeds,
+        );
+    }
+
+    let top_up = required_lamports.saturating_sub(current_lamports);
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, top_up),
+            &[payer, account.clone(), system_program.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program.clone()],
+        signer_seeds,
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &[account, system_program],
+        signer_seeds,
+    )
+}
+
+/// Reallocates `account` to `new_len` bytes, topping up its lamports from
+/// `payer` to keep it rent-exempt. The account must be owned by the program.
+pub fn realloc_account<'a>(
+    payer: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    let top_up = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, top_up),
+            &[payer, account.clone(), system_program],
+        )?;
+    }
+    account.realloc(new_len, true)
+}
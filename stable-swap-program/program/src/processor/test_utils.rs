@@ -1,23 +1,52 @@
 //! Test utility methods
 #![allow(clippy::too_many_arguments)]
 
-use crate::{curve::ZERO_TS, fees::Fees, instruction::*, processor::Processor, state::SwapInfo};
+use crate::{
+    curve::ZERO_TS,
+    events::Event,
+    fees::{ExtraFees, Fees},
+    instruction::*,
+    oracle::{pyth_program, PriceFeed},
+    pda::{find_allowlist_entry_address, find_gate_address},
+    processor::Processor,
+    results::InstructionResult,
+    state::{AdminFeeMode, AdminRole, AllowlistEntry, Gate, PauseFlags, RegistryEntry, SwapInfo},
+    token_2022,
+};
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+    account_info::AccountInfo,
+    entrypoint::{deserialize, ProgramResult, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE},
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
 };
 use solana_program::{
-    clock::Clock, msg, program_pack::Pack, program_stubs, pubkey::Pubkey, rent::Rent,
+    bpf_loader_upgradeable,
+    clock::{Clock, Slot},
+    msg,
+    program_pack::Pack,
+    program_stubs,
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::{SystemError, SystemInstruction},
+    system_program,
 };
-use solana_sdk::account::{create_account_for_test, create_is_signer_account_infos, Account};
+use solana_sdk::account::{create_account_for_test, Account};
 use spl_token::{
     instruction::{initialize_account, initialize_mint, mint_to},
     state::{Account as SplAccount, Mint as SplMint},
 };
+use std::cell::{Cell, RefCell};
 
 /// Test program id for the swap program.
 pub static SWAP_PROGRAM_ID: Pubkey = crate::ID;
 
+/// Mock flash loan receiver that repays the loan plus fee.
+pub const FLASH_LOAN_RECEIVER_ID: Pubkey = Pubkey::new_from_array([1u8; 32]);
+
+/// Mock flash loan receiver that only repays the principal.
+pub const FLASH_LOAN_DEFAULTER_ID: Pubkey = Pubkey::new_from_array([2u8; 32]);
+
 /// Fees for testing
 pub const DEFAULT_TEST_FEES: Fees = Fees {
     admin_trade_fee_numerator: 1,
@@ -30,6 +59,14 @@ pub const DEFAULT_TEST_FEES: Fees = Fees {
     withdraw_fee_denominator: 100,
 };
 
+/// Flash loan fees for testing
+pub const DEFAULT_TEST_EXTRA_FEES: ExtraFees = ExtraFees {
+    flash_loan_fee_numerator: 1,
+    flash_loan_fee_denominator: 100,
+    referral_fee_numerator: 1,
+    referral_fee_denominator: 4,
+};
+
 /// Default token decimals
 pub const DEFAULT_TOKEN_DECIMALS: u8 = 6;
 
@@ -63,6 +100,10 @@ pub struct SwapAccountInfo {
     pub admin_fee_b_key: Pubkey,
     pub admin_fee_b_account: Account,
     pub fees: Fees,
+    /// Allowlist entry passed to the instructions of gated swaps
+    pub allowlist_entry: Option<(Pubkey, Account)>,
+    /// Price feed passed to the swaps of swaps with a price oracle
+    pub price_feed: Option<(Pubkey, Account)>,
 }
 
 impl SwapAccountInfo {
@@ -157,13 +198,22 @@ impl SwapAccountInfo {
             admin_fee_b_key,
             admin_fee_b_account,
             fees,
+            allowlist_entry: None,
+            price_feed: None,
         }
     }
 
     pub fn initialize_swap(&mut self) -> ProgramResult {
+        self.initialize_swap_with_token_program(&spl_token::id())
+    }
+
+    pub fn initialize_swap_with_token_program(
+        &mut self,
+        token_program_id: &Pubkey,
+    ) -> ProgramResult {
         do_process_instruction(
             initialize(
-                &spl_token::id(),
+                token_program_id,
                 &self.swap_key,
                 &self.authority_key,
                 &self.admin_key,
@@ -196,6 +246,23 @@ impl SwapAccountInfo {
         )
     }
 
+    /// Moves all tokens of the swap to the Token-2022 program. Token-2022
+    /// instructions on accounts without extensions are processed by spl_token.
+    pub fn convert_to_token_2022(&mut self) {
+        for account in [
+            &mut self.pool_mint_account,
+            &mut self.pool_token_account,
+            &mut self.token_a_mint_account,
+            &mut self.token_a_account,
+            &mut self.admin_fee_a_account,
+            &mut self.token_b_mint_account,
+            &mut self.token_b_account,
+            &mut self.admin_fee_b_account,
+        ] {
+            account.owner = token_2022::id();
+        }
+    }
+
     pub fn setup_token_accounts(
         &mut self,
         mint_owner: &Pubkey,
@@ -298,29 +365,73 @@ impl SwapAccountInfo {
         user_destination_account: &mut Account,
         amount_in: u64,
         minimum_amount_out: u64,
+    ) -> ProgramResult {
+        self.swap_with_fee_tier(
+            user_key,
+            user_source_key,
+            user_source_account,
+            swap_source_key,
+            swap_destination_key,
+            user_destination_key,
+            user_destination_account,
+            None,
+            amount_in,
+            minimum_amount_out,
+        )
+    }
+
+    pub fn swap_with_fee_tier(
+        &mut self,
+        user_key: &Pubkey,
+        user_source_key: &Pubkey,
+        user_source_account: &mut Account,
+        swap_source_key: &Pubkey,
+        swap_destination_key: &Pubkey,
+        user_destination_key: &Pubkey,
+        user_destination_account: &mut Account,
+        fee_tier: Option<(&Pubkey, &mut Account)>,
+        amount_in: u64,
+        minimum_amount_out: u64,
     ) -> ProgramResult {
         let admin_destination_key = self.get_admin_fee_key(swap_destination_key);
         let mut admin_destination_account =
             self.get_admin_fee_account(&admin_destination_key).clone();
         let mut swap_source_account = self.get_token_account(swap_source_key).clone();
         let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
+        let (fee_tier_key, fee_tier_account) = match fee_tier {
+            Some((key, account)) => (Some(key), Some(account)),
+            None => (None, None),
+        };
+        let instruction = swap(
+            &spl_token::id(),
+            &self.swap_key,
+            &self.authority_key,
+            user_key,
+            user_source_key,
+            swap_source_key,
+            swap_destination_key,
+            user_destination_key,
+            &admin_destination_key,
+            amount_in,
+            minimum_amount_out,
+        )
+        .unwrap();
+        let (instruction, price_feed_account) = match self.price_feed.as_mut() {
+            Some((key, account)) => (with_price_feed(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        let (instruction, allowlist_entry_account) = match self.allowlist_entry.as_mut() {
+            Some((key, account)) => (with_allowlist_entry(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        let instruction = with_pool_mint(instruction, &self.pool_mint_key);
 
         // perform the swap
         do_process_instruction_at_time(
-            swap(
-                &spl_token::id(),
-                &self.swap_key,
-                &self.authority_key,
-                user_key,
-                user_source_key,
-                swap_source_key,
-                swap_destination_key,
-                user_destination_key,
-                &admin_destination_key,
-                amount_in,
-                minimum_amount_out,
-            )
-            .unwrap(),
+            match fee_tier_key {
+                Some(fee_tier_key) => with_fee_tier(instruction, fee_tier_key),
+                None => instruction,
+            },
             vec![
                 &mut self.swap_account,
                 &mut Account::default(),
@@ -331,7 +442,110 @@ impl SwapAccountInfo {
                 user_destination_account,
                 &mut admin_destination_account,
                 &mut Account::default(),
-            ],
+            ]
+            .into_iter()
+            .chain(optional_accounts(
+                vec![
+                    fee_tier_account,
+                    allowlist_entry_account,
+                    price_feed_account,
+                    Some(&mut self.pool_mint_account),
+                ],
+                &mut [
+                    Account::default(),
+                    Account::default(),
+                    Account::default(),
+                    Account::default(),
+                ],
+            ))
+            .collect(),
+            ZERO_TS,
+        )?;
+
+        self.set_admin_fee_account_(&admin_destination_key, admin_destination_account);
+        self.set_token_account(swap_source_key, swap_source_account);
+        self.set_token_account(swap_destination_key, swap_destination_account);
+
+        Ok(())
+    }
+
+    pub fn swap_with_referral(
+        &mut self,
+        user_key: &Pubkey,
+        user_source_key: &Pubkey,
+        user_source_account: &mut Account,
+        swap_source_key: &Pubkey,
+        swap_destination_key: &Pubkey,
+        user_destination_key: &Pubkey,
+        user_destination_account: &mut Account,
+        referrer: Option<(&Pubkey, &mut Account)>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> ProgramResult {
+        let admin_destination_key = self.get_admin_fee_key(swap_destination_key);
+        let mut admin_destination_account =
+            self.get_admin_fee_account(&admin_destination_key).clone();
+        let mut swap_source_account = self.get_token_account(swap_source_key).clone();
+        let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
+        let mut no_referrer_account = Account::default();
+        let (referrer_key, referrer_account) = match referrer {
+            Some((key, account)) => (Some(key), account),
+            None => (None, &mut no_referrer_account),
+        };
+
+        let instruction = swap_with_referral(
+            &spl_token::id(),
+            &self.swap_key,
+            &self.authority_key,
+            user_key,
+            user_source_key,
+            swap_source_key,
+            swap_destination_key,
+            user_destination_key,
+            &admin_destination_key,
+            referrer_key,
+            amount_in,
+            minimum_amount_out,
+        )
+        .unwrap();
+        let (instruction, price_feed_account) = match self.price_feed.as_mut() {
+            Some((key, account)) => (with_price_feed(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        let (instruction, allowlist_entry_account) = match self.allowlist_entry.as_mut() {
+            Some((key, account)) => (with_allowlist_entry(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        do_process_instruction_at_time(
+            with_pool_mint(instruction, &self.pool_mint_key),
+            vec![
+                &mut self.swap_account,
+                &mut Account::default(),
+                &mut Account::default(),
+                user_source_account,
+                &mut swap_source_account,
+                &mut swap_destination_account,
+                user_destination_account,
+                &mut admin_destination_account,
+                &mut Account::default(),
+                referrer_account,
+            ]
+            .into_iter()
+            .chain(optional_accounts(
+                vec![
+                    None,
+                    allowlist_entry_account,
+                    price_feed_account,
+                    Some(&mut self.pool_mint_account),
+                ],
+                &mut [
+                    Account::default(),
+                    Account::default(),
+                    Account::default(),
+                    Account::default(),
+                ],
+            ))
+            .collect(),
             ZERO_TS,
         )?;
 
@@ -356,23 +570,28 @@ impl SwapAccountInfo {
         min_mint_amount: u64,
     ) -> ProgramResult {
         // perform deposit
+        let instruction = deposit(
+            &spl_token::id(),
+            &self.swap_key,
+            &self.authority_key,
+            depositor_key,
+            depositor_token_a_key,
+            depositor_token_b_key,
+            &self.token_a_key,
+            &self.token_b_key,
+            &self.pool_mint_key,
+            depositor_pool_key,
+            amount_a,
+            amount_b,
+            min_mint_amount,
+        )
+        .unwrap();
+        let (instruction, allowlist_entry_account) = match self.allowlist_entry.as_mut() {
+            Some((key, account)) => (with_allowlist_entry(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
         do_process_instruction_at_time(
-            deposit(
-                &spl_token::id(),
-                &self.swap_key,
-                &self.authority_key,
-                depositor_key,
-                depositor_token_a_key,
-                depositor_token_b_key,
-                &self.token_a_key,
-                &self.token_b_key,
-                &self.pool_mint_key,
-                depositor_pool_key,
-                amount_a,
-                amount_b,
-                min_mint_amount,
-            )
-            .unwrap(),
+            instruction,
             vec![
                 &mut self.swap_account,
                 &mut Account::default(),
@@ -384,7 +603,10 @@ impl SwapAccountInfo {
                 &mut self.pool_mint_account,
                 depositor_pool_account,
                 &mut Account::default(),
-            ],
+            ]
+            .into_iter()
+            .chain(allowlist_entry_account)
+            .collect(),
             ZERO_TS,
         )
     }
@@ -403,25 +625,30 @@ impl SwapAccountInfo {
         minimum_b_amount: u64,
     ) -> ProgramResult {
         // perform withdraw
-        do_process_instruction(
-            withdraw(
-                &spl_token::id(),
-                &self.swap_key,
-                &self.authority_key,
-                user_key,
-                &self.pool_mint_key,
-                pool_key,
-                &self.token_a_key,
-                &self.token_b_key,
-                token_a_key,
-                token_b_key,
-                &self.admin_fee_a_key,
-                &self.admin_fee_b_key,
-                pool_amount,
-                minimum_a_amount,
-                minimum_b_amount,
-            )
-            .unwrap(),
+        let instruction = withdraw(
+            &spl_token::id(),
+            &self.swap_key,
+            &self.authority_key,
+            user_key,
+            &self.pool_mint_key,
+            pool_key,
+            &self.token_a_key,
+            &self.token_b_key,
+            token_a_key,
+            token_b_key,
+            &self.admin_fee_a_key,
+            &self.admin_fee_b_key,
+            pool_amount,
+            minimum_a_amount,
+            minimum_b_amount,
+        )
+        .unwrap();
+        let (instruction, allowlist_entry_account) = match self.allowlist_entry.as_mut() {
+            Some((key, account)) => (with_allowlist_entry(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        do_process_instruction_at_time(
+            instruction,
             vec![
                 &mut self.swap_account,
                 &mut Account::default(),
@@ -435,7 +662,11 @@ impl SwapAccountInfo {
                 &mut self.admin_fee_a_account,
                 &mut self.admin_fee_b_account,
                 &mut Account::default(),
-            ],
+            ]
+            .into_iter()
+            .chain(allowlist_entry_account)
+            .collect(),
+            ZERO_TS,
         )?;
 
         Ok(())
@@ -452,33 +683,159 @@ impl SwapAccountInfo {
         minimum_amount: u64,
     ) -> ProgramResult {
         // perform withdraw_one
+        let instruction = withdraw_one(
+            &spl_token::id(),
+            &self.swap_key,
+            &self.authority_key,
+            user_key,
+            &self.pool_mint_key,
+            pool_key,
+            &self.token_a_key,
+            &self.token_b_key,
+            dest_token_key,
+            &self.admin_fee_a_key,
+            pool_amount,
+            minimum_amount,
+        )
+        .unwrap();
+        let (instruction, allowlist_entry_account) = match self.allowlist_entry.as_mut() {
+            Some((key, account)) => (with_allowlist_entry(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
         do_process_instruction_at_time(
-            withdraw_one(
-                &spl_token::id(),
+            instruction,
+            vec![
+                &mut self.swap_account,
+                &mut Account::default(),
+                &mut Account::default(),
+                &mut self.pool_mint_account,
+                pool_account,
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                dest_token_account,
+                &mut self.admin_fee_a_account,
+                &mut Account::default(),
+            ]
+            .into_iter()
+            .chain(allowlist_entry_account)
+            .collect(),
+            ZERO_TS,
+        )
+    }
+
+    pub fn quote_swap(
+        &mut self,
+        swap_source_key: &Pubkey,
+        swap_destination_key: &Pubkey,
+        amount_in: u64,
+    ) -> ProgramResult {
+        self.quote_swap_with_fee_tier(swap_source_key, swap_destination_key, None, amount_in)
+    }
+
+    pub fn quote_swap_with_fee_tier(
+        &mut self,
+        swap_source_key: &Pubkey,
+        swap_destination_key: &Pubkey,
+        fee_tier: Option<(&Pubkey, &mut Account)>,
+        amount_in: u64,
+    ) -> ProgramResult {
+        let mut swap_source_account = self.get_token_account(swap_source_key).clone();
+        let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
+        let instruction = quote_swap(
+            &self.swap_key,
+            swap_source_key,
+            swap_destination_key,
+            amount_in,
+        )
+        .unwrap();
+        let (instruction, fee_tier_account) = match fee_tier {
+            Some((key, account)) => (with_fee_tier(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        let (instruction, price_feed_account) = match self.price_feed.as_mut() {
+            Some((key, account)) => (with_price_feed(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        let (instruction, allowlist_entry_account) = match self.allowlist_entry.as_mut() {
+            Some((key, account)) => (with_allowlist_entry(instruction, key), Some(account)),
+            None => (instruction, None),
+        };
+        do_process_instruction_at_time(
+            instruction,
+            vec![
+                &mut self.swap_account,
+                &mut swap_source_account,
+                &mut swap_destination_account,
+            ]
+            .into_iter()
+            .chain(optional_accounts(
+                vec![
+                    fee_tier_account,
+                    allowlist_entry_account,
+                    price_feed_account,
+                ],
+                &mut [Account::default(), Account::default(), Account::default()],
+            ))
+            .collect(),
+            ZERO_TS,
+        )
+    }
+
+    pub fn quote_deposit(&mut self, amount_a: u64, amount_b: u64) -> ProgramResult {
+        do_process_instruction_at_time(
+            quote_deposit(
                 &self.swap_key,
-                &self.authority_key,
-                user_key,
+                &self.token_a_key,
+                &self.token_b_key,
                 &self.pool_mint_key,
-                pool_key,
+                amount_a,
+                amount_b,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.pool_mint_account,
+            ],
+            ZERO_TS,
+        )
+    }
+
+    pub fn quote_withdraw_one(&mut self, pool_amount: u64) -> ProgramResult {
+        do_process_instruction_at_time(
+            quote_withdraw_one(
+                &self.swap_key,
                 &self.token_a_key,
                 &self.token_b_key,
-                dest_token_key,
-                &self.admin_fee_a_key,
+                &self.pool_mint_key,
                 pool_amount,
-                minimum_amount,
             )
             .unwrap(),
             vec![
                 &mut self.swap_account,
-                &mut Account::default(),
-                &mut Account::default(),
+                &mut self.token_a_account,
+                &mut self.token_b_account,
                 &mut self.pool_mint_account,
-                pool_account,
+            ],
+            ZERO_TS,
+        )
+    }
+
+    pub fn get_virtual_price(&mut self) -> ProgramResult {
+        do_process_instruction_at_time(
+            get_virtual_price(
+                &self.swap_key,
+                &self.token_a_key,
+                &self.token_b_key,
+                &self.pool_mint_key,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
                 &mut self.token_a_account,
                 &mut self.token_b_account,
-                dest_token_account,
-                &mut self.admin_fee_a_account,
-                &mut Account::default(),
+                &mut self.pool_mint_account,
             ],
             ZERO_TS,
         )
@@ -509,47 +866,435 @@ impl SwapAccountInfo {
         )
     }
 
-    pub fn unpause(&mut self) -> ProgramResult {
+    pub fn set_pause_flags(&mut self, flags: PauseFlags) -> ProgramResult {
         do_process_instruction(
-            unpause(&self.swap_key, &self.admin_key).unwrap(),
+            set_pause_flags(&self.swap_key, &self.admin_key, flags).unwrap(),
             vec![&mut self.swap_account, &mut self.admin_account],
         )
     }
 
-    pub fn set_admin_fee_account(
+    pub fn set_circuit_breaker(
         &mut self,
-        new_admin_fee_key: &Pubkey,
-        new_admin_fee_account: &Account,
+        max_price_deviation_bps: u16,
+        window_slots: u64,
     ) -> ProgramResult {
         do_process_instruction(
-            set_fee_account(&self.swap_key, &self.admin_key, new_admin_fee_key).unwrap(),
-            vec![
-                &mut self.swap_account,
-                &mut self.admin_account,
-                &mut new_admin_fee_account.clone(),
-            ],
+            set_circuit_breaker(
+                &self.swap_key,
+                &self.admin_key,
+                max_price_deviation_bps,
+                window_slots,
+            )
+            .unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
         )
     }
 
-    pub fn apply_new_admin(&mut self, current_ts: i64) -> ProgramResult {
-        do_process_instruction_at_time(
-            apply_new_admin(&self.swap_key, &self.admin_key).unwrap(),
+    pub fn reset_circuit_breaker(&mut self) -> ProgramResult {
+        do_process_instruction(
+            reset_circuit_breaker(&self.swap_key, &self.admin_key).unwrap(),
             vec![&mut self.swap_account, &mut self.admin_account],
-            current_ts,
         )
     }
 
-    pub fn commit_new_admin(&mut self, new_admin_key: &Pubkey, current_ts: i64) -> ProgramResult {
-        do_process_instruction_at_time(
-            commit_new_admin(&self.swap_key, &self.admin_key, new_admin_key).unwrap(),
-            vec![
-                &mut self.swap_account,
-                &mut self.admin_account,
-                &mut Account::default(),
-            ],
-            current_ts,
-        )
-    }
+    pub fn set_price_oracle(
+        &mut self,
+        feed_key: &Pubkey,
+        feed_account: &mut Account,
+        max_deviation_bps: u16,
+        max_staleness_slots: u64,
+    ) -> ProgramResult {
+        do_process_instruction(
+            set_price_oracle(
+                &self.swap_key,
+                &self.admin_key,
+                feed_key,
+                &self.token_a_mint_key,
+                &self.token_b_mint_key,
+                max_deviation_bps,
+                max_staleness_slots,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                feed_account,
+                &mut self.token_a_mint_account,
+                &mut self.token_b_mint_account,
+            ],
+        )
+    }
+
+    pub fn set_guardian(&mut self, guardian_key: &Pubkey) -> ProgramResult {
+        do_process_instruction(
+            set_guardian(&self.swap_key, &self.admin_key, guardian_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut Account::default(),
+            ],
+        )
+    }
+
+    pub fn grant_role(&mut self, role: AdminRole, grantee_key: &Pubkey) -> ProgramResult {
+        do_process_instruction(
+            grant_role(&self.swap_key, &self.admin_key, role, grantee_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut Account::default(),
+            ],
+        )
+    }
+
+    pub fn revoke_role(&mut self, role: AdminRole) -> ProgramResult {
+        do_process_instruction(
+            revoke_role(&self.swap_key, &self.admin_key, role).unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
+        )
+    }
+
+    pub fn set_timelock_delay(&mut self, delay: i64) -> ProgramResult {
+        do_process_instruction(
+            set_timelock_delay(&self.swap_key, &self.admin_key, delay).unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
+        )
+    }
+
+    pub fn initialize_admin_queue(
+        &mut self,
+        admin_queue_key: &Pubkey,
+        admin_queue_account: &mut Account,
+    ) -> ProgramResult {
+        do_process_instruction(
+            initialize_admin_queue(&self.swap_key, &self.admin_key, admin_queue_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                admin_queue_account,
+            ],
+        )
+    }
+
+    pub fn queue_admin_action(
+        &mut self,
+        admin_queue_key: &Pubkey,
+        admin_queue_account: &mut Account,
+        action: &AdminInstruction,
+        action_accounts: &mut [(Pubkey, Account)],
+        current_ts: i64,
+    ) -> ProgramResult {
+        let action_keys: Vec<Pubkey> = action_accounts.iter().map(|(key, _)| *key).collect();
+        let mut accounts = vec![
+            &mut self.swap_account,
+            &mut self.admin_account,
+            admin_queue_account,
+        ];
+        accounts.extend(action_accounts.iter_mut().map(|(_, account)| account));
+        do_process_instruction_at_time(
+            queue_admin_action(
+                &self.swap_key,
+                &self.admin_key,
+                admin_queue_key,
+                action,
+                &action_keys,
+            )
+            .unwrap(),
+            accounts,
+            current_ts,
+        )
+    }
+
+    pub fn cancel_admin_action(
+        &mut self,
+        admin_queue_key: &Pubkey,
+        admin_queue_account: &mut Account,
+        index: u8,
+    ) -> ProgramResult {
+        do_process_instruction(
+            cancel_admin_action(&self.swap_key, &self.admin_key, admin_queue_key, index).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                admin_queue_account,
+            ],
+        )
+    }
+
+    pub fn execute_admin_action(
+        &mut self,
+        admin_queue_key: &Pubkey,
+        admin_queue_account: &mut Account,
+        index: u8,
+        action_accounts: &mut [(AccountMeta, Account)],
+        current_ts: i64,
+    ) -> ProgramResult {
+        let action_metas: Vec<AccountMeta> = action_accounts
+            .iter()
+            .map(|(meta, _)| meta.clone())
+            .collect();
+        let mut accounts = vec![
+            &mut self.swap_account,
+            &mut self.admin_account,
+            admin_queue_account,
+        ];
+        accounts.extend(action_accounts.iter_mut().map(|(_, account)| account));
+        do_process_instruction_at_time(
+            execute_admin_action(
+                &self.swap_key,
+                &self.admin_key,
+                admin_queue_key,
+                index,
+                &action_metas,
+            )
+            .unwrap(),
+            accounts,
+            current_ts,
+        )
+    }
+
+    pub fn unpause(&mut self) -> ProgramResult {
+        do_process_instruction(
+            unpause(&self.swap_key, &self.admin_key).unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
+        )
+    }
+
+    pub fn set_admin_fee_account(
+        &mut self,
+        new_admin_fee_key: &Pubkey,
+        new_admin_fee_account: &Account,
+    ) -> ProgramResult {
+        do_process_instruction(
+            set_fee_account(&self.swap_key, &self.admin_key, new_admin_fee_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut new_admin_fee_account.clone(),
+            ],
+        )
+    }
+
+    pub fn apply_new_admin(&mut self, new_admin_key: &Pubkey, current_ts: i64) -> ProgramResult {
+        do_process_instruction_at_time(
+            apply_new_admin(&self.swap_key, &self.admin_key, new_admin_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut Account::default(),
+            ],
+            current_ts,
+        )
+    }
+
+    pub fn commit_new_admin(&mut self, new_admin_key: &Pubkey, current_ts: i64) -> ProgramResult {
+        do_process_instruction_at_time(
+            commit_new_admin(&self.swap_key, &self.admin_key, new_admin_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut Account::default(),
+            ],
+            current_ts,
+        )
+    }
+
+    pub fn accept_admin(&mut self, new_admin_key: &Pubkey, current_ts: i64) -> ProgramResult {
+        do_process_instruction_at_time(
+            accept_admin(&self.swap_key, new_admin_key).unwrap(),
+            vec![&mut self.swap_account, &mut Account::default()],
+            current_ts,
+        )
+    }
+
+    pub fn cancel_admin_transfer(&mut self) -> ProgramResult {
+        do_process_instruction(
+            cancel_admin_transfer(&self.swap_key, &self.admin_key).unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
+        )
+    }
+
+    pub fn renounce_admin(&mut self) -> ProgramResult {
+        do_process_instruction(
+            renounce_admin(&self.swap_key, &self.admin_key).unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
+        )
+    }
+
+    pub fn migrate_swap_info(
+        &mut self,
+        payer_key: &Pubkey,
+        payer_account: &mut Account,
+    ) -> ProgramResult {
+        do_process_instruction(
+            migrate_swap_info(&self.swap_key, &self.admin_key, payer_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                payer_account,
+                &mut Account::default(),
+            ],
+        )
+    }
+
+    pub fn set_fee_tier(
+        &mut self,
+        payer_key: &Pubkey,
+        payer_account: &mut Account,
+        user_key: &Pubkey,
+        fee_tier_account: &mut Account,
+        fee_multiplier_bps: u16,
+    ) -> ProgramResult {
+        do_process_instruction(
+            set_fee_tier(
+                &self.swap_key,
+                &self.admin_key,
+                payer_key,
+                user_key,
+                fee_multiplier_bps,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                payer_account,
+                fee_tier_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+    }
+
+    pub fn remove_fee_tier(
+        &mut self,
+        user_key: &Pubkey,
+        fee_tier_account: &mut Account,
+        destination_key: &Pubkey,
+        destination_account: &mut Account,
+    ) -> ProgramResult {
+        do_process_instruction(
+            remove_fee_tier(&self.swap_key, &self.admin_key, user_key, destination_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                fee_tier_account,
+                destination_account,
+            ],
+        )
+    }
+
+    pub fn set_gate(&mut self, gate_key: &Pubkey, gate_account: &mut Account) -> ProgramResult {
+        do_process_instruction(
+            set_gate(&self.swap_key, &self.admin_key, gate_key).unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                gate_account,
+            ],
+        )
+    }
+
+    pub fn flash_loan(
+        &mut self,
+        user_key: &Pubkey,
+        swap_reserve_key: &Pubkey,
+        user_destination_key: &Pubkey,
+        user_destination_account: &mut Account,
+        receiver_program_id: &Pubkey,
+        amount: u64,
+    ) -> ProgramResult {
+        let admin_destination_key = self.get_admin_fee_key(swap_reserve_key);
+        let mut admin_destination_account =
+            self.get_admin_fee_account(&admin_destination_key).clone();
+        let mut swap_reserve_account = self.get_token_account(swap_reserve_key).clone();
+
+        do_process_instruction(
+            flash_loan(
+                &spl_token::id(),
+                &self.swap_key,
+                &self.authority_key,
+                swap_reserve_key,
+                user_destination_key,
+                &admin_destination_key,
+                receiver_program_id,
+                &[AccountMeta::new_readonly(*user_key, true)],
+                amount,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut Account::default(),
+                &mut swap_reserve_account,
+                user_destination_account,
+                &mut admin_destination_account,
+                &mut Account::default(),
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )?;
+
+        self.set_admin_fee_account_(&admin_destination_key, admin_destination_account);
+        self.set_token_account(swap_reserve_key, swap_reserve_account);
+
+        Ok(())
+    }
+
+    pub fn claim_admin_fees(&mut self) -> ProgramResult {
+        do_process_instruction(
+            claim_admin_fees(
+                &spl_token::id(),
+                &self.swap_key,
+                &self.authority_key,
+                &self.token_a_key,
+                &self.token_b_key,
+                &self.admin_fee_a_key,
+                &self.admin_fee_b_key,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut Account::default(),
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.admin_fee_a_account,
+                &mut self.admin_fee_b_account,
+                &mut Account::default(),
+            ],
+        )
+    }
+
+    pub fn claim_admin_fees_as_lp(
+        &mut self,
+        lp_admin_fee_key: &Pubkey,
+        lp_admin_fee_account: &mut Account,
+    ) -> ProgramResult {
+        do_process_instruction_at_time(
+            claim_admin_fees_as_lp(
+                &spl_token::id(),
+                &self.swap_key,
+                &self.authority_key,
+                &self.token_a_key,
+                &self.token_b_key,
+                &self.admin_fee_a_key,
+                &self.admin_fee_b_key,
+                &self.pool_mint_key,
+                lp_admin_fee_key,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut Account::default(),
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.admin_fee_a_account,
+                &mut self.admin_fee_b_account,
+                &mut Account::default(),
+                &mut self.pool_mint_account,
+                lp_admin_fee_account,
+            ],
+            ZERO_TS,
+        )
+    }
 
     pub fn set_new_fees(&mut self, new_fees: Fees) -> ProgramResult {
         do_process_instruction(
@@ -557,16 +1302,324 @@ impl SwapAccountInfo {
             vec![&mut self.swap_account, &mut self.admin_account],
         )
     }
+
+    pub fn set_extra_fees(&mut self, extra_fees: ExtraFees) -> ProgramResult {
+        do_process_instruction(
+            set_extra_fees(&self.swap_key, &self.admin_key, extra_fees).unwrap(),
+            vec![&mut self.swap_account, &mut self.admin_account],
+        )
+    }
+
+    pub fn set_admin_fee_mode(&mut self, mode: AdminFeeMode) -> ProgramResult {
+        do_process_instruction_at_time(
+            set_admin_fee_mode(
+                &self.swap_key,
+                &self.admin_key,
+                &self.token_a_key,
+                &self.token_b_key,
+                &self.pool_mint_key,
+                mode,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.pool_mint_account,
+            ],
+            ZERO_TS,
+        )
+    }
+
+    pub fn close_pool(&mut self) -> ProgramResult {
+        do_process_instruction(
+            close_pool(
+                &spl_token::id(),
+                &self.swap_key,
+                &self.admin_key,
+                &self.authority_key,
+                &self.pool_mint_key,
+                &self.token_a_key,
+                &self.token_b_key,
+                &self.admin_fee_a_key,
+                &self.admin_fee_b_key,
+            )
+            .unwrap(),
+            vec![
+                &mut self.swap_account,
+                &mut self.admin_account,
+                &mut Account::default(),
+                &mut self.pool_mint_account,
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.admin_fee_a_account,
+                &mut self.admin_fee_b_account,
+                &mut Account::default(),
+            ],
+        )
+    }
+}
+
+thread_local! {
+    /// Data logged with `sol_log_data` by the last processed instruction.
+    static LOGGED_DATA: RefCell<Vec<Vec<u8>>> = const { RefCell::new(vec![]) };
+    /// Return data set by the last processed instruction.
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    /// Slot of the clock of the processed instructions.
+    static CURRENT_SLOT: Cell<Slot> = const { Cell::new(0) };
+}
+
+/// Sets the slot of the clock of the instructions processed afterwards.
+pub fn set_current_slot(slot: Slot) {
+    CURRENT_SLOT.with(|current_slot| current_slot.set(slot));
+}
+
+/// Accounts in the positions of the optional accounts of an instruction, with
+/// the placeholders in the positions of accounts that are not passed.
+fn optional_accounts<'a>(
+    accounts: Vec<Option<&'a mut Account>>,
+    placeholders: &'a mut [Account],
+) -> Vec<&'a mut Account> {
+    let len = accounts
+        .iter()
+        .rposition(Option::is_some)
+        .map_or(0, |index| index.checked_add(1).unwrap());
+    accounts
+        .into_iter()
+        .zip(placeholders.iter_mut())
+        .take(len)
+        .map(|(account, placeholder)| account.unwrap_or(placeholder))
+        .collect()
+}
+
+/// Decodes the result returned by the last processed instruction.
+pub fn take_result<T: InstructionResult>() -> T {
+    RETURN_DATA.with(|data| {
+        let (program_id, data) = data.take().unwrap();
+        assert_eq!(program_id, SWAP_PROGRAM_ID);
+        T::decode(&data).unwrap()
+    })
+}
+
+/// Decodes the events emitted by the last processed instruction.
+pub fn take_events() -> Vec<Event> {
+    LOGGED_DATA.with(|data| {
+        data.take()
+            .iter()
+            .map(|data| Event::decode(data).unwrap())
+            .collect()
+    })
+}
+
+/// Creates the program data account of the swap program.
+pub fn create_program_data(upgrade_authority: Option<&Pubkey>) -> Account {
+    // bincode encoding of `UpgradeableLoaderState::ProgramData`
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&0u64.to_le_bytes());
+    match upgrade_authority {
+        Some(upgrade_authority) => {
+            data.push(1);
+            data.extend_from_slice(upgrade_authority.as_ref());
+        }
+        None => data.push(0),
+    }
+    Account {
+        lamports: 1,
+        data,
+        owner: bpf_loader_upgradeable::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Creates the gate of `gatekeeper_key`.
+pub fn create_gate(gatekeeper_key: &Pubkey) -> (Pubkey, Account) {
+    let (gate_key, _) = find_gate_address(&SWAP_PROGRAM_ID, gatekeeper_key);
+    let mut gate_account = Account::new(0, Gate::LEN, &system_program::id());
+    do_process_instruction(
+        initialize_gate(&pubkey_rand(), gatekeeper_key).unwrap(),
+        vec![
+            &mut Account::new(1_000_000_000, 0, &system_program::id()),
+            &mut gate_account,
+            &mut Account::default(),
+            &mut Account::default(),
+        ],
+    )
+    .unwrap();
+    (gate_key, gate_account)
+}
+
+/// Adds `user_key` to the allowlist of a gate and returns its entry.
+pub fn create_allowlist_entry(
+    gate_key: &Pubkey,
+    gate_account: &mut Account,
+    gatekeeper_key: &Pubkey,
+    user_key: &Pubkey,
+) -> Result<(Pubkey, Account), ProgramError> {
+    let (entry_key, _) = find_allowlist_entry_address(&SWAP_PROGRAM_ID, gate_key, user_key);
+    let mut entry_account = Account::new(0, AllowlistEntry::LEN, &system_program::id());
+    do_process_instruction(
+        add_to_allowlist(gate_key, gatekeeper_key, &pubkey_rand(), user_key).unwrap(),
+        vec![
+            gate_account,
+            &mut Account::default(),
+            &mut Account::new(1_000_000_000, 0, &system_program::id()),
+            &mut entry_account,
+            &mut Account::default(),
+            &mut Account::default(),
+        ],
+    )?;
+    Ok((entry_key, entry_account))
+}
+
+/// Creates a mock price account holding `feed`.
+pub fn create_price_feed(feed: &PriceFeed) -> Account {
+    let mut feed_account = Account::new(0, 3312, &pyth_program::id());
+    feed.pack_into_slice(&mut feed_account.data);
+    feed_account
+}
+
+/// Accounts of a pool created by the factory.
+pub struct FactoryPoolAccounts {
+    pub payer_key: Pubkey,
+    pub admin_key: Pubkey,
+    pub token_a_mint: (Pubkey, Account),
+    pub token_b_mint: (Pubkey, Account),
+    pub source_a: (Pubkey, Account),
+    pub source_b: (Pubkey, Account),
+    pub admin_fee_a: (Pubkey, Account),
+    pub admin_fee_b: (Pubkey, Account),
+    pub registry_entry: Account,
+    pub swap: Account,
+    pub token_a_reserves: Account,
+    pub token_b_reserves: Account,
+    pub pool_mint: Account,
+    pub destination: (Pubkey, Account),
+}
+
+impl FactoryPoolAccounts {
+    /// Creates sorted mints, the liquidity of the payer and admin fee accounts
+    /// owned by the factory admin.
+    pub fn new(payer_key: &Pubkey, admin_key: &Pubkey) -> Self {
+        let mut mint_x = create_mint(&spl_token::id(), payer_key, DEFAULT_TOKEN_DECIMALS, None);
+        let mut mint_y = create_mint(&spl_token::id(), payer_key, DEFAULT_TOKEN_DECIMALS, None);
+        if mint_y.0 < mint_x.0 {
+            std::mem::swap(&mut mint_x, &mut mint_y);
+        }
+        let (mut token_a_mint, mut token_b_mint) = (mint_x, mint_y);
+        let token_account = |mint: &mut (Pubkey, Account), owner: &Pubkey, amount: u64| {
+            mint_token(
+                &spl_token::id(),
+                &mint.0,
+                &mut mint.1,
+                payer_key,
+                owner,
+                amount,
+            )
+        };
+        let source_a = token_account(&mut token_a_mint, payer_key, 1_000);
+        let source_b = token_account(&mut token_b_mint, payer_key, 2_000);
+        let admin_fee_a = token_account(&mut token_a_mint, admin_key, 0);
+        let admin_fee_b = token_account(&mut token_b_mint, admin_key, 0);
+        let new_account = |len: usize| Account::new(0, len, &solana_program::system_program::id());
+
+        Self {
+            payer_key: *payer_key,
+            admin_key: *admin_key,
+            token_a_mint,
+            token_b_mint,
+            source_a,
+            source_b,
+            admin_fee_a,
+            admin_fee_b,
+            registry_entry: new_account(RegistryEntry::LEN),
+            swap: new_account(SwapInfo::LEN),
+            token_a_reserves: new_account(SplAccount::LEN),
+            token_b_reserves: new_account(SplAccount::LEN),
+            pool_mint: new_account(SplMint::LEN),
+            destination: (pubkey_rand(), new_account(SplAccount::LEN)),
+        }
+    }
+
+    /// Creates the pool with the template of amp tier 1, optionally
+    /// passing the mints in the wrong order.
+    pub fn create(
+        &mut self,
+        factory_account: &mut Account,
+        template_account: &mut Account,
+        payer_account: &mut Account,
+        reverse_mints: bool,
+    ) -> ProgramResult {
+        let (token_a_mint, token_b_mint) = if reverse_mints {
+            (&mut self.token_b_mint, &mut self.token_a_mint)
+        } else {
+            (&mut self.token_a_mint, &mut self.token_b_mint)
+        };
+        do_process_instruction(
+            create_pool(
+                &spl_token::id(),
+                &self.payer_key,
+                &self.admin_key,
+                &self.admin_fee_a.0,
+                &self.admin_fee_b.0,
+                &token_a_mint.0,
+                &token_b_mint.0,
+                &self.source_a.0,
+                &self.source_b.0,
+                &self.destination.0,
+                1,
+                1_000,
+                2_000,
+            )?,
+            vec![
+                factory_account,
+                template_account,
+                &mut self.registry_entry,
+                payer_account,
+                &mut self.swap,
+                &mut Account::default(),
+                &mut Account::default(),
+                &mut self.admin_fee_a.1,
+                &mut self.admin_fee_b.1,
+                &mut token_a_mint.1,
+                &mut self.token_a_reserves,
+                &mut token_b_mint.1,
+                &mut self.token_b_reserves,
+                &mut self.pool_mint,
+                &mut self.source_a.1,
+                &mut self.source_b.1,
+                &mut self.destination.1,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+    }
 }
 
 struct TestSyscallStubs {
     unix_timestamp: Option<i64>,
 }
 impl program_stubs::SyscallStubs for TestSyscallStubs {
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        LOGGED_DATA.with(|data| data.borrow_mut().push(fields.concat()));
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|data| data.borrow().clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        RETURN_DATA.with(|return_data| {
+            *return_data.borrow_mut() = Some((SWAP_PROGRAM_ID, data.to_vec()));
+        });
+    }
+
     fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
         let clock: Option<i64> = self.unix_timestamp;
         unsafe {
             *(var_addr as *mut _ as *mut Clock) = Clock {
+                slot: CURRENT_SLOT.with(|slot| slot.get()),
                 unix_timestamp: clock.unwrap(),
                 ..Clock::default()
             };
@@ -574,6 +1627,13 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
         solana_program::entrypoint::SUCCESS
     }
 
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut _ as *mut Rent) = Rent::default();
+        }
+        solana_program::entrypoint::SUCCESS
+    }
+
     fn sol_invoke_signed(
         &self,
         instruction: &Instruction,
@@ -582,10 +1642,23 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
     ) -> ProgramResult {
         msg!("TestSyscallStubs::sol_invoke_signed()");
 
+        if instruction.program_id == system_program::id() {
+            return process_mock_system_program(instruction, account_infos, signers_seeds);
+        }
+
+        if instruction.program_id == FLASH_LOAN_RECEIVER_ID
+            || instruction.program_id == FLASH_LOAN_DEFAULTER_ID
+        {
+            return process_mock_flash_loan_receiver(instruction, account_infos);
+        }
+
         let mut new_account_infos = vec![];
 
         // mimic check for token program in accounts
-        if !account_infos.iter().any(|x| *x.key == spl_token::id()) {
+        if !account_infos
+            .iter()
+            .any(|x| *x.key == spl_token::id() || *x.key == token_2022::id())
+        {
             return Err(ProgramError::InvalidAccountData);
         }
 
@@ -605,6 +1678,10 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
             }
         }
 
+        if instruction.program_id == token_2022::id() {
+            return process_mock_token_2022(instruction, new_account_infos);
+        }
+
         spl_token::processor::Processor::process(
             &instruction.program_id,
             &new_account_infos,
@@ -613,6 +1690,242 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
     }
 }
 
+/// Offset of the withheld amount in the transfer fee config of a mint.
+const MINT_WITHHELD_AMOUNT_OFFSET: usize = 64;
+
+/// Returns the range of the value of an extension of a Token-2022 account.
+#[allow(clippy::integer_arithmetic)]
+fn find_extension(data: &[u8], extension_type: u16) -> Option<std::ops::Range<usize>> {
+    let mut offset = token_2022::ACCOUNT_LEN + 1;
+    while offset + 4 <= data.len() {
+        let current_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let len = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let value = offset + 4..offset + 4 + len;
+        if current_type == extension_type {
+            return Some(value);
+        }
+        offset = value.end;
+    }
+    None
+}
+
+/// Reads the withheld transfer fees of a Token-2022 token account.
+#[allow(clippy::integer_arithmetic)]
+fn withheld_amount(data: &[u8]) -> u64 {
+    find_extension(data, token_2022::ExtensionType::TransferFeeAmount as u16)
+        .map(|value| u64::from_le_bytes(data[value.start..value.start + 8].try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+/// Processes Token-2022 instructions: harvests withheld fees, initializes and
+/// refuses to close accounts with withheld fees and runs the rest through
+/// spl_token on the base of the accounts.
+#[allow(clippy::integer_arithmetic)]
+fn process_mock_token_2022(
+    instruction: &Instruction,
+    account_infos: Vec<AccountInfo>,
+) -> ProgramResult {
+    if instruction.data
+        == [
+            token_2022::TRANSFER_FEE_EXTENSION,
+            token_2022::HARVEST_WITHHELD_TOKENS_TO_MINT,
+        ]
+    {
+        let mut mint_data = account_infos[0].data.borrow_mut();
+        let config = find_extension(
+            &mint_data,
+            token_2022::ExtensionType::TransferFeeConfig as u16,
+        )
+        .ok_or(ProgramError::InvalidAccountData)?;
+        let withheld_range = config.start + MINT_WITHHELD_AMOUNT_OFFSET
+            ..config.start + MINT_WITHHELD_AMOUNT_OFFSET + 8;
+        let mut mint_withheld =
+            u64::from_le_bytes(mint_data[withheld_range.clone()].try_into().unwrap());
+        for source_info in &account_infos[1..] {
+            let mut source_data = source_info.data.borrow_mut();
+            if let Some(value) = find_extension(
+                &source_data,
+                token_2022::ExtensionType::TransferFeeAmount as u16,
+            ) {
+                let withheld = withheld_amount(&source_data);
+                mint_withheld = mint_withheld.checked_add(withheld).unwrap();
+                source_data[value.start..value.start + 8].fill(0);
+            }
+        }
+        mint_data[withheld_range].copy_from_slice(&mint_withheld.to_le_bytes());
+        return Ok(());
+    }
+
+    if let Ok(spl_token::instruction::TokenInstruction::InitializeAccount3 { .. }) =
+        spl_token::instruction::TokenInstruction::unpack(&instruction.data)
+    {
+        // accounts of mints with transfer fees need room for the withheld fees
+        let is_transfer_fee_mint = find_extension(
+            &account_infos[1].data.borrow(),
+            token_2022::ExtensionType::TransferFeeConfig as u16,
+        )
+        .is_some();
+        if is_transfer_fee_mint {
+            let mut data = account_infos[0].data.borrow_mut();
+            if data.len() < token_2022::TRANSFER_FEE_ACCOUNT_LEN {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let extension = token_2022::ACCOUNT_LEN + 1;
+            data[extension - 1] = 2;
+            data[extension..extension + 2].copy_from_slice(
+                &(token_2022::ExtensionType::TransferFeeAmount as u16).to_le_bytes(),
+            );
+            data[extension + 2..extension + 4].copy_from_slice(&8u16.to_le_bytes());
+        }
+    }
+
+    if let Ok(spl_token::instruction::TokenInstruction::CloseAccount) =
+        spl_token::instruction::TokenInstruction::unpack(&instruction.data)
+    {
+        if withheld_amount(&account_infos[0].data.borrow()) > 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let base_account_infos = account_infos
+        .into_iter()
+        .map(|mut account_info| {
+            let account_type = account_info
+                .data
+                .borrow()
+                .get(token_2022::ACCOUNT_LEN)
+                .copied();
+            let base_len = match account_type {
+                Some(1) => token_2022::MINT_LEN,
+                Some(_) => token_2022::ACCOUNT_LEN,
+                None => return account_info,
+            };
+            let data_ptr = account_info.data.borrow_mut().as_mut_ptr();
+            // SAFETY: the base is a prefix of the account data, which outlives the
+            // processing of the instruction
+            let base = unsafe { std::slice::from_raw_parts_mut(data_ptr, base_len) };
+            account_info.data = std::rc::Rc::new(RefCell::new(base));
+            account_info
+        })
+        .collect::<Vec<_>>();
+    spl_token::processor::Processor::process(
+        &instruction.program_id,
+        &base_account_infos,
+        &instruction.data,
+    )
+}
+
+/// Repays a flash loan from the destination account, signed by the first
+/// forwarded account.
+fn process_mock_flash_loan_receiver(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+) -> ProgramResult {
+    let ReceiveFlashLoanData { amount, fee } = ReceiveFlashLoanData::unpack(&instruction.data)?;
+    let repay_amount = if instruction.program_id == FLASH_LOAN_RECEIVER_ID {
+        amount.checked_add(fee).unwrap()
+    } else {
+        amount
+    };
+
+    let find_account = |index: usize| {
+        let key = instruction.accounts[index].pubkey;
+        account_infos
+            .iter()
+            .find(|info| *info.key == key)
+            .unwrap()
+            .clone()
+    };
+    let destination_info = find_account(0);
+    let reserve_info = find_account(1);
+    let user_info = find_account(3);
+
+    let repay = spl_token::instruction::transfer(
+        &spl_token::id(),
+        destination_info.key,
+        reserve_info.key,
+        user_info.key,
+        &[],
+        repay_amount,
+    )?;
+    spl_token::processor::Processor::process(
+        &repay.program_id,
+        &[destination_info, reserve_info, user_info],
+        &repay.data,
+    )
+}
+
+/// Processes the system program instructions used to create accounts.
+/// Accounts cannot be resized in tests, so they must already have the
+/// length they are allocated with.
+fn process_mock_system_program(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let find_account = |index: usize| {
+        let meta = &instruction.accounts[index];
+        let info = account_infos
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .unwrap()
+            .clone();
+        let is_signer = info.is_signer
+            || signers_seeds.iter().any(|seeds| {
+                Pubkey::create_program_address(seeds, &SWAP_PROGRAM_ID).ok() == Some(*info.key)
+            });
+        if meta.is_signer && !is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Ok(info)
+    };
+    let transfer = |from: &AccountInfo, to: &AccountInfo, lamports: u64| -> ProgramResult {
+        let from_lamports = from.lamports().checked_sub(lamports).unwrap();
+        **from.try_borrow_mut_lamports()? = from_lamports;
+        let to_lamports = to.lamports().checked_add(lamports).unwrap();
+        **to.try_borrow_mut_lamports()? = to_lamports;
+        Ok(())
+    };
+    let allocate = |account: &AccountInfo, space: u64| -> ProgramResult {
+        if *account.owner != system_program::id() || account.data.borrow().iter().any(|&b| b != 0) {
+            return Err(ProgramError::Custom(
+                SystemError::AccountAlreadyInUse as u32,
+            ));
+        }
+        assert_eq!(account.data_len() as u64, space);
+        Ok(())
+    };
+
+    match limited_deserialize(&instruction.data, 1024).unwrap() {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let from = find_account(0)?;
+            let to = find_account(1)?;
+            if to.lamports() != 0 {
+                return Err(ProgramError::Custom(
+                    SystemError::AccountAlreadyInUse as u32,
+                ));
+            }
+            allocate(&to, space)?;
+            transfer(&from, &to, lamports)?;
+            to.assign(&owner);
+            Ok(())
+        }
+        SystemInstruction::Transfer { lamports } => {
+            transfer(&find_account(0)?, &find_account(1)?, lamports)
+        }
+        SystemInstruction::Allocate { space } => allocate(&find_account(0)?, space),
+        SystemInstruction::Assign { owner } => {
+            find_account(0)?.assign(&owner);
+            Ok(())
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
 fn test_syscall_stubs(unix_timestamp: Option<i64>) {
     // only one test may run at a time
     program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs { unix_timestamp }));
@@ -639,17 +1952,14 @@ fn do_process_instruction_maybe_at_time(
     current_ts: Option<i64>,
 ) -> ProgramResult {
     test_syscall_stubs(current_ts);
+    LOGGED_DATA.with(|data| data.borrow_mut().clear());
+    RETURN_DATA.with(|data| data.borrow_mut().take());
 
     // approximate the logic in the actual runtime which runs the instruction
     // and only updates accounts if the instruction is successful
-    let mut account_clones = accounts.iter().map(|x| (*x).clone()).collect::<Vec<_>>();
-    let mut meta = instruction
-        .accounts
-        .iter()
-        .zip(account_clones.iter_mut())
-        .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
-        .collect::<Vec<_>>();
-    let mut account_infos = create_is_signer_account_infos(&mut meta);
+    let mut input = serialize_parameters(&instruction, &accounts);
+    // SAFETY: `input` is serialized in the layout of the runtime and outlives the account infos
+    let (_, account_infos, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
     let res = if instruction.program_id == SWAP_PROGRAM_ID {
         Processor::process(&instruction.program_id, &account_infos, &instruction.data)
     } else {
@@ -667,7 +1977,7 @@ fn do_process_instruction_maybe_at_time(
             .zip(accounts)
             .map(|(account_meta, account)| (&account_meta.pubkey, account))
             .collect::<Vec<_>>();
-        for account_info in account_infos.iter_mut() {
+        for account_info in account_infos.iter() {
             for account_meta in account_metas.iter_mut() {
                 if account_info.key == account_meta.0 {
                     let account = &mut account_meta.1;
@@ -681,6 +1991,49 @@ fn do_process_instruction_maybe_at_time(
     res
 }
 
+/// Serializes the accounts of an instruction in the input layout of the runtime,
+/// so that the account infos deserialized from it can be reallocated
+fn serialize_parameters(instruction: &Instruction, accounts: &[&mut Account]) -> Vec<u64> {
+    let metas = instruction.accounts.iter().zip(accounts.iter());
+    let mut input = vec![];
+    input.extend_from_slice(&(metas.len() as u64).to_le_bytes());
+    for (meta, account) in metas {
+        input.push(u8::MAX); // not a duplicate
+        input.push(meta.is_signer as u8);
+        input.push(meta.is_writable as u8);
+        input.push(account.executable as u8);
+        input.extend_from_slice(&[0u8; 4]); // original data length
+        input.extend_from_slice(meta.pubkey.as_ref());
+        input.extend_from_slice(account.owner.as_ref());
+        input.extend_from_slice(&account.lamports.to_le_bytes());
+        input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&account.data);
+        input.resize(
+            input
+                .len()
+                .checked_add(MAX_PERMITTED_DATA_INCREASE)
+                .unwrap(),
+            0,
+        );
+        while input.len().checked_rem(BPF_ALIGN_OF_U128) != Some(0) {
+            input.push(0);
+        }
+        input.extend_from_slice(&account.rent_epoch.to_le_bytes());
+    }
+    input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+    input.extend_from_slice(&instruction.data);
+    input.extend_from_slice(instruction.program_id.as_ref());
+    // u64 words keep the buffer aligned like the input of the runtime
+    input
+        .chunks(8)
+        .map(|chunk| {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        })
+        .collect()
+}
+
 fn mint_minimum_balance() -> u64 {
     Rent::default().minimum_balance(SplMint::get_packed_len())
 }
@@ -769,3 +2122,19 @@ pub fn create_mint(
 
     (mint_key, mint_account)
 }
+
+/// Makes a Token-2022 mint charge `transfer_fee` on every transfer.
+pub fn set_transfer_fee(mint_account: &mut Account, transfer_fee: &token_2022::TransferFee) {
+    let mut fee_data = transfer_fee.epoch.to_le_bytes().to_vec();
+    fee_data.extend_from_slice(&transfer_fee.maximum_fee.to_le_bytes());
+    fee_data.extend_from_slice(&transfer_fee.transfer_fee_basis_points.to_le_bytes());
+    let data = &mut mint_account.data;
+    data.resize(token_2022::ACCOUNT_LEN, 0);
+    data.push(1);
+    data.extend_from_slice(&(token_2022::ExtensionType::TransferFeeConfig as u16).to_le_bytes());
+    data.extend_from_slice(&108u16.to_le_bytes());
+    // authorities and withheld amount, then the older and newer fees
+    data.extend([0; 72]);
+    data.extend_from_slice(&fee_data);
+    data.extend_from_slice(&fee_data);
+}
@@ -1,9 +1,215 @@
 //! Token helpers
 
+use crate::{
+    error::SwapError,
+    processor::utils,
+    state::SwapInfo,
+    token_2022::{self, MintExtensions, TransferFee},
+};
 use solana_program::{
-    account_info::AccountInfo, program::invoke_signed, program_error::ProgramError, pubkey::Pubkey,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
 };
 
+/// A mint passed to Token-2022 `TransferChecked` instructions.
+#[derive(Clone)]
+pub struct TransferMint<'a> {
+    /// The mint account.
+    pub info: AccountInfo<'a>,
+    /// Decimals of the mint.
+    pub decimals: u8,
+    /// Transfer fee charged by the mint in the current epoch.
+    pub transfer_fee: Option<TransferFee>,
+}
+
+impl<'a> TransferMint<'a> {
+    /// Loads a mint of the swap.
+    fn load(
+        info: &AccountInfo<'a>,
+        expected_mint: &Pubkey,
+        epoch: u64,
+    ) -> Result<Self, ProgramError> {
+        check_mint(info.key, expected_mint)?;
+        let mint = utils::unpack_mint(&info.data.borrow())?;
+        let extensions = MintExtensions::unpack(&info.data.borrow())?;
+        Ok(Self {
+            info: info.clone(),
+            decimals: mint.decimals,
+            transfer_fee: extensions.get_epoch_fee(epoch).copied(),
+        })
+    }
+}
+
+fn check_mint(mint_key: &Pubkey, expected_mint: &Pubkey) -> ProgramResult {
+    check_keys_equal!(*mint_key, *expected_mint, "Mint", SwapError::IncorrectMint);
+    Ok(())
+}
+
+/// Checks that the token program can be used by a swap.
+pub fn check_supported_program(token_program_id: &Pubkey) -> ProgramResult {
+    if *token_program_id != spl_token::id() && *token_program_id != token_2022::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Loads a mint of a pool that is being created. Pools on spl_token do not need
+/// it.
+pub fn load_transfer_mint<'a>(
+    token_program_id: &Pubkey,
+    mint_info: &AccountInfo<'a>,
+) -> Result<Option<TransferMint<'a>>, ProgramError> {
+    if *token_program_id != token_2022::id() {
+        return Ok(None);
+    }
+    Ok(Some(TransferMint::load(
+        mint_info,
+        mint_info.key,
+        Clock::get()?.epoch,
+    )?))
+}
+
+/// Length of a new token account of the mint, which has room for the
+/// extensions that Token-2022 requires of accounts of the mint.
+pub fn account_len(
+    token_program_id: &Pubkey,
+    mint_info: &AccountInfo,
+) -> Result<usize, ProgramError> {
+    if *token_program_id != token_2022::id() {
+        return Ok(token_2022::ACCOUNT_LEN);
+    }
+    Ok(MintExtensions::unpack(&mint_info.data.borrow())?.account_len())
+}
+
+/// Loads the token A and token B mints, which Token-2022 pools take as their
+/// last two accounts so that transfers can be checked against the mint.
+/// Pools on spl_token do not need them.
+pub fn load_transfer_mints<'a>(
+    token_swap: &SwapInfo,
+    accounts: &[AccountInfo<'a>],
+) -> Result<(Option<TransferMint<'a>>, Option<TransferMint<'a>>), ProgramError> {
+    if token_swap.token_program_id != token_2022::id() {
+        return Ok((None, None));
+    }
+    let (mint_a_info, mint_b_info) = match accounts {
+        [.., mint_a_info, mint_b_info] => (mint_a_info, mint_b_info),
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+    let epoch = Clock::get()?.epoch;
+    Ok((
+        Some(TransferMint::load(
+            mint_a_info,
+            &token_swap.token_a.mint,
+            epoch,
+        )?),
+        Some(TransferMint::load(
+            mint_b_info,
+            &token_swap.token_b.mint,
+            epoch,
+        )?),
+    ))
+}
+
+/// Number of trailing mint accounts taken by instructions of the swap.
+pub fn transfer_mint_account_count(token_swap: &SwapInfo) -> usize {
+    if token_swap.token_program_id == token_2022::id() {
+        2
+    } else {
+        0
+    }
+}
+
+/// Returns the optional accounts that follow the `account_count` accounts of
+/// an instruction. They precede the mints of Token-2022 pools.
+pub fn optional_accounts<'a, 'b>(
+    token_swap: &SwapInfo,
+    accounts: &'a [AccountInfo<'b>],
+    account_count: usize,
+) -> &'a [AccountInfo<'b>] {
+    accounts
+        .get(
+            account_count
+                ..accounts
+                    .len()
+                    .saturating_sub(transfer_mint_account_count(token_swap)),
+        )
+        .unwrap_or_default()
+}
+
+/// Returns the optional account at `index` of the optional accounts of an
+/// instruction, or None if the program id is passed in its position.
+pub fn optional_account<'a, 'b>(
+    program_id: &Pubkey,
+    optional_accounts: &'a [AccountInfo<'b>],
+    index: usize,
+) -> Option<&'a AccountInfo<'b>> {
+    optional_accounts
+        .get(index)
+        .filter(|info| info.key != program_id)
+}
+
+/// Amount received by the destination of a transfer of `amount` of the mint.
+pub fn post_fee_amount(mint: Option<&TransferMint>, amount: u64) -> Result<u64, ProgramError> {
+    match mint.and_then(|mint| mint.transfer_fee.as_ref()) {
+        Some(transfer_fee) => Ok(transfer_fee
+            .calculate_post_fee_amount(amount)
+            .ok_or(SwapError::CalculationFailure)?),
+        None => Ok(amount),
+    }
+}
+
+/// Builds the instruction for the given token program. Token-2022 shares the
+/// spl_token instruction layout.
+fn for_token_program(token_program_id: &Pubkey, mut ix: Instruction) -> Instruction {
+    ix.program_id = *token_program_id;
+    ix
+}
+
+/// Issue a spl_token `InitializeAccount3` instruction.
+pub fn initialize_account<'a>(
+    token_program: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    mint: AccountInfo<'a>,
+    owner: &Pubkey,
+) -> Result<(), ProgramError> {
+    let ix = spl_token::instruction::initialize_account3(
+        &spl_token::id(),
+        account.key,
+        mint.key,
+        owner,
+    )?;
+    solana_program::program::invoke(
+        &for_token_program(token_program.key, ix),
+        &[token_program, account, mint],
+    )
+}
+
+/// Issue a spl_token `InitializeMint2` instruction for a mint without a
+/// freeze authority.
+pub fn initialize_mint<'a>(
+    token_program: AccountInfo<'a>,
+    mint: AccountInfo<'a>,
+    mint_authority: &Pubkey,
+    decimals: u8,
+) -> Result<(), ProgramError> {
+    let ix = spl_token::instruction::initialize_mint2(
+        &spl_token::id(),
+        mint.key,
+        mint_authority,
+        None,
+        decimals,
+    )?;
+    solana_program::program::invoke(
+        &for_token_program(token_program.key, ix),
+        &[token_program, mint],
+    )
+}
+
 /// Issue a spl_token `Burn` instruction.
 pub fn burn<'a>(
     token_program: AccountInfo<'a>,
@@ -13,14 +219,17 @@ pub fn burn<'a>(
     amount: u64,
 ) -> Result<(), ProgramError> {
     let ix = spl_token::instruction::burn(
-        token_program.key,
+        &spl_token::id(),
         burn_account.key,
         mint.key,
         user_authority.key,
         &[],
         amount,
     )?;
-    solana_program::program::invoke(&ix, &[token_program, burn_account, mint, user_authority])
+    solana_program::program::invoke(
+        &for_token_program(token_program.key, ix),
+        &[token_program, burn_account, mint, user_authority],
+    )
 }
 
 /// Issue a spl_token `MintTo` instruction.
@@ -37,7 +246,7 @@ pub fn mint_to<'a>(
     let authority_signature_seeds = [&swap_bytes[..32], &[nonce]];
     let signers = &[&authority_signature_seeds[..]];
     let ix = spl_token::instruction::mint_to(
-        token_program.key,
+        &spl_token::id(),
         mint.key,
         destination.key,
         authority.key,
@@ -45,10 +254,48 @@ pub fn mint_to<'a>(
         amount,
     )?;
 
-    invoke_signed(&ix, &[mint, destination, authority, token_program], signers)
+    invoke_signed(
+        &for_token_program(token_program.key, ix),
+        &[mint, destination, authority, token_program],
+        signers,
+    )
+}
+
+/// Builds a `Transfer` instruction, or a `TransferChecked` instruction if the mint is provided.
+fn transfer_instruction(
+    token_program: &AccountInfo,
+    source: &AccountInfo,
+    destination: &AccountInfo,
+    authority: &AccountInfo,
+    mint: Option<&TransferMint>,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let ix = match mint {
+        Some(mint) => spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            source.key,
+            mint.info.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+            mint.decimals,
+        )?,
+        None => spl_token::instruction::transfer(
+            &spl_token::id(),
+            source.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+        )?,
+    };
+    Ok(for_token_program(token_program.key, ix))
 }
 
-/// Issue a spl_token `Transfer` instruction signed by the authority.
+/// Issue a spl_token `Transfer` instruction signed by the authority, checked
+/// against the mint if one is provided.
+#[allow(clippy::too_many_arguments)]
 pub fn transfer_as_swap<'a>(
     swap: &Pubkey,
     token_program: AccountInfo<'a>,
@@ -57,41 +304,83 @@ pub fn transfer_as_swap<'a>(
     program_authority: AccountInfo<'a>,
     nonce: u8,
     amount: u64,
+    mint: Option<&TransferMint<'a>>,
 ) -> Result<(), ProgramError> {
     let swap_bytes = swap.to_bytes();
     let authority_signature_seeds = [&swap_bytes[..32], &[nonce]];
     let signers = &[&authority_signature_seeds[..]];
-    let ix = spl_token::instruction::transfer(
-        token_program.key,
-        source.key,
+    let ix = transfer_instruction(
+        &token_program,
+        &source,
+        &destination,
+        &program_authority,
+        mint,
+        amount,
+    )?;
+
+    let mut account_infos = vec![token_program, source, destination, program_authority];
+    account_infos.extend(mint.map(|mint| mint.info.clone()));
+    invoke_signed(&ix, &account_infos, signers)
+}
+
+/// Issue a spl_token `CloseAccount` instruction signed by the authority.
+pub fn close_account_as_swap<'a>(
+    swap: &Pubkey,
+    token_program: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    destination: AccountInfo<'a>,
+    program_authority: AccountInfo<'a>,
+    nonce: u8,
+) -> Result<(), ProgramError> {
+    let swap_bytes = swap.to_bytes();
+    let authority_signature_seeds = [&swap_bytes[..32], &[nonce]];
+    let signers = &[&authority_signature_seeds[..]];
+    let ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        account.key,
         destination.key,
         program_authority.key,
         &[],
-        amount,
     )?;
 
     invoke_signed(
-        &ix,
-        &[token_program, source, destination, program_authority],
+        &for_token_program(token_program.key, ix),
+        &[token_program, account, destination, program_authority],
         signers,
     )
 }
 
-/// Issue a spl_token `Transfer` instruction as the user.
+/// Issue a Token-2022 `HarvestWithheldTokensToMint` instruction moving the
+/// transfer fees withheld in `account` to its mint.
+pub fn harvest_withheld_tokens_to_mint<'a>(
+    token_program: AccountInfo<'a>,
+    mint: &TransferMint<'a>,
+    account: AccountInfo<'a>,
+) -> ProgramResult {
+    let ix = token_2022::harvest_withheld_tokens_to_mint(mint.info.key, &[account.key]);
+    solana_program::program::invoke(&ix, &[token_program, mint.info.clone(), account])
+}
+
+/// Issue a spl_token `Transfer` instruction as the user, checked against the
+/// mint if one is provided.
 pub fn transfer_as_user<'a>(
     token_program: AccountInfo<'a>,
     source: AccountInfo<'a>,
     destination: AccountInfo<'a>,
     user_authority: AccountInfo<'a>,
     amount: u64,
+    mint: Option<&TransferMint<'a>>,
 ) -> Result<(), ProgramError> {
-    let ix = spl_token::instruction::transfer(
-        token_program.key,
-        source.key,
-        destination.key,
-        user_authority.key,
-        &[],
+    let ix = transfer_instruction(
+        &token_program,
+        &source,
+        &destination,
+        &user_authority,
+        mint,
         amount,
     )?;
-    solana_program::program::invoke(&ix, &[token_program, source, destination, user_authority])
+
+    let mut account_infos = vec![token_program, source, destination, user_authority];
+    account_infos.extend(mint.map(|mint| mint.info.clone()));
+    solana_program::program::invoke(&ix, &account_infos)
 }
@@ -1,6 +1,6 @@
 //! Utility methods
 
-use crate::error::SwapError;
+use crate::{error::SwapError, token_2022};
 use solana_program::program_pack::Pack;
 use solana_program::pubkey::Pubkey;
 use spl_token::state::{Account, Mint};
@@ -11,12 +11,14 @@ pub fn authority_id(program_id: &Pubkey, my_info: &Pubkey, nonce: u8) -> Result<
         .or(Err(SwapError::InvalidProgramAddress))
 }
 
-/// Unpacks a spl_token `Account`.
+/// Unpacks a spl_token `Account`, ignoring any Token-2022 extensions.
 pub fn unpack_token_account(data: &[u8]) -> Result<Account, SwapError> {
+    let data = token_2022::account_base(data).ok_or(SwapError::ExpectedAccount)?;
     Account::unpack(data).map_err(|_| SwapError::ExpectedAccount)
 }
 
-/// Unpacks a spl_token `Mint`.
+/// Unpacks a spl_token `Mint`, ignoring any Token-2022 extensions.
 pub fn unpack_mint(data: &[u8]) -> Result<Mint, SwapError> {
+    let data = token_2022::mint_base(data).ok_or(SwapError::ExpectedMint)?;
     Mint::unpack(data).map_err(|_| SwapError::ExpectedMint)
 }